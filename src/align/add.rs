@@ -0,0 +1,178 @@
+//! Adding sequences to an existing multiple alignment, like MAFFT's `--add`.
+//! https://mafft.cbrc.jp/alignment/software/addsequences.html
+//!
+//! The existing columns are never changed or realigned: each new sequence is
+//! aligned to the alignment as a profile, and gap columns are inserted into
+//! the existing rows only where the new sequence has residues that don't fit
+//! in any existing column. Removing a sequence drops only the columns it
+//! alone had residues in.
+
+use crate::seq::SeqRecord;
+
+use super::{
+    clustal_w::{profile_columns, Profile},
+    profile_merge::widened_metadata,
+    MSAlignment, Scoring,
+};
+
+impl MSAlignment {
+    /// add a sequence to the alignment, keeping the existing columns fixed.
+    ///
+    /// Returns the new column of each of the existing columns, so annotations
+    /// on the old columns can be carried over.
+    pub fn add(&mut self, id: &str, seq: &str, scoring: &Scoring) -> Vec<usize> {
+        let existing = Profile {
+            seqs: (0..self.rows.len()).collect(),
+            rows: self
+                .rows
+                .iter()
+                .map(|r| r.iter().map(|c| *c as u8).collect())
+                .collect(),
+        };
+        let added = Profile::from_seq(self.rows.len(), seq);
+        let columns = profile_columns(&existing, &added, scoring);
+
+        let seq: Vec<char> = seq.chars().collect();
+        let mut rows: Vec<Vec<char>> = vec![Vec::with_capacity(columns.len()); self.rows.len() + 1];
+        let mut moved = Vec::with_capacity(self.len());
+        for (col, (i, j)) in columns.iter().enumerate() {
+            for (row, old) in rows.iter_mut().zip(self.rows.iter()) {
+                row.push(i.map_or('-', |i| old[i]));
+            }
+            rows[self.rows.len()].push(j.map_or('-', |j| seq[j]));
+            if i.is_some() {
+                moved.push(col);
+            }
+        }
+
+        self.metadata = widened_metadata(self, &moved, columns.len());
+        self.rows = rows;
+        self.ids.push(id.to_string());
+        moved
+    }
+
+    /// insert a sequence record into the alignment, like `add`.
+    pub fn insert(&mut self, record: &SeqRecord, scoring: &Scoring) -> Vec<usize> {
+        self.add(&record.id, &record.seq, scoring)
+    }
+
+    /// remove the row of a sequence by its ID, and the columns that are all
+    /// gap without it. The row's per-residue markup is dropped with it.
+    ///
+    /// Returns the removed sequence without its gaps, or None if no row has the ID.
+    pub fn remove(&mut self, id: &str) -> Option<SeqRecord> {
+        let row = self.row(id)?;
+        let keep: Vec<bool> = (0..self.len())
+            .map(|col| (0..self.rows.len()).any(|r| r != row && self.rows[r][col] != '-'))
+            .collect();
+        let ids: Vec<String> = (0..self.ids.len())
+            .filter(|r| *r != row)
+            .map(|r| self.ids[r].clone())
+            .collect();
+        let cols: Vec<usize> = (0..keep.len()).filter(|col| keep[*col]).collect();
+        self.metadata = self.sliced_metadata(&ids, &cols);
+
+        let removed = self.rows.remove(row);
+        let id = self.ids.remove(row);
+        for r in self.rows.iter_mut() {
+            let mut col = 0;
+            r.retain(|_| {
+                col += 1;
+                keep[col - 1]
+            });
+        }
+
+        Some(SeqRecord::new(
+            id,
+            removed
+                .into_iter()
+                .filter(|c| *c != '-')
+                .collect::<String>(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_msalignment_add() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let mut msa = MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["ACGT-ACGT".chars().collect(), "ACGTTACGT".chars().collect()],
+        );
+
+        // fits the existing columns
+        assert_eq!(
+            (0..9).collect::<Vec<_>>(),
+            msa.add("c", "ACGTACGT", &scoring)
+        );
+        assert_eq!("ACGT-ACGT\nACGTTACGT\nACGT-ACGT", msa.to_string());
+
+        // an insertion adds gap columns to the existing rows
+        assert_eq!(
+            vec![0, 1, 2, 3, 4, 5, 6, 10, 11],
+            msa.add("d", "ACGTTACGGGGT", &scoring)
+        );
+        assert_eq!(
+            "ACGT-AC---GT\nACGTTAC---GT\nACGT-AC---GT\nACGTTACGGGGT",
+            msa.to_string()
+        );
+        assert_eq!(Some(3), msa.row("d"));
+
+        // removing it drops the columns only it had residues in
+        assert_eq!(Some(SeqRecord::new("d", "ACGTTACGGGGT")), msa.remove("d"));
+        assert_eq!("ACGT-ACGT\nACGTTACGT\nACGT-ACGT", msa.to_string());
+        assert_eq!(None, msa.remove("d"));
+
+        msa.insert(&SeqRecord::new("e", "ACGTTACGT"), &scoring);
+        assert_eq!(Some(3), msa.row("e"));
+        assert_eq!("ACGTTACGT", msa.rows[3].iter().collect::<String>());
+    }
+
+    #[test]
+    fn test_msalignment_add_keeps_masks() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let mut msa = MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["ACGT-ACGT".chars().collect(), "ACGTTACGT".chars().collect()],
+        );
+        msa.metadata.insert("GC:MASK".into(), "xx.......".into());
+        msa.metadata.insert("GR:b:MASK".into(), "......xxx".into());
+        msa.metadata.insert("GF:ID".into(), "family".into());
+
+        // inserted columns aren't masked
+        msa.add("d", "ACGTTACGGGGT", &scoring);
+        assert_eq!("ACGT-AC---GT", msa.rows[0].iter().collect::<String>());
+        assert_eq!("xx..........", msa.metadata["GC:MASK"]);
+        assert_eq!("......x...xx", msa.metadata["GR:b:MASK"]);
+        msa.metadata
+            .insert("GR:d:MASK".into(), "xxxxxxxxxxxx".into());
+
+        // and removing the row that had them drops them again, with its markup
+        msa.remove("d");
+        assert_eq!("xx.......", msa.metadata["GC:MASK"]);
+        assert_eq!("......xxx", msa.metadata["GR:b:MASK"]);
+        assert_eq!("family", msa.metadata["GF:ID"]);
+        assert!(!msa.metadata.contains_key("GR:d:MASK"));
+
+        // removing a row whose residue column goes too
+        msa.remove("b");
+        assert_eq!("xx......", msa.metadata["GC:MASK"]);
+        assert!(!msa.metadata.contains_key("GR:b:MASK"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Aligners chosen by name at runtime.
+//!
+//! Every pairwise algorithm is wrapped in the [`Aligner`] trait and built from
+//! the same [`AlignerConfig`], so an application can take the algorithm from a
+//! config file or command line flag and look it up in a [`Registry`] instead
+//! of matching on each one. Applications can register aligners of their own.
+
+use std::collections::BTreeMap;
+
+use crate::seq::SeqRecord;
+
+use super::{
+    align, align_wfa, needleman_wunsch, smith_waterman, strategy::Strategy, Alignment, Error,
+    Result, Scoring, TerminalGap, TerminalGaps,
+};
+
+/// Aligner aligns a pair of sequences.
+pub trait Aligner: Send + Sync {
+    fn align(&self, a: &str, b: &str) -> Alignment;
+
+    /// align_records aligns a pair of records, keeping their IDs in the
+    /// alignment's metadata as `a_id` and `b_id`.
+    fn align_records(&self, a: &SeqRecord, b: &SeqRecord) -> Alignment {
+        self.align(&a.seq, &b.seq)
+            .with_metadata("a_id", a.id.as_str())
+            .with_metadata("b_id", b.id.as_str())
+    }
+}
+
+/// AlignerConfig is the configuration every aligner is built from.
+#[derive(Clone, Debug, Default)]
+pub struct AlignerConfig {
+    pub scoring: Scoring,
+}
+
+/// Constructor builds an aligner from the shared config.
+pub type Constructor = fn(&AlignerConfig) -> Box<dyn Aligner>;
+
+/// Registry maps algorithm names to aligner constructors.
+pub struct Registry {
+    constructors: BTreeMap<String, Constructor>,
+}
+
+impl Default for Registry {
+    /// default has the built-in aligners:
+    ///
+    /// - `nw`: Needleman-Wunsch global alignment
+    /// - `sw`: Smith-Waterman local alignment
+    /// - `semiglobal`: Needleman-Wunsch with free terminal gaps
+    /// - `wfa`: wavefront alignment by edit distance, which ignores the scoring
+    fn default() -> Self {
+        let mut registry = Registry {
+            constructors: BTreeMap::new(),
+        };
+        registry.register("nw", |config| {
+            Box::new(StrategyAligner {
+                strategy: &needleman_wunsch::STRATEGY,
+                scoring: config.scoring.clone(),
+            })
+        });
+        registry.register("sw", |config| {
+            Box::new(StrategyAligner {
+                strategy: &smith_waterman::STRATEGY,
+                scoring: config.scoring.clone(),
+            })
+        });
+        registry.register("semiglobal", |config| {
+            Box::new(StrategyAligner {
+                strategy: &needleman_wunsch::STRATEGY,
+                scoring: Scoring {
+                    terminal_gaps: TerminalGaps::all(TerminalGap::Free),
+                    ..config.scoring.clone()
+                },
+            })
+        });
+        registry.register("wfa", |_| Box::new(WfaAligner));
+        registry
+    }
+}
+
+impl Registry {
+    /// register an aligner under a name, replacing any already registered.
+    pub fn register(&mut self, name: &str, constructor: Constructor) {
+        self.constructors.insert(name.to_string(), constructor);
+    }
+
+    /// get builds the aligner registered under a name.
+    pub fn get(&self, name: &str, config: &AlignerConfig) -> Result<Box<dyn Aligner>> {
+        match self.constructors.get(name) {
+            Some(constructor) => Ok(constructor(config)),
+            None => Err(Error::UnknownAligner(name.to_string())),
+        }
+    }
+
+    /// names of the registered aligners, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(|k| k.as_str())
+    }
+}
+
+/// StrategyAligner aligns with one of the grid strategies.
+struct StrategyAligner {
+    strategy: &'static Strategy,
+    scoring: Scoring,
+}
+
+impl Aligner for StrategyAligner {
+    fn align(&self, a: &str, b: &str) -> Alignment {
+        align(
+            vec![a.to_string(), b.to_string()],
+            self.strategy,
+            &self.scoring,
+        )
+    }
+}
+
+struct WfaAligner;
+
+impl Aligner for WfaAligner {
+    fn align(&self, a: &str, b: &str) -> Alignment {
+        align_wfa(a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::MATCH;
+
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::default();
+        assert_eq!(
+            vec!["nw", "semiglobal", "sw", "wfa"],
+            registry.names().collect::<Vec<_>>()
+        );
+
+        let config = AlignerConfig {
+            scoring: Scoring {
+                matrix: MATCH::MATRIX,
+                ..Default::default()
+            },
+        };
+        let semiglobal = registry.get("semiglobal", &config).unwrap();
+        assert_eq!(
+            "--ACGT--",
+            semiglobal.align("TTACGTTT", "ACGT").rows[1]
+                .iter()
+                .collect::<String>()
+        );
+        assert_eq!(
+            -1f32,
+            registry
+                .get("wfa", &config)
+                .unwrap()
+                .align("ACGT", "AGT")
+                .score
+        );
+        let named = semiglobal.align_records(
+            &SeqRecord::new("chr1", "TTACGTTT"),
+            &SeqRecord::new("read1", "ACGT"),
+        );
+        assert_eq!(Some("chr1"), named.metadata.get("a_id").map(String::as_str));
+        assert_eq!(
+            Some("read1"),
+            named.metadata.get("b_id").map(String::as_str)
+        );
+        assert!(matches!(
+            registry.get("blast", &config),
+            Err(Error::UnknownAligner(name)) if name == "blast"
+        ));
+    }
+}
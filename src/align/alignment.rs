@@ -3,15 +3,27 @@ use std::{
     fmt::{Debug, Display},
 };
 
-use crate::matrices::Matrix;
+use ordered_float::OrderedFloat;
 
-use super::{step::Step, strategy::Strategy};
+use crate::{
+    matrices::{Matrix, BLOSUM62},
+    trace::stage,
+};
+
+use super::{
+    fixed::{Precision, Score},
+    step::Step,
+    strategy::{Ends, Strategy},
+    terminal_gaps::TerminalGaps,
+    traceback::Traceback,
+    Metadata,
+};
 
 pub struct Alignment {
     /// the 2D-grid holding the alignment of the sequences.
     pub rows: Vec<Vec<char>>,
 
-    /// steps are the steps from the full 2D alignment.
+    /// steps are the steps from the full 2D alignment, only kept by [`align_grid`].
     pub steps: Vec<Vec<Step>>,
 
     /// score is the final alignment score.
@@ -19,6 +31,9 @@ pub struct Alignment {
 
     /// distance is the ratio of characters that differ between the two sequences.
     pub distance: f32,
+
+    /// metadata is where the alignment came from: sequence IDs, samples, parameters.
+    pub metadata: Metadata,
 }
 
 impl Alignment {
@@ -34,12 +49,38 @@ impl Alignment {
             steps,
             score,
             distance,
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// distance_excluding_terminal_gaps is the [`distance`](Alignment::distance)
+    /// over the columns from where every row has started to where the first
+    /// one ends, leaving out the overhangs of a fragment aligned to a longer
+    /// sequence. It's 1 if the rows don't overlap.
+    pub fn distance_excluding_terminal_gaps(&self) -> f32 {
+        let residue = |c: &char| *c != '-';
+        let (mut start, mut end) = (0, usize::MAX);
+        for row in self.rows.iter() {
+            match (row.iter().position(residue), row.iter().rposition(residue)) {
+                (Some(first), Some(last)) => (start, end) = (start.max(first), end.min(last + 1)),
+                _ => return 1f32,
+            }
         }
+        if start >= end {
+            return 1f32;
+        }
+
+        let window: Vec<Vec<char>> = self
+            .rows
+            .iter()
+            .map(|row| row[start..end.min(row.len())].to_vec())
+            .collect();
+        Alignment::calc_distance(&window)
     }
 
     /// returns the ratio of residues that have a difference between the
     /// sequences in the alignment. Residues that are entirely gap are ignored.
-    fn calc_distance(alignment: &Vec<Vec<char>>) -> f32 {
+    fn calc_distance(alignment: &[Vec<char>]) -> f32 {
         let mut residues = 0f32;
         let mut diffs = 0f32;
         for col in 0..alignment[0].len() {
@@ -97,6 +138,11 @@ impl Debug for Alignment {
         );
         let mut result: Vec<String> = vec![header];
 
+        // the grid is only drawn if it was kept
+        if self.steps.is_empty() {
+            return write!(f, "{}", result.join(""));
+        }
+
         let a: Vec<char> = self.rows[0]
             .clone()
             .into_iter()
@@ -134,7 +180,7 @@ impl Debug for Alignment {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Scoring {
     /// replacement matrix
     pub matrix: Matrix,
@@ -144,6 +190,25 @@ pub struct Scoring {
 
     /// penalty for a gap extension
     pub gap_extension: f32,
+
+    /// penalties for gaps at the ends of the sequences (global alignment only)
+    pub terminal_gaps: TerminalGaps,
+
+    /// the number type the pairwise aligners sum scores in. A multiple
+    /// alignment's distances and profiles are always summed in f32.
+    pub precision: Precision,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            matrix: BLOSUM62::MATRIX,
+            gap_opening: -1f32,
+            gap_extension: -1f32,
+            terminal_gaps: TerminalGaps::default(),
+            precision: Precision::Float,
+        }
+    }
 }
 
 /// align sequences using a strategy and scoring scheme.
@@ -152,155 +217,462 @@ pub fn align<I: IntoIterator<Item = String>>(
     strategy: &Strategy,
     scoring: &Scoring,
 ) -> Alignment {
+    let (a, b) = pair(seqs);
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    align_with(&a, &b, strategy, scoring, &|j, i| {
+        scoring.matrix[a_bytes[j] as usize][b_bytes[i] as usize] as f32
+    })
+}
+
+/// align_grid is [`align`] and also keeps the score and traceback pointer of
+/// every cell of the grid in [`Alignment::steps`], to draw or export it. The
+/// grid is many times the size of the packed traceback `align` keeps, so
+/// it's for short sequences.
+pub fn align_grid<I: IntoIterator<Item = String>>(
+    seqs: I,
+    strategy: &Strategy,
+    scoring: &Scoring,
+) -> Alignment {
+    let (a, b) = pair(seqs);
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    let substitution =
+        |j: usize, i: usize| scoring.matrix[a_bytes[j] as usize][b_bytes[i] as usize] as f32;
+    let grid = fill_grid(strategy, scoring, a_bytes, b_bytes, &substitution, true);
+    let mut alignment = backtrace(strategy, scoring, &grid, a_bytes, b_bytes);
+    alignment.steps = grid.steps();
+    alignment
+}
+
+/// pair is the first two sequences.
+fn pair<I: IntoIterator<Item = String>>(seqs: I) -> (String, String) {
     let mut input = seqs.into_iter();
     let a = input.next().unwrap();
     let b = input.next().unwrap();
+    (a, b)
+}
 
-    // Initialize the alignment grid.
-    let grid = &mut init_grid(strategy, a.len(), b.len());
-
+/// align_with aligns two sequences with a custom substitution score.
+///
+/// `substitution` is the score of aligning `a[j]` with `b[i]`.
+pub(super) fn align_with(
+    a: &str,
+    b: &str,
+    strategy: &Strategy,
+    scoring: &Scoring,
+    substitution: &dyn Fn(usize, usize) -> f32,
+) -> Alignment {
     // Fill in the alignment grid.
-    fill_grid(strategy, scoring, grid, a.as_bytes(), b.as_bytes());
+    let grid = {
+        let _stage = stage!("fill_grid", a = a.len(), b = b.len());
+        fill_grid(
+            strategy,
+            scoring,
+            a.as_bytes(),
+            b.as_bytes(),
+            substitution,
+            false,
+        )
+    };
 
     // Backtrace the grid to get the final alignment.
-    backtrace(strategy, grid, a.as_bytes(), b.as_bytes())
+    let _stage = stage!("traceback", a = a.len(), b = b.len());
+    backtrace(strategy, scoring, &grid, a.as_bytes(), b.as_bytes())
 }
 
-/// init grid sets up the 2D alignment grid.
-fn init_grid(strategy: &Strategy, a_len: usize, b_len: usize) -> Vec<Vec<Step>> {
-    let mut grid: Vec<Vec<Step>> = Vec::new();
-    for i in 0..b_len + 1 {
-        grid.push(vec![Step::default(); a_len + 1]);
-
-        grid[i][0] = Step {
-            i,
-            j: 0,
-            val: (strategy.init_grid_value)(i),
-            next: match i {
-                0 => None,
-                _ => Some((i - 1, 0)),
-            },
-        };
-    }
+// traceback codes of a cell: where its score came from, and whether the gap
+// ending in it in each sequence opened in the cell before or extends a gap
+const DIAGONAL: u8 = 0;
+const UP: u8 = 1;
+const LEFT: u8 = 2;
+const START: u8 = 3;
+const OPEN: u8 = 0;
+const EXTEND: u8 = 1;
+
+/// Grid is the packed traceback of a filled alignment grid, three codes per
+/// cell, and the scores of the cells the alignment can end in.
+struct Grid {
+    width: usize,
+    traceback: Traceback,
+    ends: Ends,
+
+    /// the score of every cell, if kept
+    scores: Vec<Vec<f32>>,
+}
 
-    for j in 0..a_len + 1 {
-        grid[0][j] = Step {
-            i: 0,
-            j,
-            val: (strategy.init_grid_value)(j),
-            next: match j {
-                0 => None,
-                _ => Some((0, j - 1)),
-            },
-        };
+impl Grid {
+    /// code is the traceback code of a cell for the score (0), a gap in a
+    /// (1) or a gap in b (2).
+    fn code(&self, i: usize, j: usize, kind: usize) -> u8 {
+        self.traceback.get(3 * (i * self.width + j) + kind)
     }
 
-    grid
-}
-
-/// fill_grid traverses the grid from top-left to bottom right, filling in each step.
-fn fill_grid(strategy: &Strategy, scoring: &Scoring, grid: &mut [Vec<Step>], a: &[u8], b: &[u8]) {
-    for i in 1..=b.len() {
-        for j in 1..=a.len() {
-            // negative values are ignored, 0 is as low as we'll go
-            let mut options: Vec<Step> = (strategy.init_step_options)(i, j);
-
-            // gaps
-            let mut k = 1;
-            while k < i {
-                if a[j - 1] == b[k - 1] {
-                    options.push(Step {
-                        val: grid[k][j].val
-                            + scoring.gap_opening
-                            + scoring.gap_extension * (i - k - 1) as f32,
-                        i,
-                        j,
-                        next: Some((k, j)),
-                    });
+    /// next is the cell the traceback pointer of a cell points to: the cell
+    /// before a match, or where the gap ending in it opened.
+    fn next(&self, i: usize, j: usize) -> Option<(usize, usize)> {
+        let (mut k, mut l) = (i, j);
+        match self.code(i, j, 0) {
+            DIAGONAL => return Some((i - 1, j - 1)),
+            START => return None,
+            UP => {
+                while self.code(k, j, 1) == EXTEND {
+                    k -= 1;
+                }
+                k -= 1;
+            }
+            _ => {
+                while self.code(i, l, 2) == EXTEND {
+                    l -= 1;
                 }
-                k += 1;
+                l -= 1;
             }
+        }
+        Some((k, l))
+    }
 
-            let mut l = 1;
-            while l < j {
-                if a[l - 1] == b[i - 1] {
-                    options.push(Step {
-                        val: grid[i][l].val
-                            + scoring.gap_opening
-                            + scoring.gap_extension * (j - l - 1) as f32,
+    /// steps are the cells of the grid, with their kept scores.
+    fn steps(&self) -> Vec<Vec<Step>> {
+        self.scores
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, val)| Step {
+                        val: OrderedFloat(*val),
                         i,
                         j,
-                        next: Some((i, l)),
-                    });
+                        next: self.next(i, j),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// fill_grid traverses the grid from top-left to bottom right, keeping only
+/// the traceback of each cell and the scores of the row before.
+///
+/// The first column holds leading gaps in `a` and the first row leading gaps
+/// in `b`, so each is scaled by the terminal gap weight of that end. A gap
+/// can only open after a residue of the other sequence that's the same as
+/// the one the gap is against.
+fn fill_grid(
+    strategy: &Strategy,
+    scoring: &Scoring,
+    a: &[u8],
+    b: &[u8],
+    substitution: &dyn Fn(usize, usize) -> f32,
+    keep_scores: bool,
+) -> Grid {
+    match scoring.precision {
+        Precision::Float => fill::<f32>(strategy, scoring, a, b, substitution, keep_scores, 1),
+        Precision::Fixed(scale) => fill::<i64>(
+            strategy,
+            scoring,
+            a,
+            b,
+            substitution,
+            keep_scores,
+            scale.max(1),
+        ),
+    }
+}
+
+/// fill is `fill_grid` summing scores as `S`, scaled by `scale`.
+fn fill<S: Score>(
+    strategy: &Strategy,
+    scoring: &Scoring,
+    a: &[u8],
+    b: &[u8],
+    substitution: &dyn Fn(usize, usize) -> f32,
+    keep_scores: bool,
+    scale: u32,
+) -> Grid {
+    let a_start = scoring.terminal_gaps.a_start.weight();
+    let b_start = scoring.terminal_gaps.b_start.weight();
+    let open = S::from_f32(scoring.gap_opening, scale);
+    let extend = S::from_f32(scoring.gap_extension, scale);
+    let width = a.len() + 1;
+
+    let mut traceback = Traceback::new(3 * (b.len() + 1) * width);
+    let mut set =
+        |i: usize, j: usize, kind: usize, code: u8| traceback.set(3 * (i * width + j) + kind, code);
+
+    // the first row is leading gaps in b
+    let mut prev: Vec<S> = (0..width)
+        .map(|j| S::from_f32(((strategy.init_grid_value)(j) * b_start).0, scale))
+        .collect();
+    set(0, 0, 0, START);
+    for j in 1..width {
+        set(0, j, 0, LEFT);
+    }
+
+    let mut last_column = vec![prev[width - 1].to_f32(scale)];
+    let mut best = (0, 0, prev[0]);
+    for (j, val) in prev.iter().enumerate() {
+        if *val > best.2 {
+            best = (0, j, *val);
+        }
+    }
+    let mut scores = Vec::new();
+    if keep_scores {
+        scores.push(prev.iter().map(|val| val.to_f32(scale)).collect());
+    }
+
+    // the best score of each column ending in a gap in a
+    let mut up = vec![S::NEG_INFINITY; width];
+    let mut cur = vec![S::ZERO; width];
+    for i in 1..=b.len() {
+        // the first column is leading gaps in a
+        cur[0] = S::from_f32(((strategy.init_grid_value)(i) * a_start).0, scale);
+        set(i, 0, 0, UP);
+        let mut left = S::NEG_INFINITY;
+
+        for j in 1..width {
+            // gaps, preferring the shortest on ties
+            let (val, code) = match up[j] + extend {
+                extended if i > 1 && a[j - 1] == b[i - 2] && prev[j] + open >= extended => {
+                    (prev[j] + open, OPEN)
+                }
+                extended => (extended, EXTEND),
+            };
+            up[j] = val;
+            set(i, j, 1, code);
+
+            let (val, code) = match left + extend {
+                extended if j > 1 && a[j - 2] == b[i - 1] && cur[j - 1] + open >= extended => {
+                    (cur[j - 1] + open, OPEN)
+                }
+                extended => (extended, EXTEND),
+            };
+            left = val;
+            set(i, j, 2, code);
+
+            // a match or mismatch beats gaps, and gaps in b beat gaps in a, on ties
+            let mut cell = match (strategy.init_step_value)(i, j) {
+                Some(val) => (START, S::from_f32(val.0, scale)),
+                None => (START, S::NEG_INFINITY),
+            };
+            let diagonal = prev[j - 1] + S::from_f32(substitution(j - 1, i - 1), scale);
+            for (code, val) in [(UP, up[j]), (LEFT, left), (DIAGONAL, diagonal)] {
+                if val >= cell.1 {
+                    cell = (code, val);
                 }
-                l += 1;
             }
+            cur[j] = cell.1;
+            set(i, j, 0, cell.0);
+        }
 
-            // match or mismatch
-            let match_val = scoring.matrix[a[j - 1] as usize][b[i - 1] as usize] as f32;
-            options.push(Step {
-                val: grid[i - 1][j - 1].val + match_val,
-                i,
-                j,
-                next: Some((i - 1, j - 1)),
-            });
-
-            grid[i][j] = options.iter().max().unwrap().clone();
+        for (j, val) in cur.iter().enumerate() {
+            if *val > best.2 {
+                best = (i, j, *val);
+            }
+        }
+        last_column.push(cur[width - 1].to_f32(scale));
+        if keep_scores {
+            scores.push(cur.iter().map(|val| val.to_f32(scale)).collect());
         }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    let ends = Ends {
+        last_column,
+        last_row: prev.iter().map(|val| val.to_f32(scale)).collect(),
+        best: (best.0, best.1, best.2.to_f32(scale)),
+    };
+    Grid {
+        width,
+        traceback,
+        ends,
+        scores,
     }
 }
 
 /// backtrace walks to the result of alignment to find the optimal alignment.
-fn backtrace(strategy: &Strategy, grid: &mut [Vec<Step>], a: &[u8], b: &[u8]) -> Alignment {
-    let mut step = &(strategy.init_backtrace)(grid);
-    let score = step.val.0;
+fn backtrace(strategy: &Strategy, scoring: &Scoring, grid: &Grid, a: &[u8], b: &[u8]) -> Alignment {
+    let end = (strategy.init_backtrace)(&grid.ends, &scoring.terminal_gaps);
 
     let mut alignment: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
-    while let Some((next_i, next_j)) = step.next {
-        let i_delta = step.i - next_i;
-        let j_delta = step.j - next_j;
-
-        if i_delta == 1 && j_delta == 1 {
-            // match/mismatch
-            alignment[0].push(a[step.j - 1] as char);
-            alignment[1].push(b[step.i - 1] as char);
-        } else if i_delta > 0 {
-            // gap in seq a
-            let mut i = step.i;
-            while i > next_i {
+    let (mut i, mut j) = end.cell;
+    if end.terminal_gap {
+        // the gap from the end of the path to the corner
+        for i in (i + 1..=b.len()).rev() {
+            alignment[0].push('-');
+            alignment[1].push(b[i - 1] as char);
+        }
+        for j in (j + 1..=a.len()).rev() {
+            alignment[0].push(a[j - 1] as char);
+            alignment[1].push('-');
+        }
+    }
+
+    let mut kind = 0;
+    while i > 0 || j > 0 {
+        match kind {
+            0 => match grid.code(i, j, 0) {
+                DIAGONAL => {
+                    // match/mismatch
+                    alignment[0].push(a[j - 1] as char);
+                    alignment[1].push(b[i - 1] as char);
+                    i -= 1;
+                    j -= 1;
+                }
+                START => break,
+                // continue as the gap ending in this cell
+                code => kind = code as usize,
+            },
+            1 => {
+                // gap in seq a
                 alignment[0].push('-');
                 alignment[1].push(b[i - 1] as char);
+                if grid.code(i, j, 1) == OPEN {
+                    kind = 0;
+                }
                 i -= 1;
             }
-        } else if j_delta > 0 {
-            // gap in seq b
-            let mut j = step.j;
-            while j > next_j {
+            _ => {
+                // gap in seq b
                 alignment[0].push(a[j - 1] as char);
                 alignment[1].push('-');
+                if grid.code(i, j, 2) == OPEN {
+                    kind = 0;
+                }
                 j -= 1;
             }
-        } else {
-            panic!("unexpected step");
         }
-
-        // move to the next step in the alignment
-        step = &grid[next_i][next_j];
     }
 
     for line in alignment.iter_mut() {
         line.reverse();
     }
 
-    Alignment::new(alignment, grid.to_vec(), score)
+    Alignment::new(alignment, vec![], end.score)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::align::step::Step;
+    use crate::{
+        align::{step::Step, Method, TerminalGap},
+        matrices::NUC_4_4,
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
 
     use super::*;
 
+    /// reference fills the grid the way it was before the traceback was
+    /// packed: every option of every cell is scored and the best kept.
+    fn reference(strategy: &Strategy, scoring: &Scoring, a: &[u8], b: &[u8]) -> Vec<Vec<Step>> {
+        let (open, extend) = (scoring.gap_opening, scoring.gap_extension);
+        let ends = &scoring.terminal_gaps;
+        let mut grid = vec![vec![Step::default(); a.len() + 1]; b.len() + 1];
+        for i in 0..=b.len() {
+            for j in 0..=a.len() {
+                if j == 0 {
+                    let val = (strategy.init_grid_value)(i) * ends.a_start.weight();
+                    let next = i.checked_sub(1).map(|i| (i, 0));
+                    grid[i][j] = Step { val, i, j, next };
+                    continue;
+                }
+                if i == 0 {
+                    let val = (strategy.init_grid_value)(j) * ends.b_start.weight();
+                    grid[i][j] = Step {
+                        val,
+                        i,
+                        j,
+                        next: Some((0, j - 1)),
+                    };
+                    continue;
+                }
+
+                let mut options: Vec<Step> = (strategy.init_step_value)(i, j)
+                    .map(|val| Step {
+                        val,
+                        i,
+                        j,
+                        next: None,
+                    })
+                    .into_iter()
+                    .collect();
+                for k in (1..i).filter(|k| a[j - 1] == b[k - 1]) {
+                    let val = grid[k][j].val + open + extend * (i - k - 1) as f32;
+                    options.push(Step {
+                        val,
+                        i,
+                        j,
+                        next: Some((k, j)),
+                    });
+                }
+                for l in (1..j).filter(|l| a[l - 1] == b[i - 1]) {
+                    let val = grid[i][l].val + open + extend * (j - l - 1) as f32;
+                    options.push(Step {
+                        val,
+                        i,
+                        j,
+                        next: Some((i, l)),
+                    });
+                }
+                let val = grid[i - 1][j - 1].val
+                    + NUC_4_4::MATRIX[a[j - 1] as usize][b[i - 1] as usize] as f32;
+                options.push(Step {
+                    val,
+                    i,
+                    j,
+                    next: Some((i - 1, j - 1)),
+                });
+                grid[i][j] = options.iter().max().unwrap().clone();
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn test_align_packed_traceback() {
+        let mut rng = Rng::new(5);
+        for (n, (open, extend), ends) in [
+            (40, (-2f32, -2f32), TerminalGap::Full),
+            (60, (-5f32, -0.5f32), TerminalGap::Full),
+            (50, (-3f32, -1f32), TerminalGap::Free),
+        ] {
+            let a = random_seq(n, &background::nucleotide(), &mut rng);
+            let b = random_seq(n - 7, &background::nucleotide(), &mut rng);
+            let scoring = Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: open,
+                gap_extension: extend,
+                terminal_gaps: TerminalGaps::all(ends),
+                ..Default::default()
+            };
+            for method in [Method::NeedlemanWunsch, Method::SmithWaterman] {
+                let strategy = method.strategy();
+                let seqs = vec![a.clone(), b.clone()];
+
+                // the grid rebuilt from the packed traceback is the one filled in full
+                let grid = align_grid(seqs.clone(), strategy, &scoring);
+                let expected = reference(strategy, &scoring, a.as_bytes(), b.as_bytes());
+                assert_eq!(expected, grid.steps);
+                for (expected, step) in expected.iter().flatten().zip(grid.steps.iter().flatten()) {
+                    assert_eq!(expected.next, step.next, "{:?}", step);
+                }
+
+                // and without it, the path is the same
+                let alignment = align(seqs.clone(), strategy, &scoring);
+                assert!(alignment.steps.is_empty());
+                assert_eq!(grid.to_string(), alignment.to_string());
+                assert_eq!(grid.score, alignment.score);
+
+                // these penalties are exact in f32, so summing in fixed point changes nothing
+                let fixed = Scoring {
+                    precision: Precision::FIXED,
+                    ..scoring.clone()
+                };
+                let fixed = align(seqs, strategy, &fixed);
+                assert_eq!(alignment.to_string(), fixed.to_string());
+                assert_eq!(alignment.score, fixed.score);
+            }
+        }
+    }
+
     #[test]
     fn test_alignment_debug() {
         let alignment = Alignment::new(
@@ -348,6 +720,17 @@ G  | -2 | 0  | 1  | 1  |
             0f32,
         );
 
-        assert_eq!(0.6, alignment.distance)
+        assert_eq!(0.6, alignment.distance);
+
+        let fragment = Alignment::new(
+            vec![
+                "TTACGTACGT".chars().collect(),
+                "--ACGAACG-".chars().collect(),
+            ],
+            vec![vec![]],
+            0f32,
+        );
+        assert_eq!(0.4, fragment.distance);
+        assert_eq!(1f32 / 7f32, fragment.distance_excluding_terminal_gaps());
     }
 }
@@ -0,0 +1,214 @@
+//! Anchored (constrained) global alignment.
+//!
+//! Anchors are blocks of residues that must align to one another without
+//! gaps: a single pair of positions, or a whole region like a known motif.
+//! The alignment is split at the anchors and Needleman-Wunsch is run on each
+//! segment between them, so the anchors are kept exactly and the work is
+//! smaller than a single alignment of the whole sequences.
+
+use super::{
+    align, needleman_wunsch, Alignment, Error, Result, Scoring, TerminalGap, TerminalGaps,
+};
+
+/// Anchor is a block of `len` residues of a and b that must align without gaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Anchor {
+    /// 0-based position of the block in a
+    pub a: usize,
+
+    /// 0-based position of the block in b
+    pub b: usize,
+
+    /// length of the block, 1 to pin a single pair of positions
+    pub len: usize,
+}
+
+/// align_anchored aligns two sequences globally through the anchors.
+///
+/// Anchors must be in order and not overlap in either sequence. The terminal
+/// gap penalties of `scoring` apply to the ends of the whole alignment.
+pub fn align_anchored(
+    a: &str,
+    b: &str,
+    anchors: &[Anchor],
+    scoring: &Scoring,
+) -> Result<Alignment> {
+    let (a_chars, b_chars): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+
+    // check the anchors are in order and within the sequences
+    let mut end = (0, 0);
+    for anchor in anchors {
+        if anchor.len == 0
+            || anchor.a < end.0
+            || anchor.b < end.1
+            || anchor.a + anchor.len > a_chars.len()
+            || anchor.b + anchor.len > b_chars.len()
+        {
+            return Err(Error::InvalidAnchor {
+                a: anchor.a,
+                b: anchor.b,
+            });
+        }
+        end = (anchor.a + anchor.len, anchor.b + anchor.len);
+    }
+
+    let mut rows: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
+    let mut score = 0f32;
+    let mut start = (0, 0);
+    for (k, anchor) in anchors.iter().enumerate() {
+        let terminal_gaps = TerminalGaps {
+            a_start: if k == 0 {
+                scoring.terminal_gaps.a_start
+            } else {
+                TerminalGap::Full
+            },
+            b_start: if k == 0 {
+                scoring.terminal_gaps.b_start
+            } else {
+                TerminalGap::Full
+            },
+            ..Default::default()
+        };
+        score += segment(
+            &a_chars[start.0..anchor.a],
+            &b_chars[start.1..anchor.b],
+            scoring,
+            terminal_gaps,
+            &mut rows,
+        );
+
+        for i in 0..anchor.len {
+            let (x, y) = (a_chars[anchor.a + i], b_chars[anchor.b + i]);
+            score += scoring.matrix[x as usize][y as usize] as f32;
+            rows[0].push(x);
+            rows[1].push(y);
+        }
+        start = (anchor.a + anchor.len, anchor.b + anchor.len);
+    }
+
+    let terminal_gaps = TerminalGaps {
+        a_start: if anchors.is_empty() {
+            scoring.terminal_gaps.a_start
+        } else {
+            TerminalGap::Full
+        },
+        b_start: if anchors.is_empty() {
+            scoring.terminal_gaps.b_start
+        } else {
+            TerminalGap::Full
+        },
+        a_end: scoring.terminal_gaps.a_end,
+        b_end: scoring.terminal_gaps.b_end,
+    };
+    score += segment(
+        &a_chars[start.0..],
+        &b_chars[start.1..],
+        scoring,
+        terminal_gaps,
+        &mut rows,
+    );
+
+    Ok(Alignment::new(rows, vec![], score))
+}
+
+/// segment aligns the residues between two anchors, appending them to the rows.
+fn segment(
+    a: &[char],
+    b: &[char],
+    scoring: &Scoring,
+    terminal_gaps: TerminalGaps,
+    rows: &mut [Vec<char>],
+) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        // nothing to align, one side is all gap
+        let (gap, weight) = if a.is_empty() {
+            (
+                b.len(),
+                terminal_gaps
+                    .a_start
+                    .weight()
+                    .min(terminal_gaps.a_end.weight()),
+            )
+        } else {
+            (
+                a.len(),
+                terminal_gaps
+                    .b_start
+                    .weight()
+                    .min(terminal_gaps.b_end.weight()),
+            )
+        };
+        rows[0].extend(a.iter().copied().chain(std::iter::repeat_n('-', b.len())));
+        rows[1].extend(std::iter::repeat_n('-', a.len()).chain(b.iter().copied()));
+        return (needleman_wunsch::STRATEGY.init_grid_value)(gap).0 * weight;
+    }
+
+    let alignment = align(
+        vec![a.iter().collect(), b.iter().collect()],
+        &needleman_wunsch::STRATEGY,
+        &Scoring {
+            matrix: scoring.matrix,
+            gap_opening: scoring.gap_opening,
+            gap_extension: scoring.gap_extension,
+            terminal_gaps,
+            precision: scoring.precision,
+        },
+    );
+    rows[0].extend(alignment.rows[0].iter());
+    rows[1].extend(alignment.rows[1].iter());
+    alignment.score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_align_anchored() {
+        // without the anchor the second A would align to the first
+        let alignment = align_anchored(
+            "GGAGG",
+            "GGGGA",
+            &[Anchor { a: 2, b: 4, len: 1 }],
+            &scoring(),
+        )
+        .unwrap();
+        let col = alignment.rows[1].iter().position(|c| *c == 'A').unwrap();
+        assert_eq!('A', alignment.rows[0][col]);
+        assert_eq!(2, alignment.coordinate_map().residue(0, col).unwrap());
+    }
+
+    #[test]
+    fn test_align_anchored_block() {
+        let alignment = align_anchored(
+            "ACGTACGT",
+            "ACGTTACGT",
+            &[Anchor { a: 0, b: 0, len: 4 }, Anchor { a: 4, b: 5, len: 4 }],
+            &scoring(),
+        )
+        .unwrap();
+        assert_eq!("ACGT-ACGT\nACGTTACGT", alignment.to_string());
+        assert_eq!(40f32 - 1f32, alignment.score);
+    }
+
+    #[test]
+    fn test_align_anchored_invalid() {
+        let anchors = [Anchor { a: 4, b: 4, len: 2 }, Anchor { a: 2, b: 6, len: 1 }];
+        assert!(matches!(
+            align_anchored("ACGTACGT", "ACGTACGT", &anchors, &scoring()),
+            Err(Error::InvalidAnchor { a: 2, b: 6 })
+        ));
+        assert!(
+            align_anchored("ACGT", "ACGT", &[Anchor { a: 3, b: 3, len: 2 }], &scoring()).is_err()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Adaptive banded global alignment.
+//!
+//! Only a band of cells around the expected path is filled in each row of the
+//! grid. Rather than a fixed diagonal band, the band is centered on the cell
+//! diagonal to the best cell of the row before, so it follows the path as it
+//! drifts through indels. When the best cell of a row is on the edge of the
+//! band the path may be leaving it, so the band is doubled from then on, and
+//! if the final path still runs along an edge of the band the alignment is
+//! redone with a band twice as wide. There's no bandwidth to guess: a small
+//! starting width only costs time.
+//!
+//! Long alignments can sum scores in fixed point, see
+//! [`Scoring::precision`], so they don't drift with rounding.
+
+use std::convert::Infallible;
+
+use super::{
+    fixed::{Precision, Score},
+    gotoh::{self, Affine, Row},
+    traceback::Traceback,
+    Alignment, Scoring,
+};
+
+/// Band configures the width of an adaptive band.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Band {
+    /// cells filled on each side of the band center to start with
+    pub width: usize,
+
+    /// the band isn't widened past this many cells on each side
+    pub max_width: usize,
+}
+
+impl Default for Band {
+    fn default() -> Self {
+        Band {
+            width: 16,
+            max_width: usize::MAX,
+        }
+    }
+}
+
+/// align_banded aligns two sequences globally with affine gaps in an adaptive band.
+///
+/// A gap of length L costs `gap_opening + gap_extension * (L - 1)`, terminal
+/// gaps included. The alignment is optimal if the best path stays in the
+/// band, which the widening makes likely but doesn't guarantee.
+pub fn align_banded(a: &str, b: &str, scoring: &Scoring, band: &Band) -> Alignment {
+    let max_width = band.max_width.max(1);
+    let mut width = band.width.max(1).min(max_width);
+    loop {
+        let (alignment, on_edge) = match scoring.precision {
+            Precision::Float => {
+                fill::<f32>(a.as_bytes(), b.as_bytes(), scoring, 1, width, max_width)
+            }
+            Precision::Fixed(scale) => fill::<i64>(
+                a.as_bytes(),
+                b.as_bytes(),
+                scoring,
+                scale.max(1),
+                width,
+                max_width,
+            ),
+        };
+        if !on_edge || width == max_width {
+            return alignment;
+        }
+        width = width.saturating_mul(2).min(max_width);
+    }
+}
+
+/// fill aligns the sequences in a band starting `width` cells wide, and says
+/// whether the path touched the edge of the band. Scores are summed as `S`,
+/// scaled by `scale`.
+fn fill<S: Score>(
+    a: &[u8],
+    b: &[u8],
+    scoring: &Scoring,
+    scale: u32,
+    mut width: usize,
+    max_width: usize,
+) -> (Alignment, bool) {
+    let (na, nb) = (a.len(), b.len());
+    let rules = Affine::new(scoring, scale, gotoh::matrix::<S>(a, b, scoring, scale));
+
+    // the filled columns of each row, lo..=hi
+    let mut bounds: Vec<(usize, usize)> = vec![(0, if nb == 0 { na } else { na.min(width) })];
+
+    // the band's own edges of each row, the last row is filled to the end
+    let mut edges: Vec<(usize, usize)> = vec![(0, na.min(width))];
+
+    // the traceback of each row's filled cells
+    let mut traceback: Vec<Traceback> = vec![Traceback::new(3 * (bounds[0].1 + 1))];
+    let (mut prev, mut cur) = (Row::empty(), Row::empty());
+    gotoh::fill_row(&rules, 0, bounds[0], &prev, &mut cur, &mut traceback[0]);
+    std::mem::swap(&mut prev, &mut cur);
+
+    // column of the best cell in the previous row
+    let mut best = 0;
+
+    for i in 1..=nb {
+        let (plo, phi) = bounds[i - 1];
+
+        // recenter on the cell diagonal to the best cell of the last row, but
+        // never past the last row's band so every cell can still be reached
+        let center = (best + 1).min(na);
+        let lo = center.saturating_sub(width).clamp(plo, (phi + 1).min(na));
+        let edge = (center + width).min(na).max(lo);
+        let hi = if i == nb { na } else { edge };
+        bounds.push((lo, hi));
+        edges.push((lo, edge));
+
+        let mut row = Traceback::new(3 * (hi - lo + 1));
+        gotoh::fill_row(&rules, i, (lo, hi), &prev, &mut cur, &mut row);
+
+        // widen the band if the best cell is against one of its edges
+        let (offset, _) = cur
+            .cells
+            .iter()
+            .map(|c| gotoh::best(*c).1)
+            .enumerate()
+            .fold(
+                (0, S::NEG_INFINITY),
+                |max, (o, s)| {
+                    if s > max.1 {
+                        (o, s)
+                    } else {
+                        max
+                    }
+                },
+            );
+        if (offset == 0 && lo > 0) || (offset == hi - lo && hi < na) {
+            width = width.saturating_mul(2).min(max_width);
+        }
+        best = lo + offset;
+
+        std::mem::swap(&mut prev, &mut cur);
+        traceback.push(row);
+    }
+
+    // walk back from the bottom-right corner
+    let (state, score) = gotoh::best(prev.get(na));
+    let mut on_edge = false;
+    let Ok(path) = gotoh::walk_back::<Infallible, _>((nb, na), state, |i, j, state| {
+        let (lo, hi) = edges[i];
+        on_edge |= (j <= lo && lo > 0) || (j >= hi && hi < na);
+        Ok(traceback[i].get(3 * (j - bounds[i].0) + state as usize))
+    });
+
+    (
+        Alignment::new(path.rows(a, b), vec![], score.to_f32(scale)),
+        on_edge,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align, Method},
+        matrices::NUC_4_4,
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_align_banded() {
+        let alignment = align_banded("ACGTACGTAC", "ACGTTACGTAC", &scoring(), &Band::default());
+        assert_eq!("ACG-TACGTAC\nACGTTACGTAC", alignment.to_string());
+        assert_eq!(50f32 - 10f32, alignment.score);
+        let fixed = Scoring {
+            precision: Precision::FIXED,
+            ..scoring()
+        };
+        let alignment = align_banded("ACGTACGTAC", "ACGTTACGTAC", &fixed, &Band::default());
+        assert_eq!("ACG-TACGTAC\nACGTTACGTAC", alignment.to_string());
+        assert_eq!(50f32 - 10f32, alignment.score);
+
+        let alignment = align_banded("", "ACG", &scoring(), &Band::default());
+        assert_eq!("---\nACG", alignment.to_string());
+        assert_eq!(-12f32, alignment.score);
+    }
+
+    #[test]
+    fn test_align_banded_widens() {
+        // a 40bp deletion is far outside a band of 2 cells
+        let mut state = 7u32;
+        let a: String = (0..100)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                b"ACGT"[(state >> 16) as usize % 4] as char
+            })
+            .collect();
+        let b = format!("{}{}", &a[..30], &a[70..]);
+        let alignment = align_banded(
+            &a,
+            &b,
+            &scoring(),
+            &Band {
+                width: 2,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            format!("{}{}{}", &a[..30], "-".repeat(40), &a[70..]),
+            alignment.rows[1].iter().collect::<String>()
+        );
+        assert_eq!(60f32 * 5f32 - 10f32 - 39f32, alignment.score);
+
+        // no worse than filling the whole grid
+        let full = align(
+            vec![a.clone(), b.clone()],
+            Method::NeedlemanWunsch.strategy(),
+            &scoring(),
+        );
+        assert!(alignment.score >= full.score);
+    }
+
+    #[test]
+    fn test_align_banded_fixed_point() {
+        // 1 Mb with a substitution every 1000 bases, a 3 base deletion every
+        // 10 kb, and a 2 base insertion every 25 kb
+        let mut rng = Rng::new(31);
+        let a = random_seq(1_000_000, &background::nucleotide(), &mut rng);
+        let mut b: Vec<u8> = Vec::with_capacity(a.len());
+        let mut pos = 0;
+        while pos < a.len() {
+            if pos % 10_000 == 5_000 {
+                pos += 3;
+                continue;
+            }
+            if pos % 25_000 == 12_750 {
+                b.extend(b"GG");
+            }
+            let base = a.as_bytes()[pos];
+            b.push(match pos % 1000 {
+                500 if base == b'A' => b'C',
+                500 => b'A',
+                _ => base,
+            });
+            pos += 1;
+        }
+        let b = String::from_utf8(b).unwrap();
+
+        // penalties that aren't exact in binary, on a score past 2^22
+        let scoring = Scoring {
+            gap_opening: -10.1,
+            gap_extension: -0.1,
+            ..scoring()
+        };
+        // the score of a path in tenths, summed in integers
+        let exact = |alignment: &Alignment| -> i64 {
+            let (mut score, mut gap) = (0i64, None);
+            for (x, y) in alignment.rows[0].iter().zip(alignment.rows[1].iter()) {
+                let row = if *x == '-' {
+                    Some(0)
+                } else if *y == '-' {
+                    Some(1)
+                } else {
+                    None
+                };
+                score += match row {
+                    None => 10 * NUC_4_4::MATRIX[*x as usize][*y as usize] as i64,
+                    Some(_) if row == gap => -1,
+                    Some(_) => -101,
+                };
+                gap = row;
+            }
+            score
+        };
+        let band = Band {
+            width: 16,
+            max_width: 16,
+        };
+
+        let fixed = align_banded(
+            &a,
+            &b,
+            &Scoring {
+                precision: Precision::FIXED,
+                ..scoring
+            },
+            &band,
+        );
+        let ungapped = |row: &[char]| row.iter().filter(|c| **c != '-').collect::<String>();
+        assert_eq!(a, ungapped(&fixed.rows[0]));
+        assert_eq!(b, ungapped(&fixed.rows[1]));
+        // the score is that of its path, summed exactly
+        assert_eq!((exact(&fixed) as f64 / 10f64) as f32, fixed.score);
+
+        // while in f32 the same path drifts from it
+        let float = align_banded(&a, &b, &scoring, &band);
+        assert_eq!(exact(&fixed), exact(&float));
+        assert!(
+            (float.score - fixed.score).abs() >= 1f32,
+            "{} {}",
+            float.score,
+            fixed.score
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Batch Smith-Waterman scoring for database search.
+//!
+//! Searching a database scores thousands of query/target pairs and only the
+//! best few are aligned in full, so the batch only computes local alignment
+//! scores: two rows of the grid per pair, with the pairs split across
+//! threads. With the `gpu` feature the batch is scored on the GPU instead,
+//! thousands of pairs to a dispatch, and on threads if there's no GPU.
+
+use std::thread;
+
+use super::{
+    fixed::{Precision, Score},
+    gotoh::{self, Affine, Row, DIAGONAL},
+    Scoring,
+};
+
+/// smith_waterman_batch finds the best local alignment score of each pair.
+///
+/// A gap of length L costs `gap_opening + gap_extension * (L - 1)`. Scores
+/// are in the order of the pairs.
+pub fn smith_waterman_batch<Q: AsRef<str> + Sync, T: AsRef<str> + Sync>(
+    pairs: &[(Q, T)],
+    scoring: &Scoring,
+) -> Vec<f32> {
+    #[cfg(feature = "gpu")]
+    {
+        let bytes: Vec<_> = pairs
+            .iter()
+            .map(|(q, t)| (q.as_ref().as_bytes(), t.as_ref().as_bytes()))
+            .collect();
+        if let Some(scores) = super::gpu::smith_waterman_batch(&bytes, scoring) {
+            return scores;
+        }
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = pairs.len().div_ceil(threads).max(1);
+    thread::scope(|s| {
+        let handles: Vec<_> = pairs
+            .chunks(chunk)
+            .map(|pairs| {
+                s.spawn(move || {
+                    pairs
+                        .iter()
+                        .map(|(q, t)| {
+                            let (q, t) = (q.as_ref().as_bytes(), t.as_ref().as_bytes());
+                            match scoring.precision {
+                                Precision::Float => local_score::<f32>(q, t, scoring, 1),
+                                Precision::Fixed(scale) => {
+                                    local_score::<i64>(q, t, scoring, scale.max(1))
+                                }
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    })
+}
+
+/// local_score is the best Smith-Waterman score of two sequences with affine
+/// gaps, keeping only two rows and no traceback. Scores are summed as `S`,
+/// scaled by `scale`.
+fn local_score<S: Score>(a: &[u8], b: &[u8], scoring: &Scoring, scale: u32) -> f32 {
+    let rules = Affine::new(scoring, scale, gotoh::matrix::<S>(a, b, scoring, scale)).local();
+    let (mut prev, mut cur) = (Row::empty(), Row::empty());
+    let mut best = S::ZERO;
+    for i in 0..=b.len() {
+        gotoh::fill_row(&rules, i, (0, a.len()), &prev, &mut cur, &mut ());
+        for cell in &cur.cells {
+            if cell[DIAGONAL as usize] > best {
+                best = cell[DIAGONAL as usize];
+            }
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    best.to_f32(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align, Method},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_smith_waterman_batch() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -2f32,
+            gap_extension: -2f32,
+            ..Default::default()
+        };
+        let pairs = [("GTT", "GAT"), ("TTACGTAA", "ACGT"), ("AAAA", "CCCC")];
+        let scores = smith_waterman_batch(&pairs, &scoring);
+        assert_eq!(vec![8f32, 20f32, 0f32], scores);
+
+        for ((q, t), score) in pairs.iter().zip(scores) {
+            let alignment = align(
+                vec![q.to_string(), t.to_string()],
+                Method::SmithWaterman.strategy(),
+                &scoring,
+            );
+            assert_eq!(alignment.score, score);
+        }
+    }
+}
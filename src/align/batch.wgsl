@@ -0,0 +1,86 @@
+// Smith-Waterman scores with affine gaps, one invocation per pair.
+//
+// Each invocation keeps a single row of its grid in `rows`, three scores per
+// cell: the best path ending in a pair, in a gap in a, and in a gap in b.
+// Row i is residue i of b and column j residue j of a, as in the CPU kernel.
+
+struct Params {
+    open: f32,
+    extend: f32,
+    pairs: u32,
+}
+
+struct Pair {
+    // offset and length of a and b in `residues`
+    a: u32,
+    a_len: u32,
+    b: u32,
+    b_len: u32,
+    // offset of the pair's row in `rows`, in cells
+    row: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> matrix: array<f32>;
+@group(0) @binding(2) var<storage, read> residues: array<u32>;
+@group(0) @binding(3) var<storage, read> pairs: array<Pair>;
+@group(0) @binding(4) var<storage, read_write> rows: array<f32>;
+@group(0) @binding(5) var<storage, read_write> scores: array<f32>;
+
+// below any reachable score, and safe to add penalties to
+const UNREACHABLE: f32 = -1e30;
+
+// residue k, packed four to a word
+fn residue(k: u32) -> u32 {
+    return (residues[k >> 2u] >> ((k & 3u) * 8u)) & 0xffu;
+}
+
+// cell k of `rows`
+fn cell_at(k: u32) -> vec3<f32> {
+    return vec3<f32>(rows[3u * k], rows[3u * k + 1u], rows[3u * k + 2u]);
+}
+
+// set cell k of `rows`
+fn set_cell(k: u32, cell: vec3<f32>) {
+    rows[3u * k] = cell.x;
+    rows[3u * k + 1u] = cell.y;
+    rows[3u * k + 2u] = cell.z;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.pairs {
+        return;
+    }
+    let pair = pairs[id.x];
+    let unreachable = vec3<f32>(UNREACHABLE);
+
+    // no local path ends in the first row or column
+    for (var j = 0u; j <= pair.a_len; j++) {
+        set_cell(pair.row + j, unreachable);
+    }
+
+    var best = 0.0;
+    for (var i = 1u; i <= pair.b_len; i++) {
+        let y = residue(pair.b + i - 1u);
+        var diagonal = cell_at(pair.row);
+        var left = unreachable;
+        for (var j = 1u; j <= pair.a_len; j++) {
+            let above = cell_at(pair.row + j);
+            let x = residue(pair.a + j - 1u);
+
+            // a path starts at the pair unless it's better to continue one
+            let previous = max(diagonal.x, max(diagonal.y, diagonal.z));
+            let pair_score = select(0.0, previous, previous > 0.0) + matrix[x * 128u + y];
+            let up = max(above.x + params.open, max(above.y + params.extend, above.z + params.open));
+            let gap = max(left.x + params.open, max(left.y + params.open, left.z + params.extend));
+            let cell = vec3<f32>(pair_score, up, gap);
+            best = max(best, pair_score);
+
+            set_cell(pair.row + j, cell);
+            diagonal = above;
+            left = cell;
+        }
+    }
+    scores[id.x] = best;
+}
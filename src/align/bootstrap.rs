@@ -0,0 +1,145 @@
+//! Bootstrap support for guide trees.
+//! https://doi.org/10.1111/j.1558-5646.1985.tb00420.x
+//!
+//! The columns of the alignment are resampled with replacement and a tree is
+//! built from each resampled alignment. The support of a node of the tree
+//! built from the real alignment is the percent of those trees with the same
+//! group of sequences under some node: a rough confidence that the group is
+//! real and not an accident of which columns happen to be in the alignment.
+
+use std::collections::HashSet;
+
+use crate::seq::random::Rng;
+
+use super::{DistanceMatrix, GuideTree, MSAlignment};
+
+/// BootstrapConfig configures bootstrap resampling.
+#[derive(Clone, Debug)]
+pub struct BootstrapConfig {
+    /// number of resampled alignments
+    pub replicates: usize,
+
+    /// seed of the random column sampling
+    pub seed: u64,
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        BootstrapConfig {
+            replicates: 100,
+            seed: 0,
+        }
+    }
+}
+
+impl MSAlignment {
+    /// distances are the fraction of differing residues between each pair of
+    /// rows, over the columns with a residue in both.
+    pub fn distances(&self) -> DistanceMatrix {
+        let columns: Vec<usize> = (0..self.len()).collect();
+        self.distances_of(&columns)
+    }
+
+    /// distances_of the rows over a list of columns, which may repeat.
+    fn distances_of(&self, columns: &[usize]) -> DistanceMatrix {
+        let mut distances = DistanceMatrix::new(self.ids.clone());
+        for i in 0..self.rows.len() {
+            for j in i + 1..self.rows.len() {
+                let (mut compared, mut differ) = (0, 0);
+                for col in columns {
+                    let (x, y) = (self.rows[i][*col], self.rows[j][*col]);
+                    if x != '-' && y != '-' {
+                        compared += 1;
+                        differ += usize::from(x != y);
+                    }
+                }
+                let distance = if compared == 0 {
+                    1f32
+                } else {
+                    differ as f32 / compared as f32
+                };
+                distances.set(i, j, distance);
+            }
+        }
+        distances
+    }
+
+    /// bootstrap builds a UPGMA tree from the alignment with the bootstrap
+    /// support of each internal node.
+    pub fn bootstrap(&self, config: &BootstrapConfig) -> GuideTree {
+        self.bootstrap_with_rng(config.replicates, &mut Rng::new(config.seed))
+    }
+
+    /// bootstrap_with_rng is [`bootstrap`](MSAlignment::bootstrap) with
+    /// columns sampled from a given generator, so a pipeline can draw every
+    /// random number it uses from one seed.
+    pub fn bootstrap_with_rng(&self, replicates: usize, rng: &mut Rng) -> GuideTree {
+        let mut tree = GuideTree::upgma(&self.distances());
+        if replicates == 0 {
+            return tree;
+        }
+
+        let mut counts = vec![0usize; tree.nodes.len()];
+        let clades: Vec<Vec<usize>> = (0..tree.nodes.len()).map(|i| clade(&tree, i)).collect();
+        for _ in 0..replicates {
+            let columns: Vec<usize> = (0..self.len()).map(|_| rng.below(self.len())).collect();
+            let replicate = GuideTree::upgma(&self.distances_of(&columns));
+            let found: HashSet<Vec<usize>> = (0..replicate.nodes.len())
+                .map(|i| clade(&replicate, i))
+                .collect();
+            for (count, clade) in counts.iter_mut().zip(clades.iter()) {
+                if found.contains(clade) {
+                    *count += 1;
+                }
+            }
+        }
+
+        for (node, count) in tree.nodes.iter_mut().zip(counts) {
+            if !node.is_leaf() {
+                node.support = Some(100f32 * count as f32 / replicates as f32);
+            }
+        }
+        tree
+    }
+}
+
+/// clade is the sorted list of sequences under a node.
+fn clade(tree: &GuideTree, node: usize) -> Vec<usize> {
+    let mut leaves = tree.leaves_of(node);
+    leaves.sort_unstable();
+    leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into(), "d".into()],
+            vec![
+                "ACGTACGTACGTACGTACGT".chars().collect(),
+                "ACGTACGTACGTACGTACGA".chars().collect(),
+                "TCGAACTTACGAACCTAGGT".chars().collect(),
+                "TCGAACTTACGAACCTAGGA".chars().collect(),
+            ],
+        );
+        assert_eq!(0.05, msa.distances().distance(0, 1));
+
+        let tree = msa.bootstrap(&BootstrapConfig::default());
+        let ab = tree.nodes.iter().position(|n| n.left == Some(0)).unwrap();
+        assert_eq!(
+            (Some(0), Some(1)),
+            (tree.nodes[ab].left, tree.nodes[ab].right)
+        );
+        assert!(tree.nodes[ab].support.unwrap() > 90f32);
+        assert_eq!(None, tree.nodes[0].support);
+        assert_eq!(Some(100f32), tree.nodes[tree.root()].support);
+        assert!(tree.to_newick(&msa.ids).ends_with(")100;"));
+
+        // the same seed gives the same support
+        assert_eq!(tree, msa.bootstrap(&BootstrapConfig::default()));
+        assert_eq!(tree, msa.bootstrap_with_rng(100, &mut Rng::new(0)));
+    }
+}
@@ -0,0 +1,139 @@
+//! Ukkonen's k-difference global alignment.
+//! https://doi.org/10.1016/S0019-9958(85)80046-2
+//!
+//! An alignment with at most k edits never leaves the 2k + 1 diagonals around
+//! the main one, so only those are filled, and the alignment gives up as soon
+//! as every cell in a row is over k. Checking that two sequences are nearly
+//! identical is then linear in their length rather than quadratic.
+
+use super::{traceback::Traceback, Alignment};
+
+// traceback moves
+const DIAGONAL: u8 = 0;
+const UP: u8 = 1;
+const LEFT: u8 = 2;
+
+/// align_within aligns two sequences globally if they're at most `max_edits`
+/// substitutions, insertions and deletions apart, and returns None otherwise.
+///
+/// The score of the alignment is the negative number of edits.
+pub fn align_within(a: &str, b: &str, max_edits: usize) -> Option<Alignment> {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (na, nb) = (a.len(), b.len());
+    if na.abs_diff(nb) > max_edits {
+        return None;
+    }
+
+    // cell (i, j) is at offset j + k - i of row i. No alignment has more
+    // edits than the longer sequence has residues.
+    let k = max_edits.min(na.max(nb));
+    let width = 2 * k + 1;
+    let mut traceback = Traceback::new((nb + 1) * width);
+    let over = k + 1;
+
+    let mut prev = vec![over; width];
+    for j in 0..=na.min(k) {
+        prev[j + k] = j;
+        traceback.set(j + k, LEFT);
+    }
+
+    for i in 1..=nb {
+        let mut cur = vec![over; width];
+        let (lo, hi) = (i.saturating_sub(k), (i + k).min(na));
+        for j in lo..=hi {
+            let d = j + k - i;
+            let mut best = (over, DIAGONAL);
+            if j == 0 {
+                best = (i, UP);
+            } else {
+                let edit = usize::from(a[j - 1] != b[i - 1]);
+                best = min(best, (prev[d] + edit, DIAGONAL));
+            }
+            if d + 1 < width {
+                best = min(best, (prev[d + 1] + 1, UP));
+            }
+            if d > 0 && j > lo {
+                best = min(best, (cur[d - 1] + 1, LEFT));
+            }
+            cur[d] = best.0.min(over);
+            traceback.set(i * width + d, best.1);
+        }
+
+        if cur.iter().all(|e| *e > k) {
+            return None;
+        }
+        prev = cur;
+    }
+
+    let edits = prev[na + k - nb];
+    if edits > k {
+        return None;
+    }
+
+    // walk back from the bottom-right corner
+    let (mut i, mut j) = (nb, na);
+    let mut rows: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
+    while i > 0 || j > 0 {
+        match traceback.get(i * width + j + k - i) {
+            DIAGONAL => {
+                rows[0].push(a[j - 1] as char);
+                rows[1].push(b[i - 1] as char);
+                i -= 1;
+                j -= 1;
+            }
+            UP => {
+                rows[0].push('-');
+                rows[1].push(b[i - 1] as char);
+                i -= 1;
+            }
+            _ => {
+                rows[0].push(a[j - 1] as char);
+                rows[1].push('-');
+                j -= 1;
+            }
+        }
+    }
+    for row in rows.iter_mut() {
+        row.reverse();
+    }
+
+    Some(Alignment::new(rows, vec![], -(edits as f32)))
+}
+
+/// min of two moves, preferring the first on ties.
+fn min(best: (usize, u8), other: (usize, u8)) -> (usize, u8) {
+    if other.0 < best.0 {
+        other
+    } else {
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_within() {
+        let alignment = align_within("ACGTACGTAC", "ACGTTACGAC", 2).unwrap();
+        assert_eq!("ACG-TACGTAC\nACGTTACG-AC", alignment.to_string());
+        assert_eq!(-2f32, alignment.score);
+
+        assert!(align_within("ACGTACGTAC", "ACGTTACGAC", 1).is_none());
+        assert!(align_within("ACGTACGTAC", "ACG", 5).is_none());
+        assert_eq!(0f32, align_within("ACGT", "ACGT", 0).unwrap().score);
+        assert_eq!("--\nAC", align_within("", "AC", 2).unwrap().to_string());
+
+        // and however many edits are allowed, no more are filled than needed
+        let alignment = align_within("ACGTACGTAC", "ACGTTACGAC", usize::MAX).unwrap();
+        assert_eq!(-2f32, alignment.score);
+        assert_eq!(
+            0f32,
+            align_within("ACGT", "ACGT", usize::MAX).unwrap().score
+        );
+        assert_eq!(
+            "--\nAC",
+            align_within("", "AC", usize::MAX).unwrap().to_string()
+        );
+    }
+}
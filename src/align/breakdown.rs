@@ -0,0 +1,196 @@
+//! Breakdown of an alignment's score into its parts.
+//!
+//! When tuning gap penalties it helps to see how much of a score comes from
+//! the residues and how much from opening and extending gaps, rather than
+//! the single total.
+
+use super::{Alignment, Scoring, TerminalGap};
+
+/// ScoreBreakdown splits the score of an alignment by where it comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreBreakdown {
+    /// total substitution score of columns with identical residues
+    pub matches: f32,
+
+    /// total substitution score of columns with different residues
+    pub mismatches: f32,
+
+    /// total of the gap opening penalties
+    pub gap_opening: f32,
+
+    /// total of the gap extension penalties
+    pub gap_extension: f32,
+
+    /// total of the terminal gaps scored by their length rather than opened
+    /// and extended
+    pub terminal_gaps: f32,
+}
+
+impl ScoreBreakdown {
+    /// total is the sum of all the parts.
+    pub fn total(&self) -> f32 {
+        self.matches + self.mismatches + self.gap_opening + self.gap_extension + self.terminal_gaps
+    }
+}
+
+impl Alignment {
+    /// score_breakdown rescores the first two rows of the alignment by part,
+    /// the way [`align`](super::align) scores a global alignment.
+    ///
+    /// A gap of length L is one opening and L - 1 extensions. Gaps before the
+    /// first residue of a sequence are the edges of the alignment grid, so
+    /// they cost 1 per residue times the end's terminal gap weight. Gaps after
+    /// the last residue are opened and extended if the end's gaps are full,
+    /// and otherwise cost whichever of that and 1 per residue times the
+    /// weight is less.
+    pub fn score_breakdown(&self, scoring: &Scoring) -> ScoreBreakdown {
+        let mut breakdown = ScoreBreakdown::default();
+        let (a, b) = (&self.rows[0], &self.rows[1]);
+        let columns = a.len().min(b.len());
+
+        for (k, (row, other)) in [(a, b), (b, a)].into_iter().enumerate() {
+            let first = row.iter().position(|c| *c != '-').unwrap_or(columns);
+            let last = row.iter().rposition(|c| *c != '-').map_or(0, |i| i + 1);
+            let is_a = k == 0;
+
+            let mut col = 0;
+            while col < columns {
+                if row[col] != '-' || other[col] == '-' {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < columns && row[col] == '-' && other[col] != '-' {
+                    col += 1;
+                }
+
+                let len = col - start;
+                let by_length = |end: TerminalGap| -(len as f32) * end.weight();
+                let opened = scoring.gap_opening + scoring.gap_extension * (len - 1) as f32;
+                let ends = &scoring.terminal_gaps;
+                let terminal = match (start < first, col > last, is_a) {
+                    (true, _, true) => Some(by_length(ends.a_start)),
+                    (true, _, false) => Some(by_length(ends.b_start)),
+                    (_, true, true) if ends.a_end != TerminalGap::Full => {
+                        Some(by_length(ends.a_end))
+                    }
+                    (_, true, false) if ends.b_end != TerminalGap::Full => {
+                        Some(by_length(ends.b_end))
+                    }
+                    _ => None,
+                };
+                match terminal {
+                    Some(val) if start < first || val >= opened => breakdown.terminal_gaps += val,
+                    _ => {
+                        breakdown.gap_opening += scoring.gap_opening;
+                        breakdown.gap_extension += scoring.gap_extension * (len - 1) as f32;
+                    }
+                }
+            }
+        }
+
+        for (x, y) in a.iter().zip(b.iter()) {
+            if *x == '-' || *y == '-' {
+                continue;
+            }
+            let score = scoring.matrix[*x as usize][*y as usize] as f32;
+            if x == y {
+                breakdown.matches += score;
+            } else {
+                breakdown.mismatches += score;
+            }
+        }
+
+        breakdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align, needleman_wunsch, TerminalGaps},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_score_breakdown() {
+        let alignment = Alignment::new(
+            vec![
+                "--ACGTTTACGA".chars().collect(),
+                "TTACG---ACTA".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            terminal_gaps: TerminalGaps {
+                a_start: TerminalGap::Half,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let breakdown = alignment.score_breakdown(&scoring);
+        assert_eq!(
+            ScoreBreakdown {
+                matches: 30f32,
+                mismatches: -4f32,
+                gap_opening: -10f32,
+                gap_extension: -2f32,
+                terminal_gaps: -1f32,
+            },
+            breakdown
+        );
+        assert_eq!(13f32, breakdown.total());
+    }
+
+    #[test]
+    fn test_score_breakdown_matches_align() {
+        for (a, b, terminal_gaps) in [
+            ("GATTACA", "GCATGCU", TerminalGaps::default()),
+            ("ACGTACGTTTTACGT", "ACGTACGTACGT", TerminalGaps::default()),
+            (
+                "TTACGTACGTTT",
+                "ACGTACGT",
+                TerminalGaps::all(TerminalGap::Half),
+            ),
+            (
+                "TTACGTACGTTT",
+                "ACGTACGT",
+                TerminalGaps::all(TerminalGap::Free),
+            ),
+            (
+                "ACGTACGTAAAA",
+                "CCACGTACGT",
+                TerminalGaps {
+                    a_end: TerminalGap::Half,
+                    ..Default::default()
+                },
+            ),
+        ] {
+            let scoring = Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                terminal_gaps,
+                ..Default::default()
+            };
+            let alignment = align(
+                vec![a.to_string(), b.to_string()],
+                &needleman_wunsch::STRATEGY,
+                &scoring,
+            );
+            assert_eq!(
+                alignment.score,
+                alignment.score_breakdown(&scoring).total(),
+                "{:?}",
+                alignment
+            );
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Breakpoints between a query and a reference: where a construct joins
+//! parts that don't follow each other in its design, or where a recombinant
+//! switches from one parent to the other.
+//!
+//! Junctions between parts that come from different places in the reference
+//! are between the split alignments of [`map_split`]. Junctions between
+//! parts that come from the same place, like two near-identical parents of a
+//! recombinant, are within one alignment, where its identity shifts: the
+//! [`identity_profile`](Alignment::identity_profile) windows before and after
+//! a column differ the most at the junction.
+
+use std::ops::Range;
+
+use super::{map_split, Alignment, MapConfig, Mapping};
+
+/// BreakpointConfig configures the search for breakpoints.
+#[derive(Clone, Debug)]
+pub struct BreakpointConfig {
+    /// mapping of the query to the reference
+    pub map: MapConfig,
+
+    /// columns of the identity windows on each side of a junction
+    pub window: usize,
+
+    /// least difference in percent identity between the windows on each
+    /// side of a column for it to be a junction
+    pub min_shift: f32,
+}
+
+impl Default for BreakpointConfig {
+    fn default() -> Self {
+        BreakpointConfig {
+            map: MapConfig::default(),
+            window: 100,
+            min_shift: 10f32,
+        }
+    }
+}
+
+/// BreakpointKind is how a breakpoint was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakpointKind {
+    /// between two split alignments of the query
+    Split,
+
+    /// within an alignment, where its identity shifts
+    IdentityShift,
+}
+
+/// Breakpoint is a junction in the query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+    /// the junction is just before one of these 0-based positions of the
+    /// query, more than one if the residues around it fit either side
+    pub query: Range<usize>,
+
+    /// position in the reference where the part before the junction ends
+    pub reference_before: usize,
+
+    /// position in the reference where the part after the junction starts
+    pub reference_after: usize,
+
+    pub kind: BreakpointKind,
+
+    /// 0 to 1. For a split, the least fraction of either part covered by
+    /// seeds. For an identity shift, how unlikely the difference between
+    /// the windows is by chance, from a two-proportion z-test.
+    pub confidence: f32,
+}
+
+/// breakpoints finds the junctions in a query against a reference, in order
+/// of the query.
+pub fn breakpoints(query: &str, reference: &str, config: &BreakpointConfig) -> Vec<Breakpoint> {
+    let mappings = map_split(query, reference, &config.map);
+
+    let mut breakpoints: Vec<Breakpoint> = mappings
+        .windows(2)
+        .map(|pair| {
+            let (before, after) = (&pair[0], &pair[1]);
+            let (lo, hi) = (
+                before.query.end.min(after.query.start),
+                before.query.end.max(after.query.start),
+            );
+            Breakpoint {
+                query: lo..hi + 1,
+                reference_before: before.reference.end,
+                reference_after: after.reference.start,
+                kind: BreakpointKind::Split,
+                confidence: seeded(before).min(seeded(after)),
+            }
+        })
+        .collect();
+    for mapping in mappings.iter() {
+        breakpoints.extend(identity_shifts(mapping, config));
+    }
+    breakpoints.sort_by_key(|b| b.query.start);
+    breakpoints
+}
+
+/// seeded is the fraction of a mapping's query covered by its seeds.
+fn seeded(mapping: &Mapping) -> f32 {
+    let covered: usize = mapping.anchors.iter().map(|a| a.len).sum();
+    (covered as f32 / mapping.query.len().max(1) as f32).min(1f32)
+}
+
+/// identity_shifts finds the columns of a mapping where the identity of the
+/// window after differs from that of the window before by at least the
+/// config's shift. Each run of such columns is one breakpoint, at the
+/// columns of the run with the largest difference.
+fn identity_shifts(mapping: &Mapping, config: &BreakpointConfig) -> Vec<Breakpoint> {
+    let alignment: &Alignment = &mapping.alignment;
+    let (window, len) = (config.window.max(1), alignment.rows[0].len());
+    if len < 2 * window {
+        return vec![];
+    }
+
+    let profile = alignment.identity_profile(window, 1);
+    let shifts: Vec<f32> = (window..=len - window)
+        .map(|col| (profile[col].identity - profile[col - window].identity).abs())
+        .collect();
+
+    // residues of each row before each column
+    let before = |row: &[char]| {
+        let mut counts = vec![0];
+        for c in row.iter() {
+            counts.push(counts.last().unwrap() + usize::from(*c != '-'));
+        }
+        counts
+    };
+    let (query, reference) = (before(&alignment.rows[0]), before(&alignment.rows[1]));
+
+    let mut breakpoints = vec![];
+    let mut i = 0;
+    while i < shifts.len() {
+        if shifts[i] < config.min_shift {
+            i += 1;
+            continue;
+        }
+        let end = (i..shifts.len())
+            .find(|j| shifts[*j] < config.min_shift)
+            .unwrap_or(shifts.len());
+        let peak = shifts[i..end].iter().cloned().fold(0f32, f32::max);
+        let first = (i..end).find(|j| shifts[*j] == peak).unwrap();
+        let last = (i..end).rfind(|j| shifts[*j] == peak).unwrap();
+
+        let col = first + window;
+        breakpoints.push(Breakpoint {
+            query: mapping.query.start + query[col]..mapping.query.start + query[last + window] + 1,
+            reference_before: mapping.reference.start + reference[col],
+            reference_after: mapping.reference.start + reference[col],
+            kind: BreakpointKind::IdentityShift,
+            confidence: significance(
+                profile[col - window].identity / 100f32,
+                profile[col].identity / 100f32,
+                window,
+            ),
+        });
+        i = end;
+    }
+    breakpoints
+}
+
+/// significance is 1 minus the two-sided p-value of a two-proportion z-test
+/// of the fractions of identical columns in two windows of n columns.
+fn significance(p1: f32, p2: f32, n: usize) -> f32 {
+    let pooled = (p1 + p2) / 2f32;
+    let se = (pooled * (1f32 - pooled) * 2f32 / n as f32).sqrt();
+    if se == 0f32 {
+        return if p1 == p2 { 0f32 } else { 1f32 };
+    }
+    erf((p1 - p2).abs() / se / std::f32::consts::SQRT_2)
+}
+
+/// erf approximates the error function, Abramowitz and Stegun 7.1.26.
+fn erf(x: f32) -> f32 {
+    let t = 1f32 / (1f32 + 0.3275911 * x);
+    let poly =
+        t * (0.2548296 + t * (-0.28449672 + t * (1.4214137 + t * (-1.4531521 + t * 1.0614054))));
+    1f32 - poly * (-x * x).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_breakpoints_split() {
+        let mut rng = Rng::new(12);
+        let reference = random_seq(6000, &background::nucleotide(), &mut rng);
+
+        // a construct joining 3000..5500 of the design to 500..2000
+        let query = format!("{}{}", &reference[3000..5500], &reference[500..2000]);
+        let config = BreakpointConfig {
+            map: MapConfig {
+                window: 500,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let found = breakpoints(&query, &reference, &config);
+        assert_eq!(1, found.len(), "{:?}", found);
+        let junction = &found[0];
+        assert_eq!(BreakpointKind::Split, junction.kind);
+        assert_eq!(
+            (2500..2501, 5500, 500),
+            (
+                junction.query.clone(),
+                junction.reference_before,
+                junction.reference_after
+            )
+        );
+        assert!(junction.confidence > 0.9, "{}", junction.confidence);
+    }
+
+    #[test]
+    fn test_breakpoints_identity_shift() {
+        let mut rng = Rng::new(13);
+        let reference = random_seq(3000, &background::nucleotide(), &mut rng);
+
+        // a recombinant of the reference up to 1000 and a parent 90%
+        // identical to it after
+        let mut query: Vec<u8> = reference.as_bytes()[..2000].to_vec();
+        for pos in (1005..2000).step_by(10) {
+            query[pos] = if query[pos] == b'A' { b'C' } else { b'A' };
+        }
+        let query = String::from_utf8(query).unwrap();
+        let config = BreakpointConfig {
+            map: MapConfig {
+                window: 500,
+                k: 8,
+                ..Default::default()
+            },
+            min_shift: 5f32,
+            ..Default::default()
+        };
+
+        let found = breakpoints(&query, &reference, &config);
+        assert_eq!(1, found.len(), "{:?}", found);
+        let junction = &found[0];
+        assert_eq!(BreakpointKind::IdentityShift, junction.kind);
+        assert_eq!(996..1006, junction.query);
+        assert_eq!(996, junction.reference_before);
+        assert!(junction.confidence > 0.99, "{}", junction.confidence);
+    }
+}
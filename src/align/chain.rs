@@ -0,0 +1,166 @@
+//! Chaining of anchors with sparse dynamic programming.
+//! https://doi.org/10.1016/S1570-8667(03)00009-1
+//!
+//! Seed matches between two long sequences are many and mostly noise. The
+//! best chain is the set of anchors that are in order in both sequences and
+//! cover the most residues, the backbone of a whole-genome alignment. Rather
+//! than looking back from each anchor to every other, anchors are swept by
+//! their position in a, and the best chain ending before each one in b is a
+//! range-maximum query on a Fenwick tree, so n anchors take O(n log n).
+
+use super::Anchor;
+
+/// chain is the chain of anchors, in order in both sequences and not
+/// overlapping in either, that covers the most residues.
+///
+/// Anchors of length 0 are ignored.
+pub fn chain(anchors: &[Anchor]) -> Vec<Anchor> {
+    let anchors: Vec<Anchor> = anchors.iter().filter(|a| a.len > 0).copied().collect();
+    if anchors.is_empty() {
+        return vec![];
+    }
+
+    let mut b_ends: Vec<usize> = anchors.iter().map(|a| a.b + a.len).collect();
+    b_ends.sort_unstable();
+    b_ends.dedup();
+
+    let mut by_start: Vec<usize> = (0..anchors.len()).collect();
+    by_start.sort_by_key(|i| (anchors[*i].a, anchors[*i].b));
+    let mut by_end: Vec<usize> = (0..anchors.len()).collect();
+    by_end.sort_by_key(|i| anchors[*i].a + anchors[*i].len);
+
+    let mut tree = Fenwick::new(b_ends.len());
+    let mut score = vec![0usize; anchors.len()];
+    let mut pred: Vec<Option<usize>> = vec![None; anchors.len()];
+    let mut ended = 0;
+    for i in &by_start {
+        let anchor = anchors[*i];
+
+        // anchors that end in a before this one starts can precede it
+        while ended < by_end.len() {
+            let j = by_end[ended];
+            if anchors[j].a + anchors[j].len > anchor.a {
+                break;
+            }
+            let key = b_ends.partition_point(|e| *e < anchors[j].b + anchors[j].len);
+            tree.update(key, (score[j], j));
+            ended += 1;
+        }
+
+        let before = b_ends.partition_point(|e| *e <= anchor.b);
+        let (prev_score, prev) = tree.query(before);
+        score[*i] = prev_score + anchor.len;
+        pred[*i] = prev;
+    }
+
+    let mut best = (0..anchors.len()).max_by_key(|i| (score[*i], usize::MAX - i));
+    let mut chain = Vec::new();
+    while let Some(i) = best {
+        chain.push(anchors[i]);
+        best = pred[i];
+    }
+    chain.reverse();
+    chain
+}
+
+/// Fenwick is a Fenwick tree of the best score, and its anchor, at or before
+/// each key.
+struct Fenwick(Vec<(usize, Option<usize>)>);
+
+impl Fenwick {
+    fn new(len: usize) -> Self {
+        Fenwick(vec![(0, None); len + 1])
+    }
+
+    /// update sets the score at a 0-based key if it's better.
+    fn update(&mut self, key: usize, (score, anchor): (usize, usize)) {
+        let mut k = key + 1;
+        while k < self.0.len() {
+            if score > self.0[k].0 {
+                self.0[k] = (score, Some(anchor));
+            }
+            k += k & k.wrapping_neg();
+        }
+    }
+
+    /// query is the best score of the first `len` keys.
+    fn query(&self, len: usize) -> (usize, Option<usize>) {
+        let mut best = (0, None);
+        let mut k = len;
+        while k > 0 {
+            if self.0[k].0 > best.0 {
+                best = self.0[k];
+            }
+            k -= k & k.wrapping_neg();
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seq::random::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let anchor = |a, b, len| Anchor { a, b, len };
+        let anchors = [
+            anchor(0, 0, 10),
+            anchor(5, 30, 20),
+            anchor(12, 11, 5),
+            anchor(20, 18, 8),
+            anchor(8, 8, 4),
+            anchor(40, 40, 6),
+        ];
+        assert_eq!(
+            vec![
+                anchor(0, 0, 10),
+                anchor(12, 11, 5),
+                anchor(20, 18, 8),
+                anchor(40, 40, 6)
+            ],
+            chain(&anchors)
+        );
+        assert!(chain(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_chain_matches_quadratic() {
+        let mut rng = Rng::new(7);
+        let anchors: Vec<Anchor> = (0..300)
+            .map(|_| Anchor {
+                a: rng.below(1000),
+                b: rng.below(1000),
+                len: 1 + rng.below(30),
+            })
+            .collect();
+
+        // the O(n^2) DP over anchors sorted by position in a
+        let mut sorted = anchors.clone();
+        sorted.sort_by_key(|a| (a.a, a.b));
+        let mut best = vec![0; sorted.len()];
+        for i in 0..sorted.len() {
+            best[i] = sorted[i].len
+                + (0..i)
+                    .filter(|j| {
+                        sorted[*j].a + sorted[*j].len <= sorted[i].a
+                            && sorted[*j].b + sorted[*j].len <= sorted[i].b
+                    })
+                    .map(|j| best[j])
+                    .max()
+                    .unwrap_or(0);
+        }
+
+        let chained = chain(&anchors);
+        assert_eq!(
+            *best.iter().max().unwrap(),
+            chained.iter().map(|a| a.len).sum::<usize>()
+        );
+        for pair in chained.windows(2) {
+            assert!(pair[0].a + pair[0].len <= pair[1].a);
+            assert!(pair[0].b + pair[0].len <= pair[1].b);
+        }
+    }
+}
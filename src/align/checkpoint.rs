@@ -0,0 +1,443 @@
+//! Checkpoints of a progressive multiple alignment.
+//!
+//! A checkpoint is a directory holding each finished stage as a text file:
+//!
+//! - `manifest`: the number of sequences and a fingerprint of the input and scoring
+//! - `distances`: the all-vs-all distance matrix
+//! - `tree`: the internal nodes of the guide tree
+//! - `profile_<node>`: the profile of each guide tree node not yet merged into its parent
+//!
+//! Files are written to a temporary name and renamed, so a crash never leaves
+//! a half-written stage behind. Resuming checks the fingerprint so a
+//! checkpoint isn't applied to different input.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    clustal_w::Profile,
+    guide_tree::{GuideTree, Node},
+    DistanceConfig, DistanceMatrix, Error, Result, Scoring, UnknownResidues,
+};
+
+const VERSION: &str = "seqalign checkpoint 1";
+
+/// Checkpoint directory for one progressive alignment.
+pub(super) struct Checkpoint {
+    dir: PathBuf,
+    seqs: usize,
+}
+
+impl Checkpoint {
+    /// open the checkpoint directory for the input, creating it if needed.
+    pub(super) fn open<S: AsRef<str>>(
+        dir: &Path,
+        seqs: &[S],
+        scoring: &Scoring,
+        distances: DistanceConfig,
+    ) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let checkpoint = Checkpoint {
+            dir: dir.to_path_buf(),
+            seqs: seqs.len(),
+        };
+
+        let manifest = format!(
+            "{}\n{}\n{:016x}\n",
+            VERSION,
+            seqs.len(),
+            fingerprint(seqs, scoring, distances)
+        );
+        match checkpoint.read("manifest")? {
+            Some(existing) if existing != manifest => {
+                return Err(Error::InvalidCheckpoint(checkpoint.dir.clone()))
+            }
+            Some(_) => {}
+            None => checkpoint.write("manifest", &manifest)?,
+        }
+        Ok(checkpoint)
+    }
+
+    pub(super) fn load_distances(&self) -> Result<Option<DistanceMatrix>> {
+        let Some(contents) = self.read("distances")? else {
+            return Ok(None);
+        };
+
+        let mut distances = DistanceMatrix::new((1..=self.seqs).map(|i| i.to_string()).collect());
+        let mut lines = contents.lines();
+        for i in 0..self.seqs {
+            let row = lines.next().ok_or_else(|| self.invalid())?;
+            let row = self.parse_floats(row, self.seqs)?;
+            for (j, d) in row.into_iter().enumerate() {
+                distances.set(i, j, d);
+            }
+        }
+        Ok(Some(distances))
+    }
+
+    pub(super) fn save_distances(&self, distances: &DistanceMatrix) -> Result<()> {
+        let mut contents = String::new();
+        for i in 0..distances.len() {
+            let row: Vec<String> = (0..distances.len())
+                .map(|j| distances.distance(i, j).to_string())
+                .collect();
+            writeln!(contents, "{}", row.join(" ")).unwrap();
+        }
+        self.write("distances", &contents)
+    }
+
+    pub(super) fn load_tree(&self) -> Result<Option<GuideTree>> {
+        let Some(contents) = self.read("tree")? else {
+            return Ok(None);
+        };
+
+        let mut nodes = vec![Node::leaf(); self.seqs];
+        let mut used = vec![false; self.seqs];
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split(' ').collect();
+            if fields.len() != 5 {
+                return Err(self.invalid());
+            }
+            let child = |s: &str| -> Result<usize> {
+                match s.parse::<usize>() {
+                    Ok(c) if c < nodes.len() => Ok(c),
+                    _ => Err(self.invalid()),
+                }
+            };
+            let (left, right) = (child(fields[0])?, child(fields[1])?);
+            // every node but the root has exactly one parent
+            if left == right || used[left] || used[right] {
+                return Err(self.invalid());
+            }
+            used[left] = true;
+            used[right] = true;
+            used.push(false);
+            let lens = self.parse_floats(&fields[2..].join(" "), 3)?;
+            nodes.push(Node {
+                left: Some(left),
+                right: Some(right),
+                left_branch_len: lens[0],
+                right_branch_len: lens[1],
+                height: lens[2],
+                support: None,
+            });
+        }
+        if nodes.len() != (2 * self.seqs).max(2) - 1 {
+            return Err(self.invalid());
+        }
+        Ok(Some(GuideTree { nodes }))
+    }
+
+    pub(super) fn save_tree(&self, tree: &GuideTree) -> Result<()> {
+        let mut contents = String::new();
+        for node in tree.nodes.iter().filter(|n| !n.is_leaf()) {
+            writeln!(
+                contents,
+                "{} {} {} {} {}",
+                node.left.unwrap(),
+                node.right.unwrap(),
+                node.left_branch_len,
+                node.right_branch_len,
+                node.height
+            )
+            .unwrap();
+        }
+        self.write("tree", &contents)
+    }
+
+    /// load_profiles returns the finished profiles that haven't been merged into a parent.
+    pub(super) fn load_profiles(&self, tree: &GuideTree) -> Result<Vec<(usize, Profile)>> {
+        let mut profiles: HashMap<usize, Profile> = HashMap::new();
+        for node in self.seqs..tree.nodes.len() {
+            let Some(contents) = self.read(&format!("profile_{}", node))? else {
+                continue;
+            };
+
+            let mut profile = Profile {
+                seqs: Vec::new(),
+                rows: Vec::new(),
+            };
+            for line in contents.lines() {
+                let (seq, row) = line.split_once('\t').ok_or_else(|| self.invalid())?;
+                profile.seqs.push(seq.parse().map_err(|_| self.invalid())?);
+                profile.rows.push(row.as_bytes().to_vec());
+            }
+            // a profile holds the sequences under its node, each once, in rows of one length
+            let mut seqs = profile.seqs.clone();
+            let mut leaves = tree.leaves_of(node);
+            seqs.sort_unstable();
+            leaves.sort_unstable();
+            if seqs != leaves
+                || profile
+                    .rows
+                    .iter()
+                    .any(|r| r.len() != profile.rows[0].len())
+            {
+                return Err(self.invalid());
+            }
+            profiles.insert(node, profile);
+
+            // a crash between writing a parent and removing its children leaves both
+            for child in [tree.nodes[node].left, tree.nodes[node].right]
+                .into_iter()
+                .flatten()
+            {
+                profiles.remove(&child);
+            }
+        }
+
+        let mut profiles: Vec<(usize, Profile)> = profiles.into_iter().collect();
+        profiles.sort_by_key(|(node, _)| *node);
+        Ok(profiles)
+    }
+
+    /// save_profile of a node and remove those of the children merged into it.
+    pub(super) fn save_profile(
+        &self,
+        node: usize,
+        profile: &Profile,
+        children: (usize, usize),
+    ) -> Result<()> {
+        let mut contents = String::new();
+        for (seq, row) in profile.seqs.iter().zip(profile.rows.iter()) {
+            writeln!(contents, "{}\t{}", seq, String::from_utf8_lossy(row)).unwrap();
+        }
+        self.write(&format!("profile_{}", node), &contents)?;
+
+        for child in [children.0, children.1] {
+            let path = self.dir.join(format!("profile_{}", child));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        let path = self.dir.join(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(path)?))
+    }
+
+    fn write(&self, name: &str, contents: &str) -> Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp, contents)?;
+        fs::rename(tmp, self.dir.join(name))?;
+        Ok(())
+    }
+
+    fn parse_floats(&self, line: &str, n: usize) -> Result<Vec<f32>> {
+        let floats: Vec<f32> = line
+            .split(' ')
+            .map(|f| f.parse::<f32>().map_err(|_| self.invalid()))
+            .collect::<Result<_>>()?;
+        if floats.len() != n {
+            return Err(self.invalid());
+        }
+        Ok(floats)
+    }
+
+    fn invalid(&self) -> Error {
+        Error::InvalidCheckpoint(self.dir.clone())
+    }
+}
+
+/// fingerprint is an FNV-1a hash of the sequences and scoring.
+fn fingerprint<S: AsRef<str>>(seqs: &[S], scoring: &Scoring, distances: DistanceConfig) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut add = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    for seq in seqs {
+        add(seq.as_ref().as_bytes());
+        add(&[0xff]);
+    }
+    add(&scoring.gap_opening.to_le_bytes());
+    add(&scoring.gap_extension.to_le_bytes());
+    for row in scoring.matrix.iter() {
+        for v in row.iter() {
+            add(&v.to_le_bytes());
+        }
+    }
+    // only when changed from the default, so older checkpoints still match
+    if distances.unknown == UnknownResidues::Ignore {
+        add(b"ignore unknown");
+    }
+    if distances.exclude_terminal_gaps {
+        add(b"exclude terminal gaps");
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_multiple, ProgressiveConfig},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("checkpoint");
+        let seqs = ["ACGTACGTAC", "ACGTCGTAC", "ACGTACGTAC", "TTACGTACGTAC"];
+        let config = ProgressiveConfig {
+            checkpoint: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        let expected = align_multiple(&seqs, &scoring(), &ProgressiveConfig::default()).unwrap();
+        let msa = align_multiple(&seqs, &scoring(), &config).unwrap();
+        assert_eq!(expected, msa);
+
+        // only the root profile is left
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(vec!["distances", "manifest", "profile_6", "tree"], files);
+
+        // resuming loads every stage
+        assert_eq!(
+            expected,
+            align_multiple(&seqs, &scoring(), &config).unwrap()
+        );
+
+        // resuming from the tree realigns the profiles
+        fs::remove_file(dir.join("profile_6")).unwrap();
+        assert_eq!(
+            expected,
+            align_multiple(&seqs, &scoring(), &config).unwrap()
+        );
+        assert!(dir.join("profile_6").exists());
+
+        // a crash after writing the root but before removing a child leaves both
+        let checkpoint =
+            Checkpoint::open(&dir, &seqs, &scoring(), DistanceConfig::default()).unwrap();
+        let tree = checkpoint.load_tree().unwrap().unwrap();
+        let child = tree.nodes[6]
+            .left
+            .unwrap()
+            .max(tree.nodes[6].right.unwrap());
+        let leaves = tree.leaves_of(child);
+        let rows: String = fs::read_to_string(dir.join("profile_6"))
+            .unwrap()
+            .lines()
+            .filter(|l| leaves.contains(&l.split('\t').next().unwrap().parse().unwrap()))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        fs::write(dir.join(format!("profile_{}", child)), rows).unwrap();
+        let profiles = checkpoint.load_profiles(&tree).unwrap();
+        assert_eq!(
+            vec![6],
+            profiles.iter().map(|(n, _)| *n).collect::<Vec<_>>()
+        );
+
+        // and a different input is rejected
+        assert!(matches!(
+            align_multiple(&seqs[..3], &scoring(), &config),
+            Err(Error::InvalidCheckpoint(_))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_corrupt_tree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("checkpoint");
+        let seqs = ["ACGT", "ACGA", "TTTT"];
+        let checkpoint =
+            Checkpoint::open(&dir, &seqs, &scoring(), DistanceConfig::default()).unwrap();
+
+        for tree in [
+            // a child used twice
+            "0 0 1 1 1\n3 2 1 1 2\n",
+            "0 1 1 1 1\n3 1 1 1 2\n",
+            // a leaf left out, so node 3 is never merged
+            "0 1 1 1 1\n0 3 1 1 2\n",
+            // a child after its parent
+            "0 4 1 1 1\n1 2 1 1 2\n",
+        ] {
+            fs::write(dir.join("tree"), tree).unwrap();
+            assert!(
+                matches!(checkpoint.load_tree(), Err(Error::InvalidCheckpoint(_))),
+                "{:?}",
+                tree
+            );
+        }
+
+        fs::write(dir.join("tree"), "0 1 1 1 1\n3 2 1 1 2\n").unwrap();
+        assert!(checkpoint.load_tree().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_corrupt_profiles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("checkpoint");
+        let seqs = ["ACGT", "ACGA", "TTTT"];
+        let checkpoint =
+            Checkpoint::open(&dir, &seqs, &scoring(), DistanceConfig::default()).unwrap();
+        fs::write(dir.join("tree"), "0 1 1 1 1\n3 2 1 1 2\n").unwrap();
+        let tree = checkpoint.load_tree().unwrap().unwrap();
+
+        for profile in [
+            // a sequence index past the input
+            "0\tACGT\n5\tACGA\n",
+            // a sequence that isn't under the node
+            "0\tACGT\n2\tTTTT\n",
+            // a sequence twice
+            "0\tACGT\n0\tACGT\n",
+            // rows of different lengths
+            "0\tACGT\n1\tACG\n",
+        ] {
+            fs::write(dir.join("profile_3"), profile).unwrap();
+            assert!(
+                matches!(
+                    checkpoint.load_profiles(&tree),
+                    Err(Error::InvalidCheckpoint(_))
+                ),
+                "{:?}",
+                profile
+            );
+        }
+
+        fs::write(dir.join("profile_3"), "1\tACGA\n0\tACGT\n").unwrap();
+        assert_eq!(1, checkpoint.load_profiles(&tree).unwrap().len());
+    }
+
+    #[test]
+    fn test_checkpoint_distances_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("checkpoint");
+        let seqs = ["ACGT", "ACGA", "TTTT"];
+
+        let checkpoint =
+            Checkpoint::open(&dir, &seqs, &scoring(), DistanceConfig::default()).unwrap();
+        assert_eq!(None, checkpoint.load_distances().unwrap());
+
+        let distances = crate::align::identity_matrix(&seqs, &scoring());
+        checkpoint.save_distances(&distances).unwrap();
+        assert_eq!(Some(distances), checkpoint.load_distances().unwrap());
+    }
+}
@@ -0,0 +1,212 @@
+//! Clipping: the residues at the ends of each sequence outside an alignment.
+//!
+//! A local alignment aligns only a region of each sequence, like the good
+//! middle of a read that wasn't quality-trimmed, and a semi-global one leaves
+//! the overhangs of each end unaligned. The clips are those unaligned ends,
+//! which SAM writes as soft or hard clips and PAF as the start and end of the
+//! query. Both kinds of alignment are [`Clipped`], which is what those
+//! writers take.
+
+use std::ops::Range;
+
+use super::{Alignment, LocalAlignment};
+
+/// Clips are the residues left unaligned at each end of both sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clips {
+    /// residues of a before the alignment
+    pub a_start: usize,
+
+    /// residues of a after the alignment
+    pub a_end: usize,
+
+    /// residues of b before the alignment
+    pub b_start: usize,
+
+    /// residues of b after the alignment
+    pub b_end: usize,
+}
+
+impl LocalAlignment {
+    /// clips are the residues of each sequence outside the local alignment.
+    pub fn clips(&self) -> Clips {
+        Clips {
+            a_start: self.a.start,
+            a_end: self.a_len - self.a.end,
+            b_start: self.b.start,
+            b_end: self.b_len - self.b.end,
+        }
+    }
+
+    /// cigar is the CIGAR of the local alignment, with a as the query and b
+    /// as the reference: `M` for aligned residues, `I` for residues only in a
+    /// and `D` for those only in b. The clips aren't in it.
+    pub fn cigar(&self) -> String {
+        self.aligned_cigar()
+    }
+}
+
+/// Clipped is an alignment of a region of a over a region of b, with the
+/// residues outside them clipped.
+pub trait Clipped {
+    /// clips are the residues of each sequence outside the aligned regions.
+    fn clips(&self) -> Clips;
+
+    /// aligned are the rows of a over b in the aligned regions.
+    fn aligned(&self) -> [&[char]; 2];
+
+    /// alignment is the whole alignment, for its score and metadata.
+    fn alignment(&self) -> &Alignment;
+
+    /// is_unaligned is whether no residue of a is aligned to one of b.
+    fn is_unaligned(&self) -> bool {
+        self.aligned()[0].is_empty()
+    }
+
+    /// aligned_len is the number of residues of a and of b in the aligned
+    /// regions.
+    fn aligned_len(&self) -> (usize, usize) {
+        let residues = |row: &[char]| row.iter().filter(|c| **c != '-').count();
+        let [a, b] = self.aligned();
+        (residues(a), residues(b))
+    }
+
+    /// aligned_cigar is the CIGAR of the aligned regions, as in
+    /// [`LocalAlignment::cigar`].
+    fn aligned_cigar(&self) -> String {
+        let [a, b] = self.aligned();
+        cigar_of(a.iter().zip(b.iter()).filter_map(|pair| match pair {
+            ('-', '-') => None,
+            (_, '-') => Some('I'),
+            ('-', _) => Some('D'),
+            _ => Some('M'),
+        }))
+    }
+}
+
+impl Clipped for LocalAlignment {
+    fn clips(&self) -> Clips {
+        LocalAlignment::clips(self)
+    }
+
+    fn aligned(&self) -> [&[char]; 2] {
+        [&self.alignment.rows[0], &self.alignment.rows[1]]
+    }
+
+    fn alignment(&self) -> &Alignment {
+        &self.alignment
+    }
+}
+
+impl Clipped for Alignment {
+    fn clips(&self) -> Clips {
+        Alignment::clips(self)
+    }
+
+    fn aligned(&self) -> [&[char]; 2] {
+        let cols = self.aligned_columns();
+        [&self.rows[0][cols.clone()], &self.rows[1][cols]]
+    }
+
+    fn alignment(&self) -> &Alignment {
+        self
+    }
+}
+
+/// cigar_of run-length encodes CIGAR operations.
+pub(super) fn cigar_of<I: IntoIterator<Item = char>>(ops: I) -> String {
+    let mut cigar = String::new();
+    let mut run: Option<(char, usize)> = None;
+    for op in ops {
+        run = match run {
+            Some((last, len)) if last == op => Some((last, len + 1)),
+            Some((last, len)) => {
+                cigar.push_str(&format!("{}{}", len, last));
+                Some((op, 1))
+            }
+            None => Some((op, 1)),
+        };
+    }
+    if let Some((last, len)) = run {
+        cigar.push_str(&format!("{}{}", len, last));
+    }
+    cigar
+}
+
+impl Alignment {
+    /// clips of a semi-global alignment are the residues of each row before
+    /// the first column, and after the last, with residues in both rows.
+    ///
+    /// A row with no such column is clipped whole, at its start.
+    pub fn clips(&self) -> Clips {
+        let cols = self.rows.first().map_or(0, |r| r.len());
+        let aligned = self.aligned_columns();
+        let residues = |row: usize, cols: Range<usize>| {
+            self.rows[row][cols].iter().filter(|c| **c != '-').count()
+        };
+
+        Clips {
+            a_start: residues(0, 0..aligned.start),
+            a_end: residues(0, aligned.end..cols),
+            b_start: residues(1, 0..aligned.start),
+            b_end: residues(1, aligned.end..cols),
+        }
+    }
+
+    /// aligned_columns are the columns from the first with residues in both
+    /// rows to the last, empty at the end of the rows if there's none.
+    fn aligned_columns(&self) -> Range<usize> {
+        let aligned = |col: usize| self.rows.iter().all(|r| r[col] != '-');
+        let cols = self.rows.first().map_or(0, |r| r.len());
+        match (0..cols).find(|c| aligned(*c)) {
+            Some(first) => first..(first..cols).rfind(|c| aligned(*c)).unwrap() + 1,
+            None => cols..cols,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::align_local, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_clips() {
+        let scoring = crate::align::Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let local = align_local("CCCCACGTACGTCCCCC", "GGACGTTACGTGG", &scoring);
+        assert_eq!(
+            Clips {
+                a_start: 4,
+                a_end: 5,
+                b_start: 2,
+                b_end: 2,
+            },
+            local.clips()
+        );
+        assert_eq!("3M1D5M", local.cigar());
+
+        let overlap = Alignment::new(
+            vec![
+                "TTACGT-AC---".chars().collect(),
+                "--ACGTTACGGG".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+        assert_eq!(
+            Clips {
+                a_start: 2,
+                a_end: 0,
+                b_start: 0,
+                b_end: 3,
+            },
+            overlap.clips()
+        );
+    }
+}
@@ -3,230 +3,715 @@
 //!
 //! unweighted pair group method with arithmetic mean
 //! https://en.wikipedia.org/wiki/UPGMA
+//!
+//! Progressive multiple alignment: the sequences are compared all-vs-all, a
+//! guide tree is built from the distances, and then partial alignments
+//! (profiles) are aligned to one another from the leaves to the root of the
+//! tree. Gaps in a profile are never removed once they're placed. Both the
+//! comparisons and the profiles of independent subtrees are spread across
+//! threads.
+//!
+//! Every stage can be checkpointed to a directory so that a long alignment
+//! picks up where it left off after a crash rather than starting over.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::seq::SeqRecord;
+
+use super::{
+    checkpoint::Checkpoint,
+    distance_matrix::identity_matrix_with_threads,
+    gotoh::{self, Affine, DIAGONAL, UP},
+    scheduler::align_tree,
+    DistanceConfig, Error, GuideTree, MSAlignment, Merge, Result, RunReport, Scoring,
+};
+
+/// OutputOrder is the order of the rows of a multiple alignment, like
+/// ClustalW's `-OUTORDER`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// the order of the input sequences
+    #[default]
+    Input,
+
+    /// the order of the leaves of the guide tree, so similar sequences are together
+    GuideTree,
+
+    /// the first sequence, then the rest from most to least identical to it
+    Similarity,
+}
+
+/// ProgressiveConfig configures a progressive multiple alignment.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressiveConfig {
+    /// directory to checkpoint to and resume from, None to not checkpoint
+    pub checkpoint: Option<PathBuf>,
+
+    /// order of the rows of the alignment
+    pub order: OutputOrder,
+
+    /// most threads to compare sequences and align profiles on, 0 for one per
+    /// core. The alignment is the same, byte for byte, whatever this is.
+    pub threads: usize,
 
-use std::collections::{HashMap, HashSet};
+    /// align the guide tree a level at a time, so checkpoints are written in
+    /// the same order and the same error is returned on any number of
+    /// threads. It's slower, since each level waits on its slowest profile.
+    pub deterministic: bool,
+
+    /// what's done with sequences identical to an earlier one
+    pub duplicates: Duplicates,
+
+    /// what's done with sequences without residues
+    pub empty: EmptySequences,
+
+    /// columns the distances the guide tree is built from are counted over
+    pub distances: DistanceConfig,
+}
+
+/// Duplicates is what's done with a sequence identical to an earlier one in
+/// a multiple alignment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Duplicates {
+    /// aligned like any other sequence
+    #[default]
+    Align,
+
+    /// left out of the alignment, then given the row of its first copy, next
+    /// to it. The copies don't weigh down the guide tree or cost any
+    /// comparisons.
+    Collapse,
+}
 
-use super::{align, needleman_wunsch, Alignment};
-use crate::matrices::BLOSUM62;
+/// EmptySequences is what's done with a sequence without residues in a
+/// multiple alignment, which has no distance to the others to build the
+/// guide tree with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptySequences {
+    /// aligned like any other sequence, as a row of gaps
+    #[default]
+    Align,
 
-type Distances = HashMap<(usize, usize), f32>;
+    /// fail with [`Error::EmptySequence`]
+    Error,
 
-#[derive(Clone)]
-struct Node {
-    index: usize,
+    /// leave it out of the alignment, and list it as skipped in the report
+    Skip,
+}
 
-    /// either seq is set OR this is truly a node
-    seq: Option<String>,
+/// Profile is a partial alignment of some of the input sequences.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Profile {
+    /// input index of the sequence in each row
+    pub(super) seqs: Vec<usize>,
 
-    /// or left and right are set
-    left: Option<Box<Node>>,
-    left_branch_len: f32,
-    right: Option<Box<Node>>,
-    right_branch_len: f32,
+    /// the gapped rows, all the same length
+    pub(super) rows: Vec<Vec<u8>>,
 }
 
-impl Node {
-    fn from_seq(index: usize, s: String) -> Node {
-        Node {
-            index,
-            seq: Some(s),
-            left: None,
-            left_branch_len: 0f32,
-            right: None,
-            right_branch_len: 0f32,
+impl Profile {
+    /// from_seq creates a profile of a single ungapped sequence.
+    pub(super) fn from_seq(index: usize, seq: &str) -> Self {
+        Profile {
+            seqs: vec![index],
+            rows: vec![seq.as_bytes().to_vec()],
         }
     }
 
-    fn from_nodes(index: usize, left: &Node, right: &Node, dist: f32) -> Node {
-        Node {
-            index,
-            seq: None,
-            left: Some(Box::new(left.clone())),
-            left_branch_len: (dist / 2f32) - left.left_branch_len,
-            right: Some(Box::new(right.clone())),
-            right_branch_len: (dist / 2f32) - right.right_branch_len,
-        }
+    /// len is the number of columns in the profile.
+    pub(super) fn len(&self) -> usize {
+        self.rows.first().map_or(0, |r| r.len())
     }
 
-    fn is_leaf(&self) -> bool {
-        self.seq.is_some()
+    /// counts of each residue in each column.
+    fn counts(&self) -> Vec<Vec<(u8, f32)>> {
+        (0..self.len())
+            .map(|col| {
+                let mut counts: Vec<(u8, f32)> = Vec::new();
+                for row in self.rows.iter().filter(|r| r[col] != b'-') {
+                    match counts.iter_mut().find(|(c, _)| *c == row[col]) {
+                        Some((_, n)) => *n += 1f32,
+                        None => counts.push((row[col], 1f32)),
+                    }
+                }
+                counts
+            })
+            .collect()
     }
 }
 
-/// upgma
+/// align_multiple aligns any number of sequences progressively.
+///
+/// Rows are named by the 1-based index of their sequence; use
+/// [`align_records`] to name them by record ID instead. They're in input
+/// order unless `config.order` says otherwise.
+pub fn align_multiple<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<MSAlignment> {
+    let names = (1..=seqs.len()).map(|i| i.to_string()).collect();
+    Ok(progressive(seqs, names, scoring, config)?.0)
+}
+
+/// align_records aligns sequence records progressively, naming each row by
+/// its record's ID.
+pub fn align_records(
+    records: &[SeqRecord],
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<MSAlignment> {
+    let names = records.iter().map(|r| r.id.clone()).collect();
+    Ok(progressive(records, names, scoring, config)?.0)
+}
+
+/// align_multiple_with_report is [`align_multiple`] with a report of the run.
+pub fn align_multiple_with_report<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<(MSAlignment, RunReport)> {
+    let names = (1..=seqs.len()).map(|i| i.to_string()).collect();
+    let (msa, report) = progressive(seqs, names, scoring, config)?;
+    Ok(finish_report(msa, report, scoring))
+}
+
+/// align_records_with_report is [`align_records`] with a report of the run.
+pub fn align_records_with_report(
+    records: &[SeqRecord],
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<(MSAlignment, RunReport)> {
+    let names = records.iter().map(|r| r.id.clone()).collect();
+    let (msa, report) = progressive(records, names, scoring, config)?;
+    Ok(finish_report(msa, report, scoring))
+}
+
+/// upgma is the guide tree the sequences are aligned along, clustered by the
+/// distances of their pairwise alignments.
 ///
-/// Put each Seq in its own Node (by itself)
-/// Maintain a clusters Vec that holds all remaining clusters
-///   - Each sequence is initially stuck in its own cluster
-/// Maintain a distances HashMap that maps pairs of clusters to their distance
+/// Each sequence starts in a cluster by itself, then the two closest clusters
+/// are merged until there's only one, the root. The distance of a merged
+/// cluster to another is the mean of its sequences' distances to it.
+pub fn upgma<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> GuideTree {
+    let distances = identity_matrix_with_threads(seqs, scoring, config.distances, config.threads);
+    GuideTree::upgma(&distances)
+}
+
+/// finish_report scores the finished alignment for its report.
+fn finish_report(
+    msa: MSAlignment,
+    mut report: RunReport,
+    scoring: &Scoring,
+) -> (MSAlignment, RunReport) {
+    report.sum_of_pairs = msa.sum_of_pairs(scoring);
+    (msa, report)
+}
+
+/// progressive aligns the sequences, naming each row, and reports the run,
+/// all but its final score.
 ///
-/// On each iteration:
-///  - Take the cluster in distances with the minimum value
-///  - Merge the two clusters in that pair
-///  - Remove the pair from distances
-fn upgma(seqs: &mut [String]) -> Vec<Node> {
-    let mut clusters: Vec<Node> = seqs
+/// Empty sequences are skipped, and duplicates collapsed, here if the config
+/// says to, so the stages only see the distinct sequences that are aligned.
+fn progressive<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    names: Vec<String>,
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<(MSAlignment, RunReport)> {
+    let mut skipped = vec![];
+    // input index of each distinct sequence, and of its later copies
+    let mut distinct: Vec<(usize, Vec<usize>)> = vec![];
+    // index in distinct of each sequence, to collapse its copies into
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (i, seq) in seqs.iter().enumerate() {
+        let seq = seq.as_ref();
+        if seq.is_empty() {
+            match config.empty {
+                EmptySequences::Align => {}
+                EmptySequences::Error => return Err(Error::EmptySequence(names[i].clone())),
+                EmptySequences::Skip => {
+                    skipped.push(names[i].clone());
+                    continue;
+                }
+            }
+        }
+        if config.duplicates == Duplicates::Collapse {
+            if let Some(first) = seen.get(seq) {
+                distinct[*first].1.push(i);
+                continue;
+            }
+            seen.insert(seq, distinct.len());
+        }
+        distinct.push((i, vec![]));
+    }
+
+    let unique: Vec<&str> = distinct.iter().map(|(i, _)| seqs[*i].as_ref()).collect();
+    let unique_names: Vec<String> = distinct.iter().map(|(i, _)| names[*i].clone()).collect();
+    let (order, rows, mut report) = align_distinct(&unique, unique_names, scoring, config)?;
+
+    // the copies are next to their first, or in input order
+    let mut placed: Vec<(usize, Vec<char>)> = vec![];
+    for (u, row) in order.into_iter().zip(rows) {
+        let (first, copies) = &distinct[u];
+        let len = placed.len();
+        placed.extend(copies.iter().map(|c| (*c, row.clone())));
+        placed.push((*first, row));
+        placed[len..].rotate_right(1);
+    }
+    if config.order == OutputOrder::Input {
+        placed.sort_by_key(|(i, _)| *i);
+    }
+
+    report.skipped = skipped;
+    report.collapsed = distinct
+        .iter()
+        .flat_map(|(first, copies)| {
+            copies
+                .iter()
+                .map(|c| (names[*c].clone(), names[*first].clone()))
+        })
+        .collect();
+    report.collapsed.sort();
+    let (ids, rows) = placed
+        .into_iter()
+        .map(|(i, row)| (names[i].clone(), row))
+        .unzip();
+    Ok((MSAlignment::new(ids, rows), report))
+}
+
+/// align_distinct aligns sequences with residues, and returns the index of
+/// the sequence of each row, the rows in output order and the report of the
+/// run.
+fn align_distinct(
+    seqs: &[&str],
+    names: Vec<String>,
+    scoring: &Scoring,
+    config: &ProgressiveConfig,
+) -> Result<(Vec<usize>, Vec<Vec<char>>, RunReport)> {
+    if seqs.is_empty() {
+        return Ok((vec![], vec![], RunReport::default()));
+    }
+
+    let checkpoint = match &config.checkpoint {
+        Some(dir) => Some(Checkpoint::open(dir, seqs, scoring, config.distances)?),
+        None => None,
+    };
+
+    // all-vs-all distances
+    let mut pairwise_time = Duration::ZERO;
+    let distances = match checkpoint
+        .as_ref()
+        .map(|c| c.load_distances())
+        .transpose()?
+    {
+        Some(Some(distances)) => distances,
+        _ => {
+            let start = Instant::now();
+            let distances =
+                identity_matrix_with_threads(seqs, scoring, config.distances, config.threads);
+            pairwise_time = start.elapsed();
+            if let Some(c) = &checkpoint {
+                c.save_distances(&distances)?;
+            }
+            distances
+        }
+    };
+
+    // the guide tree
+    let tree = match checkpoint.as_ref().map(|c| c.load_tree()).transpose()? {
+        Some(Some(tree)) => tree,
+        _ => {
+            let tree = GuideTree::upgma(&distances);
+            if let Some(c) = &checkpoint {
+                c.save_tree(&tree)?;
+            }
+            tree
+        }
+    };
+
+    // profiles from the leaves to the root, skipping those already done
+    let mut profiles: Vec<Option<Profile>> = seqs
         .iter()
         .enumerate()
-        .map(|(i, s)| Node::from_seq(i, s.to_string()))
+        .map(|(i, s)| Some(Profile::from_seq(i, s)))
+        .chain((seqs.len()..tree.nodes.len()).map(|_| None))
         .collect();
-    let mut unpaired: HashSet<usize> = (0..clusters.len()).collect(); // unpaired seqs
-
-    // Initialize distances
-    let mut distances: Distances = HashMap::new();
-    for i in 0..clusters.len() {
-        for j in i + 1..clusters.len() {
-            let alignment = align(
-                vec![seqs[i].clone(), seqs[j].clone()],
-                &needleman_wunsch::STRATEGY,
-                &super::Scoring {
-                    matrix: BLOSUM62::MATRIX,
-                    gap_opening: -1f32,
-                    gap_extension: -0.5f32,
-                },
-            );
-
-            distances.insert((i, j), alignment.distance);
+    let mut done = vec![false; tree.nodes.len()];
+    if let Some(c) = &checkpoint {
+        for (node, profile) in c.load_profiles(&tree)? {
+            mark_done(&tree, node, &mut done);
+            profiles[node] = Some(profile);
         }
     }
 
-    while !distances.is_empty() {
-        // find minimum distance in distances
-        let mut min = f32::MAX;
-        let mut min_pair: (usize, usize) = (0, 0);
-        for ((i, j), d) in distances.iter() {
-            if *d < min {
-                min = *d;
-                min_pair = (*i, *j);
+    let start = Instant::now();
+    let merges = Mutex::new(vec![]);
+    let root = align_tree(
+        &tree,
+        profiles,
+        &done,
+        scoring,
+        config.threads,
+        config.deterministic,
+        |node, merged, (left, right), score| {
+            merges.lock().unwrap().push(Merge {
+                node,
+                left,
+                right,
+                rows: merged.rows.len(),
+                columns: merged.len(),
+                score,
+            });
+            match &checkpoint {
+                Some(c) => c.save_profile(node, merged, (left, right)),
+                None => Ok(()),
             }
+        },
+    )?;
+    let progressive_time = start.elapsed();
+    let mut merges = merges.into_inner().unwrap();
+    merges.sort_by_key(|m| m.node);
+
+    let mut rows: Vec<(usize, Vec<char>)> = root
+        .seqs
+        .into_iter()
+        .zip(root.rows)
+        .map(|(i, row)| (i, row.into_iter().map(char::from).collect()))
+        .collect();
+    rows.sort_by_key(|(i, _)| *i);
+
+    let order: Vec<usize> = match config.order {
+        OutputOrder::Input => (0..seqs.len()).collect(),
+        OutputOrder::GuideTree => tree.leaves_of(tree.root()),
+        OutputOrder::Similarity => {
+            let mut order: Vec<usize> = (1..seqs.len()).collect();
+            order.sort_by(|i, j| {
+                distances
+                    .distance(0, *i)
+                    .total_cmp(&distances.distance(0, *j))
+            });
+            order.insert(0, 0);
+            order
         }
+    };
+    let mut rows: Vec<Option<Vec<char>>> = rows.into_iter().map(|(_, row)| Some(row)).collect();
+    let rows = order.iter().map(|i| rows[*i].take().unwrap()).collect();
+
+    let report = RunReport {
+        pairwise_time,
+        tree_method: "UPGMA",
+        tree: tree.to_newick(&names),
+        ids: names,
+        weights: tree.sequence_weights(),
+        merges,
+        progressive_time,
+        ..Default::default()
+    };
+    Ok((order, rows, report))
+}
 
-        // create a new cluster from the two in the minimum distance pair
-        let left = &clusters[min_pair.0].clone();
-        let right = &clusters[min_pair.1].clone();
-        clusters.push(Node::from_nodes(clusters.len(), left, right, min));
-
-        // remove the two clusters from unpaired
-        unpaired.remove(&min_pair.0);
-        unpaired.remove(&min_pair.1);
-
-        // updated distances
-        distances = new_distances(
-            distances,
-            &unpaired,
-            min_pair.0,
-            min_pair.1,
-            clusters.len() - 1,
-        );
+/// mark_done marks a node and everything under it as aligned.
+fn mark_done(tree: &GuideTree, node: usize, done: &mut [bool]) {
+    let mut stack = vec![node];
+    while let Some(i) = stack.pop() {
+        done[i] = true;
+        stack.extend(tree.nodes[i].left);
+        stack.extend(tree.nodes[i].right);
     }
-
-    clusters
 }
 
-/// new_distances removes distances from the right/left clustered sequences and
-/// returns a new Distances map with updated distances to the new cluster.
-fn new_distances(
-    distances_old: Distances,
-    unpaired: &HashSet<usize>,
-    left: usize,
-    right: usize,
-    new: usize,
-) -> Distances {
-    // create a new distances map using the newly created cluster
-    let mut distances_new: Distances = HashMap::new();
+/// align_profiles aligns two profiles globally with affine gaps, and returns
+/// the score of their alignment with it.
+pub(super) fn align_profiles(a: &Profile, b: &Profile, scoring: &Scoring) -> (Profile, f32) {
+    let (columns, score) = profile_columns_scored(a, b, scoring);
 
-    // keep all unrelated distances
-    distances_new.extend(
-        distances_old
+    let rows_a = a.rows.iter().map(|row| {
+        columns
             .iter()
-            .filter(|((i, j), _)| *i != left && *j != left && *i != right && *j != right),
-    );
-
-    // update the distances of all unpaired clusters to the new cluster
-    for unpaired_cluster in unpaired.iter() {
-        let left_key: (usize, usize) = (*unpaired_cluster.min(&left), *unpaired_cluster.max(&left));
-        let right_key: (usize, usize) =
-            (*unpaired_cluster.min(&right), *unpaired_cluster.max(&right));
-
-        distances_new.insert(
-            (*unpaired_cluster, new),
-            (distances_old[&left_key] + distances_old[&right_key]) / 2f32,
-        );
-    }
+            .map(|(i, _)| i.map_or(b'-', |i| row[i]))
+            .collect()
+    });
+    let rows_b = b.rows.iter().map(|row| {
+        columns
+            .iter()
+            .map(|(_, j)| j.map_or(b'-', |j| row[j]))
+            .collect()
+    });
+
+    let profile = Profile {
+        seqs: a.seqs.iter().chain(b.seqs.iter()).copied().collect(),
+        rows: rows_a.chain(rows_b).collect(),
+    };
+    (profile, score)
+}
+
+/// profile_columns finds the columns of the alignment of two profiles, as
+/// pairs of the column in each profile (None for a new gap column).
+///
+/// Columns are scored by the average substitution score of every pair of
+/// residues between them. A gap of length L costs `gap_opening + gap_extension * (L - 1)`.
+pub(super) fn profile_columns(
+    a: &Profile,
+    b: &Profile,
+    scoring: &Scoring,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    profile_columns_scored(a, b, scoring).0
+}
 
-    distances_new
-}
-
-impl Alignment {
-    // align combines this alignment with another one. Gaps are preserved.
-    //
-    // This matches the process described in https://www.ncbi.nlm.nih.gov/pmc/articles/PMC308517/pdf/nar00046-0131.pdf
-    // fn align(&self, other: Alignment) -> Alignment {
-    //     let mut alignment = Alignment {
-    //         seqs: vec![self.seqs[0].clone(), other.seqs[0].clone()],
-    //         distance: 0f32,
-    //     };
-
-    //     for i in 0..self.seqs[0].len() {
-    //         if self.seqs[0][i] == other.seqs[0][i] {
-    //             alignment.seqs[0].push(self.seqs[0][i]);
-    //             alignment.seqs[1].push(other.seqs[0][i]);
-    //         } else {
-    //             alignment.seqs[0].push('-');
-    //             alignment.seqs[1].push('-');
-    //             alignment.distance += 1f32;
-    //         }
-    //     }
-
-    //     alignment
-    // }
-
-    // /// init_grid creates a new 2D alignment grid with negatives values for each initial row.
-    // fn init_grid(&self, a_len: usize, b_len: usize) -> Vec<Vec<Step>> {
-    //     let mut grid: Vec<Vec<Step>> = Vec::new();
-    //     for i in 0..b_len + 1 {
-    //         grid.push(vec![Step::default(); a_len + 1]);
-
-    //         grid[i][0] = Step {
-    //             i,
-    //             j: 0,
-    //             val: OrderedFloat(-(i as f32)),
-    //             next: match i {
-    //                 0 => None,
-    //                 _ => Some((i - 1, 0)),
-    //             },
-    //         };
-    //     }
-
-    //     for j in 0..a_len + 1 {
-    //         grid[0][j] = Step {
-    //             i: 0,
-    //             j,
-    //             val: OrderedFloat(-(j as f32)),
-    //             next: match j {
-    //                 0 => None,
-    //                 _ => Some((0, j - 1)),
-    //             },
-    //         };
-    //     }
-
-    //     grid
-    // }
+/// ProfileColumns are the columns of the alignment of two profiles and its
+/// score.
+type ProfileColumns = (Vec<(Option<usize>, Option<usize>)>, f32);
+
+/// profile_columns_scored is [`profile_columns`] with the score of the
+/// alignment of the profiles.
+pub(super) fn profile_columns_scored(
+    a: &Profile,
+    b: &Profile,
+    scoring: &Scoring,
+) -> ProfileColumns {
+    let (a_counts, b_counts) = (a.counts(), b.counts());
+    let pairs = (a.rows.len() * b.rows.len()) as f32;
+    let column_score = |i: usize, j: usize| -> f32 {
+        let mut score = 0f32;
+        for (x, n) in a_counts[i].iter() {
+            for (y, m) in b_counts[j].iter() {
+                score += n * m * scoring.matrix[*x as usize][*y as usize] as f32;
+            }
+        }
+        score / pairs
+    };
+
+    // a's columns are the rows of the grid, b's its columns
+    let (na, nb) = (a.len(), b.len());
+    let rules = Affine::new(scoring, 1, |i, j| column_score(i - 1, j - 1));
+    let grid = gotoh::fill(&rules, nb, na, |_, _| {});
+    let (state, score) = gotoh::best(grid.last.get(nb));
+    let path = grid.path((na, nb), state);
+
+    // gaps in b take a column of a, gaps in a one of b
+    let (mut i, mut j) = path.start;
+    let mut columns: Vec<(Option<usize>, Option<usize>)> = Vec::with_capacity(na + nb);
+    for state in path.states {
+        match state {
+            DIAGONAL => {
+                columns.push((Some(i), Some(j)));
+                i += 1;
+                j += 1;
+            }
+            UP => {
+                columns.push((Some(i), None));
+                i += 1;
+            }
+            _ => {
+                columns.push((None, Some(j)));
+                j += 1;
+            }
+        }
+    }
+    (columns, score)
 }
 
 #[cfg(test)]
 mod test {
+    use crate::{
+        matrices::NUC_4_4,
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
     use super::*;
 
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_upgma() {
-        let clusters = upgma(&mut [
-            "ACGTA".to_string(),
-            "ACGCA".to_string(),
-            "ACTTA".to_string(),
-            "ACATA".to_string(),
-        ]);
-
-        // assert_eq!(7, clusters.len())
+        let tree = upgma(
+            &[
+                "ACGTA".to_string(),
+                "ACGCA".to_string(),
+                "ACTTA".to_string(),
+                "ACATA".to_string(),
+            ],
+            &scoring(),
+            &ProgressiveConfig::default(),
+        );
+
+        // the leaves, then the joins, the closest first and the lowest on ties
+        assert_eq!(7, tree.nodes.len());
+        assert!(tree.nodes[..4].iter().all(|n| n.is_leaf()));
+        assert_eq!(
+            (Some(0), Some(1)),
+            (tree.nodes[4].left, tree.nodes[4].right)
+        );
+        assert_eq!(6, tree.root());
+    }
+
+    #[test]
+    fn test_align_profiles() {
+        let a = Profile::from_seq(0, "ACGTACGT");
+        let b = Profile::from_seq(1, "ACGACGT");
+        let (ab, _) = align_profiles(&a, &b, &scoring());
+        assert_eq!(b"ACG-ACGT".to_vec(), ab.rows[1]);
+
+        // the gap in the profile is kept
+        let c = Profile::from_seq(2, "ACGTTACGT");
+        let (abc, _) = align_profiles(&ab, &c, &scoring());
+        assert_eq!(vec![0, 1, 2], abc.seqs);
+        assert_eq!(
+            vec![
+                b"ACG-TACGT".to_vec(),
+                b"ACG--ACGT".to_vec(),
+                b"ACGTTACGT".to_vec()
+            ],
+            abc.rows
+        );
+    }
+
+    #[test]
+    fn test_align_multiple() {
+        let seqs = ["ACGTACGTAC", "ACGTCGTAC", "ACGTACGTAC", "TTACGTACGTAC"];
+        let msa = align_multiple(&seqs, &scoring(), &ProgressiveConfig::default()).unwrap();
+
+        assert_eq!(vec!["1", "2", "3", "4"], msa.ids);
+        assert_eq!(
+            "--ACGTACGTAC
+--ACGT-CGTAC
+--ACGTACGTAC
+TTACGTACGTAC",
+            msa.to_string()
+        );
+
+        let records: Vec<SeqRecord> = seqs
+            .iter()
+            .zip(["w", "x", "y", "z"])
+            .map(|(seq, id)| SeqRecord::new(id, *seq))
+            .collect();
+        let named = align_records(&records, &scoring(), &ProgressiveConfig::default()).unwrap();
+        assert_eq!(vec!["w", "x", "y", "z"], named.ids);
+        assert_eq!(msa.rows, named.rows);
+    }
+
+    #[test]
+    fn test_align_multiple_order() {
+        let seqs = ["ACGTACGTAC", "TTACGTACGTAC", "ACGTCGTAC", "ACGTACGTAC"];
+        let order = |order| {
+            let config = ProgressiveConfig {
+                order,
+                ..Default::default()
+            };
+            align_multiple(&seqs, &scoring(), &config).unwrap().ids
+        };
+
+        assert_eq!(vec!["1", "2", "3", "4"], order(OutputOrder::Input));
+        assert_eq!(vec!["1", "4", "3", "2"], order(OutputOrder::Similarity));
+        let tree = order(OutputOrder::GuideTree);
+        assert_eq!(4, tree.len());
+        // the identical sequences are next to each other
+        let (one, four) = (
+            tree.iter().position(|i| i == "1").unwrap(),
+            tree.iter().position(|i| i == "4").unwrap(),
+        );
+        assert_eq!(1, one.abs_diff(four));
+    }
+
+    #[test]
+    fn test_align_multiple_threads() {
+        let mut rng = Rng::new(7);
+        let root = random_seq(80, &background::nucleotide(), &mut rng);
+        let seqs: Vec<String> = (0..12)
+            .map(|_| {
+                let mut seq: Vec<u8> = root.bytes().collect();
+                for _ in 0..8 {
+                    let (pos, base) = (rng.below(seq.len()), b"ACGT"[rng.below(4)]);
+                    match rng.below(3) {
+                        0 => seq[pos] = base,
+                        1 => {
+                            seq.remove(pos);
+                        }
+                        _ => seq.insert(pos, base),
+                    }
+                }
+                String::from_utf8(seq).unwrap()
+            })
+            .collect();
+
+        let align = |threads, deterministic| {
+            let config = ProgressiveConfig {
+                threads,
+                deterministic,
+                ..Default::default()
+            };
+            align_multiple(&seqs, &scoring(), &config)
+                .unwrap()
+                .to_string()
+        };
+        let serial = align(1, false);
+        for threads in [2, 3, 16, 0] {
+            assert_eq!(serial, align(threads, false));
+            assert_eq!(serial, align(threads, true));
+        }
+    }
+
+    #[test]
+    fn test_align_multiple_empty() {
+        let seqs: [&str; 0] = [];
+        let msa = align_multiple(&seqs, &scoring(), &ProgressiveConfig::default()).unwrap();
+        assert!(msa.rows.is_empty());
+    }
+
+    #[test]
+    fn test_align_multiple_duplicates() {
+        let seqs = ["ACGTACGTAC", "", "TTACGTACGTAC", "ACGTACGTAC", "ACGTCGTAC"];
+        let msa = align_multiple(&seqs, &scoring(), &ProgressiveConfig::default()).unwrap();
+        assert_eq!(5, msa.rows.len());
+        assert!(msa.rows[1].iter().all(|c| *c == '-'));
+        assert_eq!(msa.rows[0], msa.rows[3]);
+
+        let config = ProgressiveConfig {
+            empty: EmptySequences::Error,
+            ..Default::default()
+        };
+        assert!(matches!(
+            align_multiple(&seqs, &scoring(), &config),
+            Err(Error::EmptySequence(id)) if id == "2"
+        ));
+
+        let config = ProgressiveConfig {
+            duplicates: Duplicates::Collapse,
+            empty: EmptySequences::Skip,
+            ..Default::default()
+        };
+        let (msa, report) = align_multiple_with_report(&seqs, &scoring(), &config).unwrap();
+        assert_eq!(vec!["1", "3", "4", "5"], msa.ids);
+        assert_eq!(msa.rows[0], msa.rows[2]);
+        assert_eq!(vec!["2"], report.skipped);
+        assert_eq!(vec![("4".to_string(), "1".to_string())], report.collapsed);
+        assert_eq!(2, report.merges.len());
+
+        // the copy is next to its first in the guide tree's order
+        let config = ProgressiveConfig {
+            order: OutputOrder::GuideTree,
+            ..config
+        };
+        let ids = align_multiple(&seqs, &scoring(), &config).unwrap().ids;
+        let one = ids.iter().position(|i| i == "1").unwrap();
+        assert_eq!("4", ids[one + 1]);
     }
 }
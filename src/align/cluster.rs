@@ -0,0 +1,138 @@
+//! Greedy clustering of sequences by identity, like a minimal CD-HIT.
+//! https://doi.org/10.1093/bioinformatics/btl158
+//!
+//! Sequences are visited longest first. Each joins the first cluster whose
+//! representative it matches at or above the threshold, or else starts a new
+//! cluster as its representative. A short word filter skips the alignment for
+//! pairs that can't reach the threshold: a pair at identity `t` over the
+//! shorter length `L` shares at least `L - k + 1 - k * (1 - t) * L` k-mers,
+//! since each residue that isn't identical breaks at most k of them.
+
+use std::collections::HashMap;
+
+use super::{distance_matrix::identity, DistanceConfig, Scoring};
+
+/// length of the words used to filter pairs before aligning them.
+const WORD_LEN: usize = 4;
+
+/// Cluster is a group of sequences within the identity threshold of a representative.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cluster {
+    /// index of the representative sequence, the longest in the cluster
+    pub representative: usize,
+
+    /// indexes of every sequence in the cluster, including the representative
+    pub members: Vec<usize>,
+}
+
+/// cluster sequences greedily at a percent identity threshold.
+///
+/// Clusters are returned in the order their representatives were found, so
+/// by decreasing representative length.
+pub fn cluster<S: AsRef<str>>(seqs: &[S], threshold: f32, scoring: &Scoring) -> Vec<Cluster> {
+    let t = threshold / 100f32;
+
+    let mut order: Vec<usize> = (0..seqs.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(seqs[*i].as_ref().len()));
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut words: Vec<HashMap<&[u8], usize>> = Vec::new();
+    for i in order {
+        let seq = seqs[i].as_ref().as_bytes();
+        let seq_words = count_words(seq);
+
+        let found = clusters
+            .iter()
+            .zip(words.iter())
+            .position(|(c, rep_words)| {
+                let rep = seqs[c.representative].as_ref().as_bytes();
+                let short = seq.len().min(rep.len()) as f32;
+                let bound = short - WORD_LEN as f32 + 1f32 - WORD_LEN as f32 * (1f32 - t) * short;
+                if (shared_words(&seq_words, rep_words) as f32) < bound {
+                    return false;
+                }
+                identity(rep, seq, scoring, DistanceConfig::default()) >= t
+            });
+
+        match found {
+            Some(c) => clusters[c].members.push(i),
+            None => {
+                clusters.push(Cluster {
+                    representative: i,
+                    members: vec![i],
+                });
+                words.push(seq_words);
+            }
+        }
+    }
+    clusters
+}
+
+/// count_words counts each k-mer of a sequence.
+fn count_words(seq: &[u8]) -> HashMap<&[u8], usize> {
+    let mut counts = HashMap::new();
+    for word in seq.windows(WORD_LEN) {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// shared_words is the number of k-mers two sequences have in common, with multiplicity.
+fn shared_words(a: &HashMap<&[u8], usize>, b: &HashMap<&[u8], usize>) -> usize {
+    a.iter()
+        .map(|(word, n)| b.get(word).map_or(0, |m| *n.min(m)))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_cluster() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let seqs = [
+            "ACGTACGTAA",
+            "GGGCCCTTTAAAGGGCCC",
+            "ACGTACGTAT", // 90% identical to the first
+            "GGGCCCTTTAAAGGGCCC",
+            "TTTTTTTTTT",
+        ];
+
+        let clusters = cluster(&seqs, 90f32, &scoring);
+        assert_eq!(
+            vec![
+                Cluster {
+                    representative: 1,
+                    members: vec![1, 3]
+                },
+                Cluster {
+                    representative: 0,
+                    members: vec![0, 2]
+                },
+                Cluster {
+                    representative: 4,
+                    members: vec![4]
+                },
+            ],
+            clusters
+        );
+
+        // nothing but exact duplicates at 100%
+        assert_eq!(4, cluster(&seqs, 100f32, &scoring).len());
+    }
+
+    #[test]
+    fn test_shared_words() {
+        let a = count_words(b"AAAAAC");
+        let b = count_words(b"AAAAA");
+        assert_eq!(2, shared_words(&a, &b));
+    }
+}
@@ -0,0 +1,125 @@
+//! Per-column scores of a multiple alignment.
+//!
+//! These are the tracks shown under alignments in viewers: how much of each
+//! column agrees with its most common residue, and how alike its residues
+//! are by a substitution matrix, and the consensus of the columns. Masked
+//! cells are left out of all of them.
+
+use crate::matrices::Matrix;
+
+use super::MSAlignment;
+
+impl MSAlignment {
+    /// column_conservation is, per column, the fraction of unmasked rows with
+    /// the column's most common residue. Gaps count against it.
+    pub fn column_conservation(&self) -> Vec<f32> {
+        let mask = self.mask();
+        (0..self.len())
+            .map(|col| {
+                let (counts, gaps) = self.counts(&mask, col);
+                let most = counts.iter().map(|(_, n)| *n).max().unwrap_or(0);
+                let rows = gaps + counts.iter().map(|(_, n)| n).sum::<usize>();
+                match rows {
+                    0 => 0f32,
+                    _ => most as f32 / rows as f32,
+                }
+            })
+            .collect()
+    }
+
+    /// consensus is the most common residue of each column, the first in the
+    /// alignment on ties, or a gap for a column with more gaps than that.
+    pub fn consensus(&self) -> String {
+        let mask = self.mask();
+        (0..self.len())
+            .map(|col| {
+                let (counts, gaps) = self.counts(&mask, col);
+                counts
+                    .iter()
+                    .rev()
+                    .max_by_key(|(_, n)| *n)
+                    .filter(|(_, n)| *n >= gaps.max(1))
+                    .map_or('-', |(c, _)| *c)
+            })
+            .collect()
+    }
+
+    /// column_quality is, per column, the mean substitution score of every
+    /// pair of its residues. Columns with fewer than two residues score 0.
+    pub fn column_quality(&self, matrix: &Matrix) -> Vec<f32> {
+        let mask = self.mask();
+        (0..self.len())
+            .map(|col| {
+                let residues: Vec<usize> = self
+                    .rows
+                    .iter()
+                    .zip(mask.iter())
+                    .filter(|(_, m)| !m[col])
+                    .map(|(r, _)| r[col])
+                    .filter(|c| *c != '-')
+                    .map(|c| c as usize % 128)
+                    .collect();
+                let (mut total, mut pairs) = (0f32, 0f32);
+                for (i, a) in residues.iter().enumerate() {
+                    for b in residues.iter().skip(i + 1) {
+                        total += matrix[*a][*b] as f32;
+                        pairs += 1f32;
+                    }
+                }
+                if pairs == 0f32 {
+                    0f32
+                } else {
+                    total / pairs
+                }
+            })
+            .collect()
+    }
+
+    /// counts of the unmasked residues of a column, uppercased and in order of
+    /// first appearance, and of its unmasked gaps.
+    fn counts(&self, mask: &[Vec<bool>], col: usize) -> (Vec<(char, usize)>, usize) {
+        let mut counts: Vec<(char, usize)> = vec![];
+        let mut gaps = 0;
+        for (row, masked) in self.rows.iter().zip(mask.iter()) {
+            match row[col] {
+                _ if masked[col] => {}
+                '-' => gaps += 1,
+                c => {
+                    let c = c.to_ascii_uppercase();
+                    match counts.iter_mut().find(|(r, _)| *r == c) {
+                        Some((_, n)) => *n += 1,
+                        None => counts.push((c, 1)),
+                    }
+                }
+            }
+        }
+        (counts, gaps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_column_scores() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into(), "d".into()],
+            vec![
+                "ACG-".chars().collect(),
+                "ACT-".chars().collect(),
+                "AGT-".chars().collect(),
+                "A-TC".chars().collect(),
+            ],
+        );
+
+        assert_eq!(vec![1f32, 0.5, 0.75, 0.25], msa.column_conservation());
+        assert_eq!(
+            vec![5f32, (5f32 - 4f32 - 4f32) / 3f32, 0.5, 0f32],
+            msa.column_quality(&NUC_4_4::MATRIX)
+        );
+        assert_eq!("ACT-", msa.consensus());
+    }
+}
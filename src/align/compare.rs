@@ -0,0 +1,142 @@
+//! Comparison of two alignments of the same sequences.
+//!
+//! Scores from different parameters or algorithms aren't comparable, but the
+//! residues they pair up are. Two alignments agree on a residue if they align
+//! it to the same residue of the other sequence, or both to a gap; the
+//! residues they disagree on are grouped into regions to look at.
+
+use std::{collections::HashSet, ops::Range};
+
+use super::{Alignment, Error, Result};
+
+/// Comparison is how much two alignments of the same sequences agree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// pairs of aligned residues in both alignments
+    pub shared_pairs: usize,
+
+    /// pairs of aligned residues in either alignment
+    pub total_pairs: usize,
+
+    /// 0-based, half-open ranges of the first sequence whose residues are
+    /// aligned differently
+    pub a_regions: Vec<Range<usize>>,
+
+    /// 0-based, half-open ranges of the second sequence whose residues are
+    /// aligned differently
+    pub b_regions: Vec<Range<usize>>,
+}
+
+impl Comparison {
+    /// agreement is the fraction of aligned residue pairs shared by both
+    /// alignments, 1 if neither pairs any residues.
+    pub fn agreement(&self) -> f32 {
+        if self.total_pairs == 0 {
+            return 1f32;
+        }
+        self.shared_pairs as f32 / self.total_pairs as f32
+    }
+
+    /// identical is whether the alignments pair up all residues the same.
+    pub fn identical(&self) -> bool {
+        self.a_regions.is_empty() && self.b_regions.is_empty()
+    }
+}
+
+/// compare two pairwise alignments of the same two sequences.
+///
+/// Returns [`Error::DifferentSequences`] if their rows, without gaps, aren't
+/// the same.
+pub fn compare(first: &Alignment, second: &Alignment) -> Result<Comparison> {
+    for row in 0..2 {
+        let same = first.rows[row]
+            .iter()
+            .filter(|c| **c != '-')
+            .eq(second.rows[row].iter().filter(|c| **c != '-'));
+        if !same {
+            return Err(Error::DifferentSequences);
+        }
+    }
+
+    let first_pairs = pairs(first);
+    let second_pairs = pairs(second);
+
+    let (a_first, b_first) = partners(first);
+    let (a_second, b_second) = partners(second);
+
+    Ok(Comparison {
+        shared_pairs: first_pairs.intersection(&second_pairs).count(),
+        total_pairs: first_pairs.union(&second_pairs).count(),
+        a_regions: regions(&a_first, &a_second),
+        b_regions: regions(&b_first, &b_second),
+    })
+}
+
+/// pairs of residue indexes aligned to one another.
+fn pairs(aln: &Alignment) -> HashSet<(usize, usize)> {
+    let (a, _) = partners(aln);
+    a.iter()
+        .enumerate()
+        .filter_map(|(i, p)| p.map(|j| (i, j)))
+        .collect()
+}
+
+/// partners are the index of the residue each residue of either sequence is
+/// aligned to, None if it's against a gap.
+fn partners(aln: &Alignment) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+    let (mut a, mut b) = (vec![], vec![]);
+    for (ca, cb) in aln.rows[0].iter().zip(aln.rows[1].iter()) {
+        match (*ca != '-', *cb != '-') {
+            (true, true) => {
+                a.push(Some(b.len()));
+                b.push(Some(a.len() - 1));
+            }
+            (true, false) => a.push(None),
+            (false, true) => b.push(None),
+            (false, false) => {}
+        }
+    }
+    (a, b)
+}
+
+/// regions where the partners of residues differ, merged when adjacent.
+fn regions(first: &[Option<usize>], second: &[Option<usize>]) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = vec![];
+    for (i, _) in first
+        .iter()
+        .zip(second)
+        .enumerate()
+        .filter(|(_, (f, s))| f != s)
+    {
+        match regions.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => regions.push(i..i + 1),
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(a: &str, b: &str) -> Alignment {
+        Alignment::new(vec![a.chars().collect(), b.chars().collect()], vec![], 0f32)
+    }
+
+    #[test]
+    fn test_compare() {
+        let first = alignment("ACGTTTACGA", "ACG-TTAC-A");
+        let second = alignment("ACGTTTACGA", "ACGTT-ACA-");
+
+        let comparison = compare(&first, &second).unwrap();
+        assert_eq!(5, comparison.shared_pairs);
+        assert_eq!(11, comparison.total_pairs);
+        assert!((comparison.agreement() - 5f32 / 11f32).abs() < 1e-6);
+        assert_eq!(vec![3..6, 8..10], comparison.a_regions);
+        assert_eq!(vec![3..5, 7..8], comparison.b_regions);
+
+        assert!(compare(&first, &first).unwrap().identical());
+        assert!(compare(&first, &alignment("ACGTTTACGA", "ACGTTAC")).is_err());
+    }
+}
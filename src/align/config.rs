@@ -0,0 +1,370 @@
+//! Alignment parameters from a config file, so a pipeline can keep its
+//! parameters under version control next to its code.
+//!
+//! The file is TOML, or YAML with the `yaml` feature if its extension is
+//! `.yaml` or `.yml`, with a `progressive` table for multiple alignments.
+//! Keys that are left out keep their defaults, and unknown keys are errors so
+//! a typo isn't silently ignored:
+//!
+//! ```toml
+//! method = "needleman-wunsch"   # or "smith-waterman"
+//! matrix = "BLOSUM62"           # or any bundled matrix, like "NUC.4.4"
+//! gap_opening = -10
+//! gap_extension = -1
+//! terminal_gaps = "free"        # or "full", "half"
+//!
+//! [progressive]
+//! threads = 4
+//! deterministic = false
+//! order = "guide-tree"          # or "input", "similarity"
+//! checkpoint = "align.ckpt"
+//! duplicates = "collapse"       # or "align"
+//! empty = "skip"                # or "align", "error"
+//! unknown = "ignore"            # or "count", for N and X in distances
+//! exclude_terminal_gaps = true  # from distances
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::matrices;
+
+use super::{
+    DistanceConfig, Duplicates, EmptySequences, Error, Method, OutputOrder, ProgressiveConfig,
+    Result, Scoring, TerminalGap, TerminalGaps, UnknownResidues,
+};
+
+/// AlignConfig is the parameters of pairwise and multiple alignments.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlignConfig {
+    /// algorithm of pairwise alignments
+    #[serde(deserialize_with = "value_enum")]
+    pub method: Method,
+
+    /// name of the substitution matrix
+    pub matrix: String,
+
+    /// penalty of opening a gap, 0 or less
+    pub gap_opening: f32,
+
+    /// penalty of extending a gap, 0 or less
+    pub gap_extension: f32,
+
+    /// penalty of gaps at the ends of the sequences
+    #[serde(deserialize_with = "value_enum")]
+    pub terminal_gaps: TerminalGap,
+
+    /// configuration of multiple alignments
+    #[serde(deserialize_with = "progressive")]
+    pub progressive: ProgressiveConfig,
+}
+
+impl Default for AlignConfig {
+    fn default() -> Self {
+        AlignConfig {
+            method: Method::NeedlemanWunsch,
+            matrix: "BLOSUM62".to_string(),
+            gap_opening: -1f32,
+            gap_extension: -1f32,
+            terminal_gaps: TerminalGap::Full,
+            progressive: ProgressiveConfig::default(),
+        }
+    }
+}
+
+impl AlignConfig {
+    /// from_path reads and validates a config file, YAML if its extension
+    /// is `.yaml` or `.yml` and TOML otherwise.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let read = |source| Error::ConfigRead {
+            path: path.to_path_buf(),
+            source,
+        };
+        let text = fs::read_to_string(path).map_err(read)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => AlignConfig::from_yaml(&text),
+            #[cfg(not(feature = "yaml"))]
+            Some("yaml" | "yml") => Err(read(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "config is YAML, rebuild with the yaml feature to read it",
+            ))),
+            _ => text.parse(),
+        }
+    }
+
+    /// from_yaml parses and validates the text of a YAML config file.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(s: &str) -> Result<Self> {
+        let config: AlignConfig = serde_yaml::from_str(s).map_err(|e| Error::InvalidConfig {
+            line: e.location().map_or(1, |l| l.line()),
+            message: e.to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// scoring of the config's matrix and gap penalties.
+    pub fn scoring(&self) -> Result<Scoring> {
+        Ok(Scoring {
+            matrix: *matrices::named(&self.matrix)
+                .ok_or_else(|| Error::InvalidParameter(format!("no matrix {}", self.matrix)))?,
+            gap_opening: self.gap_opening,
+            gap_extension: self.gap_extension,
+            terminal_gaps: TerminalGaps::all(self.terminal_gaps),
+            ..Default::default()
+        })
+    }
+
+    /// validate checks that the parameters are usable.
+    pub fn validate(&self) -> Result<()> {
+        self.scoring()?;
+        for (name, penalty) in [
+            ("gap_opening", self.gap_opening),
+            ("gap_extension", self.gap_extension),
+        ] {
+            if !penalty.is_finite() || penalty > 0f32 {
+                return Err(Error::InvalidParameter(format!(
+                    "{} is {}, it must be 0 or less",
+                    name, penalty
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AlignConfig {
+    type Err = Error;
+
+    /// from_str parses and validates the text of a TOML config file.
+    fn from_str(s: &str) -> Result<Self> {
+        let config: AlignConfig = toml::from_str(s).map_err(|e| Error::InvalidConfig {
+            line: e
+                .span()
+                .map_or(1, |span| s[..span.start].matches('\n').count() + 1),
+            message: e.message().to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Progressive is the `progressive` table of a config file, the
+/// [`ProgressiveConfig`] with its distances flattened into it.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Progressive {
+    threads: usize,
+    deterministic: bool,
+    #[serde(deserialize_with = "named")]
+    order: OutputOrder,
+    checkpoint: Option<PathBuf>,
+    #[serde(deserialize_with = "named")]
+    duplicates: Duplicates,
+    #[serde(deserialize_with = "named")]
+    empty: EmptySequences,
+    #[serde(deserialize_with = "named")]
+    unknown: UnknownResidues,
+    exclude_terminal_gaps: bool,
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        let config = ProgressiveConfig::default();
+        Progressive {
+            threads: config.threads,
+            deterministic: config.deterministic,
+            order: config.order,
+            checkpoint: config.checkpoint,
+            duplicates: config.duplicates,
+            empty: config.empty,
+            unknown: config.distances.unknown,
+            exclude_terminal_gaps: config.distances.exclude_terminal_gaps,
+        }
+    }
+}
+
+/// progressive deserializes the `progressive` table.
+fn progressive<'de, D: Deserializer<'de>>(
+    d: D,
+) -> std::result::Result<ProgressiveConfig, D::Error> {
+    let table = Progressive::deserialize(d)?;
+    Ok(ProgressiveConfig {
+        checkpoint: table.checkpoint,
+        order: table.order,
+        threads: table.threads,
+        deterministic: table.deterministic,
+        duplicates: table.duplicates,
+        empty: table.empty,
+        distances: DistanceConfig {
+            unknown: table.unknown,
+            exclude_terminal_gaps: table.exclude_terminal_gaps,
+        },
+    })
+}
+
+/// value_enum deserializes an option of the command line by its name there.
+fn value_enum<'de, D: Deserializer<'de>, T: ValueEnum>(d: D) -> std::result::Result<T, D::Error> {
+    let name = String::deserialize(d)?;
+    T::from_str(&name, true).map_err(de::Error::custom)
+}
+
+/// Named is an option of a config file that's only set there, by name.
+trait Named: Sized + Copy + 'static {
+    /// NAMES is each value, by its name.
+    const NAMES: &'static [(&'static str, Self)];
+}
+
+impl Named for OutputOrder {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("input", OutputOrder::Input),
+        ("guide-tree", OutputOrder::GuideTree),
+        ("similarity", OutputOrder::Similarity),
+    ];
+}
+
+impl Named for Duplicates {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("align", Duplicates::Align),
+        ("collapse", Duplicates::Collapse),
+    ];
+}
+
+impl Named for EmptySequences {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("align", EmptySequences::Align),
+        ("error", EmptySequences::Error),
+        ("skip", EmptySequences::Skip),
+    ];
+}
+
+impl Named for UnknownResidues {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("count", UnknownResidues::Count),
+        ("ignore", UnknownResidues::Ignore),
+    ];
+}
+
+/// named deserializes a [`Named`] option.
+fn named<'de, D: Deserializer<'de>, T: Named>(d: D) -> std::result::Result<T, D::Error> {
+    let name = String::deserialize(d)?;
+    T::NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| {
+            let names: Vec<_> = T::NAMES.iter().map(|(n, _)| *n).collect();
+            de::Error::custom(format!("no {}, expected one of {}", name, names.join(", ")))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::{NUC_4_4, PAM250};
+
+    use super::*;
+
+    #[test]
+    fn test_align_config() {
+        let config: AlignConfig = r#"
+# parameters of the amplicon pipeline
+method = "smith-waterman"
+matrix = "nuc.4.4"
+gap_opening = -10   # per gap
+gap_extension = -1
+terminal_gaps = "free"
+
+[progressive]
+threads = 4
+order = "guide-tree"
+checkpoint = "runs/#1"
+duplicates = "collapse"
+exclude_terminal_gaps = true
+"#
+        .parse()
+        .unwrap();
+        assert!(matches!(config.method, Method::SmithWaterman));
+        let scoring = config.scoring().unwrap();
+        assert_eq!(NUC_4_4::MATRIX, scoring.matrix);
+        assert_eq!(
+            (-10f32, -1f32),
+            (scoring.gap_opening, scoring.gap_extension)
+        );
+        assert_eq!(TerminalGaps::all(TerminalGap::Free), scoring.terminal_gaps);
+        assert_eq!(4, config.progressive.threads);
+        assert_eq!(OutputOrder::GuideTree, config.progressive.order);
+        assert_eq!(Some("runs/#1".into()), config.progressive.checkpoint);
+        assert_eq!(Duplicates::Collapse, config.progressive.duplicates);
+        assert!(config.progressive.distances.exclude_terminal_gaps);
+
+        // defaults
+        let config: AlignConfig = "gap_opening = -5".parse().unwrap();
+        assert_eq!("BLOSUM62", config.matrix);
+        assert_eq!(-1f32, config.gap_extension);
+
+        for (text, line) in [
+            ("\ngap_openning = -5", 2),
+            ("matrix = BLOSUM62", 1),
+            ("[progressive]\nthreads = 1.5", 2),
+            ("[pairwise]", 1),
+        ] {
+            match text.parse::<AlignConfig>() {
+                Err(Error::InvalidConfig { line: l, .. }) => assert_eq!(line, l, "{}", text),
+                other => panic!("{}: {:?}", text, other),
+            }
+        }
+        assert!(matches!(
+            "gap_opening = 5".parse::<AlignConfig>(),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            "matrix = \"PAM251\"".parse::<AlignConfig>(),
+            Err(Error::InvalidParameter(_))
+        ));
+
+        // any bundled matrix, and the rest of TOML
+        let config: AlignConfig =
+            "matrix = 'pam250'\nprogressive = { deterministic = true, order = 'input' }"
+                .parse()
+                .unwrap();
+        assert_eq!(PAM250::MATRIX, config.scoring().unwrap().matrix);
+        assert!(config.progressive.deterministic);
+        assert_eq!(OutputOrder::Input, config.progressive.order);
+        let nuc: AlignConfig = "matrix = 'nuc'".parse().unwrap();
+        assert_eq!(NUC_4_4::MATRIX, nuc.scoring().unwrap().matrix);
+        match "\n[progressive]\norder = \"random\"".parse::<AlignConfig>() {
+            Err(Error::InvalidConfig { line, message }) => {
+                assert_eq!(3, line);
+                assert!(message.contains("guide-tree"), "{}", message);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_align_config_yaml() {
+        let config = AlignConfig::from_yaml(
+            "method: smith-waterman\nmatrix: NUC.4.4\ngap_opening: -10\nprogressive:\n  threads: 2\n",
+        )
+        .unwrap();
+        assert!(matches!(config.method, Method::SmithWaterman));
+        assert_eq!(NUC_4_4::MATRIX, config.scoring().unwrap().matrix);
+        assert_eq!(-10f32, config.gap_opening);
+        assert_eq!(2, config.progressive.threads);
+
+        match AlignConfig::from_yaml("matrix: NUC.4.4\ngap_openning: -10\n") {
+            Err(Error::InvalidConfig { line, .. }) => assert_eq!(2, line),
+            other => panic!("{:?}", other),
+        }
+    }
+}
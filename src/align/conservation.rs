@@ -0,0 +1,139 @@
+//! The conservation line between the rows of a pairwise alignment.
+//!
+//! Like the output of EMBOSS needle and water, each column is marked by how
+//! alike its residues are: identical, similar by a positive score in the
+//! substitution matrix, weakly similar by a score of 0, or not alike at all.
+
+use std::fmt::Display;
+
+use crate::matrices::Matrix;
+
+use super::{Alignment, GapChars};
+
+/// Symbols are the characters of a conservation line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbols {
+    /// identical residues
+    pub identity: char,
+
+    /// residues with a positive score
+    pub similar: char,
+
+    /// residues with a score of 0
+    pub weak: char,
+
+    /// residues with a negative score, and gaps
+    pub other: char,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Symbols {
+            identity: '|',
+            similar: ':',
+            weak: '.',
+            other: ' ',
+        }
+    }
+}
+
+/// Conserved is an alignment formatted with a conservation line between its
+/// first two rows.
+pub struct Conserved<'a> {
+    alignment: &'a Alignment,
+    matrix: &'a Matrix,
+    symbols: Option<Symbols>,
+    gaps: GapChars,
+}
+
+impl Conserved<'_> {
+    /// with_gaps writes the rows' gaps with other characters.
+    pub fn with_gaps(mut self, gaps: GapChars) -> Self {
+        self.gaps = gaps;
+        self
+    }
+}
+
+impl Display for Conserved<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows = self.alignment.rows_with(&self.gaps);
+        match self.symbols {
+            Some(symbols) => {
+                writeln!(f, "{}", rows[0])?;
+                writeln!(f, "{}", self.alignment.conservation(self.matrix, &symbols))?;
+                write!(f, "{}", rows[1..].join("\n"))
+            }
+            None => write!(f, "{}", rows.join("\n")),
+        }
+    }
+}
+
+impl Alignment {
+    /// conservation is the conservation line of the first two rows, one symbol per column.
+    pub fn conservation(&self, matrix: &Matrix, symbols: &Symbols) -> String {
+        self.rows[0]
+            .iter()
+            .zip(self.rows[1].iter())
+            .map(|(a, b)| {
+                if *a == '-' || *b == '-' {
+                    return symbols.other;
+                }
+                if a.eq_ignore_ascii_case(b) {
+                    return symbols.identity;
+                }
+                match matrix[*a as usize % 128][*b as usize % 128] {
+                    s if s > 0 => symbols.similar,
+                    0 => symbols.weak,
+                    _ => symbols.other,
+                }
+            })
+            .collect()
+    }
+
+    /// conserved formats the alignment with a conservation line between its
+    /// first two rows, or without one if `symbols` is None.
+    pub fn conserved<'a>(&'a self, matrix: &'a Matrix, symbols: Option<Symbols>) -> Conserved<'a> {
+        Conserved {
+            alignment: self,
+            matrix,
+            symbols,
+            gaps: GapChars::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::BLOSUM62;
+
+    use super::*;
+
+    #[test]
+    fn test_conserved() {
+        let alignment = Alignment::new(
+            vec![
+                "HEAGAWGHEE".chars().collect(),
+                "HDAGKWSHE-".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+        let matrix = BLOSUM62::MATRIX;
+
+        assert_eq!(
+            "HEAGAWGHEE\n|:|| |.|| \nHDAGKWSHE-",
+            alignment
+                .conserved(&matrix, Some(Symbols::default()))
+                .to_string()
+        );
+        assert_eq!(
+            alignment.to_string(),
+            alignment.conserved(&matrix, None).to_string()
+        );
+        let symbols = Symbols {
+            identity: '*',
+            ..Default::default()
+        };
+        assert_eq!("*:** *.** ", alignment.conservation(&matrix, &symbols));
+    }
+}
@@ -0,0 +1,123 @@
+//! Alignment with context-dependent substitution scores.
+//!
+//! A matrix scores each pair of residues alone, but some substitutions depend
+//! on their neighbours: methylated cytosines in CpG dinucleotides deaminate to
+//! thymine about ten times as often as other transitions happen. Here a
+//! closure scores each pair given the residues around it in both sequences.
+//! It's called for every cell of the grid, so this is slower than aligning
+//! with the matrix alone and is only worth it when the model needs it.
+
+use super::{overlap::align_scored, Alignment, Scoring};
+
+/// Context is a pair of residues being aligned and their neighbours in each
+/// sequence. The neighbours are the residues before and after in the
+/// sequence, not in the alignment, which isn't known yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Context {
+    /// 0-based position of the residue in a
+    pub a_pos: usize,
+
+    /// 0-based position of the residue in b
+    pub b_pos: usize,
+
+    pub a: char,
+    pub b: char,
+    pub a_prev: Option<char>,
+    pub a_next: Option<char>,
+    pub b_prev: Option<char>,
+    pub b_next: Option<char>,
+}
+
+/// align_context aligns two sequences with substitutions scored by `score`
+/// and the gaps, including terminal ones, of `scoring`.
+pub fn align_context<F: Fn(&Context) -> f32>(
+    a: &str,
+    b: &str,
+    scoring: &Scoring,
+    score: F,
+) -> Alignment {
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    let at = |seq: &[u8], pos: Option<usize>| pos.and_then(|p| seq.get(p)).map(|c| *c as char);
+    align_scored(
+        a,
+        b,
+        scoring,
+        &vec![1f32; a.len()],
+        &vec![1f32; b.len()],
+        |j, i| {
+            score(&Context {
+                a_pos: j,
+                b_pos: i,
+                a: a_bytes[j] as char,
+                b: b_bytes[i] as char,
+                a_prev: at(a_bytes, j.checked_sub(1)),
+                a_next: at(a_bytes, Some(j + 1)),
+                b_prev: at(b_bytes, i.checked_sub(1)),
+                b_next: at(b_bytes, Some(i + 1)),
+            })
+        },
+    )
+}
+
+/// cpg_aware scores pairs with the matrix of `scoring`, but keeps only
+/// `weight` of the penalty of a C to T transition in a CpG of either
+/// sequence, and of the G to A on the other strand.
+pub fn cpg_aware(scoring: &Scoring, weight: f32) -> impl Fn(&Context) -> f32 + '_ {
+    move |c: &Context| {
+        let score = scoring.matrix[c.a as usize][c.b as usize] as f32;
+        let deaminated = |x: char, prev: Option<char>, next: Option<char>, y: char| match (
+            x.to_ascii_uppercase(),
+            y.to_ascii_uppercase(),
+        ) {
+            ('C', 'T') => next.is_some_and(|n| n.eq_ignore_ascii_case(&'G')),
+            ('G', 'A') => prev.is_some_and(|p| p.eq_ignore_ascii_case(&'C')),
+            _ => false,
+        };
+        if score < 0f32
+            && (deaminated(c.a, c.a_prev, c.a_next, c.b)
+                || deaminated(c.b, c.b_prev, c.b_next, c.a))
+        {
+            score * weight
+        } else {
+            score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_align_context() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let cpg = cpg_aware(&scoring, 0.25);
+
+        // C>T in a CpG on either strand costs a quarter
+        let alignment = align_context("AACGAA", "AATGAA", &scoring, &cpg);
+        assert_eq!("AACGAA\nAATGAA", alignment.to_string());
+        assert_eq!(24f32, alignment.score);
+        assert_eq!(
+            24f32,
+            align_context("AACGAA", "AACAAA", &scoring, &cpg).score
+        );
+
+        // other transitions, and a matrix-only closure, score as usual
+        assert_eq!(
+            21f32,
+            align_context("AACAAA", "AATAAA", &scoring, &cpg).score
+        );
+        let plain = align_context("ACGTTACGT", "ACGTACGT", &scoring, |c| {
+            NUC_4_4::MATRIX[c.a as usize][c.b as usize] as f32
+        });
+        assert_eq!("ACGTTACGT\nACG-TACGT", plain.to_string());
+        assert_eq!(30f32, plain.score);
+    }
+}
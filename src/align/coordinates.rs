@@ -0,0 +1,135 @@
+//! Coordinate maps between the sequences of an alignment and its columns.
+//!
+//! Residue positions are 0-based positions in the ungapped sequence of a row,
+//! plus the row's offset if the alignment is a slice of a larger one.
+
+use super::Alignment;
+
+/// CoordinateMap converts between residue positions in each row and alignment columns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoordinateMap {
+    /// for each row, the column of each residue
+    columns: Vec<Vec<usize>>,
+
+    /// for each row, the residue in each column (None for gaps)
+    residues: Vec<Vec<Option<usize>>>,
+
+    /// for each row, the position of its first residue
+    offsets: Vec<usize>,
+}
+
+impl CoordinateMap {
+    /// new builds the map for the rows of an alignment.
+    pub fn new(rows: &[Vec<char>]) -> Self {
+        CoordinateMap::with_offsets(rows, &vec![0; rows.len()])
+    }
+
+    /// with_offsets builds the map for rows whose first residues are at
+    /// positions other than 0.
+    pub fn with_offsets(rows: &[Vec<char>], offsets: &[usize]) -> Self {
+        assert_eq!(rows.len(), offsets.len(), "one offset is needed per row");
+        let mut columns = Vec::with_capacity(rows.len());
+        let mut residues = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut row_columns = Vec::with_capacity(row.len());
+            let mut row_residues = Vec::with_capacity(row.len());
+            for (col, c) in row.iter().enumerate() {
+                if *c == '-' {
+                    row_residues.push(None);
+                } else {
+                    row_residues.push(Some(row_columns.len()));
+                    row_columns.push(col);
+                }
+            }
+            columns.push(row_columns);
+            residues.push(row_residues);
+        }
+        CoordinateMap {
+            columns,
+            residues,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    /// column holding a residue of a row.
+    pub fn column(&self, row: usize, pos: usize) -> Option<usize> {
+        let pos = pos.checked_sub(*self.offsets.get(row)?)?;
+        self.columns.get(row)?.get(pos).copied()
+    }
+
+    /// residue of a row in a column, or None if the row has a gap there.
+    pub fn residue(&self, row: usize, col: usize) -> Option<usize> {
+        let pos = (*self.residues.get(row)?.get(col)?)?;
+        Some(pos + self.offsets[row])
+    }
+
+    /// project a residue of one row onto the residue it's aligned with in another row.
+    pub fn project(&self, from: usize, pos: usize, to: usize) -> Option<usize> {
+        self.residue(to, self.column(from, pos)?)
+    }
+
+    /// project_range maps a half-open residue range of one row onto another row.
+    ///
+    /// The result spans every residue of `to` aligned within the columns of the
+    /// range, so it's None only if `to` is entirely gap there.
+    pub fn project_range(
+        &self,
+        from: usize,
+        range: (usize, usize),
+        to: usize,
+    ) -> Option<(usize, usize)> {
+        let (start, end) = self.column_range(from, range)?;
+        let first = (start..end).find_map(|col| self.residue(to, col))?;
+        let last = (start..end).rev().find_map(|col| self.residue(to, col))?;
+        Some((first, last + 1))
+    }
+
+    /// column_range converts a half-open residue range of a row into a half-open column range.
+    pub fn column_range(&self, row: usize, range: (usize, usize)) -> Option<(usize, usize)> {
+        if range.0 >= range.1 {
+            return None;
+        }
+        Some((
+            self.column(row, range.0)?,
+            self.column(row, range.1 - 1)? + 1,
+        ))
+    }
+
+    /// len is the number of residues in a row.
+    pub fn len(&self, row: usize) -> usize {
+        self.columns.get(row).map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// is_empty is true if the row has no residues.
+    pub fn is_empty(&self, row: usize) -> bool {
+        self.len(row) == 0
+    }
+}
+
+impl Alignment {
+    /// coordinate_map of the rows of the alignment.
+    pub fn coordinate_map(&self) -> CoordinateMap {
+        CoordinateMap::new(&self.rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinate_map() {
+        let map = CoordinateMap::new(&["AC--GTA".chars().collect(), "ACTTG-A".chars().collect()]);
+
+        assert_eq!(Some(4), map.column(0, 2));
+        assert_eq!(Some(2), map.residue(1, 2));
+        assert_eq!(None, map.residue(0, 2));
+
+        // G is residue 2 in row 0 and residue 4 in row 1
+        assert_eq!(Some(4), map.project(0, 2, 1));
+        // the T in row 0 is aligned to a gap
+        assert_eq!(None, map.project(0, 3, 1));
+        assert_eq!(Some((1, 5)), map.project_range(0, (1, 4), 1));
+        assert_eq!(5, map.len(0));
+    }
+}
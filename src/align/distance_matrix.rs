@@ -0,0 +1,453 @@
+//! All-vs-all percent identities of a set of sequences.
+//!
+//! Each pair is aligned globally with a score-only kernel: affine gap DP that
+//! keeps two rows of the grid and carries the number of identical columns and
+//! the length of the best path along with its score. That's enough for the
+//! identity without ever building the grid or traceback, so large sets are
+//! cheap in memory. Pairs are split across threads, each filling its rows of
+//! the matrix in place, and the matrix keeps only one triangle, so the
+//! distances of thousands of sequences fit in well under a gigabyte.
+//!
+//! Unknown residues, `N` in nucleotides and `X` in proteins, tell nothing of
+//! how alike two sequences are, but the runs of them that pad and scaffold
+//! draft sequences mismatch everything, so they can be left out of the
+//! columns the identity is counted over. So can the gaps before the first
+//! and after the last aligned pair of residues, which make a fragment look
+//! far from the full-length sequence it's a piece of.
+
+use std::{fmt::Write, thread};
+
+use crate::trace::stage;
+
+use super::Scoring;
+
+/// DistanceMatrix holds the pairwise distances of a set of sequences.
+///
+/// Distances are the fraction of alignment columns that differ, like
+/// [`Alignment::distance`](super::Alignment::distance), so the percent
+/// identity of a pair is `100 * (1 - distance)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceMatrix {
+    /// names of the sequences, in input order
+    pub names: Vec<String>,
+
+    /// row-major distances above the diagonal: those of 0 to 1..n, then of
+    /// 1 to 2..n, and so on
+    distances: Vec<f32>,
+}
+
+impl DistanceMatrix {
+    /// new creates a matrix of zero distances for the names.
+    pub fn new(names: Vec<String>) -> Self {
+        let n = names.len();
+        DistanceMatrix {
+            names,
+            distances: vec![0f32; n * n.saturating_sub(1) / 2],
+        }
+    }
+
+    /// len is the number of sequences in the matrix.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// is_empty is true if the matrix has no sequences.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// distance between sequences i and j.
+    pub fn distance(&self, i: usize, j: usize) -> f32 {
+        match i.cmp(&j) {
+            std::cmp::Ordering::Equal => 0f32,
+            std::cmp::Ordering::Less => self.distances[self.index(i, j)],
+            std::cmp::Ordering::Greater => self.distances[self.index(j, i)],
+        }
+    }
+
+    /// set the distance between sequences i and j, in both directions.
+    ///
+    /// The distance of a sequence to itself is always 0, so setting it does nothing.
+    pub fn set(&mut self, i: usize, j: usize, distance: f32) {
+        if i != j {
+            let index = self.index(i.min(j), i.max(j));
+            self.distances[index] = distance;
+        }
+    }
+
+    /// index of the distance of i to j, for i < j.
+    fn index(&self, i: usize, j: usize) -> usize {
+        self.row_start(i) + (j - i - 1)
+    }
+
+    /// row_start is the index of the distance of i to i + 1.
+    fn row_start(&self, i: usize) -> usize {
+        i * (2 * self.len() - i - 1) / 2
+    }
+
+    /// rows_mut are the distances of each sequence to those after it.
+    fn rows_mut(&mut self) -> Vec<&mut [f32]> {
+        let n = self.len();
+        let mut rows = Vec::with_capacity(n);
+        let mut rest = self.distances.as_mut_slice();
+        for i in 0..n {
+            let (row, tail) = rest.split_at_mut(n - 1 - i);
+            rows.push(row);
+            rest = tail;
+        }
+        rows
+    }
+
+    /// identity is the percent identity of sequences i and j.
+    pub fn identity(&self, i: usize, j: usize) -> f32 {
+        100f32 * (1f32 - self.distance(i, j))
+    }
+
+    /// to_csv writes the percent identities with a header row and column of names.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let header: Vec<String> = self.names.iter().map(|n| csv_field(n)).collect();
+        writeln!(csv, ",{}", header.join(",")).unwrap();
+        for (i, name) in self.names.iter().enumerate() {
+            let row: Vec<String> = (0..self.len())
+                .map(|j| format!("{:.2}", self.identity(i, j)))
+                .collect();
+            writeln!(csv, "{},{}", csv_field(name), row.join(",")).unwrap();
+        }
+        csv
+    }
+
+    /// to_phylip writes the distances as a square PHYLIP distance matrix.
+    ///
+    /// Names are padded or truncated to the 10 characters strict PHYLIP allows.
+    pub fn to_phylip(&self) -> String {
+        let mut phylip = String::new();
+        writeln!(phylip, "{}", self.len()).unwrap();
+        for (i, name) in self.names.iter().enumerate() {
+            let name: String = name.chars().take(10).collect();
+            write!(phylip, "{: <10}", name).unwrap();
+            for j in 0..self.len() {
+                write!(phylip, " {:.5}", self.distance(i, j)).unwrap();
+            }
+            writeln!(phylip).unwrap();
+        }
+        phylip
+    }
+}
+
+/// csv_field quotes a field if it has a comma or quote in it.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// DistanceConfig is which columns of the alignment of two sequences their
+/// distance is counted over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DistanceConfig {
+    /// how columns with an unknown residue count
+    pub unknown: UnknownResidues,
+
+    /// leave out the gaps before the first and after the last pair of
+    /// residues, where one sequence overhangs the other
+    pub exclude_terminal_gaps: bool,
+}
+
+/// UnknownResidues is how columns with an unknown residue, `N` or `X`, count
+/// toward the distance of two sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownResidues {
+    /// like any other column: an unknown residue is identical only to another
+    #[default]
+    Count,
+
+    /// not at all, as if the column weren't in the alignment. Gaps still count.
+    Ignore,
+}
+
+impl UnknownResidues {
+    /// counts is true if a column of the two residues counts.
+    fn counts(self, a: u8, b: u8) -> bool {
+        let unknown = |c: u8| matches!(c.to_ascii_uppercase(), b'N' | b'X');
+        self == UnknownResidues::Count || !(unknown(a) || unknown(b))
+    }
+}
+
+/// identity_matrix computes the pairwise percent identities of the sequences.
+///
+/// Sequences are named by their 1-based index; set `names` on the result to
+/// use the record IDs instead.
+pub fn identity_matrix<S: AsRef<str> + Sync>(seqs: &[S], scoring: &Scoring) -> DistanceMatrix {
+    identity_matrix_with(seqs, scoring, DistanceConfig::default())
+}
+
+/// identity_matrix_with is [`identity_matrix`] over a choice of columns.
+pub fn identity_matrix_with<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    scoring: &Scoring,
+    config: DistanceConfig,
+) -> DistanceMatrix {
+    identity_matrix_with_threads(seqs, scoring, config, 0)
+}
+
+/// identity_matrix_with_threads is [`identity_matrix`] on at most `threads`
+/// threads, or as many as there are cores if it's 0.
+///
+/// Each pair is aligned on its own and its distance stored by its indexes,
+/// with nothing summed across threads, so the matrix is the same however
+/// many threads there are.
+pub(super) fn identity_matrix_with_threads<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    scoring: &Scoring,
+    config: DistanceConfig,
+    threads: usize,
+) -> DistanceMatrix {
+    let mut matrix = DistanceMatrix::new((1..=seqs.len()).map(|i| i.to_string()).collect());
+
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        threads => threads,
+    };
+    let _stage = stage!("distance_matrix", seqs = seqs.len(), threads = threads);
+
+    // rows are dealt out in turn, so each thread gets short and long rows
+    let mut dealt: Vec<Vec<(usize, &mut [f32])>> = (0..threads).map(|_| vec![]).collect();
+    for (i, row) in matrix.rows_mut().into_iter().enumerate() {
+        dealt[i % threads].push((i, row));
+    }
+    thread::scope(|s| {
+        for rows in dealt {
+            s.spawn(move || {
+                for (i, row) in rows {
+                    for (offset, d) in row.iter_mut().enumerate() {
+                        let j = i + 1 + offset;
+                        let (a, b) = (seqs[i].as_ref(), seqs[j].as_ref());
+                        *d = 1f32 - identity(a.as_bytes(), b.as_bytes(), scoring, config);
+                    }
+                }
+            });
+        }
+    });
+    matrix
+}
+
+/// Cell of the score-only grid: the best score ending here and its path's stats.
+#[derive(Clone, Copy)]
+struct Cell {
+    score: f32,
+    matches: u32,
+    columns: u32,
+
+    /// gap columns since the last pair of residues, or since the start if
+    /// there's been none, when terminal gaps are left out. They count once
+    /// a pair follows them.
+    pending: u32,
+
+    /// whether the path has a pair of residues
+    paired: bool,
+}
+
+impl Cell {
+    const NONE: Cell = Cell {
+        score: f32::NEG_INFINITY,
+        matches: 0,
+        columns: 0,
+        pending: 0,
+        paired: false,
+    };
+
+    const START: Cell = Cell {
+        score: 0f32,
+        ..Cell::NONE
+    };
+
+    /// pair extends the path by a column of two residues, which may not
+    /// count toward its identity.
+    fn pair(self, score: f32, is_match: bool, counts: bool) -> Cell {
+        let pending = if self.paired { self.pending } else { 0 };
+        Cell {
+            score: self.score + score,
+            matches: self.matches + (counts && is_match) as u32,
+            columns: self.columns + pending + counts as u32,
+            pending: 0,
+            paired: true,
+        }
+    }
+
+    /// gap extends the path by a column with a gap, held back until a pair
+    /// follows it if terminal gaps are left out.
+    fn gap(self, score: f32, config: DistanceConfig) -> Cell {
+        let held = config.exclude_terminal_gaps as u32;
+        Cell {
+            score: self.score + score,
+            columns: self.columns + 1 - held,
+            pending: self.pending + held,
+            ..self
+        }
+    }
+
+    /// better of two cells, preferring more identical columns on ties.
+    fn max(self, other: Cell) -> Cell {
+        if other.score > self.score || (other.score == self.score && other.matches > self.matches) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// identity of the best global alignment of a and b, as a fraction of its
+/// columns that count. It's 0 if none do.
+pub(super) fn identity(a: &[u8], b: &[u8], scoring: &Scoring, config: DistanceConfig) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1f32;
+    }
+
+    let (open, extend) = (scoring.gap_opening, scoring.gap_extension);
+
+    // m: ends in a match/mismatch, x: ends in a gap in b, y: ends in a gap in a
+    let mut m = vec![Cell::NONE; a.len() + 1];
+    let mut x = vec![Cell::NONE; a.len() + 1];
+    let mut y = vec![Cell::NONE; a.len() + 1];
+    m[0] = Cell::START;
+    for j in 1..=a.len() {
+        x[j] = m[j - 1]
+            .max(y[j - 1])
+            .gap(open, config)
+            .max(x[j - 1].gap(extend, config));
+    }
+
+    for i in 1..=b.len() {
+        let (mut m_diag, mut x_diag, mut y_diag) = (m[0], x[0], y[0]);
+        m[0] = Cell::NONE;
+        x[0] = Cell::NONE;
+        y[0] = y_diag
+            .max(m_diag)
+            .gap(if i == 1 { open } else { extend }, config);
+
+        for j in 1..=a.len() {
+            let (m_up, x_up, y_up) = (m[j], x[j], y[j]);
+
+            let sub = scoring.matrix[a[j - 1] as usize][b[i - 1] as usize] as f32;
+            m[j] = m_diag.max(x_diag).max(y_diag).pair(
+                sub,
+                a[j - 1] == b[i - 1],
+                config.unknown.counts(a[j - 1], b[i - 1]),
+            );
+            x[j] = m[j - 1]
+                .max(y[j - 1])
+                .gap(open, config)
+                .max(x[j - 1].gap(extend, config));
+            y[j] = m_up
+                .max(x_up)
+                .gap(open, config)
+                .max(y_up.gap(extend, config));
+
+            (m_diag, x_diag, y_diag) = (m_up, x_up, y_up);
+        }
+    }
+
+    let end = m[a.len()].max(x[a.len()]).max(y[a.len()]);
+    match end.columns {
+        0 => 0f32,
+        columns => end.matches as f32 / columns as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identity() {
+        let scoring = scoring();
+        let identity = |a: &[u8], b: &[u8]| identity(a, b, &scoring, DistanceConfig::default());
+        assert_eq!(1f32, identity(b"ACGTACGT", b"ACGTACGT"));
+        assert_eq!(0.875, identity(b"ACGTACGT", b"ACGAACGT"));
+        // one 2bp gap: 8 identical columns of 10
+        assert_eq!(0.8, identity(b"ACGTTTACGT", b"ACGTACGT"));
+        assert_eq!(0f32, identity(b"ACGT", b""));
+    }
+
+    #[test]
+    fn test_identity_unknown() {
+        let scoring = scoring();
+        let ignore = DistanceConfig {
+            unknown: UnknownResidues::Ignore,
+            ..Default::default()
+        };
+        let (a, b) = (b"NNNACGTACGTNN", b"TCAACGTACGTGA");
+        assert_eq!(
+            8f32 / 13f32,
+            identity(a, b, &scoring, DistanceConfig::default())
+        );
+        assert_eq!(1f32, identity(a, b, &scoring, ignore));
+        // a mismatch still counts
+        assert_eq!(
+            0.875,
+            identity(b"NNACGAACGT", b"ACACGTACGT", &scoring, ignore)
+        );
+        assert_eq!(0f32, identity(b"NNNN", b"ACGT", &scoring, ignore));
+    }
+
+    #[test]
+    fn test_identity_terminal_gaps() {
+        let scoring = scoring();
+        let exclude = DistanceConfig {
+            exclude_terminal_gaps: true,
+            ..Default::default()
+        };
+        let (full, fragment) = (b"TTTTTTACGTACGTACGTGGGGGG", b"ACGTACGTACGT");
+        assert_eq!(
+            0.5,
+            identity(full, fragment, &scoring, DistanceConfig::default())
+        );
+        assert_eq!(1f32, identity(full, fragment, &scoring, exclude));
+        assert_eq!(1f32, identity(fragment, full, &scoring, exclude));
+        // an internal gap still counts: 8 identical columns of 10
+        assert_eq!(0.8, identity(b"ACGTTTACGT", b"ACGTACGT", &scoring, exclude));
+    }
+
+    #[test]
+    fn test_identity_matrix() {
+        let seqs = ["ACGTACGT", "ACGAACGT", "ACGTACGT"];
+        let mut matrix = identity_matrix(&seqs, &scoring());
+        matrix.names = vec!["a".into(), "b,1".into(), "c".into()];
+
+        assert_eq!(3, matrix.len());
+        assert_eq!(100f32, matrix.identity(0, 2));
+        assert_eq!(87.5, matrix.identity(1, 0));
+        assert_eq!(0f32, matrix.distance(1, 1));
+
+        assert_eq!(
+            ",a,\"b,1\",c
+a,100.00,87.50,100.00
+\"b,1\",87.50,100.00,87.50
+c,100.00,87.50,100.00
+",
+            matrix.to_csv()
+        );
+        assert_eq!(
+            "3
+a          0.00000 0.12500 0.00000
+b,1        0.12500 0.00000 0.12500
+c          0.00000 0.12500 0.00000
+",
+            matrix.to_phylip()
+        );
+    }
+}
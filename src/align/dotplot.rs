@@ -0,0 +1,74 @@
+//! Dot plots of two sequences as data.
+//!
+//! A dot is a word of `k` residues that's in both sequences. The dots are
+//! also binned into square windows so a viewer can draw a density plot of
+//! long sequences without drawing millions of dots, and windows with fewer
+//! dots than a threshold are cleared to drop random matches.
+
+use std::collections::HashMap;
+
+/// Dotplot holds the matching words of two sequences.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dotplot {
+    /// 0-based start in a and b of each matching word, by position in a then b
+    pub dots: Vec<(usize, usize)>,
+
+    /// residues of each sequence per density window
+    pub window: usize,
+
+    /// dots per window, by window of a and then window of b
+    pub density: Vec<Vec<usize>>,
+}
+
+/// dotplot finds the words of length k shared by two sequences.
+///
+/// Windows of `window` residues with fewer than `threshold` dots have a
+/// density of 0; their dots are still in `dots`.
+pub fn dotplot(a: &str, b: &str, k: usize, window: usize, threshold: usize) -> Dotplot {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (k, window) = (k.max(1), window.max(1));
+
+    let mut words: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (j, word) in b.windows(k).enumerate() {
+        words.entry(word).or_default().push(j);
+    }
+
+    let mut dots = Vec::new();
+    for (i, word) in a.windows(k).enumerate() {
+        if let Some(js) = words.get(word) {
+            dots.extend(js.iter().map(|j| (i, *j)));
+        }
+    }
+
+    let mut density = vec![vec![0; b.len().div_ceil(window)]; a.len().div_ceil(window)];
+    for (i, j) in dots.iter() {
+        density[i / window][j / window] += 1;
+    }
+    for count in density.iter_mut().flatten() {
+        if *count < threshold {
+            *count = 0;
+        }
+    }
+
+    Dotplot {
+        dots,
+        window,
+        density,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dotplot() {
+        let plot = dotplot("ACGTAC", "TACG", 3, 2, 2);
+        assert_eq!(vec![(0, 1), (3, 0)], plot.dots);
+        assert_eq!(vec![vec![0, 0], vec![0, 0], vec![0, 0]], plot.density);
+
+        let plot = dotplot("ACGTAC", "TACG", 2, 2, 1);
+        assert_eq!(vec![(0, 1), (1, 2), (3, 0), (4, 1)], plot.dots);
+        assert_eq!(vec![vec![1, 1], vec![1, 0], vec![1, 0]], plot.density);
+    }
+}
@@ -0,0 +1,126 @@
+//! Scoring derived from the error profile of a sequencing technology.
+//!
+//! Scores are log-odds in half-bits: how much more likely a match, a
+//! mismatch, or a gap is between a read and where it came from than between
+//! unrelated sequences. A read with errors mostly in indels, like nanopore,
+//! gets cheaper gaps and a steeper mismatch penalty than the defaults, which
+//! are meant for diverged homologs rather than noisy reads.
+
+use crate::matrices::NUC_4_4;
+
+use super::Scoring;
+
+/// smallest probability of an event, so none is impossible
+const MIN_PROB: f64 = 1e-6;
+
+/// ErrorProfile is the per-base error rates of reads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorProfile {
+    /// fraction of bases that are errors
+    pub error_rate: f64,
+
+    /// fraction of errors that are insertions or deletions rather than substitutions
+    pub indel_fraction: f64,
+
+    /// mean length of an insertion or deletion, at least 1
+    pub mean_indel_len: f64,
+}
+
+impl ErrorProfile {
+    /// ONT nanopore reads, about 10% error and mostly indels.
+    pub const NANOPORE: ErrorProfile = ErrorProfile {
+        error_rate: 0.10,
+        indel_fraction: 0.6,
+        mean_indel_len: 1.5,
+    };
+
+    /// PacBio continuous long reads, about 12% error and nearly all indels.
+    pub const PACBIO_CLR: ErrorProfile = ErrorProfile {
+        error_rate: 0.12,
+        indel_fraction: 0.85,
+        mean_indel_len: 1.3,
+    };
+
+    /// PacBio HiFi reads, well under 1% error.
+    pub const PACBIO_HIFI: ErrorProfile = ErrorProfile {
+        error_rate: 0.005,
+        indel_fraction: 0.7,
+        mean_indel_len: 1.2,
+    };
+
+    /// Illumina short reads, rare errors that are nearly all substitutions.
+    pub const ILLUMINA: ErrorProfile = ErrorProfile {
+        error_rate: 0.005,
+        indel_fraction: 0.05,
+        mean_indel_len: 1.1,
+    };
+
+    /// scoring for aligning DNA reads with this profile to their reference.
+    ///
+    /// Matches of A, C, G and T score at least 1 and mismatches at most -1.
+    /// Ambiguity codes keep the scores of NUC.4.4, scaled to the match score.
+    pub fn scoring(&self) -> Scoring {
+        let e = self.error_rate.clamp(MIN_PROB, 1f64 - MIN_PROB);
+        let f = self.indel_fraction.clamp(0f64, 1f64);
+        let substitution = (e * (1f64 - f)).max(MIN_PROB);
+        let indel = (e * f).max(MIN_PROB);
+        let extend = (1f64 - 1f64 / self.mean_indel_len.max(1f64)).max(MIN_PROB);
+
+        // against pairs of uniform random bases, 1/16 each
+        let half_bits = |p: f64| 2f64 * p.log2();
+        let matched = half_bits(4f64 * (1f64 - e)).round().max(1f64) as i32;
+        let mismatched = half_bits(4f64 * substitution / 3f64).round().min(-1f64) as i32;
+
+        let mut matrix = NUC_4_4::MATRIX;
+        for (a, row) in matrix.iter_mut().enumerate() {
+            for (b, score) in row.iter_mut().enumerate() {
+                if *score == i32::MIN {
+                    continue;
+                }
+                let (a, b) = (a as u8 as char, b as u8 as char);
+                *score = match (is_base(a), is_base(b)) {
+                    (true, true) if a == b => matched,
+                    (true, true) => mismatched,
+                    _ => (*score as f64 * matched as f64 / 5f64).round() as i32,
+                };
+            }
+        }
+
+        Scoring {
+            matrix,
+            // a gap starts on either sequence
+            gap_opening: half_bits(indel / 2f64).min(-1f64) as f32,
+            gap_extension: half_bits(extend).min(0f64) as f32,
+            ..Default::default()
+        }
+    }
+}
+
+fn is_base(c: char) -> bool {
+    matches!(c, 'A' | 'C' | 'G' | 'T')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoring() {
+        let nanopore = ErrorProfile::NANOPORE.scoring();
+        let m = |a: char, b: char| nanopore.matrix[a as usize][b as usize];
+        assert_eq!(4, m('A', 'A'));
+        assert_eq!(-8, m('A', 'C'));
+        assert_eq!(-2, m('A', 'N'));
+        assert!((nanopore.gap_opening - -10.1).abs() < 0.1);
+        assert!((nanopore.gap_extension - -3.2).abs() < 0.1);
+
+        // HiFi reads have few errors, so every kind is penalized more
+        let hifi = ErrorProfile::PACBIO_HIFI.scoring();
+        assert!(hifi.matrix[b'A' as usize][b'C' as usize] < m('A', 'C'));
+        assert!(hifi.gap_opening < nanopore.gap_opening);
+
+        // Illumina's errors are substitutions, so gaps cost more than mismatches
+        let illumina = ErrorProfile::ILLUMINA.scoring();
+        assert!(illumina.gap_opening < illumina.matrix[b'A' as usize][b'C' as usize] as f32);
+    }
+}
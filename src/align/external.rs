@@ -0,0 +1,210 @@
+//! Out-of-core global alignment for grids that don't fit in memory.
+//!
+//! The scores of only two rows of the grid are kept in memory. Each row's
+//! traceback is packed and appended to a file as it's filled, and the walk
+//! back from the end reads the file a tile of rows at a time, last tile
+//! first. Memory is bounded by the tile size rather than the grid, so an
+//! exact alignment of multi-megabase regions can finish; it's slower than
+//! an in-memory one because every tile goes through the disk. Only the grid
+//! is out-of-core: the two sequences are held in memory, since they're
+//! linear in size and fit even at chromosome scale.
+//!
+//! With the `mmap` feature the file is memory-mapped for the walk back, so
+//! tiles are decoded straight from the page cache rather than being copied
+//! through reads, and the OS pages the file in and out as it's walked.
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{
+    fixed::{Precision, Score},
+    gotoh::{self, Affine, Row},
+    traceback::Traceback,
+    Alignment, Error, Result, Scoring,
+};
+
+/// ExternalConfig configures an out-of-core alignment.
+#[derive(Clone, Debug)]
+pub struct ExternalConfig {
+    /// directory for the traceback file, which is removed when done
+    pub dir: PathBuf,
+
+    /// bytes of traceback to read into memory at a time
+    pub tile_bytes: usize,
+}
+
+impl Default for ExternalConfig {
+    fn default() -> Self {
+        ExternalConfig {
+            dir: std::env::temp_dir(),
+            tile_bytes: 256 << 20,
+        }
+    }
+}
+
+// traceback files made by this process, for unique names
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// align_external aligns two sequences globally with affine gaps, keeping
+/// the traceback on disk.
+///
+/// A gap of length L costs `gap_opening + gap_extension * (L - 1)`, terminal
+/// gaps included.
+pub fn align_external(
+    a: &str,
+    b: &str,
+    scoring: &Scoring,
+    config: &ExternalConfig,
+) -> Result<Alignment> {
+    let path = config.dir.join(format!(
+        "seqalign-traceback-{}-{}",
+        process::id(),
+        FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let alignment = match scoring.precision {
+        Precision::Float => tiled::<f32>(a, b, scoring, 1, config, &path),
+        Precision::Fixed(scale) => tiled::<i64>(a, b, scoring, scale.max(1), config, &path),
+    };
+    let removed = fs::remove_file(&path);
+    let alignment = alignment.map_err(Error::TileError)?;
+    removed.map_err(Error::TileError)?;
+    Ok(alignment)
+}
+
+/// tiled fills the grid writing each row's traceback to `path`, then walks
+/// back through it. Scores are summed as `S`, scaled by `scale`.
+fn tiled<S: Score>(
+    a: &[u8],
+    b: &[u8],
+    scoring: &Scoring,
+    scale: u32,
+    config: &ExternalConfig,
+    path: &Path,
+) -> std::io::Result<Alignment> {
+    let (na, nb) = (a.len(), b.len());
+    let rules = Affine::new(scoring, scale, gotoh::matrix::<S>(a, b, scoring, scale));
+    let codes = 3 * (na + 1);
+
+    let mut file = BufWriter::new(File::create(path)?);
+    let (mut prev, mut cur) = (Row::empty(), Row::empty());
+    for i in 0..=nb {
+        let mut row = Traceback::new(codes);
+        gotoh::fill_row(&rules, i, (0, na), &prev, &mut cur, &mut row);
+        row.write_to(&mut file)?;
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    drop(file);
+
+    // walk back from the bottom-right corner, a tile of rows at a time
+    let row_bytes = Traceback::byte_len(codes);
+    let tile_rows = (config.tile_bytes / row_bytes).max(1);
+    let mut tiles = Tiles::open(path, row_bytes)?;
+    let mut tile: (usize, Vec<Traceback>) = (usize::MAX, Vec::new());
+
+    let (state, score) = gotoh::best(prev.get(na));
+    let path = gotoh::walk_back((nb, na), state, |i, j, state| {
+        if i < tile.0 || i >= tile.0 + tile.1.len() {
+            let start = i - i % tile_rows;
+            let rows = tiles.read(start..(start + tile_rows).min(nb + 1), codes)?;
+            tile = (start, rows);
+        }
+        Ok::<_, std::io::Error>(tile.1[i - tile.0].get(3 * j + state as usize))
+    })?;
+
+    Ok(Alignment::new(path.rows(a, b), vec![], score.to_f32(scale)))
+}
+
+/// Tiles reads the rows of a traceback file.
+struct Tiles {
+    #[cfg(not(feature = "mmap"))]
+    file: File,
+
+    #[cfg(feature = "mmap")]
+    map: memmap2::Mmap,
+
+    row_bytes: usize,
+}
+
+impl Tiles {
+    fn open(path: &Path, row_bytes: usize) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Tiles {
+            #[cfg(not(feature = "mmap"))]
+            file,
+            // SAFETY: the file is private to this alignment and isn't
+            // written again while it's mapped
+            #[cfg(feature = "mmap")]
+            map: unsafe { memmap2::Mmap::map(&file)? },
+            row_bytes,
+        })
+    }
+
+    /// read decodes the traceback of each row in `rows`.
+    #[cfg(not(feature = "mmap"))]
+    fn read(&mut self, rows: Range<usize>, codes: usize) -> std::io::Result<Vec<Traceback>> {
+        use std::io::{Seek, SeekFrom};
+
+        self.file
+            .seek(SeekFrom::Start((rows.start * self.row_bytes) as u64))?;
+        rows.map(|_| Traceback::read_from(&mut self.file, codes))
+            .collect()
+    }
+
+    /// read decodes the traceback of each row in `rows`.
+    #[cfg(feature = "mmap")]
+    fn read(&mut self, rows: Range<usize>, codes: usize) -> std::io::Result<Vec<Traceback>> {
+        let mut bytes = self
+            .map
+            .get(rows.start * self.row_bytes..rows.end * self.row_bytes)
+            .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        rows.map(|_| Traceback::read_from(&mut bytes, codes))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_banded, Band},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_align_external() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let a = "ACGTTGCAAGTCAGGCTAACGTTAGCCATGCAAGT".repeat(4);
+        let b = format!("{}TTTT{}", &a[..50], &a[60..]);
+
+        // a tile of a single row
+        let config = ExternalConfig {
+            tile_bytes: 1,
+            ..Default::default()
+        };
+        let alignment = align_external(&a, &b, &scoring, &config).unwrap();
+        let expected = align_banded(
+            &a,
+            &b,
+            &scoring,
+            &Band {
+                width: a.len(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(expected.to_string(), alignment.to_string());
+        assert_eq!(expected.score, alignment.score);
+    }
+}
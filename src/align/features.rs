@@ -0,0 +1,149 @@
+//! Projects annotated features from one sequence of an alignment onto another.
+//!
+//! A feature (CDS, primer, promoter) on a reference is carried over to the
+//! aligned partner through the coordinate map, along with a count of the
+//! substitutions and indels inside it, so it's clear which features an edit
+//! or sequencing result disrupts.
+
+use crate::io::genbank::{Feature, Location};
+
+use super::Alignment;
+
+/// ProjectedFeature is a feature carried over onto another row of an alignment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectedFeature {
+    /// the original feature
+    pub feature: Feature,
+
+    /// location of the feature in the other row, None if that row is all gap there
+    pub location: Option<Location>,
+
+    /// number of mismatched columns inside the feature
+    pub substitutions: usize,
+
+    /// number of residues inserted inside the feature in the other row
+    pub inserted: usize,
+
+    /// number of residues of the feature deleted in the other row
+    pub deleted: usize,
+}
+
+impl ProjectedFeature {
+    /// is_disrupted is true if the feature has an indel or is missing entirely.
+    pub fn is_disrupted(&self) -> bool {
+        self.location.is_none() || self.inserted > 0 || self.deleted > 0
+    }
+
+    /// is_frameshift is true for a CDS whose indels change its length by other than a multiple of 3.
+    pub fn is_frameshift(&self) -> bool {
+        self.feature.kind == "CDS" && (self.inserted as i64 - self.deleted as i64) % 3 != 0
+    }
+}
+
+impl Alignment {
+    /// project_features of the `from` row onto the `to` row.
+    ///
+    /// Feature locations are positions in the ungapped `from` sequence, so the
+    /// alignment should cover the whole sequence (a global alignment).
+    pub fn project_features(
+        &self,
+        features: &[Feature],
+        from: usize,
+        to: usize,
+    ) -> Vec<ProjectedFeature> {
+        let map = self.coordinate_map();
+
+        features
+            .iter()
+            .map(|feature| {
+                let mut projected = ProjectedFeature {
+                    feature: feature.clone(),
+                    location: None,
+                    substitutions: 0,
+                    inserted: 0,
+                    deleted: 0,
+                };
+
+                let mut ranges = Vec::new();
+                for range in feature.location.ranges.iter() {
+                    let Some((start, end)) = map.column_range(from, *range) else {
+                        continue;
+                    };
+                    for col in start..end {
+                        match (self.rows[from][col], self.rows[to][col]) {
+                            ('-', '-') => {}
+                            ('-', _) => projected.inserted += 1,
+                            (_, '-') => projected.deleted += 1,
+                            (a, b) if a != b => projected.substitutions += 1,
+                            _ => {}
+                        }
+                    }
+                    ranges.extend(map.project_range(from, *range, to));
+                }
+
+                if !ranges.is_empty() {
+                    projected.location = Some(Location {
+                        ranges,
+                        strand: feature.location.strand,
+                    });
+                }
+                projected
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(kind: &str, start: usize, end: usize) -> Feature {
+        Feature {
+            kind: kind.to_string(),
+            location: Location {
+                ranges: vec![(start, end)],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_project_features() {
+        let alignment = Alignment::new(
+            vec![
+                "ATGAAACCCGGG--TTTTAA".chars().collect(),
+                "ATGAAACCTGGGAATT--AA".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+
+        let projected = alignment.project_features(
+            &[
+                feature("CDS", 0, 18),
+                feature("primer_bind", 0, 6),
+                feature("misc_feature", 14, 16),
+            ],
+            0,
+            1,
+        );
+
+        let cds = &projected[0];
+        assert_eq!(1, cds.substitutions);
+        assert_eq!(2, cds.inserted);
+        assert_eq!(2, cds.deleted);
+        assert!(cds.is_disrupted());
+        assert!(!cds.is_frameshift());
+        assert_eq!(vec![(0, 18)], cds.location.as_ref().unwrap().ranges);
+
+        let primer = &projected[1];
+        assert!(!primer.is_disrupted());
+        assert_eq!(0, primer.substitutions);
+        assert_eq!(vec![(0, 6)], primer.location.as_ref().unwrap().ranges);
+
+        // entirely deleted in the other sequence
+        assert_eq!(None, projected[2].location);
+        assert!(projected[2].is_disrupted());
+    }
+}
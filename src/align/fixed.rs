@@ -0,0 +1,76 @@
+//! Fixed-point alignment scores.
+//!
+//! An f32 has 24 bits of mantissa, so past 2^24 it can't hold every integer,
+//! and a fractional penalty like -0.1 is never exact. Summed over the
+//! millions of cells of a long alignment the rounding adds up, and two paths
+//! whose exact scores tie, or differ by less than an ulp, can be ordered by
+//! where they happened to round, which changes the traceback. Fixed-point
+//! scores are integer multiples of 1/scale: the matrix and penalties are
+//! scaled and rounded once, every sum after that is exact, and only the final
+//! score is converted back to an f32.
+
+use std::ops::Add;
+
+/// Precision is the number type scores are summed in.
+///
+/// The pairwise aligners and batch scoring honor it. It's pairwise only: the
+/// distances and profile alignment of ClustalW, and graph alignment, always
+/// sum in f32.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// f32, fastest, fine for integer scores below 2^24
+    #[default]
+    Float,
+
+    /// i64 in units of 1/scale, exact for penalties that are multiples of it
+    Fixed(u32),
+}
+
+impl Precision {
+    /// Fixed with a scale of 1000, exact to three decimal places.
+    pub const FIXED: Precision = Precision::Fixed(1000);
+}
+
+/// Score is a number a dynamic programming grid is filled with.
+pub(crate) trait Score: Copy + PartialOrd + Add<Output = Self> {
+    /// below any reachable score, and safe to add penalties to
+    const NEG_INFINITY: Self;
+
+    /// the score of an empty path
+    const ZERO: Self;
+
+    /// from_f32 converts a score to this type, scaled by `scale`.
+    fn from_f32(score: f32, scale: u32) -> Self;
+
+    /// to_f32 converts a score back, undoing `scale`.
+    fn to_f32(self, scale: u32) -> f32;
+}
+
+impl Score for f32 {
+    const NEG_INFINITY: Self = f32::NEG_INFINITY;
+
+    const ZERO: Self = 0f32;
+
+    fn from_f32(score: f32, _: u32) -> Self {
+        score
+    }
+
+    fn to_f32(self, _: u32) -> f32 {
+        self
+    }
+}
+
+impl Score for i64 {
+    // far enough from i64::MIN that penalties added to it don't overflow
+    const NEG_INFINITY: Self = i64::MIN / 4;
+
+    const ZERO: Self = 0;
+
+    fn from_f32(score: f32, scale: u32) -> Self {
+        (score as f64 * scale as f64).round() as i64
+    }
+
+    fn to_f32(self, scale: u32) -> f32 {
+        (self as f64 / scale as f64) as f32
+    }
+}
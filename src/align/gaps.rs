@@ -0,0 +1,83 @@
+//! The characters gaps are written with.
+//!
+//! Alignments always use `-` for gaps internally. Output can use another
+//! character, like the `.` of some formats, and can write terminal gaps, the
+//! missing data before a sequence starts and after it ends, differently from
+//! deletions within it, as Stockholm files for HMMER do.
+
+use super::{Alignment, MSAlignment};
+
+/// GapChars are the characters gaps are written with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GapChars {
+    /// character of gaps within a row
+    pub gap: char,
+
+    /// character of gaps before the first and after the last residue of a
+    /// row, None to use `gap`
+    pub terminal: Option<char>,
+}
+
+impl Default for GapChars {
+    fn default() -> Self {
+        GapChars {
+            gap: '-',
+            terminal: None,
+        }
+    }
+}
+
+impl GapChars {
+    /// render a gapped row with these characters.
+    pub fn render(&self, row: &[char]) -> String {
+        let first = row.iter().position(|c| *c != '-').unwrap_or(row.len());
+        let last = row.iter().rposition(|c| *c != '-').map_or(0, |l| l + 1);
+        let terminal = self.terminal.unwrap_or(self.gap);
+        row.iter()
+            .enumerate()
+            .map(|(col, c)| match c {
+                '-' if col < first || col >= last => terminal,
+                '-' => self.gap,
+                c => *c,
+            })
+            .collect()
+    }
+}
+
+impl Alignment {
+    /// rows_with are the rows of the alignment written with gap characters.
+    pub fn rows_with(&self, gaps: &GapChars) -> Vec<String> {
+        self.rows.iter().map(|r| gaps.render(r)).collect()
+    }
+}
+
+impl MSAlignment {
+    /// rows_with are the rows of the alignment written with gap characters.
+    pub fn rows_with(&self, gaps: &GapChars) -> Vec<String> {
+        self.rows.iter().map(|r| gaps.render(r)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let row: Vec<char> = "--AC-GT---".chars().collect();
+        assert_eq!("--AC-GT---", GapChars::default().render(&row));
+
+        let gaps = GapChars {
+            gap: '-',
+            terminal: Some('.'),
+        };
+        assert_eq!("..AC-GT...", gaps.render(&row));
+        assert_eq!("....", gaps.render(&['-'; 4]));
+
+        let gaps = GapChars {
+            gap: '.',
+            terminal: None,
+        };
+        assert_eq!("..AC.GT...", gaps.render(&row));
+    }
+}
@@ -0,0 +1,426 @@
+//! The affine gap kernel shared by the pairwise aligners.
+//!
+//! Gotoh's recurrence keeps three scores for each cell of the grid: the best
+//! path ending in a pair of residues, in a gap in a, and in a gap in b. The
+//! aligners differ in what a pair and a gap cost in each cell and in whether
+//! a path can start at any pair, which are their [`Rules`], and in which
+//! cells they fill and where the path ends. Each fills the rows it needs with
+//! [`fill_row`], picks the end, and follows the traceback with [`walk_back`].
+//!
+//! Row i of the grid is residue i of b and column j is residue j of a, with
+//! row and column 0 before the first residue.
+
+use std::convert::Infallible;
+
+use super::{fixed::Score, traceback::Traceback, Scoring};
+
+// traceback states, START is only the start of a local alignment
+pub(crate) const DIAGONAL: u8 = 0;
+pub(crate) const UP: u8 = 1;
+pub(crate) const LEFT: u8 = 2;
+pub(super) const START: u8 = 3;
+
+/// Cell is the best score of a cell ending in each state.
+pub(crate) type Cell<S> = [S; 3];
+
+/// Gap is the cost of opening and of extending a gap into a cell.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Gap<S> {
+    pub(crate) open: S,
+    pub(crate) extend: S,
+}
+
+/// Rules are what an aligner charges in each cell of the grid.
+pub(super) trait Rules {
+    type Score: Score;
+
+    /// substitution is the score of a[j - 1] over b[i - 1], into cell (i, j).
+    fn substitution(&self, i: usize, j: usize) -> Self::Score;
+
+    /// up is the cost of a gap in a, against b[i - 1], into cell (i, j).
+    fn up(&self, i: usize, j: usize) -> Gap<Self::Score>;
+
+    /// left is the cost of a gap in b, against a[j - 1], into cell (i, j).
+    fn left(&self, i: usize, j: usize) -> Gap<Self::Score>;
+
+    /// local is whether a path can start at any pair, as in Smith-Waterman,
+    /// rather than only from the first cell.
+    fn local(&self) -> bool {
+        false
+    }
+}
+
+/// Affine charges the same for a gap anywhere, and a pair by a function of
+/// its cell.
+pub(super) struct Affine<S, F> {
+    substitution: F,
+    gap: Gap<S>,
+    local: bool,
+}
+
+impl<S: Score, F: Fn(usize, usize) -> S> Affine<S, F> {
+    /// new scores pairs with `substitution(i, j)` and gaps with the penalties
+    /// of `scoring`, scaled by `scale`.
+    pub(super) fn new(scoring: &Scoring, scale: u32, substitution: F) -> Self {
+        Affine {
+            substitution,
+            gap: Gap {
+                open: S::from_f32(scoring.gap_opening, scale),
+                extend: S::from_f32(scoring.gap_extension, scale),
+            },
+            local: false,
+        }
+    }
+
+    /// local lets the path start at any pair.
+    pub(super) fn local(self) -> Self {
+        Affine {
+            local: true,
+            ..self
+        }
+    }
+}
+
+impl<S: Score, F: Fn(usize, usize) -> S> Rules for Affine<S, F> {
+    type Score = S;
+
+    fn substitution(&self, i: usize, j: usize) -> S {
+        (self.substitution)(i, j)
+    }
+
+    fn up(&self, _: usize, _: usize) -> Gap<S> {
+        self.gap
+    }
+
+    fn left(&self, _: usize, _: usize) -> Gap<S> {
+        self.gap
+    }
+
+    fn local(&self) -> bool {
+        self.local
+    }
+}
+
+/// matrix is the substitution of a over b by the matrix of `scoring`, scaled
+/// by `scale`.
+pub(super) fn matrix<'a, S: Score>(
+    a: &'a [u8],
+    b: &'a [u8],
+    scoring: &'a Scoring,
+    scale: u32,
+) -> impl Fn(usize, usize) -> S + 'a {
+    move |i, j| {
+        S::from_f32(
+            scoring.matrix[a[j - 1] as usize][b[i - 1] as usize] as f32,
+            scale,
+        )
+    }
+}
+
+/// Row is the filled cells of a row of the grid, from column `lo`.
+#[derive(Clone, Debug)]
+pub(super) struct Row<S> {
+    pub(super) lo: usize,
+    pub(super) cells: Vec<Cell<S>>,
+}
+
+impl<S: Score> Row<S> {
+    /// empty is a row with no cells filled, like the one before the first.
+    pub(super) fn empty() -> Self {
+        Row {
+            lo: 0,
+            cells: Vec::new(),
+        }
+    }
+
+    /// get the cell in column j, unreachable if it isn't filled.
+    pub(super) fn get(&self, j: usize) -> Cell<S> {
+        match j.checked_sub(self.lo) {
+            Some(k) if k < self.cells.len() => self.cells[k],
+            _ => [S::NEG_INFINITY; 3],
+        }
+    }
+}
+
+/// Codes is where the traceback of a row goes.
+pub(super) trait Codes {
+    fn set(&mut self, index: usize, code: u8);
+}
+
+impl Codes for Traceback {
+    fn set(&mut self, index: usize, code: u8) {
+        Traceback::set(self, index, code)
+    }
+}
+
+/// the traceback is dropped when only the score is needed
+impl Codes for () {
+    fn set(&mut self, _: usize, _: u8) {}
+}
+
+/// fill_row fills columns lo..=hi of row i into `row` from the row above it.
+/// The previous state of state s in column j goes to `3 * (j - lo) + s` of
+/// `codes`.
+pub(super) fn fill_row<R: Rules, C: Codes>(
+    rules: &R,
+    i: usize,
+    (lo, hi): (usize, usize),
+    above: &Row<R::Score>,
+    row: &mut Row<R::Score>,
+    codes: &mut C,
+) {
+    let unreachable = [R::Score::NEG_INFINITY; 3];
+    row.lo = lo;
+    row.cells.clear();
+    for j in lo..=hi {
+        if i == 0 && j == 0 {
+            row.cells.push(match rules.local() {
+                true => unreachable,
+                false => [
+                    R::Score::ZERO,
+                    R::Score::NEG_INFINITY,
+                    R::Score::NEG_INFINITY,
+                ],
+            });
+            continue;
+        }
+
+        let k = 3 * (j - lo);
+        let mut cell = unreachable;
+        if i > 0 && j > 0 {
+            let x = rules.substitution(i, j);
+            let (state, score) = diagonal(above.get(j - 1), x, rules.local());
+            cell[DIAGONAL as usize] = score;
+            codes.set(k + DIAGONAL as usize, state);
+        }
+        if i > 0 {
+            let (state, score) = up(above.get(j), rules.up(i, j));
+            cell[UP as usize] = score;
+            codes.set(k + UP as usize, state);
+        }
+        if j > lo {
+            let (state, score) = left(row.cells[j - lo - 1], rules.left(i, j));
+            cell[LEFT as usize] = score;
+            codes.set(k + LEFT as usize, state);
+        }
+        row.cells.push(cell);
+    }
+}
+
+/// diagonal is the best way into a pair after the cell `from`, and its score.
+/// A local path starts at the pair unless it's better to continue one.
+pub(crate) fn diagonal<S: Score>(from: Cell<S>, substitution: S, local: bool) -> (u8, S) {
+    let (state, score) = best(from);
+    if !local || score > S::ZERO {
+        (state, score + substitution)
+    } else {
+        (START, S::ZERO + substitution)
+    }
+}
+
+/// up is the best way into a gap in a after the cell `from`, and its score.
+pub(crate) fn up<S: Score>(from: Cell<S>, gap: Gap<S>) -> (u8, S) {
+    best([from[0] + gap.open, from[1] + gap.extend, from[2] + gap.open])
+}
+
+/// left is the best way into a gap in b after the cell `from`, and its score.
+pub(crate) fn left<S: Score>(from: Cell<S>, gap: Gap<S>) -> (u8, S) {
+    best([from[0] + gap.open, from[1] + gap.open, from[2] + gap.extend])
+}
+
+/// best is the best state of a cell, preferring the diagonal then gaps in a
+/// on ties.
+pub(crate) fn best<S: Score>(cell: Cell<S>) -> (u8, S) {
+    let mut best = (DIAGONAL, cell[0]);
+    for (state, score) in [(UP, cell[1]), (LEFT, cell[2])] {
+        if score > best.1 {
+            best = (state, score);
+        }
+    }
+    best
+}
+
+/// Path is a path through the grid: the cell before its first step, and the
+/// state of each step in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Path {
+    pub(super) start: (usize, usize),
+    pub(super) states: Vec<u8>,
+}
+
+impl Path {
+    /// rows are the rows of a over b along the path.
+    pub(super) fn rows(&self, a: &[u8], b: &[u8]) -> Vec<Vec<char>> {
+        let (mut i, mut j) = self.start;
+        let mut rows: Vec<Vec<char>> = (0..2)
+            .map(|_| Vec::with_capacity(self.states.len()))
+            .collect();
+        for state in self.states.iter() {
+            match *state {
+                DIAGONAL => {
+                    rows[0].push(a[j] as char);
+                    rows[1].push(b[i] as char);
+                    i += 1;
+                    j += 1;
+                }
+                UP => {
+                    rows[0].push('-');
+                    rows[1].push(b[i] as char);
+                    i += 1;
+                }
+                _ => {
+                    rows[0].push(a[j] as char);
+                    rows[1].push('-');
+                    j += 1;
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// walk_back follows the traceback from `state` in cell `end` to the first
+/// cell, or to the start of a local path. `code(i, j, s)` is the previous
+/// state of state s in cell (i, j).
+pub(super) fn walk_back<E, F: FnMut(usize, usize, u8) -> Result<u8, E>>(
+    end: (usize, usize),
+    mut state: u8,
+    mut code: F,
+) -> Result<Path, E> {
+    let (mut i, mut j) = end;
+    let mut states = Vec::new();
+    while state != START && (i > 0 || j > 0) {
+        let prev = code(i, j, state)?;
+        states.push(state);
+        match state {
+            DIAGONAL => {
+                i -= 1;
+                j -= 1;
+            }
+            UP => i -= 1,
+            _ => j -= 1,
+        }
+        state = prev;
+    }
+    states.reverse();
+    Ok(Path {
+        start: (i, j),
+        states,
+    })
+}
+
+/// Grid is the traceback of every row of a grid, and the scores of its last.
+pub(super) struct Grid<S> {
+    traceback: Vec<Traceback>,
+    pub(super) last: Row<S>,
+}
+
+/// fill fills the whole grid of a over b, of na columns after the first and
+/// nb rows, calling `each(i, row)` on each row once it's filled.
+pub(super) fn fill<R: Rules, F: FnMut(usize, &Row<R::Score>)>(
+    rules: &R,
+    na: usize,
+    nb: usize,
+    mut each: F,
+) -> Grid<R::Score> {
+    let (mut above, mut row) = (Row::empty(), Row::empty());
+    let mut traceback = Vec::with_capacity(nb + 1);
+    for i in 0..=nb {
+        let mut codes = Traceback::new(3 * (na + 1));
+        fill_row(rules, i, (0, na), &above, &mut row, &mut codes);
+        each(i, &row);
+        traceback.push(codes);
+        std::mem::swap(&mut above, &mut row);
+    }
+    Grid {
+        traceback,
+        last: above,
+    }
+}
+
+impl<S> Grid<S> {
+    /// path is the path ending in `state` in cell `end`.
+    pub(super) fn path(&self, end: (usize, usize), state: u8) -> Path {
+        let Ok(path) = walk_back::<Infallible, _>(end, state, |i, j, s| {
+            Ok(self.traceback[i].get(3 * j + s as usize))
+        });
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let (a, b) = (b"ACGTTACGT", b"ACGACGT");
+
+        // global, from the first cell to the last
+        let rules = Affine::new(&scoring, 1, matrix::<f32>(a, b, &scoring, 1));
+        let grid = fill(&rules, a.len(), b.len(), |_, _| {});
+        let (state, score) = best(grid.last.get(a.len()));
+        let path = grid.path((b.len(), a.len()), state);
+        assert_eq!((0, 0), path.start);
+        assert_eq!(
+            vec![
+                "ACGTTACGT".chars().collect::<Vec<_>>(),
+                "ACG--ACGT".chars().collect()
+            ],
+            path.rows(a, b)
+        );
+        assert_eq!(35f32 - 11f32, score);
+
+        // the same in fixed point
+        let rules = Affine::new(&scoring, 10, matrix::<i64>(a, b, &scoring, 10));
+        let grid = fill(&rules, a.len(), b.len(), |_, _| {});
+        assert_eq!(240, best(grid.last.get(a.len())).1);
+
+        // local, from the best pair back to where the path starts
+        let (a, b) = (b"TTTTACGT", b"ACGTCCCC");
+        let rules = Affine::new(&scoring, 1, matrix::<f32>(a, b, &scoring, 1)).local();
+        let mut end = (0f32, (0, 0));
+        fill(&rules, a.len(), b.len(), |i, row| {
+            for (j, cell) in row.cells.iter().enumerate() {
+                if cell[DIAGONAL as usize] > end.0 {
+                    end = (cell[DIAGONAL as usize], (i, j));
+                }
+            }
+        });
+        let grid = fill(&rules, a.len(), b.len(), |_, _| {});
+        let path = grid.path(end.1, DIAGONAL);
+        assert_eq!((20f32, (4, 8)), end);
+        assert_eq!((0, 4), path.start);
+        assert_eq!(vec![DIAGONAL; 4], path.states);
+    }
+
+    #[test]
+    fn test_fill_row_band() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            ..Default::default()
+        };
+        let (a, b) = (b"ACGT", b"ACGT");
+        let rules = Affine::new(&scoring, 1, matrix::<f32>(a, b, &scoring, 1));
+
+        // a cell outside the band of the row above is unreachable from it
+        let above = Row {
+            lo: 0,
+            cells: vec![[0f32, f32::NEG_INFINITY, f32::NEG_INFINITY]],
+        };
+        let mut row = Row::empty();
+        fill_row(&rules, 1, (1, 2), &above, &mut row, &mut ());
+        assert_eq!(1, row.lo);
+        assert_eq!(5f32, row.cells[0][DIAGONAL as usize]);
+        assert_eq!(f32::NEG_INFINITY, row.cells[1][DIAGONAL as usize]);
+        assert_eq!(4f32, row.cells[1][LEFT as usize]);
+    }
+}
@@ -0,0 +1,286 @@
+//! Batch Smith-Waterman scoring on the GPU.
+//!
+//! Each pair is scored by one invocation of a compute shader, keeping a
+//! single row of its grid in device memory, so a batch of thousands of
+//! pairs goes out in one dispatch. Pairs are packed into as few dispatches
+//! as the device's buffer limits allow. The first batch opens the device,
+//! and later batches reuse it.
+
+use std::{ops::Range, sync::mpsc, sync::OnceLock};
+
+use wgpu::util::DeviceExt;
+
+use super::{fixed::Precision, Scoring};
+
+// pairs scored by each workgroup, as in the shader
+const WORKGROUP: u32 = 64;
+
+// bytes of a pair's offsets and lengths, as in the shader
+const PAIR_BYTES: u64 = 20;
+
+/// Gpu is an open device and the compiled shader.
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// open requests the default adapter, None if there isn't one.
+    async fn open() -> Option<Gpu> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("seqalign"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smith-waterman"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("batch.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("smith-waterman"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Some(Gpu {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// score scores one dispatch of pairs, None if the device is lost or the
+    /// scores can't be read back.
+    fn score(&self, pairs: &[(&[u8], &[u8])], scoring: &Scoring) -> Option<Vec<f32>> {
+        // residues packed four to a word, and each pair's offsets into them
+        // and into the rows
+        let mut residues = Vec::new();
+        let mut offsets = Vec::with_capacity(pairs.len() * PAIR_BYTES as usize);
+        let mut cells = 0u32;
+        for (a, b) in pairs {
+            let fields = [
+                residues.len() as u32,
+                a.len() as u32,
+                (residues.len() + a.len()) as u32,
+                b.len() as u32,
+                cells,
+            ];
+            offsets.extend(fields.iter().flat_map(|f| f.to_le_bytes()));
+            residues.extend_from_slice(a);
+            residues.extend_from_slice(b);
+            cells += a.len() as u32 + 1;
+        }
+        residues.resize(residues.len().next_multiple_of(4).max(4), 0);
+
+        let mut params = Vec::with_capacity(16);
+        params.extend(scoring.gap_opening.to_le_bytes());
+        params.extend(scoring.gap_extension.to_le_bytes());
+        params.extend((pairs.len() as u32).to_le_bytes());
+        params.extend(0u32.to_le_bytes());
+        let matrix: Vec<u8> = scoring
+            .matrix
+            .iter()
+            .flatten()
+            .flat_map(|x| (*x as f32).to_le_bytes())
+            .collect();
+
+        let init = |label, contents: &[u8], usage| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage,
+                })
+        };
+        let empty = |label, size: u64, usage| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let score_bytes = 4 * pairs.len() as u64;
+        let buffers = [
+            init("params", &params, wgpu::BufferUsages::UNIFORM),
+            init("matrix", &matrix, wgpu::BufferUsages::STORAGE),
+            init("residues", &residues, wgpu::BufferUsages::STORAGE),
+            init("pairs", &offsets, wgpu::BufferUsages::STORAGE),
+            empty("rows", 12 * cells as u64, wgpu::BufferUsages::STORAGE),
+            empty(
+                "scores",
+                score_bytes,
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            ),
+        ];
+        let readback = empty(
+            "readback",
+            score_bytes,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        );
+        let entries: Vec<_> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((pairs.len() as u32).div_ceil(WORKGROUP), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&buffers[5], 0, &readback, 0, score_bytes);
+        self.queue.submit([encoder.finish()]);
+
+        let (send, receive) = mpsc::channel();
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = send.send(mapped);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receive.recv().ok()?.ok()?;
+        let scores = slice
+            .get_mapped_range()
+            .ok()?
+            .chunks_exact(4)
+            .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+            .collect();
+        readback.unmap();
+        Some(scores)
+    }
+
+    /// dispatches splits the pairs into dispatches that fit the device's
+    /// buffers, None if a pair doesn't fit by itself.
+    fn dispatches(&self, pairs: &[(&[u8], &[u8])]) -> Option<Vec<Range<usize>>> {
+        let limits = self.device.limits();
+        let max_bytes = limits
+            .max_storage_buffer_binding_size
+            .min(limits.max_buffer_size);
+        let max_pairs = (limits.max_compute_workgroups_per_dimension as u64 * WORKGROUP as u64)
+            .min(max_bytes / PAIR_BYTES);
+
+        let mut dispatches = Vec::new();
+        let (mut start, mut residues, mut rows) = (0, 0u64, 0u64);
+        for (k, (a, b)) in pairs.iter().enumerate() {
+            let pair = ((a.len() + b.len()) as u64, 12 * (a.len() as u64 + 1));
+            if pair.0 + 4 > max_bytes || pair.1 > max_bytes {
+                return None;
+            }
+            if residues + pair.0 + 4 > max_bytes
+                || rows + pair.1 > max_bytes
+                || (k - start) as u64 == max_pairs
+            {
+                dispatches.push(start..k);
+                (start, residues, rows) = (k, 0, 0);
+            }
+            residues += pair.0;
+            rows += pair.1;
+        }
+        dispatches.push(start..pairs.len());
+        Some(dispatches)
+    }
+}
+
+/// smith_waterman_batch scores the pairs on the GPU, None if there's no GPU,
+/// the pairs can't be scored on it, or it fails while scoring them.
+///
+/// Scores are summed in f32, so pairs aligned in fixed point aren't scored
+/// here, nor are pairs with residues the matrix has no scores for.
+pub(super) fn smith_waterman_batch(
+    pairs: &[(&[u8], &[u8])],
+    scoring: &Scoring,
+) -> Option<Vec<f32>> {
+    static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+    if scoring.precision != Precision::Float
+        || pairs.iter().any(|(a, b)| !a.is_ascii() || !b.is_ascii())
+    {
+        return None;
+    }
+    let gpu = GPU
+        .get_or_init(|| pollster::block_on(Gpu::open()))
+        .as_ref()?;
+    let mut scores = Vec::with_capacity(pairs.len());
+    for dispatch in gpu.dispatches(pairs)? {
+        if !dispatch.is_empty() {
+            scores.extend(gpu.score(&pairs[dispatch], scoring)?);
+        }
+    }
+    Some(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::align_local,
+        matrices::NUC_4_4,
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_smith_waterman_batch() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let mut rng = Rng::new(3);
+        let pairs: Vec<_> = (0..200)
+            .map(|k| {
+                (
+                    random_seq(k % 50, &background::nucleotide(), &mut rng),
+                    random_seq(60, &background::nucleotide(), &mut rng),
+                )
+            })
+            .collect();
+        let bytes: Vec<_> = pairs
+            .iter()
+            .map(|(q, t)| (q.as_bytes(), t.as_bytes()))
+            .collect();
+
+        // fixed-point scores are left to the threads
+        let fixed = Scoring {
+            precision: Precision::FIXED,
+            ..scoring.clone()
+        };
+        assert_eq!(None, smith_waterman_batch(&bytes, &fixed));
+
+        // as is the whole batch without a GPU
+        let Some(scores) = smith_waterman_batch(&bytes, &scoring) else {
+            return;
+        };
+        for ((q, t), score) in pairs.iter().zip(scores) {
+            assert_eq!(align_local(q, t, &scoring).alignment.score, score);
+        }
+    }
+}
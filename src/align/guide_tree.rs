@@ -0,0 +1,318 @@
+//! Guide trees for progressive multiple alignment.
+//!
+//! unweighted pair group method with arithmetic mean
+//! https://en.wikipedia.org/wiki/UPGMA
+//!
+//! The tree is built with the nearest-neighbor chain algorithm, which finds
+//! the same tree as the naive UPGMA in O(n^2) rather than O(n^3) time. Ties
+//! go to the lowest index so the tree doesn't depend on anything but the
+//! distances.
+
+use crate::trace::stage;
+
+use super::DistanceMatrix;
+
+/// Node of a guide tree, either a leaf (an input sequence) or the join of two nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    /// left and right children, None for a leaf
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+
+    /// length of the branches to the children
+    pub left_branch_len: f32,
+    pub right_branch_len: f32,
+
+    /// height of the node above the leaves, half the distance between the clusters it joins
+    pub height: f32,
+
+    /// percent bootstrap support of the node, if it's been bootstrapped
+    pub support: Option<f32>,
+}
+
+impl Node {
+    /// leaf creates a node for an input sequence.
+    pub fn leaf() -> Self {
+        Node {
+            left: None,
+            right: None,
+            left_branch_len: 0f32,
+            right_branch_len: 0f32,
+            height: 0f32,
+            support: None,
+        }
+    }
+
+    /// is_leaf is true if the node is an input sequence.
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none()
+    }
+}
+
+/// GuideTree is a rooted binary tree over the input sequences.
+///
+/// The first nodes are the leaves, in input order, followed by the internal
+/// nodes in the order they were joined, so every node comes after its
+/// children and the last node is the root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuideTree {
+    pub nodes: Vec<Node>,
+}
+
+impl GuideTree {
+    /// upgma builds a guide tree from pairwise distances.
+    pub fn upgma(distances: &DistanceMatrix) -> Self {
+        let n = distances.len();
+        let _stage = stage!("guide_tree", leaves = n);
+        let mut nodes = vec![Node::leaf(); n];
+        if n < 2 {
+            return GuideTree { nodes };
+        }
+
+        // distances between clusters, updated in place; a merged cluster
+        // takes the slot of its left child
+        let mut d: Vec<Vec<f32>> = (0..n)
+            .map(|i| (0..n).map(|j| distances.distance(i, j)).collect())
+            .collect();
+        let mut node_of: Vec<usize> = (0..n).collect();
+        let mut size = vec![1f32; n];
+        let mut active = vec![true; n];
+
+        let mut chain: Vec<usize> = Vec::new();
+        for _ in 1..n {
+            loop {
+                if chain.is_empty() {
+                    chain.push(active.iter().position(|a| *a).unwrap());
+                }
+                let a = chain[chain.len() - 1];
+                let prev = (chain.len() > 1).then(|| chain[chain.len() - 2]);
+
+                // the nearest neighbor, preferring the previous link so the chain can't cycle
+                let mut b = prev.unwrap_or(usize::MAX);
+                let mut min = prev.map_or(f32::INFINITY, |p| d[a][p]);
+                for c in (0..n).filter(|c| active[*c] && *c != a) {
+                    if d[a][c] < min {
+                        min = d[a][c];
+                        b = c;
+                    }
+                }
+
+                if Some(b) != prev {
+                    chain.push(b);
+                    continue;
+                }
+
+                // a and b are reciprocal nearest neighbors, join them
+                chain.truncate(chain.len() - 2);
+                let (left, right) = (a.min(b), a.max(b));
+                let height = d[left][right] / 2f32;
+                nodes.push(Node {
+                    left: Some(node_of[left]),
+                    right: Some(node_of[right]),
+                    left_branch_len: height - nodes[node_of[left]].height,
+                    right_branch_len: height - nodes[node_of[right]].height,
+                    height,
+                    support: None,
+                });
+
+                for c in (0..n).filter(|c| active[*c] && *c != left && *c != right) {
+                    let joined = (size[left] * d[left][c] + size[right] * d[right][c])
+                        / (size[left] + size[right]);
+                    d[left][c] = joined;
+                    d[c][left] = joined;
+                }
+                size[left] += size[right];
+                active[right] = false;
+                node_of[left] = nodes.len() - 1;
+                break;
+            }
+        }
+
+        GuideTree { nodes }
+    }
+
+    /// leaves is the number of input sequences in the tree.
+    pub fn leaves(&self) -> usize {
+        self.nodes.len().div_ceil(2)
+    }
+
+    /// root is the index of the root node.
+    pub fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// leaves_of lists the input sequences under a node, left to right.
+    pub fn leaves_of(&self, node: usize) -> Vec<usize> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![node];
+        while let Some(i) = stack.pop() {
+            match (self.nodes[i].left, self.nodes[i].right) {
+                (Some(left), Some(right)) => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+                _ => leaves.push(i),
+            }
+        }
+        leaves
+    }
+
+    /// to_newick writes the tree in Newick format with branch lengths, naming
+    /// each leaf by its sequence's name. Bootstrap support is written as the
+    /// label of each internal node.
+    pub fn to_newick(&self, names: &[String]) -> String {
+        if self.nodes.is_empty() {
+            return ";".to_string();
+        }
+        format!("{};", self.newick(self.root(), names))
+    }
+
+    fn newick(&self, i: usize, names: &[String]) -> String {
+        let node = &self.nodes[i];
+        match (node.left, node.right) {
+            (Some(left), Some(right)) => format!(
+                "({}:{},{}:{}){}",
+                self.newick(left, names),
+                node.left_branch_len,
+                self.newick(right, names),
+                node.right_branch_len,
+                node.support.map_or(String::new(), |s| s.to_string())
+            ),
+            _ => newick_name(&names[i]),
+        }
+    }
+
+    /// to_ascii draws the tree as text, one line per node, with the length of
+    /// the branch to each node after its name.
+    pub fn to_ascii(&self, names: &[String]) -> String {
+        let mut ascii = String::new();
+        if !self.nodes.is_empty() {
+            self.ascii(self.root(), None, "", "", names, &mut ascii);
+        }
+        ascii
+    }
+
+    fn ascii(
+        &self,
+        i: usize,
+        branch_len: Option<f32>,
+        first: &str,
+        rest: &str,
+        names: &[String],
+        ascii: &mut String,
+    ) {
+        let node = &self.nodes[i];
+        let label = if node.is_leaf() {
+            names[i].as_str()
+        } else {
+            "*"
+        };
+        ascii.push_str(first);
+        ascii.push_str(label);
+        if let Some(len) = branch_len {
+            ascii.push_str(&format!(":{}", len));
+        }
+        ascii.push('\n');
+
+        if let (Some(left), Some(right)) = (node.left, node.right) {
+            self.ascii(
+                left,
+                Some(node.left_branch_len),
+                &format!("{}+-- ", rest),
+                &format!("{}|   ", rest),
+                names,
+                ascii,
+            );
+            self.ascii(
+                right,
+                Some(node.right_branch_len),
+                &format!("{}\\-- ", rest),
+                &format!("{}    ", rest),
+                names,
+                ascii,
+            );
+        }
+    }
+}
+
+/// newick_name quotes a name if it has characters with meaning in Newick.
+fn newick_name(name: &str) -> String {
+    if name.chars().any(|c| "()[]':;, \t".contains(c)) {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgma() {
+        // the example from https://en.wikipedia.org/wiki/UPGMA
+        let mut distances = DistanceMatrix::new(["a", "b", "c", "d", "e"].map(String::from).into());
+        for (i, j, d) in [
+            (0, 1, 17f32),
+            (0, 2, 21f32),
+            (0, 3, 31f32),
+            (0, 4, 23f32),
+            (1, 2, 30f32),
+            (1, 3, 34f32),
+            (1, 4, 21f32),
+            (2, 3, 28f32),
+            (2, 4, 39f32),
+            (3, 4, 43f32),
+        ] {
+            distances.set(i, j, d);
+        }
+
+        let tree = GuideTree::upgma(&distances);
+        assert_eq!(9, tree.nodes.len());
+        assert_eq!(5, tree.leaves());
+
+        // ((a,b),e) joined with (c,d) at the root
+        let root = &tree.nodes[tree.root()];
+        assert_eq!(16.5, root.height);
+        assert_eq!(vec![0, 1, 4, 2, 3], tree.leaves_of(tree.root()));
+
+        let ab = &tree.nodes[5];
+        assert_eq!((Some(0), Some(1)), (ab.left, ab.right));
+        assert_eq!(8.5, ab.left_branch_len);
+        let abe = tree.nodes.iter().find(|n| n.height == 11f32).unwrap();
+        assert_eq!(2.5, abe.left_branch_len);
+
+        let names = distances.names.clone();
+        assert_eq!(
+            "(((a:8.5,b:8.5):2.5,e:11):5.5,(c:14,d:14):2.5);",
+            tree.to_newick(&names)
+        );
+        assert_eq!(
+            "*
++-- *:5.5
+|   +-- *:2.5
+|   |   +-- a:8.5
+|   |   \\-- b:8.5
+|   \\-- e:11
+\\-- *:2.5
+    +-- c:14
+    \\-- d:14
+",
+            tree.to_ascii(&names)
+        );
+    }
+
+    #[test]
+    fn test_newick_name() {
+        assert_eq!("seq_1", newick_name("seq_1"));
+        assert_eq!("'seq 1'", newick_name("seq 1"));
+        assert_eq!("'it''s'", newick_name("it's"));
+    }
+
+    #[test]
+    fn test_upgma_single() {
+        let tree = GuideTree::upgma(&DistanceMatrix::new(vec!["a".to_string()]));
+        assert_eq!(1, tree.nodes.len());
+        assert_eq!(0, tree.root());
+    }
+}
@@ -0,0 +1,118 @@
+//! Alignment with cheaper gaps in homopolymers.
+//!
+//! Nanopore and 454 reads miscount the length of runs of one base far more
+//! often than they make other indels, and the longer the run the worse it
+//! gets. Gaps against residues in a run are discounted by the run's length,
+//! so indels land in homopolymers, where they most likely happened, rather
+//! than wherever the tie-breaking of the traceback puts them.
+
+use super::{overlap::align_weighted, Alignment, Scoring};
+
+/// HomopolymerGaps configures the discount of gaps in homopolymer runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HomopolymerGaps {
+    /// shortest run of one residue that's discounted
+    pub min_run: usize,
+
+    /// fraction of the gap penalty dropped per residue of a run
+    pub discount: f32,
+
+    /// least fraction of the gap penalty kept, however long the run
+    pub floor: f32,
+}
+
+impl Default for HomopolymerGaps {
+    fn default() -> Self {
+        HomopolymerGaps {
+            min_run: 3,
+            discount: 0.1,
+            floor: 0.3,
+        }
+    }
+}
+
+impl HomopolymerGaps {
+    /// weights are the fraction of the gap penalty kept for a gap against
+    /// each residue of a sequence.
+    pub fn weights(&self, seq: &str) -> Vec<f32> {
+        let seq = seq.as_bytes();
+        let mut weights = Vec::with_capacity(seq.len());
+        let mut start = 0;
+        while start < seq.len() {
+            let c = seq[start].to_ascii_uppercase();
+            let run = seq[start..]
+                .iter()
+                .take_while(|r| r.to_ascii_uppercase() == c)
+                .count();
+            let weight = if run >= self.min_run {
+                (1f32 - self.discount * run as f32).max(self.floor)
+            } else {
+                1f32
+            };
+            weights.extend(std::iter::repeat_n(weight, run));
+            start += run;
+        }
+        weights
+    }
+}
+
+/// align_homopolymer aligns two sequences with affine gaps that are cheaper
+/// in homopolymer runs.
+///
+/// Terminal gaps are scaled by `scoring.terminal_gaps` as in
+/// [`align_overlap`](super::align_overlap).
+pub fn align_homopolymer(
+    a: &str,
+    b: &str,
+    scoring: &Scoring,
+    homopolymers: &HomopolymerGaps,
+) -> Alignment {
+    align_weighted(
+        a,
+        b,
+        scoring,
+        &homopolymers.weights(a),
+        &homopolymers.weights(b),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::align_overlap, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_weights() {
+        let gaps = HomopolymerGaps::default();
+        let weights = gaps.weights("ACCCGAAAAAAAAT");
+        assert_eq!(1f32, weights[0]);
+        assert!((weights[1] - 0.7).abs() < 1e-6);
+        assert_eq!(gaps.floor, weights[6]);
+        assert_eq!(1f32, weights[13]);
+    }
+
+    #[test]
+    fn test_align_homopolymer() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+
+        // one A too few and one C too many: without the discount, a
+        // mismatch is cheaper than the two indels that really happened
+        let (a, b) = ("ACGTAAAAAACCGT", "ACGTAAAAACCCGT");
+        let gaps = HomopolymerGaps {
+            discount: 0.3,
+            floor: 0.1,
+            ..Default::default()
+        };
+        let plain = align_overlap(a, b, &scoring);
+        let aware = align_homopolymer(a, b, &scoring, &gaps);
+        assert_eq!("ACGTAAAAAACCGT\nACGTAAAAACCCGT", plain.to_string());
+        assert_eq!("ACGTAAAAAA-CCGT\nACGT-AAAAACCCGT", aware.to_string());
+        assert!(aware.score > plain.score);
+    }
+}
@@ -0,0 +1,175 @@
+//! Percent identity of a pairwise alignment.
+//!
+//! Tools agree on the number of identical columns but not on what to divide
+//! it by, so the same alignment can be 80% identical in one report and 90% in
+//! another. Each common denominator is a [`Definition`] so numbers can be
+//! matched to the tool they're compared against. See Raghava and Barton 2006,
+//! https://doi.org/10.1186/1471-2105-7-415, for how much they differ.
+
+use super::Alignment;
+
+/// Definition is what the number of identical columns is divided by.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Definition {
+    /// every column, gaps included, like BLAST and EMBOSS needle
+    #[default]
+    AlignmentLength,
+
+    /// columns without a gap in either sequence
+    AlignedPairs,
+
+    /// columns from the first to the last that have a residue in both sequences
+    ExcludeTerminalGaps,
+
+    /// residues in the shorter sequence
+    ShorterSequence,
+
+    /// mean number of residues in the two sequences
+    MeanLength,
+}
+
+impl Alignment {
+    /// identity is the percent identity of the first two rows of the alignment.
+    ///
+    /// It's 0 if there's nothing to divide by.
+    pub fn identity(&self, definition: Definition) -> f32 {
+        identity_of(&self.rows[0], &self.rows[1], definition)
+    }
+
+    /// identity_profile is the percent identity, by alignment length, of
+    /// windows of `window` columns every `step` columns along the first two
+    /// rows. A drop or jump in it is where a chimera's parts were joined or
+    /// where a recombinant switches parent.
+    ///
+    /// The last window ends at the end of the alignment even when the steps
+    /// don't land there, and an alignment shorter than a window is one window.
+    pub fn identity_profile(&self, window: usize, step: usize) -> Vec<IdentityWindow> {
+        let len = self.rows[0].len().min(self.rows[1].len());
+        if window == 0 || len == 0 {
+            return vec![];
+        }
+
+        let window = window.min(len);
+        let mut starts: Vec<usize> = (0..=len - window).step_by(step.max(1)).collect();
+        if starts.last() != Some(&(len - window)) {
+            starts.push(len - window);
+        }
+        starts
+            .into_iter()
+            .map(|start| IdentityWindow {
+                start,
+                end: start + window,
+                identity: identity_of(
+                    &self.rows[0][start..start + window],
+                    &self.rows[1][start..start + window],
+                    Definition::AlignmentLength,
+                ),
+            })
+            .collect()
+    }
+}
+
+/// IdentityWindow is the identity of a range of an alignment's columns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdentityWindow {
+    /// first column of the window
+    pub start: usize,
+
+    /// column after the last of the window
+    pub end: usize,
+
+    pub identity: f32,
+}
+
+fn identity_of(a: &[char], b: &[char], definition: Definition) -> f32 {
+    let columns: Vec<(char, char)> = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| (*x, *y))
+        .filter(|(x, y)| *x != '-' || *y != '-')
+        .collect();
+    let identical = columns.iter().filter(|(x, y)| x == y && *x != '-').count();
+
+    let residues = |row: &[char]| row.iter().filter(|c| **c != '-').count();
+    let length = match definition {
+        Definition::AlignmentLength => columns.len() as f32,
+        Definition::AlignedPairs => columns
+            .iter()
+            .filter(|(x, y)| *x != '-' && *y != '-')
+            .count() as f32,
+        Definition::ExcludeTerminalGaps => {
+            let pair = |(x, y): &(char, char)| *x != '-' && *y != '-';
+            match (
+                columns.iter().position(pair),
+                columns.iter().rposition(pair),
+            ) {
+                (Some(first), Some(last)) => (last - first + 1) as f32,
+                _ => 0f32,
+            }
+        }
+        Definition::ShorterSequence => residues(a).min(residues(b)) as f32,
+        Definition::MeanLength => (residues(a) + residues(b)) as f32 / 2f32,
+    };
+
+    if length == 0f32 {
+        0f32
+    } else {
+        100f32 * identical as f32 / length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let alignment = Alignment::new(
+            vec![
+                "--ACGTACGT".chars().collect(),
+                "TTACGAAC-T".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+
+        assert_eq!(60f32, alignment.identity(Definition::AlignmentLength));
+        assert_eq!(
+            6f32 / 7f32 * 100f32,
+            alignment.identity(Definition::AlignedPairs)
+        );
+        assert_eq!(
+            6f32 / 8f32 * 100f32,
+            alignment.identity(Definition::ExcludeTerminalGaps)
+        );
+        assert_eq!(75f32, alignment.identity(Definition::ShorterSequence));
+        assert_eq!(
+            6f32 / 8.5f32 * 100f32,
+            alignment.identity(Definition::MeanLength)
+        );
+    }
+
+    #[test]
+    fn test_identity_profile() {
+        // a chimera of the first half of one parent and the second of another
+        let alignment = Alignment::new(
+            vec![
+                "ACGTACGTACGTACGTACGT".chars().collect(),
+                "ACGTACGTACTGCATGCATG".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+
+        let profile = alignment.identity_profile(8, 4);
+        assert_eq!(
+            vec![(0, 8), (4, 12), (8, 16), (12, 20)],
+            profile.iter().map(|w| (w.start, w.end)).collect::<Vec<_>>()
+        );
+        assert_eq!(100f32, profile[0].identity);
+        assert_eq!(0f32, profile[3].identity);
+        let whole = alignment.identity_profile(50, 1);
+        assert_eq!((1, 0, 20), (whole.len(), whole[0].start, whole[0].end));
+        assert_eq!(16, alignment.identity_profile(4, 7).last().unwrap().start);
+    }
+}
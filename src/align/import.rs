@@ -0,0 +1,246 @@
+//! Alignments made by other tools, like BLAST and minimap2, as the crate's
+//! own types, so their statistics and formats work on them too.
+//!
+//! An alignment comes as its gapped rows, or as the CIGAR of a query against
+//! a region of a reference. Either is checked against its sequences before
+//! it's built: a malformed alignment fails here rather than as a panic or a
+//! wrong number further on.
+
+use std::collections::HashSet;
+
+use super::{Alignment, Error, LocalAlignment, MSAlignment, Result, Scoring};
+
+impl Alignment {
+    /// from_gapped builds an alignment from its two gapped rows, scored by
+    /// [`score_breakdown`](Alignment::score_breakdown).
+    pub fn from_gapped(a: &str, b: &str, scoring: &Scoring) -> Result<Alignment> {
+        let rows: Vec<Vec<char>> = vec![a.chars().collect(), b.chars().collect()];
+        if rows[0].len() != rows[1].len() {
+            return Err(invalid(format!(
+                "rows are {} and {} columns",
+                rows[0].len(),
+                rows[1].len()
+            )));
+        }
+        if let Some(col) = (0..rows[0].len()).find(|c| rows[0][*c] == '-' && rows[1][*c] == '-') {
+            return Err(invalid(format!("column {} is all gaps", col)));
+        }
+        if rows[0].is_empty() {
+            return Err(invalid("rows are empty".to_string()));
+        }
+        Ok(scored(rows, scoring))
+    }
+}
+
+impl LocalAlignment {
+    /// from_cigar builds the alignment of a query to the reference from a
+    /// CIGAR and the 0-based start of the alignment in the reference, as in
+    /// SAM and PAF. The query is a and the reference b.
+    ///
+    /// `M`, `=` and `X` align a residue of each, `I` and `S` are residues
+    /// only of the query, `D` and `N` residues only of the reference, and
+    /// `H` and `P` are skipped. Soft clips must be at the ends, and the
+    /// query is the whole of it, hard clips left out.
+    pub fn from_cigar(
+        query: &str,
+        reference: &str,
+        start: usize,
+        cigar: &str,
+        scoring: &Scoring,
+    ) -> Result<LocalAlignment> {
+        let ops = parse_cigar(cigar)?;
+        let (query_bytes, reference_bytes) = (query.as_bytes(), reference.as_bytes());
+
+        let mut rows = vec![vec![], vec![]];
+        let (mut i, mut j) = (0, start);
+        let mut clips = (0, 0);
+        for (k, (len, op)) in ops.iter().enumerate() {
+            let (len, op) = (*len, *op);
+            let (q, r) = match op {
+                'M' | '=' | 'X' => (len, len),
+                'I' => (len, 0),
+                'D' | 'N' => (0, len),
+                'S' => (len, 0),
+                _ => (0, 0),
+            };
+            if i + q > query.len() {
+                return Err(invalid(format!("CIGAR {} is longer than the query", cigar)));
+            }
+            if j + r > reference.len() {
+                return Err(invalid(format!(
+                    "CIGAR {} at {} runs past the reference",
+                    cigar, start
+                )));
+            }
+            match op {
+                'S' if rows[0].is_empty() && k + 1 < ops.len() => clips.0 = len,
+                'S' if ops[k + 1..].iter().all(|(_, op)| *op == 'H') => clips.1 = len,
+                'S' => return Err(invalid(format!("soft clip inside CIGAR {}", cigar))),
+                'H' | 'P' => {}
+                _ => {
+                    for n in 0..q.max(r) {
+                        rows[0].push(if q > 0 {
+                            query_bytes[i + n] as char
+                        } else {
+                            '-'
+                        });
+                        rows[1].push(if r > 0 {
+                            reference_bytes[j + n] as char
+                        } else {
+                            '-'
+                        });
+                    }
+                }
+            }
+            (i, j) = (i + q, j + r);
+        }
+        if i != query.len() {
+            return Err(invalid(format!(
+                "CIGAR {} covers {} of the {} residues of the query",
+                cigar,
+                i,
+                query.len()
+            )));
+        }
+        if rows[0].is_empty() {
+            return Err(invalid(format!("CIGAR {} aligns nothing", cigar)));
+        }
+
+        Ok(LocalAlignment {
+            alignment: scored(rows, scoring),
+            a: clips.0..query.len() - clips.1,
+            b: start..j,
+            a_len: query.len(),
+            b_len: reference.len(),
+        })
+    }
+}
+
+impl MSAlignment {
+    /// from_gapped builds a multiple alignment from its IDs and gapped rows.
+    pub fn from_gapped<S: AsRef<str>>(ids: Vec<String>, rows: &[S]) -> Result<MSAlignment> {
+        if ids.len() != rows.len() {
+            return Err(invalid(format!(
+                "{} IDs for {} rows",
+                ids.len(),
+                rows.len()
+            )));
+        }
+        let mut seen = HashSet::new();
+        if let Some(id) = ids.iter().find(|id| !seen.insert(id.as_str())) {
+            return Err(invalid(format!("ID {} is on more than one row", id)));
+        }
+        let rows: Vec<Vec<char>> = rows.iter().map(|r| r.as_ref().chars().collect()).collect();
+        if let Some((id, row)) = ids
+            .iter()
+            .zip(rows.iter())
+            .find(|(_, row)| row.len() != rows[0].len())
+        {
+            return Err(invalid(format!(
+                "row {} is {} columns, not {}",
+                id,
+                row.len(),
+                rows[0].len()
+            )));
+        }
+        Ok(MSAlignment::new(ids, rows))
+    }
+}
+
+/// parse_cigar splits a CIGAR into its lengths and operations.
+fn parse_cigar(cigar: &str) -> Result<Vec<(usize, char)>> {
+    let mut ops = vec![];
+    let mut len = String::new();
+    for c in cigar.chars() {
+        match c {
+            '0'..='9' => len.push(c),
+            'M' | 'I' | 'D' | 'N' | 'S' | 'H' | 'P' | '=' | 'X' if !len.is_empty() => {
+                ops.push((
+                    len.parse()
+                        .map_err(|_| invalid(format!("bad CIGAR {}", cigar)))?,
+                    c,
+                ));
+                len.clear();
+            }
+            _ => return Err(invalid(format!("bad CIGAR {}", cigar))),
+        }
+    }
+    if !len.is_empty() || ops.is_empty() {
+        return Err(invalid(format!("bad CIGAR {}", cigar)));
+    }
+    Ok(ops)
+}
+
+/// scored builds an alignment of two rows with its score.
+fn scored(rows: Vec<Vec<char>>, scoring: &Scoring) -> Alignment {
+    let mut alignment = Alignment::new(rows, vec![vec![]], 0f32);
+    alignment.score = alignment.score_breakdown(scoring).total();
+    alignment
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidAlignment(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_gapped() {
+        let alignment = Alignment::from_gapped("ACGT--ACGT", "ACGTTTACGA", &scoring()).unwrap();
+        assert_eq!(vec!['A', 'C', 'G', 'T', '-', '-'], alignment.rows[0][..6]);
+        assert_eq!(7f32 * 5f32 - 4f32 - 11f32, alignment.score);
+
+        for (a, b) in [("ACGT", "ACG"), ("AC-T", "AC-T"), ("", "")] {
+            assert!(matches!(
+                Alignment::from_gapped(a, b, &scoring()),
+                Err(Error::InvalidAlignment(_))
+            ));
+        }
+
+        let msa =
+            MSAlignment::from_gapped(vec!["x".into(), "y".into()], &["ACG-T", "AC-GT"]).unwrap();
+        assert_eq!("ACG-T\nAC-GT", msa.to_string());
+        assert!(MSAlignment::from_gapped(vec!["x".into(), "x".into()], &["A", "A"]).is_err());
+        assert!(MSAlignment::from_gapped(vec!["x".into(), "y".into()], &["A", "AC"]).is_err());
+    }
+
+    #[test]
+    fn test_from_cigar() {
+        let (query, reference) = ("TTACGTGGACGTA", "CCCCACGTACGTCCCC");
+        let local =
+            LocalAlignment::from_cigar(query, reference, 4, "2S4M2I4M1S", &scoring()).unwrap();
+        assert_eq!("2S4M2I4M1S", format!("2S{}1S", local.cigar()));
+        assert_eq!((2..12, 4..12), (local.a.clone(), local.b.clone()));
+        assert_eq!(
+            "ACGTGGACGT\nACGT--ACGT",
+            local
+                .alignment
+                .rows
+                .iter()
+                .map(|r| r.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        for cigar in ["2S4M2I4M", "2S4M2I4M1S4M", "2S4M2I20M1S", "4Q", "M"] {
+            assert!(
+                LocalAlignment::from_cigar(query, reference, 4, cigar, &scoring()).is_err(),
+                "{}",
+                cigar
+            );
+        }
+    }
+}
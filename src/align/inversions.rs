@@ -0,0 +1,214 @@
+//! Alignment of a query that is collinear with a reference except for
+//! inverted segments, like a plasmid with a fragment cloned in backwards.
+//!
+//! The forward seeds of the query are chained into the collinear backbone.
+//! Each stretch of the query the backbone skips is then seeded against the
+//! reverse complement of the reference between the flanking seeds. When the
+//! inverted alignment of that stretch scores more than the penalty of
+//! switching strand and back, it's an inversion and the backbone is split
+//! around it into blocks. Otherwise the stretch is left to the forward
+//! alignment between the flanking seeds, as an indel or a run of mismatches.
+
+use std::ops::Range;
+
+use crate::seq::reverse_complement;
+
+use super::{
+    align_anchored, chain,
+    mapping::{kmer_index, seeds},
+    Alignment, Anchor, MapConfig,
+};
+
+/// InversionBlock is a stretch of the query aligned to the reference on one
+/// strand.
+#[derive(Debug)]
+pub struct InversionBlock {
+    /// the query over the reference, or over its reverse complement if inverted
+    pub alignment: Alignment,
+
+    /// 0-based, half-open region of the query in the block
+    pub query: Range<usize>,
+
+    /// 0-based, half-open region of the forward reference in the block
+    pub reference: Range<usize>,
+
+    /// whether the query is inverted relative to the reference in the block
+    pub inverted: bool,
+}
+
+/// InversionAlignment is a query aligned to a reference as blocks in order
+/// of the query, alternating between forward and inverted.
+///
+/// The few residues around each strand switch that no seed reached are in
+/// no block.
+#[derive(Debug)]
+pub struct InversionAlignment {
+    pub blocks: Vec<InversionBlock>,
+}
+
+impl InversionAlignment {
+    /// inversions are the inverted blocks.
+    pub fn inversions(&self) -> impl Iterator<Item = &InversionBlock> {
+        self.blocks.iter().filter(|b| b.inverted)
+    }
+}
+
+/// align_inversions aligns a query to a reference, switching strand where
+/// segments of the query are inverted.
+///
+/// Each inversion costs `switch_penalty` twice, once into it and once out of
+/// it, so only those whose alignment scores more are kept. Returns None if the query has no
+/// forward seeds in the reference to anchor the blocks to.
+pub fn align_inversions(
+    query: &str,
+    reference: &str,
+    config: &MapConfig,
+    switch_penalty: f32,
+) -> Option<InversionAlignment> {
+    let rc = reverse_complement(reference);
+    let (q, r) = (query.as_bytes(), reference.as_bytes());
+    let k = config.k.max(1);
+    if q.len() < k || r.len() < k {
+        return None;
+    }
+
+    let forward = chain(&seeds(
+        q,
+        &kmer_index(r, k, config.max_occurrences),
+        k,
+        0..q.len(),
+    ));
+    // reverse seeds stay in coordinates of the reverse complement, where an
+    // inverted segment is collinear
+    let reverse = seeds(
+        q,
+        &kmer_index(rc.as_bytes(), k, config.max_occurrences),
+        k,
+        0..q.len(),
+    );
+    let (first, last) = (forward.first()?, forward.last()?);
+
+    let mut blocks = vec![];
+    let mut backbone: Vec<Anchor> = vec![*first];
+    for next in &forward[1..] {
+        let prev = backbone.last().unwrap();
+        let (q_gap, r_gap) = (prev.a + prev.len..next.a, prev.b + prev.len..next.b);
+        let rc_gap = r.len() - r_gap.end..r.len() - r_gap.start;
+        let in_gap: Vec<Anchor> = reverse
+            .iter()
+            .filter_map(|a| clip(a, &q_gap, &rc_gap))
+            .collect();
+        let inverted = chain(&in_gap);
+        if let (Some(start), Some(end)) = (inverted.first(), inverted.last()) {
+            let q_range = start.a..end.a + end.len;
+            let rc_range = start.b..end.b + end.len;
+            let r_range = r.len() - rc_range.end..r.len() - rc_range.start;
+
+            let alignment = align_block(query, &rc, &inverted, config);
+            if alignment.score > 2. * switch_penalty {
+                blocks.push(block(query, reference, &backbone, config));
+                blocks.push(InversionBlock {
+                    alignment,
+                    query: q_range,
+                    reference: r_range,
+                    inverted: true,
+                });
+                backbone.clear();
+            }
+        }
+        backbone.push(*next);
+    }
+    debug_assert_eq!(Some(last), backbone.last());
+    blocks.push(block(query, reference, &backbone, config));
+
+    Some(InversionAlignment { blocks })
+}
+
+/// clip is the part of an anchor within a region of each sequence, if any.
+fn clip(anchor: &Anchor, a: &Range<usize>, b: &Range<usize>) -> Option<Anchor> {
+    let start = a
+        .start
+        .saturating_sub(anchor.a)
+        .max(b.start.saturating_sub(anchor.b));
+    let end = anchor
+        .len
+        .min(a.end.saturating_sub(anchor.a))
+        .min(b.end.saturating_sub(anchor.b));
+    (end > start).then(|| Anchor {
+        a: anchor.a + start,
+        b: anchor.b + start,
+        len: end - start,
+    })
+}
+
+/// block is the forward block through a stretch of the backbone.
+fn block(query: &str, reference: &str, anchors: &[Anchor], config: &MapConfig) -> InversionBlock {
+    let (first, last) = (anchors[0], anchors[anchors.len() - 1]);
+    InversionBlock {
+        alignment: align_block(query, reference, anchors, config),
+        query: first.a..last.a + last.len,
+        reference: first.b..last.b + last.len,
+        inverted: false,
+    }
+}
+
+/// align_block aligns the query to a target from the first anchor to the last.
+fn align_block(query: &str, target: &str, anchors: &[Anchor], config: &MapConfig) -> Alignment {
+    let (first, last) = (anchors[0], anchors[anchors.len() - 1]);
+    let relative: Vec<Anchor> = anchors
+        .iter()
+        .map(|a| Anchor {
+            a: a.a - first.a,
+            b: a.b - first.b,
+            len: a.len,
+        })
+        .collect();
+    align_anchored(
+        &query[first.a..last.a + last.len],
+        &target[first.b..last.b + last.len],
+        &relative,
+        &config.scoring,
+    )
+    .expect("chained anchors are in order and don't overlap")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_align_inversions() {
+        let mut rng = Rng::new(13);
+        let reference = random_seq(4000, &background::nucleotide(), &mut rng);
+        let query = format!(
+            "{}{}{}",
+            &reference[..1500],
+            reverse_complement(&reference[1500..1700]),
+            &reference[1700..]
+        );
+        let config = MapConfig::default();
+
+        let aligned = align_inversions(&query, &reference, &config, 20.).unwrap();
+        let strands: Vec<bool> = aligned.blocks.iter().map(|b| b.inverted).collect();
+        assert_eq!(vec![false, true, false], strands);
+
+        let inversion = aligned.inversions().next().unwrap();
+        assert!(inversion.query.start.abs_diff(1500) < 5 && inversion.query.end.abs_diff(1700) < 5);
+        assert_eq!(inversion.query, inversion.reference);
+        assert_eq!(
+            reverse_complement(&reference[inversion.reference.clone()]),
+            inversion.alignment.rows[1].iter().collect::<String>()
+        );
+        assert_eq!(0..aligned.blocks[0].query.end, aligned.blocks[0].reference);
+
+        // too costly to switch strand
+        let aligned = align_inversions(&query, &reference, &config, 1e6).unwrap();
+        assert_eq!(1, aligned.blocks.len());
+        assert_eq!(0, aligned.inversions().count());
+    }
+}
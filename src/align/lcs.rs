@@ -0,0 +1,93 @@
+//! Longest common subsequence with the Hunt-Szymanski algorithm.
+//! https://doi.org/10.1145/359581.359603
+//!
+//! An LCS alignment only has matches and gaps, there are no substitutions,
+//! which is what a diff of two sequences wants. Rather than filling a grid,
+//! Hunt-Szymanski walks only the pairs of positions where the sequences
+//! match, so it's fast when matches are sparse, like for large alphabets or
+//! lines of text.
+
+use std::collections::HashMap;
+
+use super::Alignment;
+
+/// lcs aligns two sequences by their longest common subsequence.
+///
+/// Residues that aren't in the subsequence are gaps in the other sequence,
+/// those of `a` coming before those of `b` between two matches. The score is
+/// the length of the subsequence.
+pub fn lcs(a: &str, b: &str) -> Alignment {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+
+    // positions of each residue in a, last first
+    let mut positions: HashMap<char, Vec<usize>> = HashMap::new();
+    for (j, c) in a.iter().enumerate().rev() {
+        positions.entry(*c).or_default().push(j);
+    }
+
+    // thresholds[k] is the smallest position in a that a common subsequence
+    // of length k + 1 ends at, and links[k] the last match of it
+    let mut thresholds: Vec<usize> = Vec::new();
+    let mut links: Vec<usize> = Vec::new();
+
+    // matches as (i in b, j in a, index of the previous match)
+    let mut matches: Vec<(usize, usize, Option<usize>)> = Vec::new();
+    for (i, c) in b.iter().enumerate() {
+        for j in positions.get(c).map_or(&[][..], |p| p.as_slice()) {
+            let k = thresholds.partition_point(|t| t < j);
+            let prev = k.checked_sub(1).map(|k| links[k]);
+            matches.push((i, *j, prev));
+            if k == thresholds.len() {
+                thresholds.push(*j);
+                links.push(matches.len() - 1);
+            } else {
+                thresholds[k] = *j;
+                links[k] = matches.len() - 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = Vec::with_capacity(links.len());
+    let mut link = links.last().copied();
+    while let Some(m) = link {
+        let (i, j, prev) = matches[m];
+        pairs.push((i, j));
+        link = prev;
+    }
+    pairs.reverse();
+
+    let mut rows: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in pairs.iter().copied().chain([(b.len(), a.len())]) {
+        for c in &a[j..next_j] {
+            rows[0].push(*c);
+            rows[1].push('-');
+        }
+        for c in &b[i..next_i] {
+            rows[0].push('-');
+            rows[1].push(*c);
+        }
+        if next_j < a.len() {
+            rows[0].push(a[next_j]);
+            rows[1].push(b[next_i]);
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+
+    Alignment::new(rows, vec![], pairs.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lcs() {
+        let alignment = lcs("ABCBDAB", "BDCABA");
+        assert_eq!("AB-C-BDAB\n-BDCAB-A-", alignment.to_string());
+        assert_eq!(4f32, alignment.score);
+
+        assert_eq!("ACG---T\n---TTTT", lcs("ACGT", "TTTT").to_string());
+        assert_eq!(0f32, lcs("", "AC").score);
+    }
+}
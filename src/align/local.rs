@@ -0,0 +1,111 @@
+//! Local alignment with affine gaps and the coordinates of the aligned regions.
+//!
+//! This is Smith-Waterman with Gotoh's three states, which charges a gap of
+//! length L `gap_opening + gap_extension * (L - 1)` wherever it is, and keeps
+//! where the local alignment starts and ends in each sequence so hits can be
+//! reported in sequence coordinates.
+
+use std::ops::Range;
+
+use super::{
+    fixed::{Precision, Score},
+    gotoh::{self, Affine, DIAGONAL, START},
+    Alignment, Scoring,
+};
+
+/// LocalAlignment is a local alignment and the regions of the sequences in it.
+#[derive(Debug)]
+pub struct LocalAlignment {
+    /// the aligned regions, a over b
+    pub alignment: Alignment,
+
+    /// 0-based, half-open region of a in the alignment
+    pub a: Range<usize>,
+
+    /// 0-based, half-open region of b in the alignment
+    pub b: Range<usize>,
+
+    /// length of the whole of a
+    pub a_len: usize,
+
+    /// length of the whole of b
+    pub b_len: usize,
+}
+
+/// align_local finds the best local alignment of two sequences.
+///
+/// The alignment is empty, with a score of 0, if no pair of residues scores
+/// above 0.
+pub fn align_local(a: &str, b: &str, scoring: &Scoring) -> LocalAlignment {
+    match scoring.precision {
+        Precision::Float => local::<f32>(a.as_bytes(), b.as_bytes(), scoring, 1),
+        Precision::Fixed(scale) => local::<i64>(a.as_bytes(), b.as_bytes(), scoring, scale.max(1)),
+    }
+}
+
+/// local is `align_local` summing scores as `S`, scaled by `scale`.
+fn local<S: Score>(a: &[u8], b: &[u8], scoring: &Scoring, scale: u32) -> LocalAlignment {
+    let rules = Affine::new(scoring, scale, gotoh::matrix::<S>(a, b, scoring, scale)).local();
+
+    // the alignment ends in the best match
+    let mut best = (S::ZERO, 0, 0);
+    let grid = gotoh::fill(&rules, a.len(), b.len(), |i, row| {
+        for (j, cell) in row.cells.iter().enumerate() {
+            if cell[DIAGONAL as usize] > best.0 {
+                best = (cell[DIAGONAL as usize], i, j);
+            }
+        }
+    });
+
+    // walk back from the best match to the start of the alignment
+    let (score, end_i, end_j) = best;
+    let state = if score > S::ZERO { DIAGONAL } else { START };
+    let path = grid.path((end_i, end_j), state);
+    let (i, j) = path.start;
+
+    LocalAlignment {
+        alignment: Alignment::new(path.rows(a, b), vec![], score.to_f32(scale)),
+        a: j..end_j,
+        b: i..end_i,
+        a_len: a.len(),
+        b_len: b.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::smith_waterman_batch, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_align_local() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let local = align_local("CCCCACGTACGTCCCC", "GGACGTTACGTGG", &scoring);
+        assert_eq!("ACG-TACGT\nACGTTACGT", local.alignment.to_string());
+        assert_eq!(4..12, local.a);
+        assert_eq!(2..11, local.b);
+        assert_eq!(
+            smith_waterman_batch(&[("CCCCACGTACGTCCCC", "GGACGTTACGTGG")], &scoring)[0],
+            local.alignment.score
+        );
+
+        let fixed = Scoring {
+            precision: Precision::FIXED,
+            ..scoring.clone()
+        };
+        let fixed = align_local("CCCCACGTACGTCCCC", "GGACGTTACGTGG", &fixed);
+        assert_eq!(local.alignment.to_string(), fixed.alignment.to_string());
+        assert_eq!(local.alignment.score, fixed.alignment.score);
+        assert_eq!((local.a, local.b), (fixed.a, fixed.b));
+
+        let local = align_local("AAAA", "CCCC", &scoring);
+        assert_eq!(0f32, local.alignment.score);
+        assert_eq!(0..0, local.a);
+    }
+}
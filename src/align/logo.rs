@@ -0,0 +1,115 @@
+//! Sequence logo data of a multiple alignment.
+//!
+//! Each column of a logo is a stack of its residues, as tall as the column's
+//! information content in bits and split by the residues' frequencies, as in
+//! Schneider and Stephens, https://doi.org/10.1093/nar/18.20.6097. Columns of
+//! few residues get a small-sample correction so they don't look conserved
+//! only because they're sparse.
+
+use std::collections::HashMap;
+
+use super::MSAlignment;
+
+/// LogoColumn is the stack of residues of one column of a logo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogoColumn {
+    /// information content of the column, in bits
+    pub information: f64,
+
+    /// frequency of each residue among the column's residues, most frequent first
+    pub frequencies: Vec<(char, f64)>,
+
+    /// number of rows with a residue, rather than a gap, in the column
+    pub residues: usize,
+}
+
+impl LogoColumn {
+    /// heights of the residues in the stack, in bits, most frequent first.
+    pub fn heights(&self) -> impl Iterator<Item = (char, f64)> + '_ {
+        self.frequencies
+            .iter()
+            .map(|(c, f)| (*c, f * self.information))
+    }
+}
+
+/// Logo is the stacks of a multiple alignment, one per column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Logo {
+    pub columns: Vec<LogoColumn>,
+
+    /// most information a column can have, log2 of the alphabet size
+    pub max_bits: f64,
+}
+
+impl MSAlignment {
+    /// logo is the sequence logo of the alignment over an alphabet of
+    /// `alphabet_size` residues, 4 for DNA and 20 for protein.
+    ///
+    /// Residues are counted case-insensitively and gaps are left out.
+    pub fn logo(&self, alphabet_size: usize) -> Logo {
+        let max_bits = (alphabet_size.max(2) as f64).log2();
+        let columns = (0..self.len())
+            .map(|col| {
+                let mut counts: HashMap<char, usize> = HashMap::new();
+                for row in self.rows.iter() {
+                    if row[col] != '-' {
+                        *counts.entry(row[col].to_ascii_uppercase()).or_default() += 1;
+                    }
+                }
+                let residues: usize = counts.values().sum();
+                if residues == 0 {
+                    return LogoColumn {
+                        information: 0f64,
+                        frequencies: vec![],
+                        residues,
+                    };
+                }
+
+                let mut frequencies: Vec<(char, f64)> = counts
+                    .into_iter()
+                    .map(|(c, n)| (c, n as f64 / residues as f64))
+                    .collect();
+                frequencies.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+                let entropy: f64 = frequencies.iter().map(|(_, f)| -f * f.log2()).sum();
+                let correction =
+                    (alphabet_size.max(2) - 1) as f64 / (2f64 * 2f64.ln() * residues as f64);
+                LogoColumn {
+                    information: (max_bits - entropy - correction).max(0f64),
+                    frequencies,
+                    residues,
+                }
+            })
+            .collect();
+        Logo { columns, max_bits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logo() {
+        let rows = [
+            "ACG-", "ACT-", "ACGA", "ATGA", "ACGT", "ACGT", "ACTT", "ACGG",
+        ];
+        let msa = MSAlignment::new(
+            (0..rows.len()).map(|i| i.to_string()).collect(),
+            rows.iter().map(|r| r.chars().collect()).collect(),
+        );
+        let logo = msa.logo(4);
+        assert_eq!(2f64, logo.max_bits);
+
+        // 8 residues: 2 bits less a correction of 3 / (16 ln 2)
+        let correction = 3f64 / (16f64 * 2f64.ln());
+        assert!((logo.columns[0].information - (2f64 - correction)).abs() < 1e-9);
+        assert_eq!(vec![('A', 1f64)], logo.columns[0].frequencies);
+
+        let g = &logo.columns[2];
+        assert_eq!(vec![('G', 0.75), ('T', 0.25)], g.frequencies);
+        let heights: Vec<(char, f64)> = g.heights().collect();
+        assert!((heights[0].1 - 0.75 * g.information).abs() < 1e-9);
+        assert_eq!(6, logo.columns[3].residues);
+    }
+}
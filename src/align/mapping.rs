@@ -0,0 +1,325 @@
+//! Mapping of long sequences, like contigs of an assembly, to a reference.
+//!
+//! Whole-length dynamic programming of megabases is out of the question, so
+//! this works like a read mapper. The query is split into windows, each
+//! window is seeded with the k-mers it shares with the reference, and its
+//! seeds are chained into its best placement. The chains of the windows that
+//! placed are chained again across the query, which drops windows in
+//! repeats that landed elsewhere, and the result is an anchored alignment
+//! through the kept seeds: only the short stretches between seeds are
+//! aligned with DP.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::trace::stage;
+
+use super::{align_anchored, chain, Alignment, Anchor, Scoring};
+
+/// MapConfig configures the mapping of a long query to a reference.
+#[derive(Clone, Debug)]
+pub struct MapConfig {
+    /// residues of the query per window
+    pub window: usize,
+
+    /// length of the seeds
+    pub k: usize,
+
+    /// k-mers in the reference more often than this are repeats and not seeds
+    pub max_occurrences: usize,
+
+    /// least fraction of a window its chain of seeds must cover for it to place
+    pub min_coverage: f32,
+
+    /// scoring of the alignment between seeds
+    pub scoring: Scoring,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        MapConfig {
+            window: 1000,
+            k: 15,
+            max_occurrences: 10,
+            min_coverage: 0.1,
+            scoring: Scoring {
+                matrix: crate::matrices::NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Mapping is the alignment of the mapped region of a query to a reference.
+#[derive(Debug)]
+pub struct Mapping {
+    /// the query over the reference, from the first seed to the last
+    pub alignment: Alignment,
+
+    /// 0-based, half-open region of the query in the alignment
+    pub query: Range<usize>,
+
+    /// 0-based, half-open region of the reference in the alignment
+    pub reference: Range<usize>,
+
+    /// the seeds the alignment goes through, in query and reference coordinates
+    pub anchors: Vec<Anchor>,
+
+    /// windows of the query whose seeds are in the alignment
+    pub mapped_windows: usize,
+
+    /// windows the query was split into
+    pub windows: usize,
+
+    /// whether this is a supplementary alignment of a split query, rather
+    /// than its primary one
+    pub supplementary: bool,
+}
+
+/// map_long maps a long query to a reference, forward strand only.
+///
+/// Returns None if no window of the query placed on the reference.
+pub fn map_long(query: &str, reference: &str, config: &MapConfig) -> Option<Mapping> {
+    let (placed, windows) = place(query.as_bytes(), reference.as_bytes(), config);
+    let anchors = chain(&placed.iter().map(|(_, a)| *a).collect::<Vec<_>>());
+    Some(align_chain(
+        query, reference, &placed, anchors, windows, config,
+    ))
+    .filter(|m| !m.anchors.is_empty())
+}
+
+/// map_split maps a long query to a reference as a set of split alignments,
+/// like a mapper's primary and supplementary records, for queries that don't
+/// follow the reference in one collinear path, e.g. across a translocation.
+///
+/// The best chain of seeds is the primary alignment. Its seeds and every
+/// other seed in its span of the query are dropped, and the best chain of
+/// what's left is a supplementary alignment, until no seeds are left. The
+/// alignments are in order of the query.
+pub fn map_split(query: &str, reference: &str, config: &MapConfig) -> Vec<Mapping> {
+    let (mut placed, windows) = place(query.as_bytes(), reference.as_bytes(), config);
+    let mut mappings: Vec<Mapping> = vec![];
+    while !placed.is_empty() {
+        let anchors = chain(&placed.iter().map(|(_, a)| *a).collect::<Vec<_>>());
+        let mut mapping = align_chain(query, reference, &placed, anchors, windows, config);
+        mapping.supplementary = !mappings.is_empty();
+        placed.retain(|(_, a)| a.a + a.len <= mapping.query.start || a.a >= mapping.query.end);
+        mappings.push(mapping);
+    }
+    mappings.sort_by_key(|m| m.query.start);
+    mappings
+}
+
+/// place seeds each window of the query, keeping the chains of the windows
+/// that placed with their window, and counts the windows.
+fn place(q: &[u8], r: &[u8], config: &MapConfig) -> (Vec<(usize, Anchor)>, usize) {
+    let _stage = stage!("seeding", query = q.len(), reference = r.len());
+    let (k, window) = (config.k.max(1), config.window.max(1));
+    let windows = q.len().div_ceil(window);
+    if q.len() < k || r.len() < k {
+        return (vec![], windows);
+    }
+
+    let index = kmer_index(r, k, config.max_occurrences);
+    let mut placed: Vec<(usize, Anchor)> = vec![];
+    for w in 0..windows {
+        let start = w * window;
+        let end = (start + window).min(q.len());
+        let anchors = seeds(q, &index, k, start..end);
+        let chained = chain(&anchors);
+        let covered: usize = chained.iter().map(|a| a.len).sum();
+        if covered as f32 >= config.min_coverage * (end - start) as f32 {
+            placed.extend(chained.into_iter().map(|a| (w, a)));
+        }
+    }
+    (placed, windows)
+}
+
+/// align_chain aligns the query to the reference through a chain of the
+/// placed seeds.
+fn align_chain(
+    query: &str,
+    reference: &str,
+    placed: &[(usize, Anchor)],
+    anchors: Vec<Anchor>,
+    windows: usize,
+    config: &MapConfig,
+) -> Mapping {
+    let (query_range, reference_range) = match (anchors.first(), anchors.last()) {
+        (Some(first), Some(last)) => (first.a..last.a + last.len, first.b..last.b + last.len),
+        _ => (0..0, 0..0),
+    };
+
+    let mut mapped: Vec<usize> = placed
+        .iter()
+        .filter(|(_, a)| anchors.contains(a))
+        .map(|(w, _)| *w)
+        .collect();
+    mapped.dedup();
+
+    let relative: Vec<Anchor> = anchors
+        .iter()
+        .map(|a| Anchor {
+            a: a.a - query_range.start,
+            b: a.b - reference_range.start,
+            len: a.len,
+        })
+        .collect();
+    let alignment = align_anchored(
+        &query[query_range.clone()],
+        &reference[reference_range.clone()],
+        &relative,
+        &config.scoring,
+    )
+    .expect("chained anchors are in order and don't overlap");
+
+    Mapping {
+        alignment,
+        query: query_range,
+        reference: reference_range,
+        anchors,
+        mapped_windows: mapped.len(),
+        windows,
+        supplementary: false,
+    }
+}
+
+/// kmer_index is the positions of each k-mer of a reference, leaving out
+/// those in it more than `max_occurrences` times.
+pub(super) fn kmer_index(r: &[u8], k: usize, max_occurrences: usize) -> HashMap<&[u8], Vec<usize>> {
+    let mut index: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (pos, word) in r.windows(k).enumerate() {
+        index.entry(word).or_default().push(pos);
+    }
+    index.retain(|_, positions| positions.len() <= max_occurrences);
+    index
+}
+
+/// seeds of the k-mers starting in a region of the query, with hits on the
+/// same diagonal that overlap merged into one anchor. Anchors are cut at the
+/// end of the region so those of neighbouring windows chain together.
+pub(super) fn seeds(
+    q: &[u8],
+    index: &HashMap<&[u8], Vec<usize>>,
+    k: usize,
+    region: Range<usize>,
+) -> Vec<Anchor> {
+    let mut hits: Vec<(usize, usize)> = vec![];
+    for a in region.start..region.end.min(q.len() + 1 - k) {
+        if let Some(positions) = index.get(&q[a..a + k]) {
+            hits.extend(positions.iter().map(|b| (a, *b)));
+        }
+    }
+    hits.sort_by_key(|(a, b)| (*b as isize - *a as isize, *a));
+
+    let mut anchors: Vec<Anchor> = vec![];
+    for (a, b) in hits {
+        match anchors.last_mut() {
+            Some(last)
+                if last.b as isize - last.a as isize == b as isize - a as isize
+                    && a <= last.a + last.len =>
+            {
+                last.len = (a + k).min(region.end) - last.a;
+            }
+            _ => anchors.push(Anchor {
+                a,
+                b,
+                len: k.min(region.end - a),
+            }),
+        }
+    }
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_map_long() {
+        let mut rng = Rng::new(11);
+        let reference = random_seq(6000, &background::nucleotide(), &mut rng);
+
+        // a contig of 1000..5000 with a SNP every 97 bases, a deletion of 10
+        // and an insertion of 5
+        let mut query: Vec<u8> = reference.as_bytes()[1000..5000].to_vec();
+        for pos in (50..query.len()).step_by(97) {
+            query[pos] = if query[pos] == b'A' { b'C' } else { b'A' };
+        }
+        query.drain(2000..2010);
+        query.splice(3000..3000, b"GGGGG".iter().copied());
+        let query = String::from_utf8(query).unwrap();
+
+        let config = MapConfig {
+            window: 500,
+            ..Default::default()
+        };
+        let mapping = map_long(&query, &reference, &config).unwrap();
+        assert_eq!(8, mapping.windows);
+        assert_eq!(8, mapping.mapped_windows);
+        assert!(mapping.query.start < 50 && mapping.query.end > query.len() - 50);
+        assert_eq!(
+            mapping.reference.start,
+            1000 + mapping.query.start,
+            "{:?}",
+            mapping.reference
+        );
+
+        let ungapped = |row: &[char]| row.iter().filter(|c| **c != '-').collect::<String>();
+        assert_eq!(
+            query[mapping.query.clone()],
+            ungapped(&mapping.alignment.rows[0])
+        );
+        assert_eq!(
+            reference[mapping.reference.clone()],
+            ungapped(&mapping.alignment.rows[1])
+        );
+        let gaps: usize = mapping
+            .alignment
+            .rows
+            .iter()
+            .map(|r| r.iter().filter(|c| **c == '-').count())
+            .sum();
+        assert_eq!(15, gaps);
+
+        assert!(map_long("ACGTACGTACGTACGTACGT", &reference, &config).is_none());
+    }
+
+    #[test]
+    fn test_map_split() {
+        let mut rng = Rng::new(12);
+        let reference = random_seq(6000, &background::nucleotide(), &mut rng);
+
+        // a translocation: 3000..5500 of the reference, then 500..2000
+        let query = format!("{}{}", &reference[3000..5500], &reference[500..2000]);
+        let config = MapConfig {
+            window: 500,
+            ..Default::default()
+        };
+
+        let mappings = map_split(&query, &reference, &config);
+        assert_eq!(2, mappings.len());
+        let (first, second) = (&mappings[0], &mappings[1]);
+        assert!(!first.supplementary && second.supplementary);
+        assert_eq!(
+            (0..2500, 3000..5500),
+            (first.query.clone(), first.reference.clone())
+        );
+        assert_eq!(
+            (2500..4000, 500..2000),
+            (second.query.clone(), second.reference.clone())
+        );
+        assert_eq!(5, first.mapped_windows);
+        assert_eq!(3, second.mapped_windows);
+        assert_eq!(
+            first.reference,
+            map_long(&query, &reference, &config).unwrap().reference
+        );
+    }
+}
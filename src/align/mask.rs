@@ -0,0 +1,162 @@
+//! Masked regions that are left out of an alignment.
+//!
+//! Masked residues, like a vector backbone or an adapter, are removed before
+//! aligning and put back afterwards as forced gap columns, so the result
+//! still covers the whole sequences and positions don't need to be shifted
+//! the way they would after trimming.
+
+use super::{align, strategy::Strategy, Alignment, Scoring};
+
+/// MaskMode decides how masked residues count toward the alignment score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaskMode {
+    /// masked residues aren't scored
+    #[default]
+    Skip,
+
+    /// each masked region is scored as a gap of its length
+    Gap,
+}
+
+/// Mask holds the half-open, 0-based intervals of each sequence to leave unaligned.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mask {
+    pub a: Vec<(usize, usize)>,
+    pub b: Vec<(usize, usize)>,
+    pub mode: MaskMode,
+}
+
+/// align_masked aligns two sequences, leaving the masked intervals as gaps.
+///
+/// Masked residues are put back by counting residues from the start of each
+/// sequence, so the strategy should be a global one.
+pub fn align_masked(
+    a: &str,
+    b: &str,
+    strategy: &Strategy,
+    scoring: &Scoring,
+    mask: &Mask,
+) -> Alignment {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (a_masked, b_masked) = (masked(a.len(), &mask.a), masked(b.len(), &mask.b));
+
+    let unmasked = |seq: &[char], masked: &[bool]| -> String {
+        seq.iter()
+            .zip(masked)
+            .filter(|(_, m)| !**m)
+            .map(|(c, _)| *c)
+            .collect()
+    };
+    let alignment = align(
+        vec![unmasked(&a, &a_masked), unmasked(&b, &b_masked)],
+        strategy,
+        scoring,
+    );
+
+    // walk the alignment putting the masked residues back where they were
+    let mut rows: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
+    let (mut i, mut j) = (0, 0);
+    let flush = |rows: &mut Vec<Vec<char>>, i: &mut usize, j: &mut usize| {
+        while *i < a.len() && a_masked[*i] {
+            rows[0].push(a[*i]);
+            rows[1].push('-');
+            *i += 1;
+        }
+        while *j < b.len() && b_masked[*j] {
+            rows[0].push('-');
+            rows[1].push(b[*j]);
+            *j += 1;
+        }
+    };
+    flush(&mut rows, &mut i, &mut j);
+    for (x, y) in alignment.rows[0].iter().zip(alignment.rows[1].iter()) {
+        rows[0].push(*x);
+        rows[1].push(*y);
+        if *x != '-' {
+            i += 1;
+        }
+        if *y != '-' {
+            j += 1;
+        }
+        flush(&mut rows, &mut i, &mut j);
+    }
+
+    let mut score = alignment.score;
+    if mask.mode == MaskMode::Gap {
+        for len in runs(&a_masked).chain(runs(&b_masked)) {
+            score += scoring.gap_opening + scoring.gap_extension * (len - 1) as f32;
+        }
+    }
+
+    Alignment::new(rows, vec![], score)
+}
+
+/// masked flags each position of a sequence that's in an interval.
+fn masked(len: usize, intervals: &[(usize, usize)]) -> Vec<bool> {
+    let mut masked = vec![false; len];
+    for (start, end) in intervals {
+        for m in masked.iter_mut().take(*end).skip(*start) {
+            *m = true;
+        }
+    }
+    masked
+}
+
+/// runs are the lengths of each run of masked positions.
+fn runs(masked: &[bool]) -> impl Iterator<Item = usize> + '_ {
+    masked
+        .split(|m| !*m)
+        .filter(|run| !run.is_empty())
+        .map(|run| run.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::Method, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_align_masked() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            ..Default::default()
+        };
+        let strategy = Method::NeedlemanWunsch.strategy();
+
+        // the adapter on b is left out and kept in place
+        let mut mask = Mask {
+            b: vec![(0, 4)],
+            ..Default::default()
+        };
+        let alignment = align_masked("ACGTACGT", "TTTTACGTACGT", strategy, &scoring, &mask);
+        assert_eq!("----ACGTACGT\nTTTTACGTACGT", alignment.to_string());
+        assert_eq!(40f32, alignment.score);
+        assert_eq!(Some(4), alignment.coordinate_map().column(1, 4));
+
+        mask.mode = MaskMode::Gap;
+        let alignment = align_masked("ACGTACGT", "TTTTACGTACGT", strategy, &scoring, &mask);
+        assert_eq!(40f32 - 4f32, alignment.score);
+    }
+
+    #[test]
+    fn test_align_masked_middle() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            ..Default::default()
+        };
+        let mask = Mask {
+            a: vec![(4, 6)],
+            b: vec![(4, 5)],
+            ..Default::default()
+        };
+        let alignment = align_masked(
+            "ACGTNNACGT",
+            "ACGTXACGT",
+            Method::NeedlemanWunsch.strategy(),
+            &scoring,
+            &mask,
+        );
+        assert_eq!("ACGTNN-ACGT\nACGT--XACGT", alignment.to_string());
+    }
+}
@@ -0,0 +1,112 @@
+//! Export of the alignment grid for teaching and debugging.
+//!
+//! The `Debug` output of an alignment draws the grid for a person to read,
+//! these write the same scores and traceback pointers for a program to read.
+//! Row `i` of the grid is residue `i` of the second sequence and column `j`
+//! is residue `j` of the first, with row and column 0 before the first residue.
+//! Only alignments from [`align_grid`](super::align_grid) keep the grid.
+
+use std::fmt::Write;
+
+use super::Alignment;
+
+impl Alignment {
+    /// matrix_csv writes one line per grid cell: its row, column, score, and
+    /// the cell the traceback moves to from it (empty at the start of the path).
+    pub fn matrix_csv(&self) -> String {
+        let mut csv = String::from("i,j,score,next_i,next_j\n");
+        for row in self.steps.iter() {
+            for step in row.iter() {
+                let (next_i, next_j) = match step.next {
+                    Some((i, j)) => (i.to_string(), j.to_string()),
+                    None => (String::new(), String::new()),
+                };
+                writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    step.i,
+                    step.j,
+                    score(step.val.0),
+                    next_i,
+                    next_j
+                )
+                .unwrap();
+            }
+        }
+        csv
+    }
+
+    /// matrix_json writes the scores and traceback pointers as two nested arrays
+    /// indexed by row then column, with null for cells without a pointer.
+    pub fn matrix_json(&self) -> String {
+        let scores: Vec<String> = self
+            .steps
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(|s| score(s.val.0).to_string()).collect();
+                format!("[{}]", row.join(","))
+            })
+            .collect();
+        let traceback: Vec<String> = self
+            .steps
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row
+                    .iter()
+                    .map(|s| match s.next {
+                        Some((i, j)) => format!("[{},{}]", i, j),
+                        None => "null".to_string(),
+                    })
+                    .collect();
+                format!("[{}]", row.join(","))
+            })
+            .collect();
+
+        format!(
+            "{{\"scores\":[{}],\"traceback\":[{}]}}",
+            scores.join(","),
+            traceback.join(",")
+        )
+    }
+}
+
+/// score drops the sign of a zero score, the first cell of the grid is -0.
+fn score(val: f32) -> f32 {
+    val + 0f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_grid, Method, Scoring},
+        matrices::NUC_4_4,
+    };
+
+    #[test]
+    fn test_matrix_export() {
+        let alignment = align_grid(
+            vec!["AC".to_string(), "A".to_string()],
+            Method::NeedlemanWunsch.strategy(),
+            &Scoring {
+                matrix: NUC_4_4::MATRIX,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            "i,j,score,next_i,next_j
+0,0,0,,
+0,1,-1,0,0
+0,2,-2,0,1
+1,0,-1,0,0
+1,1,5,0,0
+1,2,4,1,1
+",
+            alignment.matrix_csv()
+        );
+        assert_eq!(
+            "{\"scores\":[[0,-1,-2],[-1,5,4]],\"traceback\":[[null,[0,0],[0,1]],[[0,0],[0,0],[1,1]]]}",
+            alignment.matrix_json()
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Merging of overlapping paired-end reads, like PEAR and FLASH.
+//!
+//! When the fragment is shorter than the two reads combined, the end of read
+//! 1 and the reverse complement of read 2 cover the same bases. Aligning them
+//! in overlap mode, with read 1 free to start before read 2 and read 2 free to
+//! run past the end of read 1, finds that region, and the two base calls of
+//! each column are combined by their qualities into one longer read.
+
+use crate::{matrices::NUC_4_4, seq::reverse_complement};
+
+use super::{
+    alignment::align,
+    needleman_wunsch,
+    terminal_gaps::{TerminalGap, TerminalGaps},
+    Error, Result, Scoring,
+};
+
+/// MergeConfig configures how read pairs are merged.
+#[derive(Clone, Debug)]
+pub struct MergeConfig {
+    /// scoring of the overlap alignment, its terminal gaps are ignored
+    pub scoring: Scoring,
+
+    /// fewest aligned pairs of bases in the overlap
+    pub min_overlap: usize,
+
+    /// most mismatches and gaps per column of the overlap
+    pub max_mismatch_rate: f32,
+
+    /// highest quality of a merged base
+    pub max_qual: u8,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        MergeConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            min_overlap: 10,
+            max_mismatch_rate: 0.1,
+            max_qual: 41,
+        }
+    }
+}
+
+/// Merged is a read pair merged into one sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merged {
+    pub seq: String,
+
+    /// Phred quality of each residue in `seq`
+    pub qual: Vec<u8>,
+
+    /// number of columns from the first to the last aligned pair of bases
+    pub overlap: usize,
+
+    /// number of mismatches and gaps in the overlap
+    pub mismatches: usize,
+}
+
+/// merge_pair merges read 1 and read 2 of a pair, as sequenced, with their
+/// Phred qualities.
+///
+/// Bases in the overlap that agree get the sum of their qualities, up to
+/// `max_qual`. Bases that disagree get the call of the higher quality one,
+/// with the difference of their qualities. It's None if the reads don't
+/// overlap by at least `min_overlap` or differ too much where they do, and an
+/// error if a read doesn't have one quality per base.
+pub fn merge_pair(
+    read1: &str,
+    qual1: &[u8],
+    read2: &str,
+    qual2: &[u8],
+    config: &MergeConfig,
+) -> Result<Option<Merged>> {
+    for (read, seq, qual) in [(1, read1, qual1), (2, read2, qual2)] {
+        if seq.len() != qual.len() {
+            return Err(Error::InvalidParameter(format!(
+                "read {} has {} bases and {} qualities",
+                read,
+                seq.len(),
+                qual.len()
+            )));
+        }
+    }
+
+    let read2 = reverse_complement(read2);
+    let qual2: Vec<u8> = qual2.iter().rev().copied().collect();
+    let scoring = Scoring {
+        terminal_gaps: TerminalGaps {
+            a_end: TerminalGap::Free,
+            b_start: TerminalGap::Free,
+            ..Default::default()
+        },
+        ..config.scoring.clone()
+    };
+    let alignment = align(
+        vec![read1.to_string(), read2],
+        &needleman_wunsch::STRATEGY,
+        &scoring,
+    );
+    let (a, b) = (&alignment.rows[0], &alignment.rows[1]);
+
+    let pair = |col: &usize| a[*col] != '-' && b[*col] != '-';
+    let (Some(first), Some(last)) = ((0..a.len()).find(pair), (0..a.len()).rfind(pair)) else {
+        return Ok(None);
+    };
+    let pairs = (first..=last).filter(pair).count();
+    let mismatches = (first..=last)
+        .filter(|col| !pair(col) || !a[*col].eq_ignore_ascii_case(&b[*col]))
+        .count();
+    let overlap = last - first + 1;
+    let too_different = mismatches as f32 > config.max_mismatch_rate * overlap as f32;
+    if pairs < config.min_overlap || too_different {
+        return Ok(None);
+    }
+
+    let mut seq = String::with_capacity(a.len());
+    let mut qual = Vec::with_capacity(a.len());
+    let (mut i1, mut i2) = (0, 0);
+    for (x, y) in a.iter().zip(b.iter()) {
+        let (base, q) = match (*x, *y) {
+            ('-', '-') => continue,
+            (x, '-') => (x, qual1[i1]),
+            ('-', y) => (y, qual2[i2]),
+            (x, y) => {
+                let (q1, q2) = (qual1[i1], qual2[i2]);
+                if x.eq_ignore_ascii_case(&y) {
+                    (x, q1.saturating_add(q2).min(config.max_qual))
+                } else if q1 >= q2 {
+                    (x, q1 - q2)
+                } else {
+                    (y, q2 - q1)
+                }
+            }
+        };
+        if *x != '-' {
+            i1 += 1;
+        }
+        if *y != '-' {
+            i2 += 1;
+        }
+        seq.push(base);
+        qual.push(q);
+    }
+
+    Ok(Some(Merged {
+        seq,
+        qual,
+        overlap,
+        mismatches,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_pair() {
+        let fragment = "ACGTACGTTTGCAAGGCTAGCCATGGATCA";
+        let read1 = &fragment[..20];
+        let mut read2 = reverse_complement(&fragment[8..]);
+
+        // a low quality miscall in read 2 where the reads overlap
+        read2.replace_range(12..13, "C");
+        let mut qual2 = vec![30; read2.len()];
+        qual2[12] = 5;
+
+        let config = MergeConfig::default();
+        let merged = merge_pair(read1, &[30; 20], &read2, &qual2, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fragment, merged.seq);
+        assert_eq!(12, merged.overlap);
+        assert_eq!(1, merged.mismatches);
+        assert_eq!(25, merged.qual[17]);
+        assert_eq!(41, merged.qual[8]);
+        assert_eq!(30, merged.qual[0]);
+        assert_eq!(30, merged.qual[29]);
+
+        assert_eq!(
+            None,
+            merge_pair(read1, &[30; 20], "GGGGGGGGGG", &[30; 10], &config).unwrap()
+        );
+
+        assert!(matches!(
+            merge_pair(read1, &[30; 19], &read2, &qual2, &config),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            merge_pair(read1, &[30; 20], &read2, &qual2[1..], &config),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+//! Key/value metadata carried by alignments to the files they're written to.
+//!
+//! An alignment on its own is just rows of residues. Metadata records where
+//! it came from, like the IDs of the sequences, the sample, or the scoring
+//! used, so output can be traced back to its inputs. Keys are kept sorted so
+//! output is the same from run to run.
+
+use std::collections::BTreeMap;
+
+use super::{Alignment, MSAlignment};
+
+/// Metadata of an alignment, by key.
+pub type Metadata = BTreeMap<String, String>;
+
+impl Alignment {
+    /// with_metadata sets a metadata value of the alignment.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl MSAlignment {
+    /// with_metadata sets a metadata value of the alignment.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_metadata() {
+        let alignment = Alignment::new(vec![vec!['A'], vec!['A']], vec![], 1f32)
+            .with_metadata("sample", "s1")
+            .with_metadata("matrix", "NUC.4.4")
+            .with_metadata("sample", "s2");
+        assert_eq!(
+            vec![("matrix", "NUC.4.4"), ("sample", "s2")],
+            alignment
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,11 +1,271 @@
+pub use crate::align::aligner::Aligner;
+pub use crate::align::aligner::AlignerConfig;
+pub use crate::align::aligner::Constructor;
+pub use crate::align::aligner::Registry;
 pub use crate::align::alignment::align;
+pub use crate::align::alignment::align_grid;
 pub use crate::align::alignment::Alignment;
 pub use crate::align::alignment::Scoring;
+pub use crate::align::anchors::align_anchored;
+pub use crate::align::anchors::Anchor;
+pub use crate::align::banded::align_banded;
+pub use crate::align::banded::Band;
+pub use crate::align::batch::smith_waterman_batch;
+pub use crate::align::bootstrap::BootstrapConfig;
+pub use crate::align::bounded::align_within;
+pub use crate::align::breakdown::ScoreBreakdown;
+pub use crate::align::breakpoints::breakpoints;
+pub use crate::align::breakpoints::Breakpoint;
+pub use crate::align::breakpoints::BreakpointConfig;
+pub use crate::align::breakpoints::BreakpointKind;
+pub use crate::align::chain::chain;
+pub use crate::align::clips::Clipped;
+pub use crate::align::clips::Clips;
+pub use crate::align::clustal_w::align_multiple;
+pub use crate::align::clustal_w::align_multiple_with_report;
+pub use crate::align::clustal_w::align_records;
+pub use crate::align::clustal_w::align_records_with_report;
+pub use crate::align::clustal_w::upgma;
+pub use crate::align::clustal_w::Duplicates;
+pub use crate::align::clustal_w::EmptySequences;
+pub use crate::align::clustal_w::OutputOrder;
+pub use crate::align::clustal_w::ProgressiveConfig;
+pub use crate::align::cluster::cluster;
+pub use crate::align::cluster::Cluster;
+pub use crate::align::compare::compare;
+pub use crate::align::compare::Comparison;
+pub use crate::align::config::AlignConfig;
+pub use crate::align::conservation::Conserved;
+pub use crate::align::conservation::Symbols;
+pub use crate::align::context::align_context;
+pub use crate::align::context::cpg_aware;
+pub use crate::align::context::Context;
+pub use crate::align::coordinates::CoordinateMap;
+pub use crate::align::distance_matrix::identity_matrix;
+pub use crate::align::distance_matrix::identity_matrix_with;
+pub use crate::align::distance_matrix::DistanceConfig;
+pub use crate::align::distance_matrix::DistanceMatrix;
+pub use crate::align::distance_matrix::UnknownResidues;
+pub use crate::align::dotplot::dotplot;
+pub use crate::align::dotplot::Dotplot;
+pub use crate::align::error_profile::ErrorProfile;
+pub use crate::align::external::align_external;
+pub use crate::align::external::ExternalConfig;
+pub use crate::align::features::ProjectedFeature;
+pub use crate::align::fixed::Precision;
+pub use crate::align::gaps::GapChars;
+pub use crate::align::guide_tree::GuideTree;
+pub use crate::align::guide_tree::Node;
+pub use crate::align::homopolymer::align_homopolymer;
+pub use crate::align::homopolymer::HomopolymerGaps;
+pub use crate::align::identity::Definition;
+pub use crate::align::identity::IdentityWindow;
+pub use crate::align::inversions::align_inversions;
+pub use crate::align::inversions::InversionAlignment;
+pub use crate::align::inversions::InversionBlock;
+pub use crate::align::lcs::lcs;
+pub use crate::align::local::align_local;
+pub use crate::align::local::LocalAlignment;
+pub use crate::align::logo::Logo;
+pub use crate::align::logo::LogoColumn;
+pub use crate::align::mapping::map_long;
+pub use crate::align::mapping::map_split;
+pub use crate::align::mapping::MapConfig;
+pub use crate::align::mapping::Mapping;
+pub use crate::align::mask::align_masked;
+pub use crate::align::mask::Mask;
+pub use crate::align::mask::MaskMode;
+pub use crate::align::merge::merge_pair;
+pub use crate::align::merge::MergeConfig;
+pub use crate::align::merge::Merged;
+pub use crate::align::metadata::Metadata;
+pub use crate::align::msa::MSAlignment;
+pub use crate::align::overlap::align_overlap;
+pub use crate::align::palindromes::palindromes;
+pub use crate::align::palindromes::Palindrome;
+pub use crate::align::palindromes::PalindromeConfig;
+pub use crate::align::patch::Edit;
+pub use crate::align::patch::Patch;
+pub use crate::align::pileup::Pileup;
+pub use crate::align::pileup::PileupColumn;
+pub use crate::align::profile_merge::merge_alignments;
+pub use crate::align::quality::align_with_quality;
+pub use crate::align::reference::align_to_reference;
+pub use crate::align::reference::Insertion;
+pub use crate::align::reference::ReferenceAlignment;
+pub use crate::align::repeats::self_align;
+pub use crate::align::repeats::Repeat;
+pub use crate::align::repeats::RepeatConfig;
+pub use crate::align::repeats::RepeatKind;
+pub use crate::align::run_report::Merge;
+pub use crate::align::run_report::RunReport;
+pub use crate::align::sanger::align_sanger;
+pub use crate::align::sanger::Call;
+pub use crate::align::sanger::HetCandidate;
+pub use crate::align::sanger::SangerConfig;
+pub use crate::align::sanger::SangerReport;
+pub use crate::align::slice::MSASlice;
+pub use crate::align::step::Step;
 pub use crate::align::strategy::Method;
+pub use crate::align::summary::align_summary;
+pub use crate::align::summary::AlignmentSummary;
+pub use crate::align::terminal_gaps::TerminalGap;
+pub use crate::align::terminal_gaps::TerminalGaps;
+pub use crate::align::variants::Variant;
+pub use crate::align::variants::VariantKind;
+pub use crate::align::verify::verify;
+pub use crate::align::verify::AssemblyError;
+pub use crate::align::verify::Verdict;
+pub use crate::align::verify::Verification;
+pub use crate::align::verify::VerifyConfig;
+pub use crate::align::wfa::align_wfa;
 
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+mod add;
+mod aligner;
 mod alignment;
+mod anchors;
+mod banded;
+mod batch;
+mod bootstrap;
+mod bounded;
+mod breakdown;
+mod breakpoints;
+mod chain;
+mod checkpoint;
+mod clips;
 mod clustal_w;
+mod cluster;
+mod columns;
+mod compare;
+mod config;
+mod conservation;
+mod context;
+mod coordinates;
+mod distance_matrix;
+mod dotplot;
+mod error_profile;
+mod external;
+mod features;
+mod fixed;
+mod gaps;
+pub(crate) mod gotoh;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod guide_tree;
+mod homopolymer;
+mod identity;
+mod import;
+mod inversions;
+mod lcs;
+mod local;
+mod logo;
+mod mapping;
+mod mask;
+mod matrix_export;
+mod merge;
+mod metadata;
+mod msa;
+mod msa_mask;
 mod needleman_wunsch;
+mod normalize;
+mod overlap;
+mod palindromes;
+mod patch;
+mod pileup;
+mod profile_merge;
+mod quality;
+mod realign;
+mod reference;
+mod repeats;
+mod run_report;
+mod sanger;
+mod scheduler;
+mod slice;
 mod smith_waterman;
 mod step;
 mod strategy;
+mod sum_of_pairs;
+mod summary;
+mod terminal_gaps;
+mod traceback;
+mod variants;
+mod verify;
+mod wfa;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("patch expected {expected} at {pos}, found {found}")]
+    PatchMismatch {
+        pos: usize,
+        expected: String,
+        found: String,
+    },
+
+    #[error("patch edit at {0} is out of order or past the end of the sequence")]
+    PatchOutOfBounds(usize),
+
+    #[error("anchor at {a},{b} is out of order or past the end of a sequence")]
+    InvalidAnchor { a: usize, b: usize },
+
+    #[error("checkpoint {0} is corrupt or for different input")]
+    InvalidCheckpoint(PathBuf),
+
+    #[error("can't read or write checkpoint")]
+    CheckpointError(#[from] io::Error),
+
+    #[error("no aligner named {0}")]
+    UnknownAligner(String),
+
+    #[error("can't read or write traceback tiles")]
+    TileError(#[source] io::Error),
+
+    #[error("alignments are of different sequences")]
+    DifferentSequences,
+
+    #[error("no sequence {0} in the alignment")]
+    UnknownSequence(String),
+
+    #[error("tree has {leaves} leaves for {alignments} alignments")]
+    TreeMismatch { leaves: usize, alignments: usize },
+
+    #[error("can't read config {path}")]
+    ConfigRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("config line {line}: {message}")]
+    InvalidConfig { line: usize, message: String },
+
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    #[error("sequence {0} is empty")]
+    EmptySequence(String),
+
+    #[error("sequence {0} isn't ASCII")]
+    NonAsciiSequence(String),
+
+    #[error("invalid alignment: {0}")]
+    InvalidAlignment(String),
+
+    #[error("region {start}..{end} is outside the alignment of {len} columns")]
+    InvalidRegion {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+
+    #[error("residues {start}..{end} are outside sequence {id}")]
+    InvalidResidues {
+        start: usize,
+        end: usize,
+        id: String,
+    },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,106 @@
+//! Multiple sequence alignments.
+
+use std::fmt::Display;
+
+use super::{CoordinateMap, Metadata};
+
+/// MSAlignment is an alignment of any number of sequences.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MSAlignment {
+    /// IDs of the sequences, one per row
+    pub ids: Vec<String>,
+
+    /// the gapped sequences, all the same length
+    pub rows: Vec<Vec<char>>,
+
+    /// where the alignment came from: samples, parameters
+    pub metadata: Metadata,
+}
+
+impl MSAlignment {
+    pub fn new(ids: Vec<String>, rows: Vec<Vec<char>>) -> Self {
+        if ids.len() != rows.len() {
+            panic!("MSAlignment must have one ID per row")
+        }
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            panic!("MSAlignment rows must be the same length")
+        }
+
+        MSAlignment {
+            ids,
+            rows,
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// len is the number of columns in the alignment.
+    pub fn len(&self) -> usize {
+        self.rows.first().map_or(0, |r| r.len())
+    }
+
+    /// is_empty is true if the alignment has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// column of the residues at an index, one per row.
+    pub fn column(&self, col: usize) -> Vec<char> {
+        self.rows.iter().map(|r| r[col]).collect()
+    }
+
+    /// row of a sequence by its ID.
+    pub fn row(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|i| i == id)
+    }
+
+    /// coordinate_map of the rows of the alignment.
+    pub fn coordinate_map(&self) -> CoordinateMap {
+        CoordinateMap::new(&self.rows)
+    }
+}
+
+impl Display for MSAlignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.rows
+                .iter()
+                .map(|a| a.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msalignment() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![
+                "AC-GT".chars().collect(),
+                "ACTGT".chars().collect(),
+                "A--GT".chars().collect(),
+            ],
+        );
+
+        assert_eq!(5, msa.len());
+        assert_eq!(vec!['-', 'T', '-'], msa.column(2));
+        assert_eq!(Some(2), msa.row("c"));
+        assert_eq!(Some(3), msa.coordinate_map().column(2, 1));
+        assert_eq!("AC-GT\nACTGT\nA--GT", msa.to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_msalignment_ragged() {
+        MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["ACGT".chars().collect(), "ACG".chars().collect()],
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Masks of a multiple alignment: columns, or residues of one sequence, that
+//! are left out of its scores and consensus but kept in its rows.
+//!
+//! Curating an alignment often finds data that's bad, like a misassembled
+//! stretch of one sequence or a column that's misaligned, but that should
+//! stay in the file. Masks are kept as Stockholm markup in the metadata, a
+//! `GC:MASK` line for columns and a `GR:<id>:MASK` line per sequence with an
+//! `x` for each masked column, so they're sliced with the alignment and
+//! written to, and read back from, Stockholm files.
+
+use std::ops::Range;
+
+use super::{Error, MSAlignment, Result};
+
+const MASKED: char = 'x';
+const UNMASKED: char = '.';
+
+impl MSAlignment {
+    /// mask_columns masks a range of columns in every row.
+    pub fn mask_columns(&mut self, cols: Range<usize>) -> Result<()> {
+        if cols.start > cols.end || cols.end > self.len() {
+            return Err(Error::InvalidRegion {
+                start: cols.start,
+                end: cols.end,
+                len: self.len(),
+            });
+        }
+        self.set_mask("GC:MASK".to_string(), cols);
+        Ok(())
+    }
+
+    /// mask_residues masks a 0-based, half-open range of the residues of a
+    /// sequence, and the gaps between them.
+    pub fn mask_residues(&mut self, id: &str, residues: Range<usize>) -> Result<()> {
+        let row = self
+            .row(id)
+            .ok_or_else(|| Error::UnknownSequence(id.to_string()))?;
+        if residues.is_empty() {
+            return Ok(());
+        }
+
+        let map = self.coordinate_map();
+        let (Some(first), Some(last)) = (
+            map.column(row, residues.start),
+            map.column(row, residues.end - 1),
+        ) else {
+            return Err(Error::InvalidResidues {
+                start: residues.start,
+                end: residues.end,
+                id: id.to_string(),
+            });
+        };
+        let cols = first..last + 1;
+        self.set_mask(format!("GR:{}:MASK", id), cols);
+        Ok(())
+    }
+
+    /// clear_mask unmasks every column and residue.
+    pub fn clear_mask(&mut self) {
+        self.metadata.retain(|key, _| {
+            !(key == "GC:MASK" || key.starts_with("GR:") && key.ends_with(":MASK"))
+        });
+    }
+
+    /// mask is, per row and column, whether the cell is masked, by its column
+    /// or its sequence.
+    pub fn mask(&self) -> Vec<Vec<bool>> {
+        let line = |key: &str| -> Vec<bool> {
+            match self.metadata.get(key) {
+                Some(line) if line.chars().count() == self.len() => {
+                    line.chars().map(|c| c == MASKED).collect()
+                }
+                _ => vec![false; self.len()],
+            }
+        };
+        let columns = line("GC:MASK");
+        self.ids
+            .iter()
+            .map(|id| {
+                line(&format!("GR:{}:MASK", id))
+                    .into_iter()
+                    .zip(columns.iter())
+                    .map(|(row, col)| row || *col)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// is_masked is whether any cell of the alignment is masked.
+    pub fn is_masked(&self) -> bool {
+        self.mask().iter().flatten().any(|m| *m)
+    }
+
+    /// set_mask marks a range of columns in a mask line of the metadata.
+    fn set_mask(&mut self, key: String, cols: Range<usize>) {
+        let len = self.len();
+        let line = self
+            .metadata
+            .entry(key)
+            .or_insert_with(|| UNMASKED.to_string().repeat(len));
+        if line.chars().count() != len {
+            *line = UNMASKED.to_string().repeat(len);
+        }
+        *line = line
+            .chars()
+            .enumerate()
+            .map(|(col, c)| if cols.contains(&col) { MASKED } else { c })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::Scoring, io::stockholm, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        let mut msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![
+                "ACGTA-GT".chars().collect(),
+                "ACGTACGT".chars().collect(),
+                "TTTTA-GT".chars().collect(),
+            ],
+        );
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let unmasked = msa.sum_of_pairs(&scoring);
+
+        msa.mask_residues("c", 0..3).unwrap();
+        msa.mask_columns(5..6).unwrap();
+        assert!(msa.mask_residues("d", 0..1).is_err());
+        assert!(matches!(
+            msa.mask_residues("c", 2..100),
+            Err(Error::InvalidResidues { .. })
+        ));
+        assert!(matches!(
+            msa.mask_columns(5..100),
+            Err(Error::InvalidRegion { .. })
+        ));
+        assert_eq!("xxx.....", msa.metadata["GR:c:MASK"]);
+        assert_eq!(".....x..", msa.metadata["GC:MASK"]);
+
+        // the bad start of c and the insertion in b are left out
+        assert_eq!("ACGTA-GT", msa.consensus());
+        assert_eq!(7, msa.total_column_score());
+        assert_eq!(1f32, msa.column_conservation()[0]);
+        assert!(msa.sum_of_pairs(&scoring) > unmasked);
+
+        // kept through slicing and a round trip through Stockholm
+        let slice = msa.slice_columns(2..7);
+        assert_eq!(vec![false, false, false, true, false], slice.msa.mask()[1]);
+        assert_eq!(vec![true, false, false, true, false], slice.msa.mask()[2]);
+        let mut out = Vec::new();
+        stockholm::write(&msa, &mut out).unwrap();
+        assert_eq!(msa.mask(), stockholm::read(&out[..]).unwrap().mask());
+
+        msa.clear_mask();
+        assert!(!msa.is_masked());
+        assert_eq!(unmasked, msa.sum_of_pairs(&scoring));
+    }
+}
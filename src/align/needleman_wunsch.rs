@@ -9,17 +9,61 @@
 //! The algorithm assigns a score to every possible alignment, and the purpose of the
 //! algorithm is to find all possible alignments having the highest score.
 
-use super::{step::Step, strategy::Strategy};
+use super::{
+    strategy::{End, Ends, Strategy},
+    terminal_gaps::{TerminalGap, TerminalGaps},
+};
 use ordered_float::OrderedFloat;
 
 /// strategy for the Needleman-Wunsch algorithm.
 pub const STRATEGY: Strategy = Strategy {
     init_grid_value: |index: usize| -> OrderedFloat<f32> { OrderedFloat(-(index as f32)) },
 
-    init_step_options: |_i: usize, _j: usize| -> Vec<Step> { vec![] },
+    init_step_value: |_i: usize, _j: usize| -> Option<OrderedFloat<f32>> { None },
+
+    init_backtrace: |ends: &Ends, terminal_gaps: &TerminalGaps| -> End {
+        let last_i = ends.last_column.len() - 1;
+        let last_j = ends.last_row.len() - 1;
+        let mut end = End {
+            score: ends.last_row[last_j],
+            cell: (last_i, last_j),
+            terminal_gap: false,
+        };
+
+        // the alignment may end early in the last column (trailing gaps in seq a)
+        // or in the last row (trailing gaps in seq b) if those gaps are discounted.
+        // The end is then the cell the terminal gap to the corner starts from.
+        if terminal_gaps.a_end != TerminalGap::Full {
+            for (i, val) in ends.last_column.iter().enumerate().take(last_i) {
+                let val = (OrderedFloat(*val)
+                    + (STRATEGY.init_grid_value)(last_i - i) * terminal_gaps.a_end.weight())
+                .0;
+                if val > end.score {
+                    end = End {
+                        score: val,
+                        cell: (i, last_j),
+                        terminal_gap: true,
+                    };
+                }
+            }
+        }
 
-    init_backtrace: |grid: &[Vec<Step>]| -> Step {
-        grid[grid.len() - 1][grid[0].len() - 1].clone()
+        if terminal_gaps.b_end != TerminalGap::Full {
+            for (j, val) in ends.last_row.iter().enumerate().take(last_j) {
+                let val = (OrderedFloat(*val)
+                    + (STRATEGY.init_grid_value)(last_j - j) * terminal_gaps.b_end.weight())
+                .0;
+                if val > end.score {
+                    end = End {
+                        score: val,
+                        cell: (last_i, j),
+                        terminal_gap: true,
+                    };
+                }
+            }
+        }
+
+        end
     },
 };
 
@@ -41,6 +85,7 @@ mod tests {
                 matrix: MATCH::MATRIX,
                 gap_opening: -1f32,
                 gap_extension: -1f32,
+                ..Default::default()
             },
         );
 
@@ -55,4 +100,40 @@ mod tests {
             alignment.rows[1].clone().into_iter().collect::<String>()
         );
     }
+
+    #[test]
+    fn test_aligner_align_free_terminal_gaps() {
+        let alignment = align(
+            vec!["TTACGTTT".to_string(), "ACGT".to_string()],
+            &STRATEGY,
+            &Scoring {
+                matrix: MATCH::MATRIX,
+                terminal_gaps: TerminalGaps::all(TerminalGap::Free),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!("TTACGTTT", alignment.rows[0].iter().collect::<String>());
+        assert_eq!("--ACGT--", alignment.rows[1].iter().collect::<String>());
+        assert_eq!(4f32, alignment.score);
+    }
+
+    #[test]
+    fn test_aligner_align_half_terminal_gaps() {
+        let alignment = align(
+            vec!["TTACGT".to_string(), "ACGT".to_string()],
+            &STRATEGY,
+            &Scoring {
+                matrix: MATCH::MATRIX,
+                terminal_gaps: TerminalGaps {
+                    b_start: TerminalGap::Half,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        assert_eq!("--ACGT", alignment.rows[1].iter().collect::<String>());
+        assert_eq!(3f32, alignment.score);
+    }
 }
@@ -0,0 +1,86 @@
+//! Normalization of indels to their leftmost position.
+//!
+//! An indel in a repeat can be placed anywhere in it at the same score, and
+//! which place an aligner picks depends on how it breaks ties. Variant callers
+//! and comparisons between tools expect the leftmost placement, so indels are
+//! shifted left for as long as that leaves both sequences and the score as
+//! they were.
+
+use super::Alignment;
+
+impl Alignment {
+    /// normalize_indels shifts every gap in the alignment to the leftmost of
+    /// the positions it could have at the same score.
+    ///
+    /// A gap moves one column left when the residue before it, in the other
+    /// row, is the same as the last residue it's against, so the residues
+    /// against the gap only rotate. Gaps don't move past gaps in the other row.
+    pub fn normalize_indels(&mut self) {
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for (gapped, other) in [(0, 1), (1, 0)] {
+                moved |= self.shift_gaps(gapped, other);
+            }
+        }
+    }
+
+    /// shift the gaps of one row left against another, returning whether any
+    /// moved.
+    fn shift_gaps(&mut self, gapped: usize, other: usize) -> bool {
+        let len = self.rows[gapped].len().min(self.rows[other].len());
+        let in_gap =
+            |rows: &[Vec<char>], col: usize| rows[gapped][col] == '-' && rows[other][col] != '-';
+
+        let mut moved = false;
+        let mut col = 0;
+        while col < len {
+            if !in_gap(&self.rows, col) {
+                col += 1;
+                continue;
+            }
+            let end = (col..len).find(|c| !in_gap(&self.rows, *c)).unwrap_or(len);
+
+            let (mut start, mut last) = (col, end - 1);
+            while start > 0
+                && self.rows[gapped][start - 1] != '-'
+                && self.rows[other][start - 1] != '-'
+                && self.rows[other][start - 1].eq_ignore_ascii_case(&self.rows[other][last])
+            {
+                self.rows[gapped].swap(start - 1, last);
+                start -= 1;
+                last -= 1;
+                moved = true;
+            }
+            col = end;
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(a: &str, b: &str) -> Alignment {
+        Alignment::new(vec![a.chars().collect(), b.chars().collect()], vec![], 0f32)
+    }
+
+    #[test]
+    fn test_normalize_indels() {
+        // a deletion in a homopolymer
+        let mut aln = alignment("ACGTTTAC", "ACGTT-AC");
+        aln.normalize_indels();
+        assert_eq!("ACGTTTAC\nACG-TTAC", aln.to_string());
+
+        // an insertion in a dinucleotide repeat, shifted through it
+        let mut aln = alignment("GCA--G", "GCACAG");
+        aln.normalize_indels();
+        assert_eq!("G--CAG\nGCACAG", aln.to_string());
+
+        // a gap between different residues stays
+        let mut aln = alignment("ACGTAC", "AC-TAC");
+        aln.normalize_indels();
+        assert_eq!("ACGTAC\nAC-TAC", aln.to_string());
+    }
+}
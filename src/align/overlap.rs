@@ -0,0 +1,184 @@
+//! Overlap (semi-global) alignment with affine gaps.
+//!
+//! This is Needleman-Wunsch with Gotoh's three states, where a gap at the
+//! start or end of a sequence is scaled by the terminal gap weight of that
+//! end. Making some ends free finds how the end of one sequence overlaps the
+//! start of another, or where a short sequence sits within a long one, in
+//! quadratic time.
+
+use std::marker::PhantomData;
+
+use super::{
+    fixed::{Precision, Score},
+    gotoh::{self, Gap, Rules},
+    Alignment, Scoring,
+};
+
+/// align_overlap aligns two sequences with the terminal gaps of `scoring`.
+///
+/// A gap of length L costs `gap_opening + gap_extension * (L - 1)`, times the
+/// terminal gap weight if it's at the end of a sequence.
+pub fn align_overlap(a: &str, b: &str, scoring: &Scoring) -> Alignment {
+    align_weighted(a, b, scoring, &vec![1f32; a.len()], &vec![1f32; b.len()])
+}
+
+/// align_weighted is `align_overlap` with every gap scaled by a weight of
+/// the residues it skips: `a_weights[j]` for gaps in b against `a[j]`, and
+/// `b_weights[i]` for gaps in a against `b[i]`.
+pub(super) fn align_weighted(
+    a: &str,
+    b: &str,
+    scoring: &Scoring,
+    a_weights: &[f32],
+    b_weights: &[f32],
+) -> Alignment {
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    align_scored(a, b, scoring, a_weights, b_weights, |j, i| {
+        scoring.matrix[a_bytes[j] as usize][b_bytes[i] as usize] as f32
+    })
+}
+
+/// align_scored is `align_weighted` with the score of aligning `a[j]` to
+/// `b[i]` from `substitution(j, i)` rather than the matrix of `scoring`.
+pub(super) fn align_scored<F: Fn(usize, usize) -> f32>(
+    a: &str,
+    b: &str,
+    scoring: &Scoring,
+    a_weights: &[f32],
+    b_weights: &[f32],
+    substitution: F,
+) -> Alignment {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    match scoring.precision {
+        Precision::Float => {
+            let rules = Weighted::<f32, F>::new(scoring, 1, a_weights, b_weights, substitution);
+            overlap(&rules, a, b)
+        }
+        Precision::Fixed(scale) => {
+            let rules =
+                Weighted::<i64, F>::new(scoring, scale.max(1), a_weights, b_weights, substitution);
+            overlap(&rules, a, b)
+        }
+    }
+}
+
+/// overlap fills the grid of a over b by the rules and walks back from the
+/// last cell.
+fn overlap<S: Score, F: Fn(usize, usize) -> f32>(
+    rules: &Weighted<S, F>,
+    a: &[u8],
+    b: &[u8],
+) -> Alignment {
+    let grid = gotoh::fill(rules, a.len(), b.len(), |_, _| {});
+    let (state, score) = gotoh::best(grid.last.get(a.len()));
+    let path = grid.path((b.len(), a.len()), state);
+
+    Alignment::new(path.rows(a, b), vec![], score.to_f32(rules.scale))
+}
+
+/// Weighted are the rules of an overlap alignment: gaps are scaled by the
+/// weight of the residues they skip, and by the terminal gap weight at the
+/// ends of the sequences.
+struct Weighted<'a, S, F> {
+    scoring: &'a Scoring,
+    scale: u32,
+    a_weights: &'a [f32],
+    b_weights: &'a [f32],
+    substitution: F,
+    score: PhantomData<S>,
+}
+
+impl<'a, S: Score, F> Weighted<'a, S, F> {
+    fn new(
+        scoring: &'a Scoring,
+        scale: u32,
+        a_weights: &'a [f32],
+        b_weights: &'a [f32],
+        substitution: F,
+    ) -> Self {
+        Weighted {
+            scoring,
+            scale,
+            a_weights,
+            b_weights,
+            substitution,
+            score: PhantomData,
+        }
+    }
+
+    fn gap(&self, weight: f32) -> Gap<S> {
+        Gap {
+            open: S::from_f32(self.scoring.gap_opening * weight, self.scale),
+            extend: S::from_f32(self.scoring.gap_extension * weight, self.scale),
+        }
+    }
+}
+
+impl<S: Score, F: Fn(usize, usize) -> f32> Rules for Weighted<'_, S, F> {
+    type Score = S;
+
+    fn substitution(&self, i: usize, j: usize) -> S {
+        S::from_f32((self.substitution)(j - 1, i - 1), self.scale)
+    }
+
+    // gaps in a in the first and last columns are terminal
+    fn up(&self, i: usize, j: usize) -> Gap<S> {
+        let ends = &self.scoring.terminal_gaps;
+        let end = match j {
+            0 => ends.a_start.weight(),
+            j if j == self.a_weights.len() => ends.a_end.weight(),
+            _ => 1f32,
+        };
+        self.gap(end * self.b_weights[i - 1])
+    }
+
+    // gaps in b in the first and last rows are terminal
+    fn left(&self, i: usize, j: usize) -> Gap<S> {
+        let ends = &self.scoring.terminal_gaps;
+        let end = match i {
+            0 => ends.b_start.weight(),
+            i if i == self.b_weights.len() => ends.b_end.weight(),
+            _ => 1f32,
+        };
+        self.gap(end * self.a_weights[j - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{TerminalGap, TerminalGaps},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_align_overlap() {
+        let mut scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            terminal_gaps: TerminalGaps {
+                a_end: TerminalGap::Free,
+                b_start: TerminalGap::Free,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // the end of a overlaps the start of b
+        let alignment = align_overlap("TTTTTACGTACGGA", "ACGTACGGACCCCC", &scoring);
+        assert_eq!(
+            "TTTTTACGTACGGA-----\n-----ACGTACGGACCCCC",
+            alignment.to_string()
+        );
+        assert_eq!(45f32, alignment.score);
+
+        // with every end penalized it's a global alignment
+        scoring.terminal_gaps = TerminalGaps::default();
+        let alignment = align_overlap("ACGTTACGT", "ACGTACGT", &scoring);
+        assert_eq!("ACGTTACGT\nACG-TACGT", alignment.to_string());
+        assert_eq!(30f32, alignment.score);
+    }
+}
@@ -0,0 +1,175 @@
+//! Inverted repeats with a short loop: hairpins and palindromes.
+//!
+//! A hairpin is a stem, a run of residues whose reverse complement follows
+//! shortly after, around a loop of unpaired residues. A palindrome is a
+//! hairpin with no loop, like the GAATTC of EcoRI. Both are an alignment of
+//! the sequence to its reverse complement constrained to a narrow band of
+//! anti-diagonals, so each loop position is extended outward without gaps.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::seq::complement;
+
+/// PalindromeConfig configures the search for hairpins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PalindromeConfig {
+    /// fewest paired residues on each side of the stem
+    pub min_stem: usize,
+
+    /// shortest loop, 0 for perfect palindromes
+    pub min_loop: usize,
+
+    /// longest loop
+    pub max_loop: usize,
+
+    /// most unpaired residues within the stem
+    pub max_mismatches: usize,
+}
+
+impl Default for PalindromeConfig {
+    fn default() -> Self {
+        PalindromeConfig {
+            min_stem: 6,
+            min_loop: 0,
+            max_loop: 20,
+            max_mismatches: 1,
+        }
+    }
+}
+
+/// Palindrome is a hairpin: two arms of a stem around a loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palindrome {
+    /// 0-based, half-open region of the arm before the loop
+    pub left: Range<usize>,
+
+    /// 0-based, half-open region of the arm after the loop
+    pub right: Range<usize>,
+
+    /// number of residues in each arm
+    pub stem: usize,
+
+    /// number of residues between the arms
+    pub loop_len: usize,
+
+    /// number of positions in the stem that don't pair
+    pub mismatches: usize,
+}
+
+/// palindromes finds the hairpins and palindromes of a DNA sequence.
+///
+/// Each stem is extended as far as it can go within `max_mismatches`, and
+/// starts and ends with a pair. Hairpins within the stem of another with a
+/// shorter loop aren't reported. They're ordered by the start of the loop,
+/// then its length.
+pub fn palindromes(seq: &str, config: &PalindromeConfig) -> Vec<Palindrome> {
+    let seq = seq.as_bytes();
+    let n = seq.len();
+    let pairs =
+        |x: usize, y: usize| complement(seq[x] as char).eq_ignore_ascii_case(&(seq[y] as char));
+
+    let mut found = Vec::new();
+    for start in 1..n {
+        for loop_len in config.min_loop..=config.max_loop {
+            // the pair around the loop must pair, the pair inside it mustn't
+            let end = start + loop_len;
+            if end >= n || !pairs(start - 1, end) {
+                continue;
+            }
+            if loop_len >= 2 && loop_len - 2 >= config.min_loop && pairs(start, end - 1) {
+                continue;
+            }
+
+            // extend outward, keeping the longest stem that ends with a pair
+            let (mut stem, mut mismatches, mut kept) = (0, 0, (0, 0));
+            while stem < start && end + stem < n {
+                if pairs(start - 1 - stem, end + stem) {
+                    kept = (stem + 1, mismatches);
+                } else {
+                    mismatches += 1;
+                    if mismatches > config.max_mismatches {
+                        break;
+                    }
+                }
+                stem += 1;
+            }
+
+            let (stem, mismatches) = kept;
+            if stem >= config.min_stem {
+                found.push(Palindrome {
+                    left: start - stem..start,
+                    right: end..end + stem,
+                    stem,
+                    loop_len,
+                    mismatches,
+                });
+            }
+        }
+    }
+
+    // drop the hairpins within the stem of another on the same axis with a
+    // shorter loop: a mismatch beside the loop restarts the stem outside it
+    found.sort_by_key(|p| p.loop_len);
+    let mut outermost = HashMap::new();
+    found.retain(|p| {
+        let axis = p.left.end + p.right.start;
+        match outermost.get(&axis) {
+            Some(&start) if start <= p.left.start => false,
+            _ => {
+                outermost.insert(axis, p.left.start);
+                true
+            }
+        }
+    });
+    found.sort_by_key(|p| (p.left.end, p.loop_len));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palindromes() {
+        // a perfect palindrome
+        let found = palindromes("TTTTGAATTCAAAAAAGGG", &PalindromeConfig::default());
+        assert_eq!(
+            vec![Palindrome {
+                left: 0..7,
+                right: 7..14,
+                stem: 7,
+                loop_len: 0,
+                mismatches: 0,
+            }],
+            found
+        );
+
+        // a mismatch in the stem isn't also reported as a 4 residue loop
+        let found = palindromes("TTTTTGCATTCAAAAA", &PalindromeConfig::default());
+        assert_eq!(
+            vec![Palindrome {
+                left: 0..8,
+                right: 8..16,
+                stem: 8,
+                loop_len: 0,
+                mismatches: 1,
+            }],
+            found
+        );
+
+        // a hairpin with a 4 residue loop and a mismatch in the stem
+        let found = palindromes(
+            "CCCCGATCGAGCTTTTGCTAGATCCCCC",
+            &PalindromeConfig {
+                min_stem: 7,
+                min_loop: 3,
+                ..Default::default()
+            },
+        );
+        assert_eq!(1, found.len());
+        assert_eq!(4..12, found[0].left);
+        assert_eq!(16..24, found[0].right);
+        assert_eq!(4, found[0].loop_len);
+        assert_eq!(1, found[0].mismatches);
+    }
+}
@@ -0,0 +1,295 @@
+//! A patch is the list of edits that turns one sequence into another.
+//!
+//! An alignment of two sequences implies a patch: every mismatched column is a
+//! substitution and every gap is an insertion or deletion. Patches can be
+//! applied to a sequence and composed with one another, so a chain of edits
+//! (design -> edit 1 -> edit 2) can be collapsed into a single patch.
+
+use std::fmt::Display;
+
+use super::{Alignment, Error, Result};
+
+/// Edit is a single change to a sequence. Positions are 0-based in the source sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// replace the residue at `pos`
+    Substitution { pos: usize, from: char, to: char },
+
+    /// insert `seq` before the residue at `pos`
+    Insertion { pos: usize, seq: String },
+
+    /// remove `seq`, which starts at `pos`
+    Deletion { pos: usize, seq: String },
+}
+
+/// Patch is an ordered list of non-overlapping edits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patch {
+    pub edits: Vec<Edit>,
+}
+
+/// Op is a single-residue operation, used to walk, apply, and compose patches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Sub(char, char),
+    Ins(char),
+    Del(char),
+}
+
+impl Edit {
+    /// pos is the position of the edit in the source sequence.
+    pub fn pos(&self) -> usize {
+        match self {
+            Edit::Substitution { pos, .. }
+            | Edit::Insertion { pos, .. }
+            | Edit::Deletion { pos, .. } => *pos,
+        }
+    }
+}
+
+// edits are displayed with 1-based positions, similar to HGVS notation.
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::Substitution { pos, from, to } => write!(f, "{}{}>{}", pos + 1, from, to),
+            Edit::Insertion { pos, seq } => write!(f, "{}ins{}", pos + 1, seq),
+            Edit::Deletion { pos, seq } => write!(f, "{}del{}", pos + 1, seq),
+        }
+    }
+}
+
+impl Patch {
+    /// apply the patch to a sequence, checking that substituted and deleted residues match.
+    pub fn apply(&self, seq: &str) -> Result<String> {
+        let source: Vec<char> = seq.chars().collect();
+        let mut result = String::with_capacity(source.len());
+        let mut pos = 0;
+
+        for edit in &self.edits {
+            if edit.pos() < pos || edit.pos() > source.len() {
+                return Err(Error::PatchOutOfBounds(edit.pos()));
+            }
+            result.extend(&source[pos..edit.pos()]);
+            pos = edit.pos();
+
+            match edit {
+                Edit::Substitution { from, to, .. } => {
+                    expect(&source, pos, &from.to_string())?;
+                    result.push(*to);
+                    pos += 1;
+                }
+                Edit::Insertion { seq, .. } => result.push_str(seq),
+                Edit::Deletion { seq, .. } => {
+                    expect(&source, pos, seq)?;
+                    pos += seq.chars().count();
+                }
+            }
+        }
+        result.extend(&source[pos..]);
+
+        Ok(result)
+    }
+
+    /// compose returns a single patch with the effect of applying this patch and then `other`.
+    ///
+    /// `other` is in the coordinates of the sequence this patch produces.
+    pub fn compose(&self, other: &Patch) -> Patch {
+        let first = self.ops();
+        let second = other.ops();
+        let (mut x, mut y) = (0, 0);
+        let mut ops = Vec::with_capacity(first.len().max(second.len()));
+
+        // like composing operational transforms: residues deleted by the first
+        // patch and residues inserted by the second never meet the other patch
+        while x < first.len() || y < second.len() {
+            let a = first.get(x).copied().unwrap_or(Op::Keep);
+            let b = second.get(y).copied().unwrap_or(Op::Keep);
+
+            if let Op::Del(_) = a {
+                ops.push(a);
+                x += 1;
+                continue;
+            }
+            if let Op::Ins(_) = b {
+                ops.push(b);
+                y += 1;
+                continue;
+            }
+
+            ops.push(match (a, b) {
+                (Op::Keep, b) => b,
+                (a, Op::Keep) => a,
+                (Op::Sub(f, _), Op::Sub(_, t)) if f == t => Op::Keep,
+                (Op::Sub(f, _), Op::Sub(_, t)) => Op::Sub(f, t),
+                (Op::Sub(f, _), Op::Del(_)) => Op::Del(f),
+                (Op::Ins(_), Op::Sub(_, t)) => Op::Ins(t),
+                (Op::Ins(_), Op::Del(_)) => {
+                    x += 1;
+                    y += 1;
+                    continue;
+                }
+                _ => unreachable!(
+                    "deletions in the first and insertions in the second are handled above"
+                ),
+            });
+            x += 1;
+            y += 1;
+        }
+
+        Patch::from_ops(&ops)
+    }
+
+    /// ops expands the patch into single-residue operations up to its last edit.
+    fn ops(&self) -> Vec<Op> {
+        let mut ops = Vec::new();
+        let mut pos = 0;
+        for edit in &self.edits {
+            ops.extend(std::iter::repeat_n(
+                Op::Keep,
+                edit.pos().saturating_sub(pos),
+            ));
+            pos = pos.max(edit.pos());
+            match edit {
+                Edit::Substitution { from, to, .. } => {
+                    ops.push(Op::Sub(*from, *to));
+                    pos += 1;
+                }
+                Edit::Insertion { seq, .. } => ops.extend(seq.chars().map(Op::Ins)),
+                Edit::Deletion { seq, .. } => {
+                    ops.extend(seq.chars().map(Op::Del));
+                    pos += seq.chars().count();
+                }
+            }
+        }
+        ops
+    }
+
+    /// from_ops groups single-residue operations into edits, merging runs of insertions and deletions.
+    fn from_ops(ops: &[Op]) -> Patch {
+        let mut edits: Vec<Edit> = Vec::new();
+        let mut pos = 0;
+        for op in ops {
+            match op {
+                Op::Keep => pos += 1,
+                Op::Sub(from, to) => {
+                    edits.push(Edit::Substitution {
+                        pos,
+                        from: *from,
+                        to: *to,
+                    });
+                    pos += 1;
+                }
+                Op::Ins(c) => match edits.last_mut() {
+                    Some(Edit::Insertion { pos: p, seq }) if *p == pos => seq.push(*c),
+                    _ => edits.push(Edit::Insertion {
+                        pos,
+                        seq: c.to_string(),
+                    }),
+                },
+                Op::Del(c) => {
+                    match edits.last_mut() {
+                        Some(Edit::Deletion { pos: p, seq }) if *p + seq.chars().count() == pos => {
+                            seq.push(*c)
+                        }
+                        _ => edits.push(Edit::Deletion {
+                            pos,
+                            seq: c.to_string(),
+                        }),
+                    }
+                    pos += 1;
+                }
+            }
+        }
+        Patch { edits }
+    }
+}
+
+impl Display for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.edits
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+/// expect checks that `seq` is in `source` at `pos`.
+fn expect(source: &[char], pos: usize, seq: &str) -> Result<()> {
+    let found: String = source.iter().skip(pos).take(seq.chars().count()).collect();
+    if found != seq {
+        return Err(Error::PatchMismatch {
+            pos,
+            expected: seq.to_string(),
+            found,
+        });
+    }
+    Ok(())
+}
+
+impl Alignment {
+    /// patch returns the edits that turn the first sequence of the alignment into the second.
+    ///
+    /// Positions are relative to the first aligned residue of the first sequence.
+    pub fn patch(&self) -> Patch {
+        let ops: Vec<Op> = self.rows[0]
+            .iter()
+            .zip(self.rows[1].iter())
+            .filter_map(|(a, b)| match (*a, *b) {
+                ('-', '-') => None,
+                ('-', b) => Some(Op::Ins(b)),
+                (a, '-') => Some(Op::Del(a)),
+                (a, b) if a == b => Some(Op::Keep),
+                (a, b) => Some(Op::Sub(a, b)),
+            })
+            .collect();
+        Patch::from_ops(&ops)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(a: &str, b: &str) -> Alignment {
+        Alignment::new(vec![a.chars().collect(), b.chars().collect()], vec![], 0f32)
+    }
+
+    #[test]
+    fn test_alignment_patch() {
+        let patch = alignment("ACGT--TAGGC", "ACCTAAT--GC").patch();
+
+        assert_eq!("3G>C,5insAA,6delAG", patch.to_string());
+        assert_eq!("ACCTAATGC", patch.apply("ACGTTAGGC").unwrap());
+    }
+
+    #[test]
+    fn test_patch_apply_mismatch() {
+        let patch = alignment("ACGT", "ACCT").patch();
+
+        assert!(matches!(
+            patch.apply("AAAT"),
+            Err(Error::PatchMismatch { pos: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_patch_compose() {
+        let a = "ACGTTAGGC";
+        let first = alignment("ACGT--TAGGC", "ACCTAAT--GC").patch();
+        let b = first.apply(a).unwrap();
+
+        // substitute over an inserted residue, delete a substituted one, insert at the end
+        let second = alignment("ACCTAATGC--", "AC-TTATGCCC").patch();
+        let c = second.apply(&b).unwrap();
+
+        let composed = first.compose(&second);
+        assert_eq!(c, composed.apply(a).unwrap());
+        assert_eq!("3delG,5insTA,6delAG,10insCC", composed.to_string());
+    }
+}
@@ -0,0 +1,190 @@
+//! Pileup of pairwise alignments of reads to the same reference, for QC.
+//!
+//! Each reference position counts the reads that span it, those with the
+//! same residue, a different one or a gap there, and the insertions right
+//! after it. Gaps before the first and after the last residue of a read are
+//! where it doesn't reach, not deletions. The counts are written as a BED-like
+//! table with a line per position, or the depth as a wiggle track.
+
+use std::io;
+
+use super::{Alignment, LocalAlignment};
+
+/// PileupColumn is the counts of the reads at a position of the reference.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PileupColumn {
+    /// reads that span the position
+    pub depth: usize,
+
+    /// reads with the reference's residue
+    pub matches: usize,
+
+    /// reads with another residue
+    pub mismatches: usize,
+
+    /// reads with a gap
+    pub deletions: usize,
+
+    /// reads with residues between this position and the next
+    pub insertions: usize,
+}
+
+impl PileupColumn {
+    /// disagreement is the fraction of the reads spanning the position that
+    /// don't have the reference's residue there, 0 if none span it.
+    pub fn disagreement(&self) -> f32 {
+        match self.depth {
+            0 => 0f32,
+            depth => (depth - self.matches) as f32 / depth as f32,
+        }
+    }
+}
+
+/// Pileup is the stacked alignments of reads to a reference.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pileup {
+    /// counts at each position of the reference
+    pub columns: Vec<PileupColumn>,
+}
+
+impl Pileup {
+    /// new creates an empty pileup of a reference of a length.
+    pub fn new(reference_len: usize) -> Self {
+        Pileup {
+            columns: vec![PileupColumn::default(); reference_len],
+        }
+    }
+
+    /// add stacks an alignment with the reference as its first row, whose
+    /// first residue is at the 0-based `ref_offset` of the reference, like
+    /// [`variants`](Alignment::variants).
+    ///
+    /// Positions past the end of the pileup are dropped.
+    pub fn add(&mut self, alignment: &Alignment, ref_offset: usize) {
+        self.stack(&alignment.rows[0], &alignment.rows[1], ref_offset);
+    }
+
+    /// add_local stacks a local alignment of a read, as a, to the reference,
+    /// as b, like those written to SAM.
+    pub fn add_local(&mut self, local: &LocalAlignment) {
+        let rows = &local.alignment.rows;
+        self.stack(&rows[1], &rows[0], local.b.start);
+    }
+
+    fn stack(&mut self, reference: &[char], read: &[char], ref_offset: usize) {
+        let (Some(first), Some(last)) = (
+            read.iter().position(|c| *c != '-'),
+            read.iter().rposition(|c| *c != '-'),
+        ) else {
+            return;
+        };
+
+        let mut pos = ref_offset;
+        let mut inserted = false;
+        for (col, (r, q)) in reference.iter().zip(read.iter()).enumerate() {
+            if *r == '-' {
+                // an insertion after the previous reference position, once
+                if (first..=last).contains(&col) && *q != '-' && !inserted && pos > ref_offset {
+                    if let Some(column) = self.columns.get_mut(pos - 1) {
+                        column.insertions += 1;
+                    }
+                    inserted = true;
+                }
+                continue;
+            }
+            inserted = false;
+            if (first..=last).contains(&col) {
+                if let Some(column) = self.columns.get_mut(pos) {
+                    column.depth += 1;
+                    match q {
+                        '-' => column.deletions += 1,
+                        q if q.eq_ignore_ascii_case(r) => column.matches += 1,
+                        _ => column.mismatches += 1,
+                    }
+                }
+            }
+            pos += 1;
+        }
+    }
+
+    /// write_table writes a line per position: the reference's name, the
+    /// 0-based start and end of the position as in BED, then its depth,
+    /// matches, mismatches, deletions, insertions and disagreement.
+    pub fn write_table<W: io::Write>(&self, reference: &str, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "#chrom\tstart\tend\tdepth\tmatches\tmismatches\tdeletions\tinsertions\tdisagreement"
+        )?;
+        for (pos, c) in self.columns.iter().enumerate() {
+            writeln!(
+                w,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.4}",
+                reference,
+                pos,
+                pos + 1,
+                c.depth,
+                c.matches,
+                c.mismatches,
+                c.deletions,
+                c.insertions,
+                c.disagreement()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// write_wiggle writes the depth at each position as a fixed-step wiggle
+    /// track.
+    pub fn write_wiggle<W: io::Write>(&self, reference: &str, mut w: W) -> io::Result<()> {
+        writeln!(w, "fixedStep chrom={} start=1 step=1", reference)?;
+        for c in self.columns.iter() {
+            writeln!(w, "{}", c.depth)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(reference: &str, read: &str) -> Alignment {
+        Alignment::new(
+            vec![reference.chars().collect(), read.chars().collect()],
+            vec![vec![]],
+            0f32,
+        )
+    }
+
+    #[test]
+    fn test_pileup() {
+        let mut pileup = Pileup::new(8);
+        pileup.add(&alignment("ACGTACGT", "ACGTACGT"), 0);
+        pileup.add(&alignment("ACG-TACGT", "ACGGTA-GT"), 0);
+        pileup.add(&alignment("ACGTAC", "--GTTC"), 0);
+        pileup.add(&alignment("ACG", "ACG"), 5);
+
+        let c = |pos: usize| pileup.columns[pos];
+        assert_eq!(2, c(0).depth);
+        assert_eq!(1, c(2).insertions);
+        assert_eq!((3, 3), (c(3).depth, c(3).matches));
+        assert_eq!((3, 1), (c(4).depth, c(4).mismatches));
+        assert_eq!((4, 1, 3), (c(5).depth, c(5).deletions, c(5).matches));
+        assert_eq!(0.25, c(5).disagreement());
+
+        let mut table = vec![];
+        pileup.write_table("chr1", &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert_eq!(
+            Some("chr1\t4\t5\t3\t2\t1\t0\t0\t0.3333"),
+            table.lines().nth(5)
+        );
+
+        let mut wiggle = vec![];
+        pileup.write_wiggle("chr1", &mut wiggle).unwrap();
+        assert_eq!(
+            "fixedStep chrom=chr1 start=1 step=1\n2\n2\n3\n3\n3\n4\n3\n3\n",
+            String::from_utf8(wiggle).unwrap()
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Merging of precomputed multiple alignments along a tree, for dividing a
+//! very large set of sequences into clades, aligning each on its own, then
+//! joining the clades.
+//!
+//! Each alignment is a leaf of the tree, and from the leaves to the root the
+//! alignments of the two children of a node are aligned profile to profile,
+//! as in the progressive step of ClustalW, with independent subtrees on
+//! their own threads. The columns of every input
+//! alignment are kept: only whole gap columns are inserted into them.
+//!
+//! So is the metadata of every input, with its per-column Stockholm markup
+//! widened by the inserted columns.
+
+use super::{
+    clustal_w::Profile, scheduler::align_tree, Error, GuideTree, MSAlignment, Metadata, Result,
+    Scoring,
+};
+
+/// merge_alignments merges multiple alignments into one, following a tree
+/// with a leaf per alignment.
+///
+/// The rows are those of the first alignment, then of the second, and so on.
+/// Where alignments have metadata with the same key, the first's is kept.
+pub fn merge_alignments(
+    msas: &[MSAlignment],
+    tree: &GuideTree,
+    scoring: &Scoring,
+) -> Result<MSAlignment> {
+    if tree.leaves() != msas.len() {
+        return Err(Error::TreeMismatch {
+            leaves: tree.leaves(),
+            alignments: msas.len(),
+        });
+    }
+    if msas.is_empty() {
+        return Ok(MSAlignment::default());
+    }
+    // profiles are aligned as bytes
+    for msa in msas {
+        if let Some(row) = msa.rows.iter().position(|r| !r.iter().all(char::is_ascii)) {
+            return Err(Error::NonAsciiSequence(msa.ids[row].clone()));
+        }
+    }
+
+    let mut first = 0;
+    let profiles: Vec<Option<Profile>> = msas
+        .iter()
+        .map(|msa| {
+            let seqs = (first..first + msa.rows.len()).collect();
+            first += msa.rows.len();
+            Some(Profile {
+                seqs,
+                rows: msa
+                    .rows
+                    .iter()
+                    .map(|r| r.iter().map(|c| *c as u8).collect())
+                    .collect(),
+            })
+        })
+        .chain((msas.len()..tree.nodes.len()).map(|_| None))
+        .collect();
+
+    let done = vec![false; tree.nodes.len()];
+    let root = align_tree(
+        tree,
+        profiles,
+        &done,
+        scoring,
+        0,
+        false,
+        |_, _, _, _| Ok(()),
+    )?;
+    let mut rows: Vec<(usize, Vec<u8>)> = root.seqs.into_iter().zip(root.rows).collect();
+    rows.sort_by_key(|(i, _)| *i);
+    let mut merged = MSAlignment::new(
+        msas.iter().flat_map(|m| m.ids.iter().cloned()).collect(),
+        rows.into_iter()
+            .map(|(_, row)| row.into_iter().map(char::from).collect())
+            .collect(),
+    );
+
+    let mut metadata = Metadata::new();
+    let mut first = 0;
+    for msa in msas {
+        let cols = columns(msa, &merged, first);
+        first += msa.rows.len();
+        for (key, value) in widened_metadata(msa, &cols, merged.len()) {
+            metadata.entry(key).or_insert(value);
+        }
+    }
+    merged.metadata = metadata;
+    Ok(merged)
+}
+
+/// columns is the column of the merged alignment each column of an input
+/// alignment is in, its rows starting at row `first` of the merged one.
+fn columns(msa: &MSAlignment, merged: &MSAlignment, first: usize) -> Vec<usize> {
+    let rows = &merged.rows[first..first + msa.rows.len()];
+    let mut cols = Vec::with_capacity(msa.len());
+    for col in 0..merged.len() {
+        let next = cols.len();
+        if next < msa.len()
+            && rows
+                .iter()
+                .zip(&msa.rows)
+                .all(|(merged, row)| merged[col] == row[next])
+        {
+            cols.push(col);
+        }
+    }
+    cols
+}
+
+/// widened_metadata is the metadata of an input alignment with its
+/// per-column markup moved to the columns `cols` of `len`, and `.` in the
+/// columns inserted between them.
+pub(super) fn widened_metadata(msa: &MSAlignment, cols: &[usize], len: usize) -> Metadata {
+    msa.metadata
+        .iter()
+        .map(|(key, value)| {
+            let per_column = key.starts_with("GC:") || key.starts_with("GR:");
+            let chars: Vec<char> = value.chars().collect();
+            let value = match per_column && chars.len() == msa.len() {
+                true => {
+                    let mut widened = vec!['.'; len];
+                    for (c, col) in chars.into_iter().zip(cols) {
+                        widened[*col] = c;
+                    }
+                    widened.into_iter().collect()
+                }
+                false => value.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_multiple, Node, ProgressiveConfig},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_merge_alignments() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let clade = |seqs: &[&str], prefix: &str| {
+            let mut msa = align_multiple(seqs, &scoring, &ProgressiveConfig::default()).unwrap();
+            for id in msa.ids.iter_mut() {
+                *id = format!("{}{}", prefix, id);
+            }
+            msa
+        };
+        let mut msas = vec![
+            clade(&["ACGTACGTACGT", "ACGTCGTACGT"], "a")
+                .with_metadata("GC:SS_cons", "<<<<....>>>>")
+                .with_metadata("GR:a1:PP", "999999999999")
+                .with_metadata("sample", "a"),
+            clade(&["ACGTACGTTTACGT", "ACGTACGTTTACG"], "b").with_metadata("sample", "b"),
+            clade(&["TTACGTACGTACGT"], "c").with_metadata("GF:ID", "c"),
+        ];
+
+        // (a, b), c
+        let mut tree = GuideTree {
+            nodes: vec![Node::leaf(), Node::leaf(), Node::leaf()],
+        };
+        for (left, right) in [(0, 1), (3, 2)] {
+            tree.nodes.push(Node {
+                left: Some(left),
+                right: Some(right),
+                ..Node::leaf()
+            });
+        }
+
+        let merged = merge_alignments(&msas, &tree, &scoring).unwrap();
+        assert_eq!(vec!["a1", "a2", "b1", "b2", "c1"], merged.ids);
+        assert_eq!(
+            "--ACGTACG--TACGT
+--ACGT-CG--TACGT
+--ACGTACGTTTACGT
+--ACGTACGTTTACG-
+TTACGTACG--TACGT",
+            merged.to_string()
+        );
+
+        // the columns of each clade are kept, with its markup
+        let a = merged.select(&["a1", "a2"]);
+        assert_eq!(msas[0].rows, a.rows);
+        assert_eq!(
+            vec![
+                ("GC:SS_cons", "..<<<<......>>>>"),
+                ("GF:ID", "c"),
+                ("GR:a1:PP", "..9999999..99999"),
+                ("sample", "a"),
+            ],
+            merged
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        msas[1].rows[0][0] = 'Å';
+        assert!(matches!(
+            merge_alignments(&msas, &tree, &scoring),
+            Err(Error::NonAsciiSequence(id)) if id == "b1"
+        ));
+
+        assert!(matches!(
+            merge_alignments(&msas[..2], &tree, &scoring),
+            Err(Error::TreeMismatch { .. })
+        ));
+    }
+}
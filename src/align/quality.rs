@@ -0,0 +1,66 @@
+//! Quality-aware alignment of sequencing reads.
+//!
+//! Each substitution score is scaled by the probability the read's base call
+//! is correct, so a mismatch against a low-quality base costs little and a
+//! match against one earns little. Gaps are scored as usual.
+
+use super::{alignment::align_with, strategy::Strategy, Alignment, Scoring};
+
+/// align_with_quality aligns a read to a reference using the read's Phred qualities.
+///
+/// `qual` holds the Phred quality of each residue of `read`.
+pub fn align_with_quality(
+    reference: &str,
+    read: &str,
+    qual: &[u8],
+    strategy: &Strategy,
+    scoring: &Scoring,
+) -> Alignment {
+    assert_eq!(
+        read.len(),
+        qual.len(),
+        "read and qualities differ in length"
+    );
+
+    let weights: Vec<f32> = qual.iter().map(|q| correct_call(*q)).collect();
+    let (a, b) = (reference.as_bytes(), read.as_bytes());
+    align_with(reference, read, strategy, scoring, &|j, i| {
+        scoring.matrix[a[j] as usize][b[i] as usize] as f32 * weights[i]
+    })
+}
+
+/// correct_call is the probability a base call with a Phred quality is correct.
+fn correct_call(qual: u8) -> f32 {
+    1f32 - 10f32.powf(-(qual as f32) / 10f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::Method, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_correct_call() {
+        assert_eq!(0f32, correct_call(0));
+        assert!((correct_call(20) - 0.99).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_align_with_quality() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            ..Default::default()
+        };
+        let strategy = Method::NeedlemanWunsch.strategy();
+
+        let confident = align_with_quality("ACGTACGT", "ACGAACGT", &[40; 8], strategy, &scoring);
+        let mut qual = [40; 8];
+        qual[3] = 0;
+        let uncertain = align_with_quality("ACGTACGT", "ACGAACGT", &qual, strategy, &scoring);
+
+        assert_eq!("ACGTACGT\nACGAACGT", uncertain.to_string());
+        // the mismatch against a Q0 base isn't penalized
+        assert!(uncertain.score > confident.score);
+    }
+}
@@ -0,0 +1,128 @@
+//! Realignment of a window of columns of a multiple alignment.
+//!
+//! Progressive alignment never removes a gap once it's placed, so early
+//! mistakes leave messy regions behind. Rather than redoing the whole
+//! alignment, the residues of a window are realigned from scratch, perhaps
+//! with other scoring, and spliced back between the untouched columns on
+//! either side.
+
+use std::ops::Range;
+
+use super::{align_multiple, Error, MSAlignment, ProgressiveConfig, Result, Scoring};
+
+impl MSAlignment {
+    /// realign_region realigns the residues in columns `col_start..col_end`.
+    ///
+    /// Returns the columns of the realigned window, which may be wider or
+    /// narrower than before. Rows without residues in the window are all gap,
+    /// and per-column markup is `.` across the window.
+    pub fn realign_region(
+        &mut self,
+        col_start: usize,
+        col_end: usize,
+        scoring: &Scoring,
+    ) -> Result<Range<usize>> {
+        if col_start > col_end || col_end > self.len() {
+            return Err(Error::InvalidRegion {
+                start: col_start,
+                end: col_end,
+                len: self.len(),
+            });
+        }
+
+        let residues: Vec<String> = self
+            .rows
+            .iter()
+            .map(|r| {
+                r[col_start..col_end]
+                    .iter()
+                    .filter(|c| **c != '-')
+                    .collect()
+            })
+            .collect();
+        let with_residues: Vec<usize> = (0..residues.len())
+            .filter(|r| !residues[*r].is_empty())
+            .collect();
+
+        let mut window: Vec<Vec<char>> = vec![vec![]; self.rows.len()];
+        match with_residues.len() {
+            0 => {}
+            1 => window[with_residues[0]] = residues[with_residues[0]].chars().collect(),
+            _ => {
+                let seqs: Vec<&str> = with_residues
+                    .iter()
+                    .map(|r| residues[*r].as_str())
+                    .collect();
+                let msa = align_multiple(&seqs, scoring, &ProgressiveConfig::default())?;
+                for (row, aligned) in with_residues.iter().zip(msa.rows) {
+                    window[*row] = aligned;
+                }
+            }
+        }
+        let width = window.iter().map(|w| w.len()).max().unwrap_or(0);
+
+        let len = self.len();
+        for (key, value) in self.metadata.iter_mut() {
+            let per_column = key.starts_with("GC:") || key.starts_with("GR:");
+            let chars: Vec<char> = value.chars().collect();
+            if per_column && chars.len() == len {
+                *value = chars[..col_start]
+                    .iter()
+                    .chain(std::iter::repeat_n(&'.', width))
+                    .chain(chars[col_end..].iter())
+                    .collect();
+            }
+        }
+        for (row, mut realigned) in self.rows.iter_mut().zip(window) {
+            realigned.resize(width, '-');
+            row.splice(col_start..col_end, realigned);
+        }
+        Ok(col_start..col_start + width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_realign_region() {
+        let mut msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![
+                "AAACGT-ACGTTT".chars().collect(),
+                "AAA-CGTACGTTT".chars().collect(),
+                "AAA---------T".chars().collect(),
+            ],
+        );
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+
+        msa.metadata
+            .insert("GC:MASK".into(), "xx.xxxxxxxxxx".into());
+        msa.metadata
+            .insert("GR:c:MASK".into(), "x...........x".into());
+
+        let window = msa.realign_region(3, 12, &scoring).unwrap();
+        assert_eq!(3..11, window);
+        assert_eq!("AAACGTACGTTT\nAAACGTACGTTT\nAAA--------T", msa.to_string());
+        assert_eq!("xx.........x", msa.metadata["GC:MASK"]);
+        assert_eq!("x..........x", msa.metadata["GR:c:MASK"]);
+
+        assert!(matches!(
+            msa.realign_region(3, 40, &scoring),
+            Err(Error::InvalidRegion {
+                start: 3,
+                end: 40,
+                len: 12
+            })
+        ));
+        assert!(msa.realign_region(5, 4, &scoring).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Reference-anchored multiple alignment.
+//!
+//! Every sequence is aligned pairwise to a reference and the alignments are
+//! stitched together in reference coordinates: there's exactly one column per
+//! reference residue, and residues a sequence has in addition to the
+//! reference are stored as insertions next to the alignment rather than as
+//! new columns. The work grows linearly with the number of sequences, and
+//! column `i` is always reference position `i`, which is what resequencing
+//! analyses want.
+
+use std::thread;
+
+use super::{
+    clustal_w::{profile_columns, Profile},
+    Error, MSAlignment, Result, Scoring,
+};
+
+/// Insertion is a run of residues in a sequence that aren't in the reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Insertion {
+    /// row of the sequence in the alignment
+    pub row: usize,
+
+    /// 0-based position of the reference residue the insertion comes before
+    pub pos: usize,
+
+    /// the inserted residues
+    pub seq: String,
+}
+
+/// ReferenceAlignment is a multiple alignment in the coordinates of a reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReferenceAlignment {
+    /// the alignment, with one column per reference residue
+    pub msa: MSAlignment,
+
+    /// insertions relative to the reference, by row and then position
+    pub insertions: Vec<Insertion>,
+}
+
+/// align_to_reference aligns each sequence to the reference sequence at an index.
+///
+/// Rows are in input order and named by their 1-based index, like
+/// [`align_multiple`](super::align_multiple). It's an error if there's no
+/// sequence at the index.
+pub fn align_to_reference<S: AsRef<str> + Sync>(
+    seqs: &[S],
+    reference: usize,
+    scoring: &Scoring,
+) -> Result<ReferenceAlignment> {
+    if reference >= seqs.len() {
+        return Err(Error::InvalidParameter(format!(
+            "reference {} of {} sequences",
+            reference,
+            seqs.len()
+        )));
+    }
+    let ref_profile = Profile::from_seq(reference, seqs[reference].as_ref());
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = seqs.len().div_ceil(threads).max(1);
+    let rows: Vec<(Vec<char>, Vec<Insertion>)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..seqs.len())
+            .collect::<Vec<_>>()
+            .chunks(chunk)
+            .map(|rows| {
+                let rows = rows.to_vec();
+                let ref_profile = &ref_profile;
+                s.spawn(move || {
+                    rows.into_iter()
+                        .map(|row| anchor(ref_profile, row, seqs[row].as_ref(), scoring))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+
+    let mut ids = Vec::with_capacity(seqs.len());
+    let mut msa_rows = Vec::with_capacity(seqs.len());
+    let mut insertions = Vec::new();
+    for (row, (gapped, inserted)) in rows.into_iter().enumerate() {
+        ids.push((row + 1).to_string());
+        msa_rows.push(gapped);
+        insertions.extend(inserted);
+    }
+
+    Ok(ReferenceAlignment {
+        msa: MSAlignment::new(ids, msa_rows),
+        insertions,
+    })
+}
+
+/// anchor aligns a sequence to the reference, returning its row in reference
+/// coordinates and its insertions.
+fn anchor(
+    reference: &Profile,
+    row: usize,
+    seq: &str,
+    scoring: &Scoring,
+) -> (Vec<char>, Vec<Insertion>) {
+    let bytes = seq.as_bytes();
+    let columns = profile_columns(reference, &Profile::from_seq(1, seq), scoring);
+
+    let mut gapped = Vec::with_capacity(reference.len());
+    let mut insertions: Vec<Insertion> = Vec::new();
+    let mut in_insertion = false;
+    for (i, j) in columns {
+        match (i, j) {
+            (Some(_), j) => {
+                gapped.push(j.map_or('-', |j| bytes[j] as char));
+                in_insertion = false;
+            }
+            (None, Some(j)) => {
+                if !in_insertion {
+                    insertions.push(Insertion {
+                        row,
+                        pos: gapped.len(),
+                        seq: String::new(),
+                    });
+                    in_insertion = true;
+                }
+                insertions.last_mut().unwrap().seq.push(bytes[j] as char);
+            }
+            (None, None) => {}
+        }
+    }
+    (gapped, insertions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_align_to_reference() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let seqs = ["ACGTACGTAC", "ACGTTTACGTAC", "ACGACGTAC", "ACGTACGTAC"];
+
+        let aligned = align_to_reference(&seqs, 0, &scoring).unwrap();
+        assert_eq!(
+            "ACGTACGTAC\nACGTACGTAC\nACG-ACGTAC\nACGTACGTAC",
+            aligned.msa.to_string()
+        );
+        assert_eq!(
+            vec![Insertion {
+                row: 1,
+                pos: 3,
+                seq: "TT".to_string()
+            }],
+            aligned.insertions
+        );
+
+        assert!(matches!(
+            align_to_reference(&seqs, 4, &scoring),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            align_to_reference::<&str>(&[], 0, &scoring),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
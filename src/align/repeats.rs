@@ -0,0 +1,234 @@
+//! Repeats found by aligning a sequence to itself.
+//!
+//! Every sequence aligns perfectly to itself on the main diagonal, so that is
+//! skipped, and the repeats are the best ungapped local alignments on the
+//! other diagonals: direct repeats on the diagonals of the sequence against
+//! itself, inverted repeats on those of the sequence against its reverse
+//! complement. Only diagonals with a shared k-mer are scored, so a sequence
+//! with few repeats is searched in about linear time.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{matrices::NUC_4_4, seq::complement};
+
+use super::{Alignment, Definition, Scoring};
+
+/// RepeatKind is the orientation of the second copy of a repeat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepeatKind {
+    /// the second copy is on the same strand
+    Direct,
+
+    /// the second copy is the reverse complement of the first
+    Inverted,
+}
+
+/// RepeatConfig configures the search for repeats.
+#[derive(Clone, Debug)]
+pub struct RepeatConfig {
+    /// scoring of the residues of the copies, gaps are not used
+    pub scoring: Scoring,
+
+    /// length of the k-mers the copies must share
+    pub k: usize,
+
+    /// lowest score of a repeat
+    pub min_score: f32,
+
+    /// k-mers at more positions than this are skipped, for low complexity sequence
+    pub max_occurrences: usize,
+}
+
+impl Default for RepeatConfig {
+    fn default() -> Self {
+        RepeatConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                ..Default::default()
+            },
+            k: 12,
+            min_score: 60f32,
+            max_occurrences: 100,
+        }
+    }
+}
+
+/// Repeat is two copies of a sequence within one sequence.
+#[derive(Debug)]
+pub struct Repeat {
+    pub kind: RepeatKind,
+
+    /// 0-based, half-open region of the first copy
+    pub first: Range<usize>,
+
+    /// 0-based, half-open region of the second copy, on the forward strand
+    /// even for inverted repeats
+    pub second: Range<usize>,
+
+    /// percent identity of the copies
+    pub identity: f32,
+
+    /// the first copy over the second, reverse complemented if inverted
+    pub alignment: Alignment,
+}
+
+/// self_align finds the direct and inverted repeats of a DNA sequence.
+///
+/// Repeats are ordered by the start of their first copy, then the start of
+/// their second copy.
+pub fn self_align(seq: &str, config: &RepeatConfig) -> Vec<Repeat> {
+    let bytes = seq.to_ascii_uppercase().into_bytes();
+    let (n, k) = (bytes.len(), config.k.max(1));
+    if n < k {
+        return vec![];
+    }
+
+    let mut words: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (pos, word) in bytes.windows(k).enumerate() {
+        words.entry(word).or_default().push(pos);
+    }
+    words.retain(|_, positions| positions.len() <= config.max_occurrences);
+
+    // diagonals q - p of direct copies and anti-diagonals p + q of inverted ones
+    let mut diagonals = Vec::new();
+    let mut anti_diagonals = Vec::new();
+    for (word, positions) in &words {
+        for (i, p) in positions.iter().enumerate() {
+            diagonals.extend(positions[i + 1..].iter().map(|q| q - p));
+        }
+        let rc: Vec<u8> = word
+            .iter()
+            .rev()
+            .map(|b| complement(*b as char) as u8)
+            .collect();
+        if let Some(others) = words.get(rc.as_slice()) {
+            for p in positions {
+                anti_diagonals.extend(others.iter().filter(|q| *q >= p).map(|q| p + q + k - 1));
+            }
+        }
+    }
+    diagonals.sort_unstable();
+    diagonals.dedup();
+    anti_diagonals.sort_unstable();
+    anti_diagonals.dedup();
+
+    let score = |x: u8, y: u8| config.scoring.matrix[x as usize][y as usize] as f32;
+    let comp = |y: usize| complement(bytes[y] as char) as u8;
+    let mut repeats = Vec::new();
+    let mut add = |kind, pairs: &[(usize, usize)], score: f32| {
+        let (start, end) = (pairs[0], pairs[pairs.len() - 1]);
+        let (second, copy): (Range<usize>, Vec<char>) = match kind {
+            RepeatKind::Direct => (
+                start.1..end.1 + 1,
+                pairs.iter().map(|(_, y)| bytes[*y] as char).collect(),
+            ),
+            RepeatKind::Inverted => (
+                end.1..start.1 + 1,
+                pairs.iter().map(|(_, y)| comp(*y) as char).collect(),
+            ),
+        };
+        let first = start.0..end.0 + 1;
+        let alignment = Alignment::new(
+            vec![
+                bytes[first.clone()].iter().map(|b| *b as char).collect(),
+                copy,
+            ],
+            vec![],
+            score,
+        );
+        repeats.push(Repeat {
+            kind,
+            first,
+            second,
+            identity: alignment.identity(Definition::AlignmentLength),
+            alignment,
+        });
+    };
+
+    for d in diagonals {
+        let pairs: Vec<(usize, usize)> = (0..n - d).map(|x| (x, x + d)).collect();
+        let scores: Vec<f32> = pairs
+            .iter()
+            .map(|(x, y)| score(bytes[*x], bytes[*y]))
+            .collect();
+        for (run, score) in segments(&scores, config.min_score) {
+            add(RepeatKind::Direct, &pairs[run], score);
+        }
+    }
+    for s in anti_diagonals {
+        let pairs: Vec<(usize, usize)> = (s.saturating_sub(n - 1)..=s / 2)
+            .map(|x| (x, s - x))
+            .filter(|(x, y)| x < y)
+            .collect();
+        let scores: Vec<f32> = pairs
+            .iter()
+            .map(|(x, y)| score(bytes[*x], comp(*y)))
+            .collect();
+        for (run, score) in segments(&scores, config.min_score) {
+            add(RepeatKind::Inverted, &pairs[run], score);
+        }
+    }
+
+    repeats.sort_by_key(|r| (r.first.start, r.second.start));
+    repeats
+}
+
+/// segments are the maximal scoring runs of scores, and their totals, of at
+/// least `min_score`.
+fn segments(scores: &[f32], min_score: f32) -> Vec<(Range<usize>, f32)> {
+    let mut runs = Vec::new();
+    let (mut sum, mut start) = (0f32, 0);
+    let mut best = (0..0, 0f32);
+    for (i, score) in scores.iter().enumerate() {
+        if sum <= 0f32 {
+            if best.1 >= min_score {
+                runs.push(best.clone());
+            }
+            sum = 0f32;
+            start = i;
+            best = (0..0, 0f32);
+        }
+        sum += score;
+        if sum > best.1 {
+            best = (start..i + 1, sum);
+        }
+    }
+    if best.1 >= min_score {
+        runs.push(best);
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seq::reverse_complement;
+
+    use super::*;
+
+    #[test]
+    fn test_self_align() {
+        let repeat = "GATTACAGGCCTTAGCATCGA";
+        let seq = format!(
+            "{}{}{}{}{}",
+            "ACGTTGCC", repeat, "TTTTCCCCGGGGAAAA", repeat, "CGCGATAT"
+        );
+        let seq = format!("{}{}{}", seq, "TGTGTCGC", reverse_complement(repeat));
+
+        let repeats = self_align(&seq, &RepeatConfig::default());
+        assert_eq!(3, repeats.len());
+
+        let direct = &repeats[0];
+        assert_eq!(RepeatKind::Direct, direct.kind);
+        assert_eq!(8..29, direct.first);
+        assert_eq!(45..66, direct.second);
+        assert_eq!(100f32, direct.identity);
+        assert_eq!(21f32 * 5f32, direct.alignment.score);
+
+        let inverted: Vec<_> = repeats
+            .iter()
+            .filter(|r| r.kind == RepeatKind::Inverted)
+            .map(|r| (r.first.clone(), r.second.clone()))
+            .collect();
+        assert_eq!(vec![(8..29, 82..103), (45..66, 82..103)], inverted);
+    }
+}
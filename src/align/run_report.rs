@@ -0,0 +1,242 @@
+//! A report of a progressive multiple alignment: how long each stage took,
+//! the guide tree, and the score of each profile alignment and of the
+//! result, to reproduce a run or find where a bad alignment went wrong.
+
+use std::{fmt, time::Duration};
+
+use crate::io::json;
+
+use super::GuideTree;
+
+/// RunReport is what a progressive multiple alignment did.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunReport {
+    /// IDs of the sequences aligned, in input order
+    pub ids: Vec<String>,
+
+    /// IDs of the empty sequences left out of the alignment
+    pub skipped: Vec<String>,
+
+    /// IDs of the duplicate sequences given the row of an aligned copy, with
+    /// the ID of the copy
+    pub collapsed: Vec<(String, String)>,
+
+    /// time to compare every pair of sequences, zero if the distances were
+    /// loaded from a checkpoint
+    pub pairwise_time: Duration,
+
+    /// method the guide tree was built with
+    pub tree_method: &'static str,
+
+    /// the guide tree as Newick
+    pub tree: String,
+
+    /// weight of each sequence by the guide tree, in input order
+    pub weights: Vec<f32>,
+
+    /// the profile alignments of the run, in the order of the tree's nodes.
+    /// Those loaded from a checkpoint aren't in it.
+    pub merges: Vec<Merge>,
+
+    /// time to align the profiles
+    pub progressive_time: Duration,
+
+    /// sum-of-pairs score of the alignment
+    pub sum_of_pairs: f32,
+}
+
+/// Merge is the alignment of the profiles of the children of a node of the
+/// guide tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    /// the node of the guide tree
+    pub node: usize,
+
+    /// its children
+    pub left: usize,
+    pub right: usize,
+
+    /// rows and columns of the profile of the node
+    pub rows: usize,
+    pub columns: usize,
+
+    /// score of the alignment of the children's profiles
+    pub score: f32,
+}
+
+impl RunReport {
+    /// to_json writes the report as one JSON object, with times in seconds.
+    pub fn to_json(&self) -> String {
+        let weights: Vec<String> = self
+            .ids
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(id, w)| {
+                format!(
+                    "{{\"id\":{},\"weight\":{}}}",
+                    json::string(id),
+                    json::number(*w)
+                )
+            })
+            .collect();
+        let merges: Vec<String> = self
+            .merges
+            .iter()
+            .map(|m| {
+                format!(
+                    "{{\"node\":{},\"left\":{},\"right\":{},\"rows\":{},\"columns\":{},\"score\":{}}}",
+                    m.node,
+                    m.left,
+                    m.right,
+                    m.rows,
+                    m.columns,
+                    json::number(m.score)
+                )
+            })
+            .collect();
+        let skipped: Vec<String> = self.skipped.iter().map(|id| json::string(id)).collect();
+        let collapsed: Vec<String> = self
+            .collapsed
+            .iter()
+            .map(|(id, copy)| {
+                format!(
+                    "{{\"id\":{},\"copy_of\":{}}}",
+                    json::string(id),
+                    json::string(copy)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"sequences\":{},\"skipped\":[{}],\"collapsed\":[{}],\"pairwise_seconds\":{},\"tree_method\":{},\"tree\":{},\"weights\":[{}],\"merges\":[{}],\"progressive_seconds\":{},\"sum_of_pairs\":{}}}",
+            self.ids.len(),
+            skipped.join(","),
+            collapsed.join(","),
+            self.pairwise_time.as_secs_f64(),
+            json::string(self.tree_method),
+            json::string(&self.tree),
+            weights.join(","),
+            merges.join(","),
+            self.progressive_time.as_secs_f64(),
+            json::number(self.sum_of_pairs)
+        )
+    }
+}
+
+impl fmt::Display for RunReport {
+    /// fmt writes the report as a table for a person to read.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sequences       {}", self.ids.len())?;
+        if !self.skipped.is_empty() {
+            writeln!(f, "skipped empty   {}", self.skipped.join(" "))?;
+        }
+        for (id, copy) in self.collapsed.iter() {
+            writeln!(f, "collapsed       {} into {}", id, copy)?;
+        }
+        writeln!(
+            f,
+            "pairwise stage  {:.3}s",
+            self.pairwise_time.as_secs_f64()
+        )?;
+        writeln!(f, "guide tree      {} {}", self.tree_method, self.tree)?;
+        writeln!(
+            f,
+            "progressive     {:.3}s",
+            self.progressive_time.as_secs_f64()
+        )?;
+        writeln!(f, "sum of pairs    {}", self.sum_of_pairs)?;
+        writeln!(f, "weights")?;
+        let width = self.ids.iter().map(|id| id.len()).max().unwrap_or(0);
+        for (id, w) in self.ids.iter().zip(self.weights.iter()) {
+            writeln!(f, "  {:width$}  {:.4}", id, w, width = width)?;
+        }
+        writeln!(f, "merges")?;
+        writeln!(f, "  node  left  right  rows  columns  score")?;
+        for m in self.merges.iter() {
+            writeln!(
+                f,
+                "  {:<4}  {:<4}  {:<5}  {:<4}  {:<7}  {}",
+                m.node, m.left, m.right, m.rows, m.columns, m.score
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl GuideTree {
+    /// sequence_weights are ClustalW's weights of the leaves, summing to 1:
+    /// the length of each branch over a leaf is shared by the leaves under
+    /// it, so sequences in a big clade of similar ones weigh less.
+    ///
+    /// The weights are only reported, profiles aren't scored by them.
+    pub fn sequence_weights(&self) -> Vec<f32> {
+        let leaves = self.leaves();
+        if self.nodes.is_empty() {
+            return vec![];
+        }
+
+        let mut under = vec![0usize; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            under[i] = match (node.left, node.right) {
+                (Some(left), Some(right)) => under[left] + under[right],
+                _ => 1,
+            };
+        }
+
+        // from the root down, the share of each branch above each node
+        let mut path = vec![0f32; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate().rev() {
+            for (child, len) in [
+                (node.left, node.left_branch_len),
+                (node.right, node.right_branch_len),
+            ] {
+                if let Some(child) = child {
+                    path[child] = path[i] + len / under[child] as f32;
+                }
+            }
+        }
+
+        let total: f32 = path[..leaves].iter().sum();
+        match total > 0f32 {
+            true => path[..leaves].iter().map(|w| w / total).collect(),
+            false => vec![1f32 / leaves as f32; leaves],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_multiple_with_report, ProgressiveConfig, Scoring},
+        matrices::NUC_4_4,
+    };
+
+    #[test]
+    fn test_run_report() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let seqs = ["ACGTACGTAC", "ACGTACGTAC", "ACGTACGTAC", "TTACGTTCGTAC"];
+        let (msa, report) =
+            align_multiple_with_report(&seqs, &scoring, &ProgressiveConfig::default()).unwrap();
+
+        assert_eq!(vec!["1", "2", "3", "4"], report.ids);
+        assert_eq!("UPGMA", report.tree_method);
+        assert_eq!(3, report.merges.len());
+        let root = report.merges.last().unwrap();
+        assert_eq!((6, 4, 12), (root.node, root.rows, root.columns));
+        assert_eq!(msa.sum_of_pairs(&scoring), report.sum_of_pairs);
+
+        // the three copies share their branches, the outlier has its own
+        let weights = &report.weights;
+        assert!((weights.iter().sum::<f32>() - 1f32).abs() < 1e-5);
+        assert!(weights[3] > weights[0] && weights[0] == weights[1]);
+
+        assert!(report.to_string().contains("guide tree      UPGMA ("));
+        let json = report.to_json();
+        assert!(json.starts_with("{\"sequences\":4,\"skipped\":[],\"collapsed\":[],"));
+        assert!(json.contains("{\"node\":6,\"left\":"));
+    }
+}
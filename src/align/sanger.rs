@@ -0,0 +1,230 @@
+//! Heterozygous positions in basecalled Sanger reads.
+//!
+//! A heterozygous site puts two peaks at one position of a trace, which the
+//! basecaller reports as an IUPAC ambiguity code, or reports as one base in
+//! some reads and the other in the rest. Each read is aligned to the
+//! reference in the quality-aware mode, and positions where confident calls,
+//! in confident stretches of their reads, need more than one base to explain
+//! them are candidates. The noisy start and end of each trace are ignored by
+//! the quality threshold rather than trimmed.
+
+use crate::{io::fastq::Record, matrices::NUC_4_4};
+
+use super::{align_with_quality, needleman_wunsch, Alignment, Scoring, TerminalGap, TerminalGaps};
+
+/// SangerConfig configures the alignment of Sanger reads.
+#[derive(Clone, Debug)]
+pub struct SangerConfig {
+    /// scoring of the alignments, its terminal gaps are ignored
+    pub scoring: Scoring,
+
+    /// lowest Phred quality of a call at a candidate position
+    pub min_quality: u8,
+
+    /// number of calls on each side of a call whose mean quality must also
+    /// be at least `min_quality`
+    pub flank: usize,
+}
+
+impl Default for SangerConfig {
+    fn default() -> Self {
+        SangerConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            min_quality: 30,
+            flank: 5,
+        }
+    }
+}
+
+/// Call is the base a read has at a reference position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    /// index of the read
+    pub read: usize,
+
+    pub base: char,
+
+    /// Phred quality of the base
+    pub qual: u8,
+}
+
+/// HetCandidate is a reference position that may be heterozygous.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HetCandidate {
+    /// 0-based position in the reference
+    pub pos: usize,
+
+    pub reference: char,
+
+    /// the bases of the confident calls, ambiguity codes expanded, sorted
+    pub alleles: Vec<char>,
+
+    /// the confident calls at the position, by read
+    pub calls: Vec<Call>,
+}
+
+/// SangerReport is the alignment of Sanger reads to a reference.
+#[derive(Debug)]
+pub struct SangerReport {
+    /// the reference over each read, in input order
+    pub alignments: Vec<Alignment>,
+
+    /// candidate heterozygous positions, by position
+    pub candidates: Vec<HetCandidate>,
+}
+
+/// align_sanger aligns Sanger reads to a reference and finds the positions
+/// that may be heterozygous.
+///
+/// Reads must be on the reference's strand. A read that is confident at a
+/// position and calls one base there, but a different base from another
+/// read, or an ambiguity code, makes it a candidate. A confident base that
+/// differs from the reference in every read is a homozygous difference and
+/// isn't reported.
+pub fn align_sanger(reference: &str, reads: &[Record], config: &SangerConfig) -> SangerReport {
+    let scoring = Scoring {
+        terminal_gaps: TerminalGaps {
+            b_start: TerminalGap::Free,
+            b_end: TerminalGap::Free,
+            ..Default::default()
+        },
+        ..config.scoring.clone()
+    };
+    let reference_bases: Vec<char> = reference.chars().collect();
+
+    let mut calls: Vec<Vec<Call>> = vec![Vec::new(); reference_bases.len()];
+    let mut alignments = Vec::with_capacity(reads.len());
+    for (index, read) in reads.iter().enumerate() {
+        let alignment = align_with_quality(
+            reference,
+            &read.seq,
+            &read.qual,
+            &needleman_wunsch::STRATEGY,
+            &scoring,
+        );
+
+        let (mut pos, mut offset) = (0, 0);
+        for (r, b) in alignment.rows[0].iter().zip(alignment.rows[1].iter()) {
+            if *r != '-' && *b != '-' && confident(&read.qual, offset, config) {
+                calls[pos].push(Call {
+                    read: index,
+                    base: b.to_ascii_uppercase(),
+                    qual: read.qual[offset],
+                });
+            }
+            pos += (*r != '-') as usize;
+            offset += (*b != '-') as usize;
+        }
+        alignments.push(alignment);
+    }
+
+    let candidates = calls
+        .into_iter()
+        .enumerate()
+        .filter_map(|(pos, calls)| {
+            let mut alleles: Vec<char> = calls.iter().flat_map(|c| bases(c.base)).collect();
+            alleles.sort_unstable();
+            alleles.dedup();
+            (alleles.len() > 1).then(|| HetCandidate {
+                pos,
+                reference: reference_bases[pos],
+                alleles,
+                calls,
+            })
+        })
+        .collect();
+
+    SangerReport {
+        alignments,
+        candidates,
+    }
+}
+
+/// confident is whether the call at an offset of a read, and the calls
+/// around it, are of high enough quality.
+fn confident(qual: &[u8], offset: usize, config: &SangerConfig) -> bool {
+    if qual[offset] < config.min_quality {
+        return false;
+    }
+    let start = offset.saturating_sub(config.flank);
+    let end = (offset + config.flank + 1).min(qual.len());
+    let flanks = end - start - 1;
+    if flanks == 0 {
+        return true;
+    }
+    let sum: usize =
+        qual[start..end].iter().map(|q| *q as usize).sum::<usize>() - qual[offset] as usize;
+    sum >= config.min_quality as usize * flanks
+}
+
+/// bases are the DNA bases an IUPAC code stands for.
+fn bases(code: char) -> Vec<char> {
+    let bases = match code {
+        'A' | 'C' | 'G' | 'T' => return vec![code],
+        'U' => "T",
+        'R' => "AG",
+        'Y' => "CT",
+        'S' => "CG",
+        'W' => "AT",
+        'K' => "GT",
+        'M' => "AC",
+        'B' => "CGT",
+        'D' => "AGT",
+        'H' => "ACT",
+        'V' => "ACG",
+        _ => "",
+    };
+    bases.chars().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_sanger() {
+        let reference = "GATTACAGGCCTTAGCATCGAACGTTGCCAGT";
+        let read = |seq: &str, qual: Vec<u8>| Record {
+            seq: seq.to_string(),
+            qual,
+            ..Default::default()
+        };
+
+        let mut noisy = vec![40; 26];
+        noisy[21] = 8;
+        let reads = [
+            // an ambiguity code at 13, and an A at 27
+            read("TACAGGCCTTMGCATCGAACGTTGACAG", vec![40; 28]),
+            // a G at 17, a low quality T at 26, and a C at 27
+            read("CAGGCCTTAGCAGCGAACGTTTCCAG", noisy),
+            // an A at 17, near the end of the read
+            read("GATTACAGGCCTTAGCAACG", vec![40; 20]),
+        ];
+        let report = align_sanger(reference, &reads, &SangerConfig::default());
+
+        assert_eq!(3, report.alignments.len());
+        assert_eq!(
+            "GATTACAGGCCTTAGCATCGAACGTTGCCAGT\n---TACAGGCCTTMGCATCGAACGTTGACAG-",
+            report.alignments[0].to_string()
+        );
+        let found: Vec<_> = report
+            .candidates
+            .iter()
+            .map(|c| (c.pos, c.reference, c.alleles.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (13, 'A', vec!['A', 'C']),
+                (17, 'T', vec!['A', 'G', 'T']),
+                (27, 'C', vec!['A', 'C'])
+            ],
+            found
+        );
+        assert_eq!(3, report.candidates[1].calls.len());
+    }
+}
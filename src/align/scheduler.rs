@@ -0,0 +1,360 @@
+//! Scheduling of the profile alignments of a guide tree across threads.
+//!
+//! A node of the tree can be aligned once both its children are, so
+//! subtrees that share no nodes are aligned at the same time. Each thread
+//! keeps a deque of nodes that are ready: the parent of a node it aligns goes
+//! on the back of its own deque, so a subtree stays on one thread, and a
+//! thread with nothing left steals from the front of another's, where the
+//! oldest and biggest work is. A profile depends only on its children, so the
+//! alignment is the same whatever order the nodes are aligned in.
+//!
+//! The order nodes finish in does change with the threads, though, and with
+//! it the order their checkpoints are written in and which error is
+//! returned if more than one fails. Deterministic scheduling aligns a level
+//! of the tree at a time instead: the nodes that are ready are aligned
+//! together, then taken in order. It's slower, since each level waits on its
+//! slowest node, but the same on any number of threads.
+
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+use crate::trace::stage;
+
+use super::{
+    clustal_w::{align_profiles, Profile},
+    Error, GuideTree, Result, Scoring,
+};
+
+/// State shared by the threads.
+struct State {
+    /// profile of each node, until its parent takes it
+    profiles: Vec<Option<Profile>>,
+
+    /// nodes ready to align, per thread
+    ready: Vec<VecDeque<usize>>,
+
+    /// children of each node still to align
+    waiting: Vec<u8>,
+
+    /// nodes still to align
+    remaining: usize,
+
+    /// the first error of a thread, which stops the rest
+    error: Option<Error>,
+}
+
+/// align_tree aligns the profiles of the nodes of a tree from the leaves to
+/// the root on at most `threads` threads, or one per core if it's 0, and
+/// returns the profile of the root.
+///
+/// Nodes that are `done` already have a profile, or are under one that does.
+/// `aligned` is called with each node aligned, its profile, its children and
+/// the score of their alignment. If `deterministic`, it's called on the
+/// nodes of each level in order and the first error in that order is
+/// returned, whatever the threads.
+pub(super) fn align_tree<F>(
+    tree: &GuideTree,
+    profiles: Vec<Option<Profile>>,
+    done: &[bool],
+    scoring: &Scoring,
+    threads: usize,
+    deterministic: bool,
+    aligned: F,
+) -> Result<Profile>
+where
+    F: Fn(usize, &Profile, (usize, usize), f32) -> Result<()> + Sync,
+{
+    let nodes = &tree.nodes;
+    let pending: Vec<bool> = (0..nodes.len())
+        .map(|n| !nodes[n].is_leaf() && !done[n])
+        .collect();
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        threads => threads,
+    };
+    if deterministic {
+        return align_levels(tree, profiles, pending, scoring, threads, aligned);
+    }
+
+    let mut parents: Vec<Option<usize>> = vec![None; nodes.len()];
+    let mut waiting = vec![0u8; nodes.len()];
+    for (n, node) in nodes.iter().enumerate() {
+        for child in [node.left, node.right].into_iter().flatten() {
+            parents[child] = Some(n);
+            waiting[n] += pending[child] as u8;
+        }
+    }
+
+    let remaining = pending.iter().filter(|p| **p).count();
+    let threads = threads.clamp(1, remaining.max(1));
+    let _stage = stage!("progressive", nodes = remaining, threads = threads);
+
+    // ready nodes are dealt out in turn
+    let mut ready: Vec<VecDeque<usize>> = vec![VecDeque::new(); threads];
+    (0..nodes.len())
+        .filter(|n| pending[*n] && waiting[*n] == 0)
+        .enumerate()
+        .for_each(|(i, n)| ready[i % threads].push_back(n));
+
+    let state = Mutex::new(State {
+        profiles,
+        ready,
+        waiting,
+        remaining,
+        error: None,
+    });
+    let wake = Condvar::new();
+
+    let work = |me: usize| {
+        let mut s = state.lock().unwrap();
+        loop {
+            if s.remaining == 0 || s.error.is_some() {
+                return;
+            }
+            let next = s.ready[me].pop_back().or_else(|| {
+                (1..threads)
+                    .map(|i| (me + i) % threads)
+                    .find_map(|other| s.ready[other].pop_front())
+            });
+            let Some(node) = next else {
+                s = wake.wait(s).unwrap();
+                continue;
+            };
+
+            let (left, right) = (nodes[node].left.unwrap(), nodes[node].right.unwrap());
+            let (a, b) = (
+                s.profiles[left].take().unwrap(),
+                s.profiles[right].take().unwrap(),
+            );
+            drop(s);
+            let (merged, score) = {
+                let _stage = stage!(
+                    "align_profiles",
+                    node = node,
+                    rows = a.rows.len() + b.rows.len(),
+                    columns = a.len() + b.len()
+                );
+                align_profiles(&a, &b, scoring)
+            };
+            let result = aligned(node, &merged, (left, right), score);
+            s = state.lock().unwrap();
+
+            if let Err(err) = result {
+                s.error.get_or_insert(err);
+                wake.notify_all();
+                return;
+            }
+            s.profiles[node] = Some(merged);
+            s.remaining -= 1;
+            if let Some(parent) = parents[node].filter(|p| pending[*p]) {
+                s.waiting[parent] -= 1;
+                if s.waiting[parent] == 0 {
+                    s.ready[me].push_back(parent);
+                }
+            }
+            wake.notify_all();
+        }
+    };
+    thread::scope(|scope| {
+        for me in 1..threads {
+            let work = &work;
+            scope.spawn(move || work(me));
+        }
+        work(0);
+    });
+
+    let mut state = state.into_inner().unwrap();
+    match state.error.take() {
+        Some(err) => Err(err),
+        None => Ok(state.profiles[tree.root()].take().unwrap()),
+    }
+}
+
+/// align_levels is `align_tree` a level at a time, on the nodes still
+/// `pending`.
+fn align_levels<F>(
+    tree: &GuideTree,
+    mut profiles: Vec<Option<Profile>>,
+    mut pending: Vec<bool>,
+    scoring: &Scoring,
+    threads: usize,
+    aligned: F,
+) -> Result<Profile>
+where
+    F: Fn(usize, &Profile, (usize, usize), f32) -> Result<()> + Sync,
+{
+    let nodes = &tree.nodes;
+    loop {
+        // the nodes whose children are both aligned, with their profiles
+        let level: Vec<(usize, Profile, Profile)> = (0..nodes.len())
+            .filter_map(|n| {
+                let (left, right) = (nodes[n].left?, nodes[n].right?);
+                (pending[n] && !pending[left] && !pending[right]).then(|| {
+                    let a = profiles[left].take().unwrap();
+                    (n, a, profiles[right].take().unwrap())
+                })
+            })
+            .collect();
+        if level.is_empty() {
+            break;
+        }
+
+        // dealt out in turn, and put back in order
+        let threads = threads.clamp(1, level.len());
+        let mut merged: Vec<(usize, Profile, f32)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|me| {
+                    let level = &level;
+                    scope.spawn(move || {
+                        level
+                            .iter()
+                            .skip(me)
+                            .step_by(threads)
+                            .map(|(node, a, b)| {
+                                let _stage = stage!(
+                                    "align_profiles",
+                                    node = node,
+                                    rows = a.rows.len() + b.rows.len(),
+                                    columns = a.len() + b.len()
+                                );
+                                let (merged, score) = align_profiles(a, b, scoring);
+                                (*node, merged, score)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        merged.sort_by_key(|(n, _, _)| *n);
+
+        for (n, profile, score) in merged {
+            let (left, right) = (nodes[n].left.unwrap(), nodes[n].right.unwrap());
+            aligned(n, &profile, (left, right), score)?;
+            profiles[n] = Some(profile);
+            pending[n] = false;
+        }
+    }
+    Ok(profiles[tree.root()].take().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::Node, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_align_tree() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let seqs = [
+            "ACGTACGT",
+            "ACGACGT",
+            "ACGTTACGT",
+            "ACGTACG",
+            "TACGTACGT",
+            "ACGTAGT",
+        ];
+
+        // a balanced tree: ((0, 1), (2, 3)), (4, 5)
+        let mut tree = GuideTree {
+            nodes: vec![Node::leaf(); seqs.len()],
+        };
+        for (left, right) in [(0, 1), (2, 3), (4, 5), (6, 7), (9, 8)] {
+            tree.nodes.push(Node {
+                left: Some(left),
+                right: Some(right),
+                ..Node::leaf()
+            });
+        }
+        let leaves = || -> Vec<Option<Profile>> {
+            seqs.iter()
+                .enumerate()
+                .map(|(i, s)| Some(Profile::from_seq(i, s)))
+                .chain((seqs.len()..tree.nodes.len()).map(|_| None))
+                .collect()
+        };
+        let done = vec![false; tree.nodes.len()];
+
+        let aligned = Mutex::new(vec![]);
+        let one = align_tree(
+            &tree,
+            leaves(),
+            &done,
+            &scoring,
+            1,
+            false,
+            |node, _, _, _| {
+                aligned.lock().unwrap().push(node);
+                Ok(())
+            },
+        )
+        .unwrap();
+        let mut aligned = aligned.into_inner().unwrap();
+        aligned.sort();
+        assert_eq!(vec![6, 7, 8, 9, 10], aligned);
+
+        for threads in [2, 4, 0] {
+            for deterministic in [false, true] {
+                let many = align_tree(
+                    &tree,
+                    leaves(),
+                    &done,
+                    &scoring,
+                    threads,
+                    deterministic,
+                    |_, _, _, _| Ok(()),
+                );
+                assert_eq!(one, many.unwrap());
+            }
+        }
+
+        let failed = align_tree(
+            &tree,
+            leaves(),
+            &done,
+            &scoring,
+            3,
+            false,
+            |node, _, _, _| match node {
+                9 => Err(Error::DifferentSequences),
+                _ => Ok(()),
+            },
+        );
+        assert!(matches!(failed, Err(Error::DifferentSequences)));
+
+        // deterministic scheduling takes each level in order, the same on any
+        // number of threads
+        for threads in [1, 2, 3, 0] {
+            let aligned = Mutex::new(vec![]);
+            let failed = align_tree(
+                &tree,
+                leaves(),
+                &done,
+                &scoring,
+                threads,
+                true,
+                |node, _, _, _| {
+                    aligned.lock().unwrap().push(node);
+                    match node {
+                        7 | 8 => Err(Error::InvalidParameter(node.to_string())),
+                        _ => Ok(()),
+                    }
+                },
+            );
+            assert!(matches!(failed, Err(Error::InvalidParameter(n)) if n == "7"));
+            assert_eq!(vec![6, 7], aligned.into_inner().unwrap());
+        }
+    }
+}
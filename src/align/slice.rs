@@ -0,0 +1,133 @@
+//! Sub-alignments of a multiple alignment by columns or rows.
+//!
+//! A column slice keeps how far into each sequence it starts, so positions
+//! in the slice can still be given in the coordinates of the full sequences.
+//! Per-column Stockholm markup in the metadata, like masks and secondary
+//! structure, is cut to the columns and rows kept.
+
+use std::ops::Range;
+
+use super::{CoordinateMap, MSAlignment, Metadata};
+
+/// MSASlice is a range of columns of a multiple alignment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MSASlice {
+    /// the columns of the slice
+    pub msa: MSAlignment,
+
+    /// for each row, the number of its residues before the slice
+    pub offsets: Vec<usize>,
+}
+
+impl MSASlice {
+    /// coordinate_map of the slice in the coordinates of the full sequences.
+    pub fn coordinate_map(&self) -> CoordinateMap {
+        CoordinateMap::with_offsets(&self.msa.rows, &self.offsets)
+    }
+}
+
+impl MSAlignment {
+    /// slice_columns is the sub-alignment of a range of columns.
+    pub fn slice_columns(&self, cols: Range<usize>) -> MSASlice {
+        if cols.start > cols.end || cols.end > self.len() {
+            panic!(
+                "columns {}..{} are outside the alignment of {} columns",
+                cols.start,
+                cols.end,
+                self.len()
+            )
+        }
+
+        MSASlice {
+            msa: MSAlignment {
+                ids: self.ids.clone(),
+                rows: self.rows.iter().map(|r| r[cols.clone()].to_vec()).collect(),
+                metadata: self.sliced_metadata(&self.ids, &cols.clone().collect::<Vec<_>>()),
+            },
+            offsets: self
+                .rows
+                .iter()
+                .map(|r| r[..cols.start].iter().filter(|c| **c != '-').count())
+                .collect(),
+        }
+    }
+
+    /// select is the sub-alignment of the rows with some IDs, in the order
+    /// given, without the columns that are all gap in them.
+    ///
+    /// IDs that aren't in the alignment are skipped.
+    pub fn select<S: AsRef<str>>(&self, ids: &[S]) -> MSAlignment {
+        let rows: Vec<usize> = ids.iter().filter_map(|id| self.row(id.as_ref())).collect();
+        let keep: Vec<usize> = (0..self.len())
+            .filter(|col| rows.iter().any(|r| self.rows[*r][*col] != '-'))
+            .collect();
+
+        let ids: Vec<String> = rows.iter().map(|r| self.ids[*r].clone()).collect();
+        MSAlignment {
+            metadata: self.sliced_metadata(&ids, &keep),
+            ids,
+            rows: rows
+                .iter()
+                .map(|r| keep.iter().map(|col| self.rows[*r][*col]).collect())
+                .collect(),
+        }
+    }
+
+    /// sliced_metadata is the metadata with its per-column markup cut to some
+    /// columns, and without the per-residue markup of rows not in `ids`.
+    pub(super) fn sliced_metadata(&self, ids: &[String], cols: &[usize]) -> Metadata {
+        self.metadata
+            .iter()
+            .filter_map(|(key, value)| {
+                let mut parts = key.splitn(3, ':');
+                let per_column = match (parts.next(), parts.next(), parts.next()) {
+                    (Some("GC"), Some(_), None) => true,
+                    (Some("GR"), Some(id), Some(_)) => {
+                        if !ids.iter().any(|i| i == id) {
+                            return None;
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                let chars: Vec<char> = value.chars().collect();
+                let value = match per_column && chars.len() == self.len() {
+                    true => cols.iter().map(|c| chars[*c]).collect(),
+                    false => value.clone(),
+                };
+                Some((key.clone(), value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![
+                "AC-GTTA".chars().collect(),
+                "ACTGT-A".chars().collect(),
+                "A--GT-A".chars().collect(),
+            ],
+        );
+
+        let slice = msa.slice_columns(2..6);
+        assert_eq!("-GTT\nTGT-\n-GT-", slice.msa.to_string());
+        assert_eq!(vec![2, 2, 1], slice.offsets);
+        // the first G is residue 2 of a, 3 of b and 1 of c
+        let map = slice.coordinate_map();
+        assert_eq!(Some(1), map.column(0, 2));
+        assert_eq!(Some(3), map.residue(1, 1));
+        assert_eq!(Some(1), map.project(0, 2, 2));
+        assert_eq!(None, map.column(0, 1));
+
+        let selected = msa.select(&["c", "a"]);
+        assert_eq!(vec!["c", "a"], selected.ids);
+        assert_eq!("A-GT-A\nACGTTA", selected.to_string());
+    }
+}
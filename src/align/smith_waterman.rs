@@ -28,31 +28,26 @@
 
 use ordered_float::OrderedFloat;
 
-use super::{step::Step, strategy::Strategy};
+use super::{
+    strategy::{End, Ends, Strategy},
+    terminal_gaps::TerminalGaps,
+};
 
 /// strategy for the Smith-Waterman algorithm.
 pub const STRATEGY: Strategy = Strategy {
     init_grid_value: |_i: usize| -> OrderedFloat<f32> { OrderedFloat(0f32) },
 
-    init_step_options: |i: usize, j: usize| -> Vec<Step> {
-        vec![Step {
-            i,
-            j,
-            val: OrderedFloat(0f32),
-            next: None,
-        }]
+    init_step_value: |_i: usize, _j: usize| -> Option<OrderedFloat<f32>> {
+        Some(OrderedFloat(0f32))
     },
 
-    init_backtrace: |grid: &[Vec<Step>]| -> Step {
-        let mut step = grid[0][0].clone();
-        for (i, _) in grid.iter().enumerate() {
-            for j in 0..grid[i].len() {
-                if grid[i][j] > step {
-                    step = grid[i][j].clone();
-                }
-            }
+    init_backtrace: |ends: &Ends, _terminal_gaps: &TerminalGaps| -> End {
+        let (i, j, score) = ends.best;
+        End {
+            score,
+            cell: (i, j),
+            terminal_gap: false,
         }
-        step
     },
 };
 
@@ -72,6 +67,7 @@ mod tests {
                 matrix: NUC_4_4::MATRIX,
                 gap_opening: -2f32,
                 gap_extension: -2f32,
+                ..Default::default()
             },
         );
 
@@ -91,6 +87,7 @@ mod tests {
                 matrix: NUC_4_4::MATRIX,
                 gap_opening: -2f32,
                 gap_extension: -2f32,
+                ..Default::default()
             },
         );
 
@@ -111,6 +108,7 @@ mod tests {
                 matrix: NUC_4_4::MATRIX,
                 gap_opening: -1f32,
                 gap_extension: -1f32,
+                ..Default::default()
             },
         );
 
@@ -137,6 +135,7 @@ mod tests {
                 matrix: NUC_4_4::MATRIX,
                 gap_opening: -5f32,
                 gap_extension: -1f32,
+                ..Default::default()
             },
         );
 
@@ -1,6 +1,6 @@
 use ordered_float::OrderedFloat;
 
-use super::{needleman_wunsch, smith_waterman, step::Step};
+use super::{needleman_wunsch, smith_waterman, terminal_gaps::TerminalGaps};
 
 /// Strategy defines the strategy for aligning two sequences.
 pub struct Strategy {
@@ -10,11 +10,42 @@ pub struct Strategy {
     /// For Smith-Waterman, this is 0.
     pub init_grid_value: fn(_i: usize) -> OrderedFloat<f32>,
 
-    /// init_step_options defines the default options for a cell in the alignment grid.
-    pub init_step_options: fn(_i: usize, _j: usize) -> Vec<Step>,
+    /// init_step_value defines the score of starting the alignment at a cell of
+    /// the grid rather than continuing a path from the cells before it, if it can.
+    pub init_step_value: fn(_i: usize, _j: usize) -> Option<OrderedFloat<f32>>,
 
-    /// init_backtrace finds the initial Step for backtracing the alignment grid.
-    pub init_backtrace: fn(grid: &[Vec<Step>]) -> Step,
+    /// init_backtrace finds the end of the path to backtrace from.
+    ///
+    /// Global strategies use the terminal gaps to decide whether the alignment
+    /// may end before the bottom-right corner of the grid.
+    pub init_backtrace: fn(ends: &Ends, terminal_gaps: &TerminalGaps) -> End,
+}
+
+/// Ends are the scores of the cells of the grid a path can end in, the grid
+/// itself isn't kept.
+pub struct Ends {
+    /// the score of the last cell of each row
+    pub last_column: Vec<f32>,
+
+    /// the score of each cell of the last row
+    pub last_row: Vec<f32>,
+
+    /// the best cell of the grid, the first if there are ties, as (i, j, score)
+    pub best: (usize, usize, f32),
+}
+
+/// End is where the backtrace of the grid starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct End {
+    /// score of the alignment
+    pub score: f32,
+
+    /// the cell the path through the grid ends in
+    pub cell: (usize, usize),
+
+    /// whether the alignment ends in a terminal gap from `cell` to the last
+    /// cell of the grid
+    pub terminal_gap: bool,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
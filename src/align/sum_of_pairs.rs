@@ -0,0 +1,113 @@
+//! Objective scores of a multiple alignment.
+//!
+//! The sum-of-pairs score is the total score of every pairwise alignment the
+//! MSA implies, the usual objective to compare or refine alignments by. The
+//! total column score counts the columns that are identical in every row.
+//! Masked cells are left out of both.
+
+use super::{MSAlignment, Scoring};
+
+impl MSAlignment {
+    /// sum_of_pairs is the total score of the pairwise alignment of every pair of rows.
+    ///
+    /// Columns that are a gap in both rows of a pair, or masked in either,
+    /// are dropped first, and a gap of length L costs
+    /// `gap_opening + gap_extension * (L - 1)`.
+    pub fn sum_of_pairs(&self, scoring: &Scoring) -> f32 {
+        let mask = self.mask();
+        let masked: Vec<bool> = mask.iter().map(|m| m.contains(&true)).collect();
+        let mut score = 0f32;
+        for (i, a) in self.rows.iter().enumerate() {
+            for (j, b) in self.rows.iter().enumerate().skip(i + 1) {
+                if !masked[i] && !masked[j] {
+                    score += pair_score(a, b, scoring);
+                    continue;
+                }
+                let kept = |(col, _): &(usize, &char)| !mask[i][*col] && !mask[j][*col];
+                let a: Vec<char> = a.iter().enumerate().filter(kept).map(|(_, c)| *c).collect();
+                let b: Vec<char> = b.iter().enumerate().filter(kept).map(|(_, c)| *c).collect();
+                score += pair_score(&a, &b, scoring);
+            }
+        }
+        score
+    }
+
+    /// total_column_score is the number of columns with the same residue in
+    /// every unmasked row.
+    pub fn total_column_score(&self) -> usize {
+        let mask = self.mask();
+        (0..self.len())
+            .filter(|col| {
+                let mut cells = self
+                    .rows
+                    .iter()
+                    .zip(mask.iter())
+                    .filter(|(_, m)| !m[*col])
+                    .map(|(r, _)| r[*col]);
+                match cells.next() {
+                    Some(first) => first != '-' && cells.all(|c| c == first),
+                    None => false,
+                }
+            })
+            .count()
+    }
+}
+
+/// pair_score is the score of two rows of an alignment.
+fn pair_score(a: &[char], b: &[char], scoring: &Scoring) -> f32 {
+    let mut score = 0f32;
+
+    // which row the gap of the last column was in, if any
+    let mut gap: Option<bool> = None;
+    for (x, y) in a.iter().zip(b.iter()) {
+        let in_a = match (*x, *y) {
+            ('-', '-') => continue,
+            ('-', _) => true,
+            (_, '-') => false,
+            _ => {
+                score += scoring.matrix[*x as usize][*y as usize] as f32;
+                gap = None;
+                continue;
+            }
+        };
+        score += if gap == Some(in_a) {
+            scoring.gap_extension
+        } else {
+            scoring.gap_opening
+        };
+        gap = Some(in_a);
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_sum_of_pairs() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![
+                "AC--GT".chars().collect(),
+                "ACTTGT".chars().collect(),
+                "AC--GA".chars().collect(),
+            ],
+        );
+
+        // a-b: 4 matches, one gap of 2; a-c: 3 matches, 1 mismatch; b-c: 3 matches, 1 mismatch, one gap of 2
+        assert_eq!(
+            (20f32 - 11f32) + (15f32 - 4f32) + (15f32 - 4f32 - 11f32),
+            msa.sum_of_pairs(&scoring)
+        );
+        assert_eq!(3, msa.total_column_score());
+    }
+}
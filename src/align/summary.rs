@@ -0,0 +1,91 @@
+//! A slim result of a pairwise alignment, for filtering passes that align
+//! many pairs and keep only their scores and identities.
+//!
+//! The alignment is the affine global one of ClustalW's profile step: its
+//! traceback is packed in 2 bits a cell and walked straight into CIGAR
+//! operations, so neither the grid of steps nor the gapped rows of an
+//! [`Alignment`](super::Alignment) are ever built.
+
+use super::{
+    clips::cigar_of,
+    clustal_w::{profile_columns_scored, Profile},
+    Scoring,
+};
+
+/// AlignmentSummary is what's left of a pairwise alignment without its rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignmentSummary {
+    /// score of the alignment
+    pub score: f32,
+
+    /// percent of the columns with the same residue in both sequences
+    pub identity: f32,
+
+    /// columns of the alignment
+    pub columns: usize,
+
+    /// columns with a residue of both sequences
+    pub aligned: usize,
+
+    /// CIGAR of the alignment, with a as the query and b as the reference
+    pub cigar: String,
+}
+
+/// align_summary aligns a and b globally with affine gaps and summarizes
+/// the alignment. Gaps at the ends cost like any other, whatever the
+/// scoring's terminal gaps.
+pub fn align_summary(a: &str, b: &str, scoring: &Scoring) -> AlignmentSummary {
+    let (columns, score) =
+        profile_columns_scored(&Profile::from_seq(0, a), &Profile::from_seq(1, b), scoring);
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    let (mut identical, mut aligned) = (0, 0);
+    for (i, j) in columns.iter() {
+        if let (Some(i), Some(j)) = (i, j) {
+            aligned += 1;
+            identical += usize::from(a[*i] == b[*j]);
+        }
+    }
+    let identity = match columns.len() {
+        0 => 0f32,
+        len => 100f32 * identical as f32 / len as f32,
+    };
+
+    AlignmentSummary {
+        score,
+        identity,
+        columns: columns.len(),
+        aligned,
+        cigar: cigar_of(columns.iter().map(|pair| match pair {
+            (Some(_), Some(_)) => 'M',
+            (Some(_), None) => 'I',
+            _ => 'D',
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_align_summary() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+
+        let summary = align_summary("ACGTGGCACGTACGT", "ACGTCACGAACGT", &scoring);
+        assert_eq!("4M2I9M", summary.cigar);
+        assert_eq!((15, 13), (summary.columns, summary.aligned));
+        assert_eq!(100f32 * 12f32 / 15f32, summary.identity);
+        assert_eq!(12f32 * 5f32 - 4f32 - 11f32, summary.score);
+
+        let empty = align_summary("", "", &scoring);
+        assert_eq!((0f32, "".to_string()), (empty.identity, empty.cigar));
+    }
+}
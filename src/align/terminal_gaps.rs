@@ -0,0 +1,54 @@
+//! Terminal gaps are gaps before the first or after the last residue of a sequence.
+//!
+//! In a global alignment they're penalized like any other gap by default. Making
+//! them cheaper, or free, on some ends turns a global alignment into an overlap
+//! (semi-global) alignment: one sequence can hang off the end of the other.
+
+/// TerminalGap defines how a gap at one end of a sequence is penalized.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerminalGap {
+    /// penalized the same as an internal gap
+    #[default]
+    Full,
+
+    /// penalized at half the cost of an internal gap
+    Half,
+
+    /// not penalized
+    Free,
+}
+
+impl TerminalGap {
+    /// weight is the multiplier applied to the normal gap penalty.
+    pub fn weight(&self) -> f32 {
+        match self {
+            TerminalGap::Full => 1f32,
+            TerminalGap::Half => 0.5f32,
+            TerminalGap::Free => 0f32,
+        }
+    }
+}
+
+/// TerminalGaps sets the terminal gap penalty on each of the four sequence ends.
+///
+/// The ends are named after the sequence that holds the gap: `a_start` is a
+/// run of gaps before the first residue of `a`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TerminalGaps {
+    pub a_start: TerminalGap,
+    pub a_end: TerminalGap,
+    pub b_start: TerminalGap,
+    pub b_end: TerminalGap,
+}
+
+impl TerminalGaps {
+    /// all uses the same terminal gap penalty on all four ends.
+    pub fn all(gap: TerminalGap) -> Self {
+        TerminalGaps {
+            a_start: gap,
+            a_end: gap,
+            b_start: gap,
+            b_end: gap,
+        }
+    }
+}
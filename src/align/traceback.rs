@@ -0,0 +1,89 @@
+//! Packed storage for traceback pointers.
+//!
+//! Every traceback move is one of a handful of directions, so it's stored as a
+//! 2-bit code rather than as the coordinates of the cell it points to. Four
+//! codes fit in a byte, which is what keeps the traceback of two long
+//! sequences in memory: a 50kb by 50kb grid is ~600 MB per state instead of
+//! tens of GB.
+
+use std::io::{self, Read, Write};
+
+/// Traceback is a fixed length vector of 2-bit codes.
+#[derive(Clone, Debug)]
+pub(super) struct Traceback {
+    bits: Vec<u64>,
+}
+
+const CODES_PER_WORD: usize = 32;
+
+impl Traceback {
+    /// new creates a traceback of `len` codes, all 0.
+    pub(super) fn new(len: usize) -> Self {
+        Traceback {
+            bits: vec![0; len.div_ceil(CODES_PER_WORD)],
+        }
+    }
+
+    /// get the code at an index.
+    pub(super) fn get(&self, index: usize) -> u8 {
+        let shift = 2 * (index % CODES_PER_WORD);
+        ((self.bits[index / CODES_PER_WORD] >> shift) & 0b11) as u8
+    }
+
+    /// set the code at an index, only the low two bits of `code` are kept.
+    pub(super) fn set(&mut self, index: usize, code: u8) {
+        let shift = 2 * (index % CODES_PER_WORD);
+        let word = &mut self.bits[index / CODES_PER_WORD];
+        *word = (*word & !(0b11 << shift)) | (((code & 0b11) as u64) << shift);
+    }
+
+    /// byte_len is the number of bytes a traceback of `len` codes is written as.
+    pub(super) fn byte_len(len: usize) -> usize {
+        8 * len.div_ceil(CODES_PER_WORD)
+    }
+
+    /// write_to writes the codes, to be read back with `read_from`.
+    pub(super) fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for word in self.bits.iter() {
+            w.write_all(&word.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// read_from reads a traceback of `len` codes written by `write_to`.
+    pub(super) fn read_from<R: Read>(r: &mut R, len: usize) -> io::Result<Self> {
+        let mut traceback = Traceback::new(len);
+        let mut word = [0u8; 8];
+        for bits in traceback.bits.iter_mut() {
+            r.read_exact(&mut word)?;
+            *bits = u64::from_le_bytes(word);
+        }
+        Ok(traceback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceback() {
+        let mut traceback = Traceback::new(100);
+        assert_eq!(4, traceback.bits.len());
+
+        for i in 0..100 {
+            traceback.set(i, (i % 4) as u8);
+        }
+        traceback.set(33, 0);
+        for i in 0..100 {
+            let expected = if i == 33 { 0 } else { (i % 4) as u8 };
+            assert_eq!(expected, traceback.get(i));
+        }
+
+        let mut bytes = Vec::new();
+        traceback.write_to(&mut bytes).unwrap();
+        assert_eq!(Traceback::byte_len(100), bytes.len());
+        let read = Traceback::read_from(&mut bytes.as_slice(), 100).unwrap();
+        assert_eq!(traceback.bits, read.bits);
+    }
+}
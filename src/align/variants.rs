@@ -0,0 +1,167 @@
+//! Variants between a reference (the first row of an alignment) and a query.
+//!
+//! Variants use the VCF conventions: positions are 1-based, and insertions and
+//! deletions include the reference base before them. Indels are left-aligned,
+//! so an indel in a repeat is always reported at the same position no matter
+//! where the aligner happened to put the gap.
+
+use std::fmt::Display;
+
+use super::{Alignment, Edit};
+
+/// VariantKind is the type of a variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariantKind {
+    Snv,
+    Insertion,
+    Deletion,
+}
+
+/// Variant is a single difference between the query and the reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant {
+    /// 1-based position of the first reference base in `reference`
+    pub pos: usize,
+
+    /// reference bases
+    pub reference: String,
+
+    /// alternate (query) bases
+    pub alt: String,
+
+    pub kind: VariantKind,
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}>{}", self.pos, self.reference, self.alt)
+    }
+}
+
+impl Alignment {
+    /// variants of the second sequence relative to the first.
+    ///
+    /// `ref_offset` is the 0-based position of the first aligned reference residue
+    /// in the full reference, so local alignments can report reference coordinates.
+    pub fn variants(&self, ref_offset: usize) -> Vec<Variant> {
+        let reference: Vec<char> = self.rows[0]
+            .iter()
+            .filter(|c| **c != '-')
+            .copied()
+            .collect();
+
+        self.patch()
+            .edits
+            .into_iter()
+            .map(|edit| match edit {
+                Edit::Substitution { pos, from, to } => Variant {
+                    pos: ref_offset + pos + 1,
+                    reference: from.to_string(),
+                    alt: to.to_string(),
+                    kind: VariantKind::Snv,
+                },
+                Edit::Insertion { pos, seq } => insertion(&reference, pos, seq, ref_offset),
+                Edit::Deletion { pos, seq } => {
+                    deletion(&reference, pos, seq.chars().count(), ref_offset)
+                }
+            })
+            .collect()
+    }
+}
+
+/// insertion of `seq` before `pos`, shifted left and padded with the preceding reference base.
+fn insertion(reference: &[char], mut pos: usize, seq: String, ref_offset: usize) -> Variant {
+    let mut seq: Vec<char> = seq.chars().collect();
+    while pos > 0 && reference[pos - 1] == seq[seq.len() - 1] {
+        seq.rotate_right(1);
+        pos -= 1;
+    }
+
+    let (pos, reference, alt) = if pos > 0 {
+        let anchor = reference[pos - 1];
+        (
+            pos,
+            anchor.to_string(),
+            std::iter::once(anchor).chain(seq).collect(),
+        )
+    } else {
+        // nothing to the left: VCF pads with the following base instead
+        let anchor = reference.first().copied().unwrap_or('N');
+        (
+            1,
+            anchor.to_string(),
+            seq.into_iter().chain(std::iter::once(anchor)).collect(),
+        )
+    };
+
+    Variant {
+        pos: ref_offset + pos,
+        reference,
+        alt,
+        kind: VariantKind::Insertion,
+    }
+}
+
+/// deletion of `len` reference bases starting at `pos`, shifted left and padded.
+fn deletion(reference: &[char], mut pos: usize, len: usize, ref_offset: usize) -> Variant {
+    while pos > 0 && reference[pos - 1] == reference[pos + len - 1] {
+        pos -= 1;
+    }
+
+    let (pos, deleted, alt) = if pos > 0 {
+        (pos, &reference[pos - 1..pos + len], reference[pos - 1])
+    } else {
+        let after = reference.get(len).copied().unwrap_or('N');
+        (1, &reference[..(len + 1).min(reference.len())], after)
+    };
+
+    Variant {
+        pos: ref_offset + pos,
+        reference: deleted.iter().collect(),
+        alt: alt.to_string(),
+        kind: VariantKind::Deletion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(a: &str, b: &str) -> Alignment {
+        Alignment::new(vec![a.chars().collect(), b.chars().collect()], vec![], 0f32)
+    }
+
+    #[test]
+    fn test_alignment_variants() {
+        let variants = alignment("ACGTAC--GTTTAC", "ACCTACGTGT-TAC").variants(0);
+
+        assert_eq!(
+            vec!["3:G>C", "6:C>CGT", "7:GT>G"],
+            variants.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                VariantKind::Snv,
+                VariantKind::Insertion,
+                VariantKind::Deletion
+            ],
+            variants.iter().map(|v| v.kind).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_alignment_variants_offset() {
+        let variants = alignment("GATTACA", "GACTACA").variants(100);
+
+        assert_eq!("103:T>C", variants[0].to_string());
+    }
+
+    #[test]
+    fn test_alignment_variants_leading_indels() {
+        let variants = alignment("--ACGT", "GGACGT").variants(0);
+        assert_eq!("1:A>GGA", variants[0].to_string());
+
+        let variants = alignment("AACGT", "--CGT").variants(0);
+        assert_eq!("1:AAC>C", variants[0].to_string());
+    }
+}
@@ -0,0 +1,420 @@
+//! Verification of a construct: sequencing results against the designed
+//! sequence.
+//!
+//! The result is oriented to the design's strand and, for a circular
+//! design, the design is rotated to start where the result does, so a
+//! plasmid sequenced from any position lines up without a gap across the
+//! origin. The result is then aligned end to end over the design, free to
+//! cover only part of it, and the differences are sorted by what they mean
+//! for the construct: point mutations, small indels, or assembly errors,
+//! where parts of the design are missing, foreign, or out of order.
+
+use std::cmp::Ordering;
+
+use crate::{
+    graph::{consensus_from_reads, ConsensusConfig},
+    matrices::NUC_4_4,
+    seq::reverse_complement,
+};
+
+use super::{
+    align_local, align_overlap, breakpoints, map_long, mapping::kmer_index, Alignment,
+    BreakpointConfig, BreakpointKind, Error, MapConfig, Result, Scoring, TerminalGap, TerminalGaps,
+    Variant, VariantKind,
+};
+
+/// VerifyConfig configures the verification of a construct.
+#[derive(Clone, Debug)]
+pub struct VerifyConfig {
+    /// scoring of the alignments of the result to the design
+    pub scoring: Scoring,
+
+    /// whether the design is circular, like a plasmid
+    pub circular: bool,
+
+    /// indels at least this long are assembly errors rather than indels
+    pub assembly_indel: usize,
+
+    /// mapping of the result to the design to find parts out of order
+    pub map: MapConfig,
+
+    /// consensus of the reads when more than one result is given
+    pub consensus: ConsensusConfig,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        VerifyConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            circular: false,
+            assembly_indel: 20,
+            map: MapConfig::default(),
+            consensus: ConsensusConfig::default(),
+        }
+    }
+}
+
+/// Verdict is the worst kind of difference between the result and the design.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// no differences
+    Perfect,
+
+    /// substitutions only
+    PointMutations,
+
+    /// indels shorter than the config's `assembly_indel`, maybe with substitutions
+    Indels,
+
+    /// parts of the design missing, foreign, or out of order
+    AssemblyErrors,
+}
+
+/// AssemblyError is a junction in the result between residues that aren't
+/// adjacent in the design.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssemblyError {
+    /// 0-based position in the result of the junction
+    pub result: usize,
+
+    /// 0-based position in the design where the part before the junction ends
+    pub design_before: usize,
+
+    /// 0-based position in the design where the part after the junction starts
+    pub design_after: usize,
+
+    /// residues of the result at the junction that aren't in the design
+    pub inserted: usize,
+
+    /// 0 to 1, see [`Breakpoint::confidence`](super::Breakpoint::confidence);
+    /// 1 for a long indel in the alignment
+    pub confidence: f32,
+}
+
+/// Verification is how a sequencing result differs from its design.
+#[derive(Debug)]
+pub struct Verification {
+    pub verdict: Verdict,
+
+    /// the result that was verified: the one sequence given, or the
+    /// consensus of the reads, on the design's strand
+    pub result: String,
+
+    /// for each result given, whether it was reverse complemented onto the
+    /// design's strand
+    pub reversed: Vec<bool>,
+
+    /// the design over the result, from the first to the last column with a
+    /// residue of the result
+    pub alignment: Alignment,
+
+    /// 0-based position in the design where the result starts
+    pub start: usize,
+
+    /// residues of the design the result spans
+    pub covered: usize,
+
+    /// differences of the result from the design, positions in the design
+    pub variants: Vec<Variant>,
+
+    pub assembly_errors: Vec<AssemblyError>,
+}
+
+/// verify aligns sequencing results to the design of a construct and
+/// reports how they differ.
+///
+/// One result is verified as is, like an assembly or a consensus. More than
+/// one are reads of the construct, and their consensus is verified; reads
+/// of a circular construct should start near the same position, like reads
+/// from one primer. Each is put on the strand sharing more k-mers with the
+/// design. Sequences are compared in upper case.
+///
+/// If parts of the result map to the design out of order, those junctions
+/// are the assembly errors, since the alignment across them isn't
+/// meaningful. Otherwise indels of at least `assembly_indel` residues are.
+pub fn verify<S: AsRef<str>>(
+    design: &str,
+    results: &[S],
+    config: &VerifyConfig,
+) -> Result<Verification> {
+    if design.is_empty() {
+        return Err(Error::EmptySequence("design".to_string()));
+    }
+    if results.is_empty() {
+        return Err(Error::EmptySequence("result".to_string()));
+    }
+    if let Some(i) = results.iter().position(|r| r.as_ref().is_empty()) {
+        return Err(Error::EmptySequence(format!("result {}", i + 1)));
+    }
+    // the design is rotated by byte
+    if !design.is_ascii() {
+        return Err(Error::NonAsciiSequence("design".to_string()));
+    }
+
+    let design = design.to_ascii_uppercase();
+    let target = if config.circular {
+        design.repeat(2)
+    } else {
+        design.clone()
+    };
+
+    let index = kmer_index(target.as_bytes(), config.map.k.max(1), usize::MAX);
+    let shared = |seq: &str| {
+        let seq = seq.as_bytes();
+        (0..(seq.len() + 1).saturating_sub(config.map.k.max(1)))
+            .filter(|i| index.contains_key(&seq[*i..*i + config.map.k.max(1)]))
+            .count()
+    };
+    let local_score = |seq: &str| align_local(seq, &target, &config.scoring).alignment.score;
+
+    let mut seqs = Vec::with_capacity(results.len());
+    let mut reversed = Vec::with_capacity(results.len());
+    for result in results {
+        let forward = result.as_ref().to_ascii_uppercase();
+        let reverse = reverse_complement(&forward);
+        let flip = match shared(&reverse).cmp(&shared(&forward)) {
+            Ordering::Equal => local_score(&reverse) > local_score(&forward),
+            order => order == Ordering::Greater,
+        };
+        seqs.push(if flip { reverse } else { forward });
+        reversed.push(flip);
+    }
+    let result = if seqs.len() == 1 {
+        seqs.remove(0)
+    } else {
+        consensus_from_reads(&seqs, &config.consensus).consensus
+    };
+
+    // the design position the result's first residue is over
+    let len = design.len();
+    let rotation = if config.circular {
+        let start = match map_long(&result, &target, &config.map) {
+            Some(mapping) => (mapping.reference.start, mapping.query.start),
+            None => {
+                let local = align_local(&result, &target, &config.scoring);
+                (local.b.start, local.a.start)
+            }
+        };
+        (start.0 as isize - start.1 as isize).rem_euclid(len as isize) as usize
+    } else {
+        0
+    };
+    let rotated = format!("{}{}", &design[rotation..], &design[..rotation]);
+    let position = |rotated_pos: usize| (rotated_pos + rotation) % len;
+
+    let scoring = Scoring {
+        terminal_gaps: TerminalGaps {
+            b_start: TerminalGap::Free,
+            b_end: TerminalGap::Free,
+            ..Default::default()
+        },
+        ..config.scoring.clone()
+    };
+    let full = align_overlap(&rotated, &result, &scoring);
+
+    // trim the design that the result doesn't reach
+    let residue = |c: &char| *c != '-';
+    let (Some(first), Some(last)) = (
+        full.rows[1].iter().position(residue),
+        full.rows[1].iter().rposition(residue),
+    ) else {
+        return Err(Error::EmptySequence("consensus of the results".to_string()));
+    };
+    let lead = full.rows[0][..first].iter().filter(|c| residue(c)).count();
+    let alignment = Alignment::new(
+        full.rows.iter().map(|r| r[first..=last].to_vec()).collect(),
+        vec![],
+        full.score,
+    );
+    let covered = alignment.rows[0].iter().filter(|c| residue(c)).count();
+
+    let variants: Vec<Variant> = alignment
+        .variants(lead)
+        .into_iter()
+        .map(|v| Variant {
+            pos: position(v.pos - 1) + 1,
+            ..v
+        })
+        .collect();
+
+    let breakpoint_config = BreakpointConfig {
+        map: config.map.clone(),
+        ..Default::default()
+    };
+    let mut assembly_errors: Vec<AssemblyError> =
+        breakpoints(&result, &rotated, &breakpoint_config)
+            .into_iter()
+            .filter(|b| b.kind == BreakpointKind::Split)
+            .map(|b| AssemblyError {
+                result: b.query.start,
+                design_before: position(b.reference_before),
+                design_after: position(b.reference_after),
+                inserted: 0,
+                confidence: b.confidence,
+            })
+            .collect();
+    if assembly_errors.is_empty() {
+        assembly_errors = long_indels(&alignment, lead, config.assembly_indel)
+            .into_iter()
+            .map(|e| AssemblyError {
+                design_before: position(e.design_before),
+                design_after: position(e.design_after),
+                ..e
+            })
+            .collect();
+    }
+
+    let verdict = if !assembly_errors.is_empty() {
+        Verdict::AssemblyErrors
+    } else if variants.iter().any(|v| v.kind != VariantKind::Snv) {
+        Verdict::Indels
+    } else if !variants.is_empty() {
+        Verdict::PointMutations
+    } else {
+        Verdict::Perfect
+    };
+
+    Ok(Verification {
+        verdict,
+        result,
+        reversed,
+        alignment,
+        start: position(lead),
+        covered,
+        variants,
+        assembly_errors,
+    })
+}
+
+/// long_indels are the runs of gaps of at least `min` columns, design
+/// positions from `lead`.
+fn long_indels(alignment: &Alignment, lead: usize, min: usize) -> Vec<AssemblyError> {
+    let (design, result) = (&alignment.rows[0], &alignment.rows[1]);
+    let (mut d, mut r) = (lead, 0);
+    let mut errors = vec![];
+    let mut col = 0;
+    while col < design.len() {
+        let run = |row: &[char]| row[col..].iter().take_while(|c| **c == '-').count();
+        let (deleted, inserted) = (run(result), run(design));
+        if deleted >= min.max(1) {
+            errors.push(AssemblyError {
+                result: r,
+                design_before: d,
+                design_after: d + deleted,
+                inserted: 0,
+                confidence: 1f32,
+            });
+        } else if inserted >= min.max(1) {
+            errors.push(AssemblyError {
+                result: r,
+                design_before: d,
+                design_after: d,
+                inserted,
+                confidence: 1f32,
+            });
+        }
+
+        let step = deleted.max(inserted).max(1);
+        d += design[col..col + step]
+            .iter()
+            .filter(|c| **c != '-')
+            .count();
+        r += result[col..col + step]
+            .iter()
+            .filter(|c| **c != '-')
+            .count();
+        col += step;
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_verify_circular() {
+        let mut rng = Rng::new(21);
+        let design = random_seq(600, &background::nucleotide(), &mut rng);
+        let config = VerifyConfig {
+            circular: true,
+            ..Default::default()
+        };
+
+        // sequenced from position 200, on the other strand
+        let rotated = format!("{}{}", &design[200..], &design[..200]);
+        let verified = verify(&design, &[reverse_complement(&rotated)], &config).unwrap();
+        assert_eq!(Verdict::Perfect, verified.verdict);
+        assert_eq!(
+            (vec![true], 200, 600),
+            (verified.reversed, verified.start, verified.covered)
+        );
+
+        // a substitution at 50, past the origin from where it was sequenced
+        let mut mutated = rotated.into_bytes();
+        let pos = 50 + 400;
+        mutated[pos] = if mutated[pos] == b'A' { b'C' } else { b'A' };
+        let mutated = String::from_utf8(mutated).unwrap();
+        let verified = verify(&design, &[&mutated], &config).unwrap();
+        assert_eq!(Verdict::PointMutations, verified.verdict);
+        assert_eq!(
+            vec![(51, VariantKind::Snv)],
+            verified
+                .variants
+                .iter()
+                .map(|v| (v.pos, v.kind))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            verify(&design, &[""], &config),
+            Err(Error::EmptySequence(_))
+        ));
+        assert!(matches!(
+            verify("ACGTÅACGT", &["ACGT"], &config),
+            Err(Error::NonAsciiSequence(_))
+        ));
+        assert!(matches!(
+            verify(&design, &["---", "--"], &config),
+            Err(Error::EmptySequence(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_assembly_error() {
+        let mut rng = Rng::new(22);
+        let design = random_seq(1500, &background::nucleotide(), &mut rng);
+
+        // a part of 500..800 missing, and a 2 base deletion after
+        let result = format!(
+            "{}{}{}",
+            &design[..500],
+            &design[800..1000],
+            &design[1002..]
+        );
+        let verified = verify(&design, &[&result], &VerifyConfig::default()).unwrap();
+        assert_eq!(Verdict::AssemblyErrors, verified.verdict);
+        assert_eq!(
+            1,
+            verified.assembly_errors.len(),
+            "{:?}",
+            verified.assembly_errors
+        );
+        let error = &verified.assembly_errors[0];
+        assert_eq!(300, error.design_after - error.design_before);
+        assert!(error.result.abs_diff(500) < 5, "{:?}", error);
+        assert!(verified
+            .variants
+            .iter()
+            .any(|v| v.kind == VariantKind::Deletion && v.reference.len() == 3));
+    }
+}
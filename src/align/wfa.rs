@@ -0,0 +1,140 @@
+//! Wavefront alignment (WFA) for edit distance.
+//! https://doi.org/10.1093/bioinformatics/btaa777
+//!
+//! Rather than filling the grid cell by cell, WFA keeps, for each number of
+//! edits, the furthest point reached on every diagonal: the wavefront. Each
+//! wavefront is slid along runs of matches for free before the next edit is
+//! taken, so similar sequences are aligned in time proportional to their
+//! length times the number of edits.
+
+use super::Alignment;
+
+// a diagonal not reached by a wavefront
+const NONE: isize = isize::MIN / 2;
+
+/// align_wfa aligns two sequences globally with the fewest substitutions,
+/// insertions and deletions.
+///
+/// The score of the alignment is the negative number of edits.
+pub fn align_wfa(a: &str, b: &str) -> Alignment {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (na, nb) = (a.len() as isize, b.len() as isize);
+
+    // a point on diagonal k is at column j of a and row j - k of b
+    let extend = |k: isize, mut j: isize| -> isize {
+        while j < na && j - k < nb && a[j as usize] == b[(j - k) as usize] {
+            j += 1;
+        }
+        j
+    };
+    let valid = |k: isize, j: isize| j >= 0 && j <= na && j - k >= 0 && j - k <= nb;
+
+    // wavefronts[d][k + d] is the furthest column on diagonal k with d edits
+    let end = na - nb;
+    let mut wavefronts: Vec<Vec<isize>> = vec![vec![extend(0, 0)]];
+    loop {
+        let d = wavefronts.len() as isize - 1;
+        if end.abs() <= d && wavefronts[d as usize][(end + d) as usize] >= na {
+            break;
+        }
+
+        let prev = &wavefronts[d as usize];
+        let next: Vec<isize> = (-(d + 1)..=d + 1)
+            .map(|k| {
+                let j = previous(prev, d, k, valid);
+                if j == NONE {
+                    NONE
+                } else {
+                    extend(k, j)
+                }
+            })
+            .collect();
+        wavefronts.push(next);
+    }
+
+    // walk back from the end, one edit per wavefront
+    let mut rows: Vec<Vec<char>> = vec![Vec::new(), Vec::new()];
+    let mut push = |x: Option<u8>, y: Option<u8>| {
+        rows[0].push(x.map_or('-', char::from));
+        rows[1].push(y.map_or('-', char::from));
+    };
+    let (mut k, mut j) = (end, na);
+    let edits = wavefronts.len() - 1;
+    for d in (1..=edits as isize).rev() {
+        let prev = &wavefronts[d as usize - 1];
+        let start = previous(prev, d - 1, k, valid);
+        for t in (start..j).rev() {
+            push(Some(a[t as usize]), Some(b[(t - k) as usize]));
+        }
+
+        let get = |k: isize| at(prev, d - 1, k);
+        if get(k) + 1 == start {
+            push(
+                Some(a[start as usize - 1]),
+                Some(b[(start - 1 - k) as usize]),
+            );
+            j = start - 1;
+        } else if get(k - 1) + 1 == start {
+            push(Some(a[start as usize - 1]), None);
+            k -= 1;
+            j = start - 1;
+        } else {
+            push(None, Some(b[(start - k - 1) as usize]));
+            k += 1;
+            j = start;
+        }
+    }
+    for t in (0..j).rev() {
+        push(Some(a[t as usize]), Some(b[t as usize]));
+    }
+    for row in rows.iter_mut() {
+        row.reverse();
+    }
+
+    Alignment::new(rows, vec![], -(edits as f32))
+}
+
+/// at is the column of diagonal k in the wavefront of d edits.
+fn at(wavefront: &[isize], d: isize, k: isize) -> isize {
+    if k.abs() > d {
+        NONE
+    } else {
+        wavefront[(k + d) as usize]
+    }
+}
+
+/// previous is the furthest column on diagonal k one edit after the wavefront
+/// of d edits, before sliding along matches: a substitution on k, a residue
+/// of a from k - 1, or a residue of b from k + 1.
+fn previous(
+    wavefront: &[isize],
+    d: isize,
+    k: isize,
+    valid: impl Fn(isize, isize) -> bool,
+) -> isize {
+    [
+        at(wavefront, d, k) + 1,
+        at(wavefront, d, k - 1) + 1,
+        at(wavefront, d, k + 1),
+    ]
+    .into_iter()
+    .filter(|j| *j > NONE / 2 && valid(k, *j))
+    .max()
+    .unwrap_or(NONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_wfa() {
+        let alignment = align_wfa("ACGTACGTAC", "ACGTTACGAC");
+        assert_eq!(-2f32, alignment.score);
+        assert_eq!("ACGT-ACGTAC\nACGTTACG-AC", alignment.to_string());
+
+        assert_eq!(-1f32, align_wfa("ACGT", "AGGT").score);
+        assert_eq!("ACGT\n----", align_wfa("ACGT", "").to_string());
+        assert_eq!(0f32, align_wfa("", "").score);
+    }
+}
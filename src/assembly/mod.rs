@@ -0,0 +1,463 @@
+//! Overlap-layout-consensus assembly of a handful of fragments.
+//!
+//! This is for the assemblies of a Gibson or Golden Gate design, or a few
+//! Sanger reads of a plasmid, rather than a genome. Every pair of fragments
+//! is aligned in overlap mode to find how the end of one overlaps the start
+//! of the other (overlap), the best overlaps are taken greedily to order the
+//! fragments into contigs (layout), and each contig is the majority residue
+//! of each column of its fragments aligned one after another (consensus).
+
+use std::ops::Range;
+
+use crate::{
+    align::{align_overlap, Alignment, Scoring, TerminalGap, TerminalGaps},
+    matrices::NUC_4_4,
+    seq::reverse_complement,
+};
+
+/// AssemblyConfig configures the assembly of fragments.
+#[derive(Clone, Debug)]
+pub struct AssemblyConfig {
+    /// scoring of the overlap alignments, its terminal gaps are ignored
+    pub scoring: Scoring,
+
+    /// fewest aligned pairs of residues in an overlap
+    pub min_overlap: usize,
+
+    /// most mismatches and gaps per column of an overlap
+    pub max_mismatch_rate: f32,
+
+    /// also try the reverse complement of each fragment
+    pub both_strands: bool,
+}
+
+impl Default for AssemblyConfig {
+    fn default() -> Self {
+        AssemblyConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            min_overlap: 20,
+            max_mismatch_rate: 0.05,
+            both_strands: true,
+        }
+    }
+}
+
+/// Overlap is the end of one fragment aligned to the start of another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overlap {
+    /// index of the fragment whose end overlaps
+    pub from: usize,
+
+    /// index of the fragment whose start overlaps
+    pub to: usize,
+
+    /// number of columns from the first to the last aligned pair of residues
+    pub len: usize,
+
+    /// number of mismatches and gaps in the overlap
+    pub mismatches: usize,
+
+    pub score: f32,
+}
+
+/// Contig is a consensus sequence of overlapping fragments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contig {
+    pub seq: String,
+
+    /// the fragments in the contig, in order, and whether each was reverse complemented
+    pub fragments: Vec<(usize, bool)>,
+
+    /// number of fragments covering each residue of `seq`
+    pub coverage: Vec<usize>,
+}
+
+/// overlaps are the overlaps of the end of each fragment with the start of
+/// every other, as given, that are long and similar enough.
+pub fn overlaps<S: AsRef<str>>(fragments: &[S], config: &AssemblyConfig) -> Vec<Overlap> {
+    let mut found = Vec::new();
+    for (i, a) in fragments.iter().enumerate() {
+        for (j, b) in fragments.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            if let Some((len, mismatches, score)) = overlap(a.as_ref(), b.as_ref(), config) {
+                found.push(Overlap {
+                    from: i,
+                    to: j,
+                    len,
+                    mismatches,
+                    score,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// assemble assembles fragments into contigs.
+///
+/// With `both_strands`, each fragment is oriented by its best overlap with a
+/// fragment already oriented, starting from the first fragment. Fragments
+/// within another are added to its contig's consensus. Contigs are ordered by
+/// their first fragment.
+pub fn assemble<S: AsRef<str>>(fragments: &[S], config: &AssemblyConfig) -> Vec<Contig> {
+    let reversed = if config.both_strands {
+        orient(fragments, config)
+    } else {
+        vec![false; fragments.len()]
+    };
+    let seqs: Vec<String> = fragments
+        .iter()
+        .zip(reversed.iter())
+        .map(|(f, r)| match r {
+            true => reverse_complement(f.as_ref()),
+            false => f.as_ref().to_string(),
+        })
+        .collect();
+
+    // fragments within a longer one, or an equal one earlier
+    let container: Vec<Option<usize>> = (0..seqs.len())
+        .map(|j| {
+            (0..seqs.len()).find(|i| {
+                *i != j
+                    && (seqs[*i].len() > seqs[j].len() || seqs[*i].len() == seqs[j].len() && *i < j)
+                    && contains(&seqs[*i], &seqs[j], config).is_some()
+            })
+        })
+        .collect();
+
+    // take the best overlaps that join the end of one chain to the start of another
+    let mut candidates: Vec<Overlap> = overlaps(&seqs, config)
+        .into_iter()
+        .filter(|o| container[o.from].is_none() && container[o.to].is_none())
+        .collect();
+    candidates.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then(x.from.cmp(&y.from))
+            .then(x.to.cmp(&y.to))
+    });
+    let mut next: Vec<Option<usize>> = vec![None; seqs.len()];
+    let mut prev: Vec<Option<usize>> = vec![None; seqs.len()];
+    for o in candidates {
+        if next[o.from].is_some() || prev[o.to].is_some() {
+            continue;
+        }
+        // skip overlaps that would close a chain into a cycle
+        let mut head = o.from;
+        while let Some(p) = prev[head] {
+            head = p;
+        }
+        if head == o.to {
+            continue;
+        }
+        next[o.from] = Some(o.to);
+        prev[o.to] = Some(o.from);
+    }
+
+    let mut contigs = Vec::new();
+    for start in (0..seqs.len()).filter(|f| container[*f].is_none() && prev[*f].is_none()) {
+        let mut layout = vec![start];
+        while let Some(n) = next[*layout.last().unwrap()] {
+            layout.push(n);
+        }
+        let mut contained: Vec<usize> = Vec::new();
+        for f in 0..seqs.len() {
+            let mut c = f;
+            while let Some(parent) = container[c] {
+                c = parent;
+            }
+            if c != f && layout.contains(&c) {
+                contained.push(f);
+            }
+        }
+
+        let mut columns = Columns::new(&seqs[layout[0]]);
+        for f in layout[1..].iter().chain(contained.iter()) {
+            columns.add(&seqs[*f], config);
+        }
+        let (seq, coverage) = columns.consensus();
+        contigs.push(Contig {
+            seq,
+            fragments: layout
+                .iter()
+                .chain(contained.iter())
+                .map(|f| (*f, reversed[*f]))
+                .collect(),
+            coverage,
+        });
+    }
+    contigs
+}
+
+/// orient decides which fragments to reverse complement.
+fn orient<S: AsRef<str>>(fragments: &[S], config: &AssemblyConfig) -> Vec<bool> {
+    let n = fragments.len();
+    let strands: Vec<[String; 2]> = fragments
+        .iter()
+        .map(|f| [f.as_ref().to_string(), reverse_complement(f.as_ref())])
+        .collect();
+
+    // the best score of fragment i forward against fragment j on each strand,
+    // in either order
+    let mut best = vec![[f32::NEG_INFINITY; 2]; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            if i == j {
+                continue;
+            }
+            for (strand, seq) in strands[j].iter().enumerate() {
+                let a = &strands[i][0];
+                let overlaps = [overlap(a, seq, config), overlap(seq, a, config)];
+                let containments = [contains(a, seq, config), contains(seq, a, config)];
+                best[i * n + j][strand] = overlaps
+                    .iter()
+                    .flatten()
+                    .map(|(_, _, score)| *score)
+                    .chain(containments.iter().flatten().copied())
+                    .fold(f32::NEG_INFINITY, f32::max);
+            }
+        }
+    }
+
+    let mut reversed: Vec<Option<bool>> = vec![None; n];
+    if n > 0 {
+        reversed[0] = Some(false);
+    }
+    loop {
+        let mut pick: Option<(f32, usize, bool)> = None;
+        for j in (0..n).filter(|j| reversed[*j].is_none()) {
+            for i in (0..n).filter(|i| reversed[*i].is_some()) {
+                for (strand, score) in best[i * n + j].iter().enumerate() {
+                    if *score > pick.map_or(f32::NEG_INFINITY, |p| p.0) {
+                        // relative to i as it's oriented
+                        pick = Some((*score, j, (strand == 1) != reversed[i].unwrap()));
+                    }
+                }
+            }
+        }
+        match pick {
+            Some((_, j, r)) => reversed[j] = Some(r),
+            None => match reversed.iter().position(|r| r.is_none()) {
+                Some(j) => reversed[j] = Some(false),
+                None => break,
+            },
+        }
+    }
+    reversed.into_iter().map(|r| r.unwrap_or(false)).collect()
+}
+
+/// overlap aligns the end of a to the start of b, and returns the length,
+/// mismatches and score of the overlap if it's good enough.
+fn overlap(a: &str, b: &str, config: &AssemblyConfig) -> Option<(usize, usize, f32)> {
+    let ends = TerminalGaps {
+        a_end: TerminalGap::Free,
+        b_start: TerminalGap::Free,
+        ..Default::default()
+    };
+    let alignment = align_with_ends(a, b, ends, config);
+    let (span, pairs, mismatches) = overlap_span(&alignment)?;
+    let good = pairs >= config.min_overlap
+        && mismatches as f32 <= config.max_mismatch_rate * span.len() as f32;
+    good.then_some((span.len(), mismatches, alignment.score))
+}
+
+/// contains is the score of b aligned within a, if all of it is.
+fn contains(a: &str, b: &str, config: &AssemblyConfig) -> Option<f32> {
+    let ends = TerminalGaps {
+        b_start: TerminalGap::Free,
+        b_end: TerminalGap::Free,
+        ..Default::default()
+    };
+    let alignment = align_with_ends(a, b, ends, config);
+    match overlap_span(&alignment) {
+        Some((span, _, mismatches)) => {
+            let residues = alignment.rows[1].iter().filter(|c| **c != '-').count();
+            let inside = alignment.rows[1][span.clone()]
+                .iter()
+                .filter(|c| **c != '-')
+                .count();
+            let good = inside == residues
+                && residues >= config.min_overlap
+                && mismatches as f32 <= config.max_mismatch_rate * span.len() as f32;
+            good.then_some(alignment.score)
+        }
+        None => None,
+    }
+}
+
+fn align_with_ends(a: &str, b: &str, ends: TerminalGaps, config: &AssemblyConfig) -> Alignment {
+    let scoring = Scoring {
+        terminal_gaps: ends,
+        ..config.scoring.clone()
+    };
+    align_overlap(a, b, &scoring)
+}
+
+/// overlap_span is the columns from the first to the last aligned pair of
+/// residues, the number of pairs, and the number of mismatches and gaps.
+fn overlap_span(alignment: &Alignment) -> Option<(Range<usize>, usize, usize)> {
+    let (a, b) = (&alignment.rows[0], &alignment.rows[1]);
+    let pair = |col: &usize| a[*col] != '-' && b[*col] != '-';
+    let first = (0..a.len()).find(pair)?;
+    let last = (0..a.len()).rfind(pair)?;
+    let pairs = (first..=last).filter(pair).count();
+    let mismatches = (first..=last)
+        .filter(|col| !pair(col) || !a[*col].eq_ignore_ascii_case(&b[*col]))
+        .count();
+    Some((first..last + 1, pairs, mismatches))
+}
+
+/// Columns are the votes for each column of a contig's multiple alignment.
+struct Columns(Vec<Vec<(char, usize)>>);
+
+impl Columns {
+    fn new(seq: &str) -> Self {
+        Columns(seq.chars().map(|c| vec![(c, 1)]).collect())
+    }
+
+    /// add aligns a fragment to the consensus and adds its votes.
+    fn add(&mut self, seq: &str, config: &AssemblyConfig) {
+        // the consensus and the column of each of its residues
+        let (consensus, cols): (String, Vec<usize>) = (0..self.0.len())
+            .filter_map(|c| match winner(&self.0[c]) {
+                '-' => None,
+                residue => Some((residue, c)),
+            })
+            .unzip();
+
+        let ends = TerminalGaps {
+            a_end: TerminalGap::Free,
+            b_start: TerminalGap::Free,
+            b_end: TerminalGap::Free,
+            ..Default::default()
+        };
+        let alignment = align_with_ends(&consensus, seq, ends, config);
+        let (a, b) = (&alignment.rows[0], &alignment.rows[1]);
+        let first = b.iter().position(|c| *c != '-').unwrap_or(b.len());
+        let last = b.iter().rposition(|c| *c != '-').map_or(0, |l| l + 1);
+
+        let mut columns: Vec<Vec<(char, usize)>> = Vec::with_capacity(self.0.len());
+        let mut next = 0; // next column of self.0 to copy
+        let mut residue = 0; // next residue of the consensus
+        for col in 0..a.len() {
+            let inside = first <= col && col < last;
+            if a[col] == '-' {
+                // a residue of the fragment past an end of the consensus, or
+                // between two of its columns where the fragments spanning both
+                // have a gap
+                let depth = match residue {
+                    0 => 0,
+                    r if r == cols.len() => 0,
+                    r => total(&self.0[cols[r - 1]]).min(total(&self.0[cols[r]])),
+                };
+                let mut votes = vec![(b[col], 1)];
+                if depth > 0 {
+                    votes.push(('-', depth));
+                }
+                columns.push(votes);
+                continue;
+            }
+
+            let c = cols[residue];
+            residue += 1;
+            columns.extend(self.0[next..c].iter().cloned());
+            next = c + 1;
+            let mut votes = self.0[c].clone();
+            if inside {
+                vote(&mut votes, b[col]);
+            }
+            columns.push(votes);
+        }
+        columns.extend(self.0[next..].iter().cloned());
+        self.0 = columns;
+    }
+
+    /// consensus is the winning residue of each column where it isn't a gap,
+    /// and how many fragments cover it.
+    fn consensus(&self) -> (String, Vec<usize>) {
+        self.0
+            .iter()
+            .filter_map(|votes| match winner(votes) {
+                '-' => None,
+                residue => Some((residue, total(votes))),
+            })
+            .unzip()
+    }
+}
+
+/// winner is the residue or gap with the most votes, the first on ties.
+fn winner(votes: &[(char, usize)]) -> char {
+    let mut best = votes[0];
+    for v in &votes[1..] {
+        if v.1 > best.1 {
+            best = *v;
+        }
+    }
+    best.0
+}
+
+fn vote(votes: &mut Vec<(char, usize)>, c: char) {
+    match votes.iter_mut().find(|(v, _)| *v == c) {
+        Some(v) => v.1 += 1,
+        None => votes.push((c, 1)),
+    }
+}
+
+fn total(votes: &[(char, usize)]) -> usize {
+    votes.iter().map(|(_, n)| n).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQ: &str = "ATGACCATGATTACGCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATCCCCGGGTACCGAGCTCGAATTCACTGGCCGTCGTTTTACAACGTCGTGACTGGGAAAACCCTGGCG";
+
+    #[test]
+    fn test_assemble() {
+        let fragments = [
+            SEQ[..50].to_string(),
+            reverse_complement(&SEQ[30..90]),
+            SEQ[70..].to_string(),
+            SEQ[40..60].replace("GGATCC", "GGTTCC"),
+        ];
+        let config = AssemblyConfig {
+            min_overlap: 15,
+            max_mismatch_rate: 0.1,
+            ..Default::default()
+        };
+
+        let contigs = assemble(&fragments, &config);
+        assert_eq!(1, contigs.len());
+        assert_eq!(SEQ, contigs[0].seq);
+        assert_eq!(
+            vec![(0, false), (1, true), (2, false), (3, false)],
+            contigs[0].fragments
+        );
+        assert_eq!(1, contigs[0].coverage[0]);
+        assert_eq!(3, contigs[0].coverage[45]);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let fragments = [&SEQ[..50], &SEQ[30..90]];
+        let found = overlaps(&fragments, &AssemblyConfig::default());
+        assert_eq!(1, found.len());
+        assert_eq!(
+            (0, 1, 20, 0),
+            (
+                found[0].from,
+                found[0].to,
+                found[0].len,
+                found[0].mismatches
+            )
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Consensus of noisy long reads of the same locus.
+//!
+//! The reads are built into a partial-order graph, the consensus is its
+//! heaviest bundle, and the multiple alignment the graph implies gives each
+//! position of the consensus how many reads cover it and how many of those
+//! agree with it.
+
+use crate::{
+    align::{MSAlignment, Scoring},
+    matrices::NUC_4_4,
+};
+
+use super::PoaGraph;
+
+/// ConsensusConfig configures the consensus of reads.
+#[derive(Clone, Debug)]
+pub struct ConsensusConfig {
+    /// scoring of the alignments of reads to the graph
+    pub scoring: Scoring,
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        ConsensusConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -8f32,
+                gap_extension: -6f32,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// ReadConsensus is the consensus of reads and how well reads support it.
+#[derive(Clone, Debug)]
+pub struct ReadConsensus {
+    pub consensus: String,
+
+    /// number of reads that span each position of the consensus
+    pub coverage: Vec<usize>,
+
+    /// fraction of the reads spanning each position of the consensus that
+    /// have its residue there, an estimate of its accuracy
+    pub support: Vec<f32>,
+
+    /// the multiple alignment of the reads, IDs from 1 in the order given
+    pub msa: MSAlignment,
+}
+
+/// consensus_from_reads builds the consensus of reads of the same locus.
+///
+/// Reads are added to the graph in the order given, so the first few should
+/// be the longest or most accurate if that's known.
+pub fn consensus_from_reads<S: AsRef<str>>(reads: &[S], config: &ConsensusConfig) -> ReadConsensus {
+    let graph = PoaGraph::from_sequences(reads, &config.scoring);
+    let path = graph.consensus_path();
+    let (columns, _) = graph.columns();
+
+    // the first and last column of each read
+    let spans: Vec<(usize, usize)> = graph
+        .paths
+        .iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let cols = p.iter().map(|n| columns[*n]);
+            (cols.clone().min().unwrap(), cols.max().unwrap())
+        })
+        .collect();
+
+    let mut coverage = Vec::with_capacity(path.len());
+    let mut support = Vec::with_capacity(path.len());
+    for node in &path {
+        let col = columns[*node];
+        let spanning = spans.iter().filter(|(s, e)| *s <= col && col <= *e).count();
+        coverage.push(spanning);
+        support.push(match spanning {
+            0 => 0f32,
+            n => graph.nodes[*node].coverage as f32 / n as f32,
+        });
+    }
+
+    ReadConsensus {
+        consensus: path.iter().map(|n| graph.nodes[*n].base).collect(),
+        coverage,
+        support,
+        msa: graph.to_msa(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consensus_from_reads() {
+        let truth = "ATGGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATGC";
+        let reads = [
+            "ATGGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATGC",
+            "ATGGCTAGCTAGGATCCGTTCGATCGGCTAAGCTTGCATGC",
+            "ATGGCTAGCTAGATCCGATCGATCGGCTAAGCTTGCATGC",
+            "ATGGCTAGCTAGGATCCGATCGATCGGCTAAAGCTTGCATGC",
+            "GGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATGC",
+            "ATGGCTAGCTAGGATCCGATCGAACGGCTAAGCTTGCATGC",
+        ];
+        let consensus = consensus_from_reads(&reads, &ConsensusConfig::default());
+        assert_eq!(truth, consensus.consensus);
+        assert_eq!(6, consensus.msa.rows.len());
+        assert_eq!(5, consensus.coverage[0]);
+        assert_eq!(6, consensus.coverage[10]);
+        assert_eq!(1f32, consensus.support[10]);
+
+        // one read has a T for the A at 18
+        assert_eq!(5f32 / 6f32, consensus.support[18]);
+    }
+}
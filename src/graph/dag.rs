@@ -0,0 +1,161 @@
+//! Alignment of a sequence to a graph of residues with affine gaps.
+//!
+//! The grid has a row per node rather than per residue: each cell looks back
+//! to the same column of every node with an edge to it, and rows are filled
+//! in topological order so those are always done first.
+
+use crate::align::{
+    gotoh::{self, Gap},
+    Scoring,
+};
+
+// traceback states: a residue and a node, a node and a gap, a residue and a gap
+const MATCH: u8 = gotoh::DIAGONAL;
+const DELETE: u8 = gotoh::UP;
+const INSERT: u8 = gotoh::LEFT;
+
+// the predecessor of source nodes: the empty start of the graph
+const START: u32 = u32::MAX;
+
+/// Pairs are aligned pairs of a node and a residue, None where either is a gap.
+pub(super) type Pairs = Vec<(Option<usize>, Option<usize>)>;
+
+/// align_dag aligns a sequence to a directed acyclic graph of residues.
+///
+/// `inputs` holds the nodes with an edge to each node, and `order` is the
+/// nodes in topological order. The whole sequence is aligned. The path
+/// through the graph runs from a source to a sink, or if `free_ends` from
+/// any node to any other without penalizing the rest of the graph.
+///
+/// It returns the aligned pairs and the score of the alignment.
+pub(super) fn align_dag(
+    bases: &[u8],
+    inputs: &[&[usize]],
+    order: &[usize],
+    seq: &str,
+    scoring: &Scoring,
+    free_ends: bool,
+) -> (Pairs, f32) {
+    let seq = seq.as_bytes();
+    let n = seq.len();
+    let (open, extend) = (scoring.gap_opening, scoring.gap_extension);
+    let gap = Gap { open, extend };
+
+    let mut rank = vec![0; bases.len()];
+    let mut sink = vec![true; bases.len()];
+    for (r, node) in order.iter().enumerate() {
+        rank[*node] = r;
+        for p in inputs[*node] {
+            sink[*p] = false;
+        }
+    }
+
+    // scores of each state by row (a node in topological order) and column
+    let width = n + 1;
+    let start = start_row(n, open, extend);
+    let mut scores = vec![[f32::NEG_INFINITY; 3]; order.len() * width];
+    let mut back = vec![(START, MATCH); 3 * order.len() * width];
+    for (r, node) in order.iter().enumerate() {
+        let mut preds: Vec<Option<usize>> = inputs[*node].iter().map(|p| Some(rank[*p])).collect();
+        if preds.is_empty() || free_ends {
+            preds.push(None);
+        }
+        let base = bases[*node] as usize;
+
+        for j in 0..width {
+            let mut cell = [f32::NEG_INFINITY; 3];
+            let mut from = [(START, MATCH); 3];
+            for p in &preds {
+                let id = p.map_or(START, |p| p as u32);
+                if j > 0 {
+                    let x = scoring.matrix[base][seq[j - 1] as usize] as f32;
+                    let (state, score) =
+                        gotoh::diagonal(cell_of(&scores, &start, *p, j - 1), x, false);
+                    if score > cell[MATCH as usize] {
+                        cell[MATCH as usize] = score;
+                        from[MATCH as usize] = (id, state);
+                    }
+                }
+                let (state, score) = gotoh::up(cell_of(&scores, &start, *p, j), gap);
+                if score > cell[DELETE as usize] {
+                    cell[DELETE as usize] = score;
+                    from[DELETE as usize] = (id, state);
+                }
+            }
+            if j > 0 {
+                let left = scores[r * width + j - 1];
+                let (state, score) = gotoh::left(left, gap);
+                cell[INSERT as usize] = score;
+                from[INSERT as usize] = (r as u32, state);
+            }
+            scores[r * width + j] = cell;
+            back[3 * (r * width + j)..3 * (r * width + j + 1)].copy_from_slice(&from);
+        }
+    }
+
+    // end in the best state of the last column of a sink, or of the start if
+    // the graph can be skipped
+    let mut end = (f32::NEG_INFINITY, START, MATCH);
+    if order.is_empty() || free_ends {
+        let (state, score) = gotoh::best(start[n]);
+        end = (score, START, state);
+    }
+    for (r, node) in order.iter().enumerate() {
+        if sink[*node] || free_ends {
+            let (state, score) = gotoh::best(scores[r * width + n]);
+            if score > end.0 {
+                end = (score, r as u32, state);
+            }
+        }
+    }
+
+    let (score, mut r, mut state) = end;
+    let mut j = n;
+    let mut pairs = Vec::new();
+    while r != START {
+        let (prev, prev_state) = back[3 * (r as usize * width + j) + state as usize];
+        match state {
+            MATCH => {
+                pairs.push((Some(order[r as usize]), Some(j - 1)));
+                j -= 1;
+            }
+            DELETE => pairs.push((Some(order[r as usize]), None)),
+            _ => {
+                pairs.push((None, Some(j - 1)));
+                j -= 1;
+            }
+        }
+        r = prev;
+        state = prev_state;
+    }
+    while j > 0 {
+        pairs.push((None, Some(j - 1)));
+        j -= 1;
+    }
+    pairs.reverse();
+
+    (pairs, score)
+}
+
+/// start_row is the score of each state before the first node: only leading
+/// insertions of the sequence.
+fn start_row(n: usize, open: f32, extend: f32) -> Vec<[f32; 3]> {
+    (0..=n)
+        .map(|j| match j {
+            0 => [0f32, f32::NEG_INFINITY, f32::NEG_INFINITY],
+            j => [
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                open + extend * (j - 1) as f32,
+            ],
+        })
+        .collect()
+}
+
+/// cell_of is the scores of a row, or the start row if None, in column j.
+fn cell_of(scores: &[[f32; 3]], start: &[[f32; 3]], row: Option<usize>, j: usize) -> [f32; 3] {
+    match row {
+        None => start[j],
+        Some(r) => scores[r * start.len() + j],
+    }
+}
@@ -0,0 +1,35 @@
+//! Sequence graphs: partial-order alignment of many sequences, and alignment
+//! of sequences to variation graphs.
+
+pub use crate::graph::consensus::consensus_from_reads;
+pub use crate::graph::consensus::ConsensusConfig;
+pub use crate::graph::consensus::ReadConsensus;
+pub use crate::graph::phasing::phase_reads;
+pub use crate::graph::phasing::PhaseConfig;
+pub use crate::graph::phasing::PhasedConsensus;
+pub use crate::graph::poa::Edge;
+pub use crate::graph::poa::PoaAlignment;
+pub use crate::graph::poa::PoaGraph;
+pub use crate::graph::poa::PoaNode;
+pub use crate::graph::seq_graph::GraphAlignment;
+pub use crate::graph::seq_graph::GraphPosition;
+pub use crate::graph::seq_graph::Segment;
+pub use crate::graph::seq_graph::SeqGraph;
+
+use thiserror::Error;
+
+mod consensus;
+mod dag;
+mod phasing;
+mod poa;
+mod seq_graph;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("graph has a cycle through segment {0}")]
+    CyclicGraph(String),
+
+    #[error("segment {0} has no sequence")]
+    EmptySegment(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
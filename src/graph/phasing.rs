@@ -0,0 +1,232 @@
+//! Consensus of reads of a locus with two haplotypes, like the two alleles
+//! of a heterozygous diploid.
+//!
+//! The reads are aligned in a partial-order graph, and the columns of their
+//! alignment where a second allele is carried by enough reads are the sites
+//! that tell the haplotypes apart; a gap is an allele, so indels count too.
+//! Reads are split in two around the pair that differ at the most sites,
+//! then each read moves to the group whose majority alleles it disagrees
+//! with least until no read moves. Each group gets its own consensus.
+
+use std::collections::HashMap;
+
+use crate::align::MSAlignment;
+
+use super::{consensus_from_reads, ConsensusConfig, PoaGraph, ReadConsensus};
+
+/// most rounds of moving reads between the groups
+const MAX_ROUNDS: usize = 20;
+
+/// PhaseConfig configures the phasing of reads into two haplotypes.
+#[derive(Clone, Debug)]
+pub struct PhaseConfig {
+    /// configuration of the alignments and consensus of the reads
+    pub consensus: ConsensusConfig,
+
+    /// fewest reads with the second allele of a column for it to be a site
+    pub min_reads: usize,
+
+    /// smallest fraction of the reads spanning a column with its second
+    /// allele for it to be a site, so the errors of single reads aren't
+    pub min_fraction: f32,
+}
+
+impl Default for PhaseConfig {
+    fn default() -> Self {
+        PhaseConfig {
+            consensus: ConsensusConfig::default(),
+            min_reads: 2,
+            min_fraction: 0.2,
+        }
+    }
+}
+
+/// PhasedConsensus is the consensus of each haplotype of a set of reads.
+#[derive(Clone, Debug)]
+pub struct PhasedConsensus {
+    /// consensus of the reads of each haplotype, one if no sites split them
+    pub haplotypes: Vec<ReadConsensus>,
+
+    /// haplotype of each read, in the order given
+    pub assignments: Vec<usize>,
+
+    /// columns of `msa` that split the haplotypes
+    pub sites: Vec<usize>,
+
+    /// the multiple alignment of all the reads, IDs from 1 in the order given
+    pub msa: MSAlignment,
+}
+
+/// phase_reads splits reads of the same locus into two haplotypes by the
+/// variants they share, and builds the consensus of each.
+pub fn phase_reads<S: AsRef<str>>(reads: &[S], config: &PhaseConfig) -> PhasedConsensus {
+    let msa = PoaGraph::from_sequences(reads, &config.consensus.scoring).to_msa();
+
+    // the allele of each read at each column it spans
+    let alleles: Vec<Vec<Option<char>>> = msa
+        .rows
+        .iter()
+        .map(|row| {
+            let first = row.iter().position(|c| *c != '-').unwrap_or(row.len());
+            let last = row.iter().rposition(|c| *c != '-').unwrap_or(0);
+            row.iter()
+                .enumerate()
+                .map(|(col, c)| (first <= col && col <= last).then_some(*c))
+                .collect()
+        })
+        .collect();
+    let sites: Vec<usize> = (0..msa.len())
+        .filter(|col| is_site(alleles.iter().filter_map(|a| a[*col]), config))
+        .collect();
+    let calls: Vec<Vec<Option<char>>> = alleles
+        .iter()
+        .map(|a| sites.iter().map(|col| a[*col]).collect())
+        .collect();
+
+    let assignments = match seeds(&calls) {
+        Some(seeds) => split(&calls, seeds),
+        None => vec![0; reads.len()],
+    };
+    let groups = assignments.iter().max().map_or(0, |h| h + 1);
+    let haplotypes = (0..groups)
+        .map(|h| {
+            let group: Vec<&str> = reads
+                .iter()
+                .zip(assignments.iter())
+                .filter(|(_, a)| **a == h)
+                .map(|(r, _)| r.as_ref())
+                .collect();
+            consensus_from_reads(&group, &config.consensus)
+        })
+        .collect();
+
+    PhasedConsensus {
+        haplotypes,
+        assignments,
+        sites,
+        msa,
+    }
+}
+
+/// is_site is whether the second allele of a column's alleles is common
+/// enough to split haplotypes.
+fn is_site<I: Iterator<Item = char>>(alleles: I, config: &PhaseConfig) -> bool {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    let mut spanning = 0;
+    for allele in alleles {
+        *counts.entry(allele.to_ascii_uppercase()).or_default() += 1;
+        spanning += 1;
+    }
+    let mut counts: Vec<usize> = counts.into_values().collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    counts.get(1).is_some_and(|second| {
+        *second >= config.min_reads && *second as f32 >= config.min_fraction * spanning as f32
+    })
+}
+
+/// differences between the calls of two reads at the sites both span.
+fn differences(a: &[Option<char>], b: &[Option<char>]) -> usize {
+    a.iter()
+        .zip(b.iter())
+        .filter(|(x, y)| matches!((x, y), (Some(x), Some(y)) if x != y))
+        .count()
+}
+
+/// seeds are the two reads that differ at the most sites, None if no two
+/// differ.
+fn seeds(calls: &[Vec<Option<char>>]) -> Option<(usize, usize)> {
+    let mut best = None;
+    let mut most = 0;
+    for i in 0..calls.len() {
+        for j in i + 1..calls.len() {
+            let d = differences(&calls[i], &calls[j]);
+            if d > most {
+                (best, most) = (Some((i, j)), d);
+            }
+        }
+    }
+    best
+}
+
+/// split assigns each read to the group of the seed, then of the majority
+/// alleles, it differs least from, until no read moves.
+fn split(calls: &[Vec<Option<char>>], seeds: (usize, usize)) -> Vec<usize> {
+    let mut centers = [calls[seeds.0].clone(), calls[seeds.1].clone()];
+    let mut assignments: Vec<usize> = vec![usize::MAX; calls.len()];
+    for _ in 0..MAX_ROUNDS {
+        let next: Vec<usize> = calls
+            .iter()
+            .map(|c| usize::from(differences(c, &centers[1]) < differences(c, &centers[0])))
+            .collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+        for (h, center) in centers.iter_mut().enumerate() {
+            let members: Vec<&Vec<Option<char>>> = calls
+                .iter()
+                .zip(assignments.iter())
+                .filter(|(_, a)| **a == h)
+                .map(|(c, _)| c)
+                .collect();
+            *center = (0..center.len())
+                .map(|site| majority(members.iter().filter_map(|m| m[site])))
+                .collect();
+        }
+    }
+    assignments
+}
+
+/// majority is the most common allele, the first seen on ties.
+fn majority<I: Iterator<Item = char>>(alleles: I) -> Option<char> {
+    let mut counts: Vec<(char, usize)> = vec![];
+    for allele in alleles {
+        match counts.iter_mut().find(|(a, _)| *a == allele) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((allele, 1)),
+        }
+    }
+    counts
+        .iter()
+        .fold(None, |best: Option<(char, usize)>, (a, n)| match best {
+            Some((_, m)) if m >= *n => best,
+            _ => Some((*a, *n)),
+        })
+        .map(|(a, _)| a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_reads() {
+        let one = "ATGGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATGC";
+        // an SNV at 10 and a 2bp deletion at 30
+        let two = "ATGGCTAGCTTGGATCCGATCGATCGGCTAGCTTGCATGC";
+        let reads = [
+            one,
+            two,
+            "ATGGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATG",
+            "TGGCTAGCTTGGATCCGATCGATCGGCTAGCTTGCATGC",
+            "ATGGCTAGCTAGGATCCGATCGTTCGGCTAAGCTTGCATGC",
+            "ATGGCTAGCTTGGATCCGATCGATCGGCTAGCTTGCATGC",
+            "ATGGCTAGCTTGGATCCGATCGATCCGCTAGCTTGCATGC",
+            "ATGGCTAGCTAGGATCCGATCGATCGGCTAAGCTTGCATGC",
+        ];
+
+        let phased = phase_reads(&reads, &PhaseConfig::default());
+        assert_eq!(2, phased.haplotypes.len());
+        assert_eq!(vec![0, 1, 0, 1, 0, 1, 1, 0], phased.assignments);
+        assert_eq!(one, phased.haplotypes[0].consensus);
+        assert_eq!(two, phased.haplotypes[1].consensus);
+        // the SNV, and the deletion stays one site or two
+        assert!((2..=3).contains(&phased.sites.len()));
+
+        let same = phase_reads(&[one, one, one], &PhaseConfig::default());
+        assert_eq!(
+            (1, vec![0, 0, 0]),
+            (same.haplotypes.len(), same.assignments)
+        );
+    }
+}
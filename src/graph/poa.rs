@@ -0,0 +1,377 @@
+//! Partial-order alignment (POA).
+//! https://doi.org/10.1093/bioinformatics/18.3.452
+//!
+//! A partial-order graph has a node per residue and an edge between residues
+//! that follow each other in some sequence. Each new sequence is aligned to
+//! the whole graph, rather than to a single consensus, and fused into it:
+//! residues that match an aligned node share it, and the rest become new
+//! nodes. Edges are weighted by how many sequences take them, so the
+//! heaviest bundle of edges through the graph is the consensus.
+
+use crate::align::{MSAlignment, Scoring};
+
+use super::dag::align_dag;
+
+/// Edge is a weighted edge to another node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    /// index of the node the edge goes to
+    pub to: usize,
+
+    /// number of sequences that take the edge
+    pub weight: usize,
+}
+
+/// PoaNode is a residue in a partial-order graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoaNode {
+    pub base: char,
+
+    /// edges to the nodes that follow this one
+    pub out: Vec<Edge>,
+
+    /// indexes of the nodes with an edge to this one
+    pub inputs: Vec<usize>,
+
+    /// nodes with other residues aligned to this one
+    pub aligned: Vec<usize>,
+
+    /// number of sequences through the node
+    pub coverage: usize,
+}
+
+/// PoaGraph is a partial-order graph of aligned sequences.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoaGraph {
+    pub nodes: Vec<PoaNode>,
+
+    /// the nodes of each sequence added to the graph, in order
+    pub paths: Vec<Vec<usize>>,
+}
+
+/// PoaAlignment is the global alignment of a sequence to a graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoaAlignment {
+    /// aligned pairs of a node of the graph and a residue of the sequence,
+    /// None where either is a gap
+    pub pairs: Vec<(Option<usize>, Option<usize>)>,
+
+    pub score: f32,
+}
+
+impl PoaGraph {
+    /// new creates an empty graph.
+    pub fn new() -> Self {
+        PoaGraph::default()
+    }
+
+    /// from_sequences creates a graph by adding each sequence in order.
+    pub fn from_sequences<I, S>(seqs: I, scoring: &Scoring) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut graph = PoaGraph::new();
+        for seq in seqs {
+            graph.add(seq.as_ref(), scoring);
+        }
+        graph
+    }
+
+    /// add aligns a sequence to the graph and fuses it in.
+    pub fn add(&mut self, seq: &str, scoring: &Scoring) {
+        let alignment = self.align(seq, scoring);
+        self.add_alignment(seq, &alignment);
+    }
+
+    /// add_alignment fuses a sequence into the graph along its alignment.
+    pub fn add_alignment(&mut self, seq: &str, alignment: &PoaAlignment) {
+        let seq: Vec<char> = seq.chars().collect();
+        let mut path = Vec::with_capacity(seq.len());
+        for (node, pos) in &alignment.pairs {
+            let Some(pos) = pos else {
+                continue;
+            };
+            let base = seq[*pos];
+            let node = match node {
+                Some(node) if self.nodes[*node].base == base => *node,
+                Some(node) => {
+                    let existing = self.nodes[*node]
+                        .aligned
+                        .iter()
+                        .find(|n| self.nodes[**n].base == base)
+                        .copied();
+                    existing.unwrap_or_else(|| self.add_aligned_node(base, *node))
+                }
+                None => self.add_node(base),
+            };
+            path.push(node);
+        }
+
+        for (i, node) in path.iter().enumerate() {
+            self.nodes[*node].coverage += 1;
+            if i > 0 {
+                self.add_edge(path[i - 1], *node);
+            }
+        }
+        self.paths.push(path);
+    }
+
+    /// align aligns a sequence globally to the graph, from a source node to a
+    /// sink node, with affine gaps.
+    pub fn align(&self, seq: &str, scoring: &Scoring) -> PoaAlignment {
+        let bases: Vec<u8> = self.nodes.iter().map(|n| n.base as u8).collect();
+        let inputs: Vec<&[usize]> = self.nodes.iter().map(|n| n.inputs.as_slice()).collect();
+        let (pairs, score) = align_dag(
+            &bases,
+            &inputs,
+            &self.topological_order(),
+            seq,
+            scoring,
+            false,
+        );
+        PoaAlignment { pairs, score }
+    }
+
+    /// consensus is the heaviest bundle through the graph.
+    ///
+    /// Each node follows the predecessor with the heaviest edge to it, ties
+    /// going to the predecessor with the heavier path, and the consensus is
+    /// the path to the node with the heaviest path of all.
+    pub fn consensus(&self) -> String {
+        self.consensus_path()
+            .iter()
+            .map(|n| self.nodes[*n].base)
+            .collect()
+    }
+
+    /// consensus_path is the nodes of the consensus, in order.
+    pub fn consensus_path(&self) -> Vec<usize> {
+        let mut weight = vec![0usize; self.nodes.len()];
+        let mut pred: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        for node in self.topological_order() {
+            for p in &self.nodes[node].inputs {
+                let w = self.edge_weight(*p, node);
+                let better = match pred[node] {
+                    None => true,
+                    Some(q) => {
+                        let qw = self.edge_weight(q, node);
+                        w > qw || (w == qw && weight[*p] > weight[q])
+                    }
+                };
+                if better {
+                    pred[node] = Some(*p);
+                }
+            }
+            if let Some(p) = pred[node] {
+                weight[node] = weight[p] + self.edge_weight(p, node);
+            }
+        }
+
+        let Some(mut node) = (0..self.nodes.len()).max_by_key(|n| (weight[*n], usize::MAX - n))
+        else {
+            return vec![];
+        };
+        let mut path = vec![node];
+        while let Some(p) = pred[node] {
+            path.push(p);
+            node = p;
+        }
+        path.reverse();
+        path
+    }
+
+    /// topological_order is every node after all the nodes with edges to it.
+    pub fn topological_order(&self) -> Vec<usize> {
+        let mut inputs: Vec<usize> = self.nodes.iter().map(|n| n.inputs.len()).collect();
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .rev()
+            .filter(|n| inputs[*n] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(node) = ready.pop() {
+            order.push(node);
+            for edge in self.nodes[node].out.iter().rev() {
+                inputs[edge.to] -= 1;
+                if inputs[edge.to] == 0 {
+                    ready.push(edge.to);
+                }
+            }
+        }
+        order
+    }
+
+    /// columns is the column of each node in a multiple alignment of the
+    /// sequences, and the number of columns.
+    ///
+    /// Aligned nodes share a column, and columns are in topological order of
+    /// the groups of aligned nodes.
+    pub fn columns(&self) -> (Vec<usize>, usize) {
+        let group: Vec<usize> = (0..self.nodes.len())
+            .map(|n| self.nodes[n].aligned.iter().fold(n, |g, a| g.min(*a)))
+            .collect();
+        let mut inputs = vec![0; self.nodes.len()];
+        for (n, node) in self.nodes.iter().enumerate() {
+            inputs[group[n]] += node
+                .inputs
+                .iter()
+                .filter(|p| group[**p] != group[n])
+                .count();
+        }
+
+        let mut members = vec![Vec::new(); self.nodes.len()];
+        for (n, g) in group.iter().enumerate() {
+            members[*g].push(n);
+        }
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .rev()
+            .filter(|n| group[*n] == *n && inputs[*n] == 0)
+            .collect();
+        let mut columns = vec![0; self.nodes.len()];
+        let mut count = 0;
+        while let Some(g) = ready.pop() {
+            for n in &members[g] {
+                columns[*n] = count;
+                for edge in self.nodes[*n].out.iter().rev() {
+                    let to = group[edge.to];
+                    if to != g {
+                        inputs[to] -= 1;
+                        if inputs[to] == 0 {
+                            ready.push(to);
+                        }
+                    }
+                }
+            }
+            count += 1;
+        }
+        (columns, count)
+    }
+
+    /// to_msa is the multiple alignment of the sequences in the graph, with
+    /// IDs from 1 in the order they were added.
+    pub fn to_msa(&self) -> MSAlignment {
+        let (columns, count) = self.columns();
+        let rows = self
+            .paths
+            .iter()
+            .map(|path| {
+                let mut row = vec!['-'; count];
+                for n in path {
+                    row[columns[*n]] = self.nodes[*n].base;
+                }
+                row
+            })
+            .collect();
+        let ids = (1..=self.paths.len()).map(|i| i.to_string()).collect();
+        MSAlignment::new(ids, rows)
+    }
+
+    fn edge_weight(&self, from: usize, to: usize) -> usize {
+        self.nodes[from]
+            .out
+            .iter()
+            .find(|e| e.to == to)
+            .map_or(0, |e| e.weight)
+    }
+
+    fn add_node(&mut self, base: char) -> usize {
+        self.nodes.push(PoaNode {
+            base,
+            ..Default::default()
+        });
+        self.nodes.len() - 1
+    }
+
+    /// add_aligned_node adds a node aligned to another and everything aligned to it.
+    fn add_aligned_node(&mut self, base: char, to: usize) -> usize {
+        let node = self.add_node(base);
+        let mut group = self.nodes[to].aligned.clone();
+        group.push(to);
+        for other in &group {
+            self.nodes[*other].aligned.push(node);
+        }
+        self.nodes[node].aligned = group;
+        node
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if let Some(edge) = self.nodes[from].out.iter_mut().find(|e| e.to == to) {
+            edge.weight += 1;
+            return;
+        }
+        self.nodes[from].out.push(Edge { to, weight: 1 });
+        self.nodes[to].inputs.push(from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    fn scoring() -> Scoring {
+        Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -8f32,
+            gap_extension: -2f32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_poa_consensus() {
+        let graph = PoaGraph::from_sequences(
+            [
+                "ACGTACGTACGT",
+                "ACGTACCTACGT",
+                "ACGTACGTACGT",
+                "ACGTAGTACGT",
+                "ACGTACGTTACGT",
+            ],
+            &scoring(),
+        );
+        assert_eq!("ACGTACGTACGT", graph.consensus());
+        assert_eq!(5, graph.paths.len());
+
+        // the C/G mismatch is a pair of aligned nodes
+        let mismatch = graph
+            .nodes
+            .iter()
+            .position(|n| !n.aligned.is_empty())
+            .unwrap();
+        assert_eq!(1, graph.nodes[mismatch].aligned.len());
+        assert_eq!(graph.paths[0].len(), 12);
+        assert_eq!(graph.paths[4].len(), 13);
+        assert_eq!(graph.nodes.len(), 14);
+
+        let msa = graph.to_msa();
+        assert_eq!(
+            "ACGTACG-TACGT ACGTACC-TACGT ACGTACG-TACGT ACGTA-G-TACGT ACGTACGTTACGT",
+            msa.rows
+                .iter()
+                .map(|r| r.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+
+    #[test]
+    fn test_poa_align() {
+        let mut graph = PoaGraph::new();
+        graph.add("ACGT", &scoring());
+        graph.add("AGGT", &scoring());
+
+        // the graph holds both the C and G in the second position
+        let alignment = graph.align("AGGT", &scoring());
+        assert_eq!(20f32, alignment.score);
+        assert_eq!(
+            graph.paths[1],
+            alignment
+                .pairs
+                .iter()
+                .map(|(n, _)| n.unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Alignment of sequences to variation graphs.
+//!
+//! A variation graph has a node per segment of sequence, and a link from one
+//! segment to another where the sequence of the first can be followed by the
+//! second, like the GFA graphs of pangenome tools. Paths through the graph spell
+//! the haplotypes it holds, so aligning a read to the graph rather than to one
+//! reference finds the haplotype it's closest to.
+
+use std::ops::Range;
+
+use crate::align::Scoring;
+
+use super::{dag::align_dag, Error, Result};
+
+/// Segment is a named sequence in a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub seq: String,
+}
+
+/// SeqGraph is a directed graph of sequence segments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeqGraph {
+    pub segments: Vec<Segment>,
+
+    /// links from the end of one segment to the start of another, by index
+    pub links: Vec<(usize, usize)>,
+}
+
+/// GraphPosition is a residue of a graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphPosition {
+    /// index of the segment
+    pub segment: usize,
+
+    /// 0-based offset in the segment
+    pub offset: usize,
+}
+
+/// GraphAlignment is the alignment of a sequence to a path through a graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphAlignment {
+    /// aligned pairs of a residue of the graph and of the sequence, None
+    /// where either is a gap
+    pub pairs: Vec<(Option<GraphPosition>, Option<usize>)>,
+
+    /// the segments the alignment passes through, in order
+    pub path: Vec<usize>,
+
+    /// 0-based, half-open region of the first segment of the path in the
+    /// alignment, and of the last segment
+    pub ends: (Range<usize>, Range<usize>),
+
+    pub score: f32,
+}
+
+impl SeqGraph {
+    /// new creates an empty graph.
+    pub fn new() -> Self {
+        SeqGraph::default()
+    }
+
+    /// add_segment adds a segment and returns its index.
+    pub fn add_segment(&mut self, name: &str, seq: &str) -> usize {
+        self.segments.push(Segment {
+            name: name.to_string(),
+            seq: seq.to_string(),
+        });
+        self.segments.len() - 1
+    }
+
+    /// add_link links the end of one segment to the start of another.
+    pub fn add_link(&mut self, from: usize, to: usize) {
+        if !self.links.contains(&(from, to)) {
+            self.links.push((from, to));
+        }
+    }
+
+    /// segment is the index of the segment with a name.
+    pub fn segment(&self, name: &str) -> Option<usize> {
+        self.segments.iter().position(|s| s.name == name)
+    }
+
+    /// topological_order is every segment after all the segments linked to it.
+    ///
+    /// It's an error if the links form a cycle.
+    pub fn topological_order(&self) -> Result<Vec<usize>> {
+        let mut inputs = vec![0; self.segments.len()];
+        let mut out = vec![Vec::new(); self.segments.len()];
+        for (from, to) in &self.links {
+            inputs[*to] += 1;
+            out[*from].push(*to);
+        }
+
+        let mut ready: Vec<usize> = (0..self.segments.len())
+            .rev()
+            .filter(|s| inputs[*s] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.segments.len());
+        while let Some(segment) = ready.pop() {
+            order.push(segment);
+            for to in out[segment].iter().rev() {
+                inputs[*to] -= 1;
+                if inputs[*to] == 0 {
+                    ready.push(*to);
+                }
+            }
+        }
+
+        match (0..self.segments.len()).find(|s| inputs[*s] > 0) {
+            Some(s) => Err(Error::CyclicGraph(self.segments[s].name.clone())),
+            None => Ok(order),
+        }
+    }
+
+    /// align aligns all of a sequence to its best path through the graph.
+    ///
+    /// The path can start and end anywhere in the graph without penalty, so
+    /// reads are aligned to the region of the graph they come from. It's an
+    /// error if the graph has a cycle or an empty segment.
+    pub fn align(&self, seq: &str, scoring: &Scoring) -> Result<GraphAlignment> {
+        if let Some(empty) = self.segments.iter().find(|s| s.seq.is_empty()) {
+            return Err(Error::EmptySegment(empty.name.clone()));
+        }
+        let order = self.topological_order()?;
+
+        // a node per residue, numbered by segment then offset
+        let mut first = Vec::with_capacity(self.segments.len());
+        let mut positions = Vec::new();
+        let mut bases = Vec::new();
+        for (segment, s) in self.segments.iter().enumerate() {
+            first.push(bases.len());
+            for (offset, base) in s.seq.bytes().enumerate() {
+                positions.push(GraphPosition { segment, offset });
+                bases.push(base);
+            }
+        }
+        let last = |s: usize| first[s] + self.segments[s].seq.len() - 1;
+
+        let mut inputs: Vec<Vec<usize>> = (0..bases.len())
+            .map(|n| match positions[n].offset {
+                0 => vec![],
+                _ => vec![n - 1],
+            })
+            .collect();
+        for (from, to) in &self.links {
+            inputs[first[*to]].push(last(*from));
+        }
+        let residues: Vec<usize> = order.iter().flat_map(|s| first[*s]..=last(*s)).collect();
+        let inputs: Vec<&[usize]> = inputs.iter().map(|i| i.as_slice()).collect();
+
+        let (pairs, score) = align_dag(&bases, &inputs, &residues, seq, scoring, true);
+        let pairs: Vec<(Option<GraphPosition>, Option<usize>)> = pairs
+            .into_iter()
+            .map(|(node, pos)| (node.map(|n| positions[n]), pos))
+            .collect();
+
+        let mut path: Vec<usize> = Vec::new();
+        let mut ends = (0..0, 0..0);
+        for position in pairs.iter().filter_map(|(p, _)| *p) {
+            if path.last() != Some(&position.segment) {
+                if path.is_empty() {
+                    ends.0 = position.offset..position.offset;
+                }
+                path.push(position.segment);
+                ends.1 = position.offset..position.offset;
+            }
+            if path.len() == 1 {
+                ends.0.end = position.offset + 1;
+            }
+            ends.1.end = position.offset + 1;
+        }
+
+        Ok(GraphAlignment {
+            pairs,
+            path,
+            ends,
+            score,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_align_to_graph() {
+        // a SNP bubble and an optional insertion
+        let mut graph = SeqGraph::new();
+        let start = graph.add_segment("1", "ACGTACGGAT");
+        let ref_allele = graph.add_segment("2", "C");
+        let alt_allele = graph.add_segment("3", "T");
+        let middle = graph.add_segment("4", "TTAGCATG");
+        let insertion = graph.add_segment("5", "GGG");
+        let end = graph.add_segment("6", "CATCCAGT");
+        graph.add_link(start, ref_allele);
+        graph.add_link(start, alt_allele);
+        graph.add_link(ref_allele, middle);
+        graph.add_link(alt_allele, middle);
+        graph.add_link(middle, insertion);
+        graph.add_link(insertion, end);
+        graph.add_link(middle, end);
+
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -8f32,
+            gap_extension: -2f32,
+            ..Default::default()
+        };
+        let alignment = graph.align("ACGGATTTTAGCATGGGGCATC", &scoring).unwrap();
+        assert_eq!(
+            vec![start, alt_allele, middle, insertion, end],
+            alignment.path
+        );
+        assert_eq!((4..10, 0..4), alignment.ends);
+        assert_eq!(22f32 * 5f32, alignment.score);
+        assert!(alignment
+            .pairs
+            .iter()
+            .all(|(g, s)| g.is_some() && s.is_some()));
+
+        let alignment = graph.align("GGATCTTAGCATGCATCC", &scoring).unwrap();
+        assert_eq!(vec![start, ref_allele, middle, end], alignment.path);
+
+        graph.add_link(end, start);
+        assert!(matches!(
+            graph.align("ACGT", &scoring),
+            Err(Error::CyclicGraph(_))
+        ));
+    }
+}
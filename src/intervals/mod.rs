@@ -0,0 +1,382 @@
+//! Genomic intervals: BED files, sets of intervals, and the intervals an
+//! alignment covers.
+//! https://samtools.github.io/hts-specs/BEDv1.pdf
+//!
+//! Intervals are 0-based and half-open, like BED. The first three columns of
+//! a BED line are read, and the name, then the rest of the columns as text so
+//! they're written back as they were. Sets merge overlapping and adjacent
+//! intervals per chromosome, so their operations work on bases regardless of
+//! how the intervals were split in the file.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{align::Alignment, io::compression};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid BED line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write BED")]
+    ReadError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Interval is a region of a chromosome, a line of a BED file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interval {
+    pub chrom: String,
+    pub start: usize,
+    pub end: usize,
+
+    /// name, the fourth column
+    pub name: Option<String>,
+
+    /// the columns after the name
+    pub rest: Vec<String>,
+}
+
+impl Interval {
+    /// new creates an unnamed interval.
+    pub fn new(chrom: &str, start: usize, end: usize) -> Self {
+        Interval {
+            chrom: chrom.to_string(),
+            start,
+            end,
+            ..Default::default()
+        }
+    }
+
+    /// range of the interval in its chromosome.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// len is the number of bases in the interval.
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// is_empty is true if the interval has no bases.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// read parses the intervals of a BED file, skipping comment, `track` and
+/// `browser` lines.
+pub fn read<R: io::Read>(reader: R) -> Result<Vec<Interval>> {
+    let mut intervals = vec![];
+    for (i, line) in io::BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let position = |k: usize| fields.get(k).and_then(|f| f.trim().parse().ok());
+        let (Some(start), Some(end)) = (position(1), position(2)) else {
+            return Err(Error::InvalidLine(i + 1, line));
+        };
+        if start > end {
+            return Err(Error::InvalidLine(i + 1, line));
+        }
+        intervals.push(Interval {
+            chrom: fields[0].to_string(),
+            start,
+            end,
+            name: fields.get(3).map(|n| n.to_string()),
+            rest: fields.iter().skip(4).map(|f| f.to_string()).collect(),
+        });
+    }
+    Ok(intervals)
+}
+
+/// from_path reads a BED file, decompressing it if it's compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Interval>> {
+    let path = path.as_ref();
+    let reader = compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    read(reader)
+}
+
+/// write intervals as BED to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write<W: io::Write>(intervals: &[Interval], mut w: W) -> Result<()> {
+    for interval in intervals {
+        write!(
+            w,
+            "{}\t{}\t{}",
+            interval.chrom, interval.start, interval.end
+        )?;
+        if let Some(name) = &interval.name {
+            write!(w, "\t{}", name)?;
+        }
+        for field in interval.rest.iter() {
+            write!(w, "\t{}", field)?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// IntervalSet is the bases of a set of intervals, merged per chromosome.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    /// sorted ranges of each chromosome that don't overlap or touch
+    ranges: BTreeMap<String, Vec<Range<usize>>>,
+}
+
+impl IntervalSet {
+    /// new creates the set of the bases of intervals.
+    pub fn new<'a, I: IntoIterator<Item = &'a Interval>>(intervals: I) -> Self {
+        let mut ranges: BTreeMap<String, Vec<Range<usize>>> = BTreeMap::new();
+        for interval in intervals.into_iter().filter(|i| !i.is_empty()) {
+            ranges
+                .entry(interval.chrom.clone())
+                .or_default()
+                .push(interval.range());
+        }
+        for chrom in ranges.values_mut() {
+            *chrom = merge(std::mem::take(chrom));
+        }
+        IntervalSet { ranges }
+    }
+
+    /// intervals of the set, by chromosome then start.
+    pub fn intervals(&self) -> Vec<Interval> {
+        self.ranges
+            .iter()
+            .flat_map(|(chrom, ranges)| ranges.iter().map(|r| Interval::new(chrom, r.start, r.end)))
+            .collect()
+    }
+
+    /// len is the number of bases in the set.
+    pub fn len(&self) -> usize {
+        self.ranges.values().flatten().map(|r| r.len()).sum()
+    }
+
+    /// is_empty is true if the set has no bases.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// overlaps is whether the set has a base of a region.
+    pub fn overlaps(&self, chrom: &str, range: Range<usize>) -> bool {
+        self.ranges.get(chrom).is_some_and(|ranges| {
+            let i = ranges.partition_point(|r| r.end <= range.start);
+            ranges.get(i).is_some_and(|r| r.start < range.end)
+        })
+    }
+
+    /// union is the bases in either set.
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = self.ranges.clone();
+        for (chrom, other) in other.ranges.iter() {
+            let chrom = ranges.entry(chrom.clone()).or_default();
+            chrom.extend(other.iter().cloned());
+            *chrom = merge(std::mem::take(chrom));
+        }
+        IntervalSet { ranges }
+    }
+
+    /// intersect is the bases in both sets.
+    pub fn intersect(&self, other: &IntervalSet) -> IntervalSet {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// subtract is the bases in this set but not the other.
+    pub fn subtract(&self, other: &IntervalSet) -> IntervalSet {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    /// combine sweeps the ends of the ranges of both sets per chromosome,
+    /// keeping the bases where `keep` of being in each set is true.
+    fn combine(&self, other: &IntervalSet, keep: fn(bool, bool) -> bool) -> IntervalSet {
+        let empty = vec![];
+        let mut ranges = BTreeMap::new();
+        for (chrom, a) in self.ranges.iter() {
+            let b = other.ranges.get(chrom).unwrap_or(&empty);
+            let mut ends: Vec<usize> = a
+                .iter()
+                .chain(b.iter())
+                .flat_map(|r| [r.start, r.end])
+                .collect();
+            ends.sort_unstable();
+            ends.dedup();
+
+            let inside = |ranges: &[Range<usize>], pos: usize| {
+                let i = ranges.partition_point(|r| r.end <= pos);
+                ranges.get(i).is_some_and(|r| r.start <= pos)
+            };
+            let kept: Vec<Range<usize>> = ends
+                .windows(2)
+                .filter(|w| keep(inside(a, w[0]), inside(b, w[0])))
+                .map(|w| w[0]..w[1])
+                .collect();
+            let kept = merge(kept);
+            if !kept.is_empty() {
+                ranges.insert(chrom.clone(), kept);
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+/// merge sorts ranges and joins those that overlap or touch.
+fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// IntervalCoverage is how an alignment covers an interval of one of its
+/// sequences.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntervalCoverage {
+    pub interval: Interval,
+
+    /// the part of the interval the alignment spans
+    pub covered: Range<usize>,
+
+    /// fraction of the interval the alignment spans
+    pub coverage: f32,
+
+    /// percent identity of the other sequence over the columns of the part
+    /// of the interval the alignment spans, gaps included
+    pub identity: f32,
+}
+
+impl Alignment {
+    /// interval_coverage finds the intervals of a chromosome the residues of
+    /// a row cover, when the row's first residue is at `offset` in it, and
+    /// at what identity the other row aligns to each.
+    pub fn interval_coverage(
+        &self,
+        row: usize,
+        chrom: &str,
+        offset: usize,
+        intervals: &[Interval],
+    ) -> Vec<IntervalCoverage> {
+        let mut offsets = vec![0; self.rows.len()];
+        offsets[row] = offset;
+        let map = crate::align::CoordinateMap::with_offsets(&self.rows, &offsets);
+        let span = offset..offset + map.len(row);
+        let other = &self.rows[1 - row.min(1)];
+
+        intervals
+            .iter()
+            .filter(|i| i.chrom == chrom && i.start < span.end && span.start < i.end)
+            .filter_map(|interval| {
+                let covered = interval.start.max(span.start)..interval.end.min(span.end);
+                let (first, last) = map.column_range(row, (covered.start, covered.end))?;
+                let columns =
+                    (first..last).filter(|c| self.rows[row][*c] != '-' || other[*c] != '-');
+                let (mut identical, mut total) = (0, 0);
+                for col in columns {
+                    total += 1;
+                    identical += usize::from(
+                        other[col] != '-' && other[col].eq_ignore_ascii_case(&self.rows[row][col]),
+                    );
+                }
+                Some(IntervalCoverage {
+                    coverage: covered.len() as f32 / interval.len() as f32,
+                    identity: 100f32 * identical as f32 / total as f32,
+                    covered,
+                    interval: interval.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bed_and_sets() {
+        let bed = "track name=genes
+chr1\t10\t20\tgeneA\t0\t+
+chr1\t15\t30
+chr2\t0\t5\tgeneB
+";
+        let intervals = read(bed.as_bytes()).unwrap();
+        assert_eq!(3, intervals.len());
+        assert_eq!(Some("geneA".to_string()), intervals[0].name);
+        let mut out = vec![];
+        write(&intervals, &mut out).unwrap();
+        assert_eq!(&bed[17..], String::from_utf8(out).unwrap());
+        assert!(matches!(
+            read("chr1\t20\t10\n".as_bytes()),
+            Err(Error::InvalidLine(1, _))
+        ));
+
+        let a = IntervalSet::new(&intervals);
+        assert_eq!(
+            vec![Interval::new("chr1", 10, 30), Interval::new("chr2", 0, 5)],
+            a.intervals()
+        );
+        assert_eq!(25, a.len());
+        let b = IntervalSet::new(&[Interval::new("chr1", 25, 40), Interval::new("chr1", 0, 12)]);
+        assert_eq!(
+            vec![Interval::new("chr1", 10, 12), Interval::new("chr1", 25, 30)],
+            a.intersect(&b).intervals()
+        );
+        assert_eq!(
+            vec![Interval::new("chr1", 12, 25), Interval::new("chr2", 0, 5)],
+            a.subtract(&b).intervals()
+        );
+        assert_eq!(45, a.union(&b).len());
+        assert!(a.overlaps("chr1", 29..31) && !a.overlaps("chr1", 30..40));
+    }
+
+    #[test]
+    fn test_interval_coverage() {
+        // the reference from position 100, and a read with a mismatch and a deletion
+        let alignment = Alignment::new(
+            vec![
+                "ACGTACGTACGTACGT".chars().collect(),
+                "ACGTACCTAC--ACGT".chars().collect(),
+            ],
+            vec![vec![]],
+            0f32,
+        );
+        let features = [
+            Interval::new("chr1", 90, 104),
+            Interval::new("chr1", 104, 112),
+            Interval::new("chr1", 112, 120),
+            Interval::new("chr2", 100, 110),
+        ];
+        let covered = alignment.interval_coverage(0, "chr1", 100, &features);
+        assert_eq!(3, covered.len());
+        assert_eq!(
+            (100..104, 4f32 / 14f32),
+            (covered[0].covered.clone(), covered[0].coverage)
+        );
+        assert_eq!(100f32, covered[0].identity);
+        assert_eq!(100f32 * 5f32 / 8f32, covered[1].identity);
+        assert_eq!((0.5, 100f32), (covered[2].coverage, covered[2].identity));
+    }
+}
@@ -0,0 +1,274 @@
+//! An A2M reader and writer for multiple alignments.
+//! https://compbio.soe.ucsc.edu/a2m-desc.html
+//!
+//! A2M is aligned FASTA that marks which columns are match states of a
+//! profile: residues in match columns are uppercase and gaps there are `-`,
+//! while residues in insert columns are lowercase and gaps there are `.`.
+//! The match columns are the residues of a reference row, or the columns
+//! with enough residues.
+//!
+//! Since the `.` gaps of insert columns may be left out, rows can differ in
+//! length. They're read back into columns by padding each row's inserts
+//! after a match column to the longest. Residues keep their case, and the
+//! match columns are kept as Stockholm `GC:RF` markup in the metadata: `x`
+//! for a match column and `.` for an insert column.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::align::MSAlignment;
+
+use super::{compression, fasta};
+
+/// residues per line of a sequence
+const LINE_WIDTH: usize = 60;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("no row with ID {0} to take match columns from")]
+    UnknownReference(String),
+
+    #[error("{found} match columns for an alignment of {expected}")]
+    WrongLength { expected: usize, found: usize },
+
+    #[error("sequence {id} has {found} match columns, expected {expected}")]
+    MatchMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write A2M")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// MatchColumns picks the columns of an alignment that are match states.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatchColumns {
+    /// columns with a residue in the row with this ID
+    Reference(String),
+
+    /// columns where at least this fraction of rows have a residue
+    Occupancy(f32),
+
+    /// columns that are true, one per column
+    Mask(Vec<bool>),
+}
+
+impl MatchColumns {
+    /// mask of the match columns of an alignment.
+    pub fn mask(&self, msa: &MSAlignment) -> Result<Vec<bool>> {
+        match self {
+            MatchColumns::Reference(id) => {
+                let row = msa
+                    .row(id)
+                    .ok_or_else(|| Error::UnknownReference(id.clone()))?;
+                Ok(msa.rows[row].iter().map(|c| *c != '-').collect())
+            }
+            MatchColumns::Occupancy(fraction) => Ok((0..msa.len())
+                .map(|col| {
+                    let residues = msa.rows.iter().filter(|r| r[col] != '-').count();
+                    residues as f32 >= fraction * msa.rows.len() as f32
+                })
+                .collect()),
+            MatchColumns::Mask(mask) if mask.len() != msa.len() => Err(Error::WrongLength {
+                expected: msa.len(),
+                found: mask.len(),
+            }),
+            MatchColumns::Mask(mask) => Ok(mask.clone()),
+        }
+    }
+}
+
+/// read parses an A2M alignment from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read<R: io::Read>(reader: R) -> Result<MSAlignment> {
+    // each row's inserts before every match column and after the last, and
+    // its match columns
+    let mut ids = vec![];
+    let mut records: Vec<(Vec<Vec<char>>, Vec<char>)> = vec![];
+    for record in fasta::Reader::new(reader) {
+        let record = record?;
+        let (mut inserts, mut matches) = (vec![vec![]], vec![]);
+        for c in record.seq.chars() {
+            match c {
+                '.' => inserts.last_mut().unwrap().push('-'),
+                c if c.is_lowercase() => inserts.last_mut().unwrap().push(c),
+                c => {
+                    matches.push(c);
+                    inserts.push(vec![]);
+                }
+            }
+        }
+        if let Some((_, first)) = records.first() {
+            if matches.len() != first.len() {
+                return Err(Error::MatchMismatch {
+                    id: record.id().to_string(),
+                    expected: first.len(),
+                    found: matches.len(),
+                });
+            }
+        }
+        ids.push(record.id().to_string());
+        records.push((inserts, matches));
+    }
+
+    if records.is_empty() {
+        return Ok(MSAlignment::default());
+    }
+
+    // the insert columns after each match column are as wide as the most
+    // inserted there
+    let matches = records.first().map_or(0, |(_, m)| m.len());
+    let widths: Vec<usize> = (0..=matches)
+        .map(|i| {
+            records
+                .iter()
+                .map(|(ins, _)| ins[i].len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let rows = records
+        .into_iter()
+        .map(|(inserts, matches)| {
+            let mut row = Vec::with_capacity(widths.iter().sum::<usize>() + matches.len());
+            for (i, mut insert) in inserts.into_iter().enumerate() {
+                insert.resize(widths[i], '-');
+                row.extend(insert);
+                row.extend(matches.get(i));
+            }
+            row
+        })
+        .collect();
+    let reference = widths
+        .iter()
+        .enumerate()
+        .flat_map(|(i, w)| {
+            let insert = std::iter::repeat_n('.', *w);
+            insert.chain((i < matches).then_some('x'))
+        })
+        .collect::<String>();
+
+    Ok(MSAlignment::new(ids, rows).with_metadata("GC:RF", reference))
+}
+
+/// from_path reads an A2M file, decompressing it if it's compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<MSAlignment> {
+    let path = path.as_ref();
+    let reader = compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    read(reader)
+}
+
+// An A2M Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+    columns: MatchColumns,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn new(writer: W, columns: MatchColumns) -> Self {
+        Writer { writer, columns }
+    }
+
+    /// write every row of an alignment as a record.
+    pub fn write(&mut self, msa: &MSAlignment) -> Result<()> {
+        let mask = self.columns.mask(msa)?;
+        for (id, row) in msa.ids.iter().zip(msa.rows.iter()) {
+            let seq: Vec<char> = row
+                .iter()
+                .zip(mask.iter())
+                .map(|(c, matched)| match (*c, *matched) {
+                    ('-', true) => '-',
+                    ('-', false) => '.',
+                    (c, true) => c.to_ascii_uppercase(),
+                    (c, false) => c.to_ascii_lowercase(),
+                })
+                .collect();
+
+            writeln!(self.writer, ">{}", id)?;
+            for line in seq.chunks(LINE_WIDTH) {
+                writeln!(self.writer, "{}", line.iter().collect::<String>())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer_a2m() {
+        let msa = MSAlignment::new(
+            vec!["ref".into(), "a".into(), "b".into()],
+            vec![
+                "AC--GT".chars().collect(),
+                "ACTTG-".chars().collect(),
+                "a-T-gt".chars().collect(),
+            ],
+        );
+
+        let mut w = Writer::new(Vec::new(), MatchColumns::Reference("ref".into()));
+        w.write(&msa).unwrap();
+        assert_eq!(
+            ">ref\nAC..GT\n>a\nACttG-\n>b\nA-t.GT\n",
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+
+        assert_eq!(
+            vec![true, true, true, false, true, true],
+            MatchColumns::Occupancy(0.5).mask(&msa).unwrap()
+        );
+        assert!(matches!(
+            MatchColumns::Reference("c".into()).mask(&msa),
+            Err(Error::UnknownReference(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_a2m() {
+        // without the '.' gaps of insert columns, and with them
+        let a2m = ">ref\nAC..GT\n>a\nACttG-\n>b\nA-t.GT\n";
+        let msa = read(a2m.as_bytes()).unwrap();
+        let bare = read(a2m.replace('.', "").as_bytes()).unwrap();
+        assert_eq!(msa, bare);
+        assert_eq!(vec!["ref", "a", "b"], msa.ids);
+        assert_eq!("AC--GT\nACttG-\nA-t-GT", msa.to_string());
+        assert_eq!("xx..xx", msa.metadata["GC:RF"]);
+
+        // inserts are padded after their residues, before the next match
+        let msa = read(">a\nxAyyC\n>b\nAC\n>c\nAzCwv\n".as_bytes()).unwrap();
+        assert_eq!("xAyyC--\n-A--C--\n-Az-Cwv", msa.to_string());
+        assert_eq!(".x..x..", msa.metadata["GC:RF"]);
+
+        // and written back out with the same match columns
+        let mask = msa.metadata["GC:RF"].chars().map(|c| c == 'x').collect();
+        let mut w = Writer::new(Vec::new(), MatchColumns::Mask(mask));
+        w.write(&msa).unwrap();
+        assert_eq!(msa, read(&w.into_inner()[..]).unwrap());
+
+        assert_eq!(MSAlignment::default(), read(&b""[..]).unwrap());
+        assert!(matches!(
+            read(">a\nACGT\n>b\nACgt\n".as_bytes()),
+            Err(Error::MatchMismatch { found: 2, .. })
+        ));
+    }
+}
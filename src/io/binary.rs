@@ -0,0 +1,444 @@
+//! A compact binary format for persisting alignments.
+//!
+//! A file is a versioned header followed by any number of pairwise and
+//! multiple alignments, so a pipeline can write millions of them as they're
+//! made and read them back without aligning again. All integers are
+//! little-endian:
+//!
+//! - `SQAB`, then the format version as a u32
+//! - each alignment starts with a u8 tag: 0 for pairwise, 1 for multiple
+//! - a pairwise alignment is its score and distance as f32s, its rows, then
+//!   its metadata
+//! - a multiple alignment is its IDs, its rows, then its metadata
+//! - rows and IDs are a u32 count, then each as a u32 byte length and UTF-8
+//! - metadata is a u32 count, then each key and value the same way
+//!
+//! The rows of a pairwise alignment are its traceback path: each column is
+//! one step through the grid. The scores of every cell of the grid, which
+//! only [`align_grid`](crate::align::align_grid) keeps, aren't written, so a
+//! record is linear in the length of the alignment rather than the area of
+//! its grid.
+//!
+//! Version 1 files, without metadata, and version 2 files are still read,
+//! along with the cells of the grid both wrote after the rows. Counts and lengths in a
+//! file are never trusted to size an allocation, so a corrupt file is an
+//! error rather than an abort.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use ordered_float::OrderedFloat;
+use thiserror::Error;
+
+use crate::align::{Alignment, MSAlignment, Metadata, Step};
+
+const MAGIC: &[u8; 4] = b"SQAB";
+const VERSION: u32 = 3;
+
+const PAIRWISE: u8 = 0;
+const MULTIPLE: u8 = 1;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("not a seqalign alignment file")]
+    InvalidHeader,
+
+    #[error("alignment format version {0} isn't supported")]
+    UnsupportedVersion(u32),
+
+    #[error("invalid alignment record: {0}")]
+    InvalidRecord(String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write alignments")]
+    IoError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Record is an alignment read from a file.
+#[derive(Debug)]
+pub enum Record {
+    Pairwise(Alignment),
+    Multiple(MSAlignment),
+}
+
+/// Writer writes alignments in the binary format.
+pub struct Writer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Writer<W> {
+    /// new writes the header to a writer.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(Writer { writer })
+    }
+
+    /// write_pairwise writes a pairwise alignment, without the scores of its
+    /// grid.
+    pub fn write_pairwise(&mut self, alignment: &Alignment) -> Result<()> {
+        let w = &mut self.writer;
+        w.write_all(&[PAIRWISE])?;
+        w.write_all(&alignment.score.to_le_bytes())?;
+        w.write_all(&alignment.distance.to_le_bytes())?;
+        write_rows(w, &alignment.rows)?;
+        write_metadata(w, &alignment.metadata)
+    }
+
+    /// write_multiple writes a multiple alignment.
+    pub fn write_multiple(&mut self, msa: &MSAlignment) -> Result<()> {
+        let w = &mut self.writer;
+        w.write_all(&[MULTIPLE])?;
+        write_len(w, msa.ids.len())?;
+        for id in &msa.ids {
+            write_str(w, id)?;
+        }
+        write_rows(w, &msa.rows)?;
+        write_metadata(w, &msa.metadata)
+    }
+
+    /// into_inner flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl Writer<BufWriter<File>> {
+    /// create creates a file and writes the header to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Writer::new(BufWriter::new(file))
+    }
+}
+
+/// Reader reads alignments written by a [`Writer`].
+pub struct Reader<R: Read> {
+    reader: R,
+    version: u32,
+}
+
+impl<R: Read> Reader<R> {
+    /// new reads and checks the header of a reader.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| Error::InvalidHeader)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidHeader);
+        }
+        let version = read_u32(&mut reader)?;
+        if version == 0 || version > VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        Ok(Reader { reader, version })
+    }
+
+    fn read(&mut self) -> Result<Option<Record>> {
+        let mut tag = [0u8; 1];
+        if self.reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+
+        let with_metadata = self.version >= 2;
+        let with_grid = self.version <= 2;
+        let r = &mut self.reader;
+        match tag[0] {
+            PAIRWISE => {
+                let score = read_f32(r)?;
+                let distance = read_f32(r)?;
+                let rows = read_rows(r)?;
+                if rows.len() < 2 {
+                    return Err(Error::InvalidRecord(format!(
+                        "pairwise alignment has {} rows",
+                        rows.len()
+                    )));
+                }
+                let metadata = read_metadata(r, with_metadata)?;
+                let steps = match with_grid {
+                    true => read_grid(r)?,
+                    false => Vec::new(),
+                };
+
+                Ok(Some(Record::Pairwise(Alignment {
+                    rows,
+                    steps,
+                    score,
+                    distance,
+                    metadata,
+                })))
+            }
+            MULTIPLE => {
+                let count = read_u32(r)?;
+                let mut ids = Vec::new();
+                for _ in 0..count {
+                    ids.push(read_str(r)?);
+                }
+                let rows = read_rows(r)?;
+                let metadata = read_metadata(r, with_metadata)?;
+                if ids.len() != rows.len() || rows.iter().any(|r| r.len() != rows[0].len()) {
+                    return Err(Error::InvalidRecord(
+                        "multiple alignment rows and IDs don't match".to_string(),
+                    ));
+                }
+                Ok(Some(Record::Multiple(MSAlignment {
+                    ids,
+                    rows,
+                    metadata,
+                })))
+            }
+            other => Err(Error::InvalidRecord(format!("unknown tag {}", other))),
+        }
+    }
+}
+
+impl Reader<BufReader<File>> {
+    /// from_path opens a file of alignments.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Reader::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> Result<()> {
+    let len = u32::try_from(len)
+        .map_err(|_| Error::InvalidRecord(format!("{} doesn't fit in a u32", len)))?;
+    Ok(w.write_all(&len.to_le_bytes())?)
+}
+
+fn write_str<W: Write>(w: &mut W, s: &str) -> Result<()> {
+    write_len(w, s.len())?;
+    Ok(w.write_all(s.as_bytes())?)
+}
+
+fn write_rows<W: Write>(w: &mut W, rows: &[Vec<char>]) -> Result<()> {
+    write_len(w, rows.len())?;
+    for row in rows {
+        write_str(w, &row.iter().collect::<String>())?;
+    }
+    Ok(())
+}
+
+fn write_metadata<W: Write>(w: &mut W, metadata: &Metadata) -> Result<()> {
+    write_len(w, metadata.len())?;
+    for (key, value) in metadata {
+        write_str(w, key)?;
+        write_str(w, value)?;
+    }
+    Ok(())
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> io::Result<f32> {
+    let mut bytes = [0u8; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+/// read_str reads a string, growing it only as its bytes arrive, so a
+/// corrupt length runs out of input instead of allocating it.
+fn read_str<R: Read>(r: &mut R) -> Result<String> {
+    let len = read_u32(r)? as u64;
+    let mut bytes = Vec::new();
+    if r.take(len).read_to_end(&mut bytes)? as u64 != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    String::from_utf8(bytes).map_err(|_| Error::InvalidRecord("invalid UTF-8".to_string()))
+}
+
+fn read_rows<R: Read>(r: &mut R) -> Result<Vec<Vec<char>>> {
+    let count = read_u32(r)?;
+    (0..count)
+        .map(|_| Ok(read_str(r)?.chars().collect()))
+        .collect()
+}
+
+/// read_grid reads the cells of the grid that version 1 and 2 files store at
+/// the end of a pairwise alignment: the number of rows, then for each the
+/// number of cells and each cell as an f32 value, u32 i and j, and a u8
+/// that's 1 if it's followed by the u32 i and j of the next cell.
+fn read_grid<R: Read>(r: &mut R) -> Result<Vec<Vec<Step>>> {
+    let mut steps = Vec::new();
+    for _ in 0..read_u32(r)? {
+        let mut row = Vec::new();
+        for _ in 0..read_u32(r)? {
+            let val = OrderedFloat(read_f32(r)?);
+            let i = read_u32(r)? as usize;
+            let j = read_u32(r)? as usize;
+            let mut has_next = [0u8; 1];
+            r.read_exact(&mut has_next)?;
+            let next = match has_next[0] {
+                0 => None,
+                _ => Some((read_u32(r)? as usize, read_u32(r)? as usize)),
+            };
+            row.push(Step { val, i, j, next });
+        }
+        steps.push(row);
+    }
+    Ok(steps)
+}
+
+/// read_metadata reads the metadata of an alignment, if the file has it.
+fn read_metadata<R: Read>(r: &mut R, with_metadata: bool) -> Result<Metadata> {
+    let mut metadata = Metadata::new();
+    if with_metadata {
+        for _ in 0..read_u32(r)? {
+            let key = read_str(r)?;
+            metadata.insert(key, read_str(r)?);
+        }
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::align::{align_grid, Method, Scoring};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let alignment = align_grid(
+            vec!["ACGTTACGT".to_string(), "ACGTACGT".to_string()],
+            Method::NeedlemanWunsch.strategy(),
+            &Scoring::default(),
+        );
+        let msa = MSAlignment::new(
+            vec!["a".to_string(), "b".to_string()],
+            vec!["AC-GT".chars().collect(), "ACTGT".chars().collect()],
+        )
+        .with_metadata("sample", "s1");
+        let alignment = alignment.with_metadata("a", "seq1");
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write_pairwise(&alignment).unwrap();
+        writer.write_multiple(&msa).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let records: Vec<Record> = Reader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(2, records.len());
+        match &records[0] {
+            Record::Pairwise(read) => {
+                assert_eq!(alignment.rows, read.rows);
+                assert!(read.steps.is_empty());
+                assert_eq!(alignment.score, read.score);
+                assert_eq!(alignment.distance, read.distance);
+                assert_eq!(alignment.metadata, read.metadata);
+            }
+            other => panic!("expected a pairwise alignment, got {:?}", other),
+        }
+        match &records[1] {
+            Record::Multiple(read) => assert_eq!(&msa, read),
+            other => panic!("expected a multiple alignment, got {:?}", other),
+        }
+
+        assert!(matches!(
+            Reader::new(&b"SQAI\x01\x00\x00\x00"[..]),
+            Err(Error::InvalidHeader)
+        ));
+
+        // a truncated record is an error, not the end of the file
+        let mut reader = Reader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+
+        // the grid isn't written, so a record grows with the alignment
+        let a = "ACGT".repeat(250);
+        let long = align_grid(
+            vec![a.clone(), a],
+            Method::NeedlemanWunsch.strategy(),
+            &Scoring::default(),
+        );
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write_pairwise(&long).unwrap();
+        assert!(writer.into_inner().unwrap().len() < 2100);
+    }
+
+    #[test]
+    fn test_read_version_2() {
+        let mut bytes = b"SQAB\x02\x00\x00\x00\x00".to_vec();
+        bytes.extend(1f32.to_le_bytes());
+        bytes.extend(0f32.to_le_bytes());
+        let mut rows = Vec::new();
+        write_rows(&mut rows, &["A".chars().collect(), "A".chars().collect()]).unwrap();
+        bytes.extend(rows);
+        bytes.extend(0u32.to_le_bytes());
+        // a grid of one row, of one cell pointing nowhere
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(1f32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.push(0);
+
+        let records: Vec<Record> = Reader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        match &records[..] {
+            [Record::Pairwise(read)] => {
+                assert_eq!(vec![vec![Step::from(1, 1, 1f32)]], read.steps);
+                assert_eq!(1f32, read.score);
+            }
+            other => panic!("expected a pairwise alignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_corrupt_counts() {
+        // counts far past the end of the input are errors, not allocations
+        let header = b"SQAB\x03\x00\x00\x00";
+        for record in [
+            // a multiple alignment of u32::MAX IDs
+            &b"\x01\xff\xff\xff\xff"[..],
+            // a pairwise alignment whose first row is u32::MAX bytes
+            &b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x02\x00\x00\x00\xff\xff\xff\xffAC"[..],
+        ] {
+            let bytes = [&header[..], record].concat();
+            let mut reader = Reader::new(bytes.as_slice()).unwrap();
+            assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+        }
+
+        let mut bytes = b"SQAB\x02\x00\x00\x00\x00".to_vec();
+        bytes.extend([0u8; 8]);
+        let mut rows = Vec::new();
+        write_rows(&mut rows, &["A".chars().collect(), "A".chars().collect()]).unwrap();
+        bytes.extend(rows);
+        bytes.extend(0u32.to_le_bytes());
+        // a grid of u32::MAX rows
+        bytes.extend(u32::MAX.to_le_bytes());
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+    }
+}
@@ -0,0 +1,372 @@
+//! Readers of BLAST's tabular (`-outfmt 6` and `7`) and XML (`-outfmt 5`)
+//! reports, as the hits of [`search`](crate::search), so hits found by BLAST
+//! can be filtered and reported with those found here.
+//! https://www.ncbi.nlm.nih.gov/books/NBK279684/
+//!
+//! Targets are numbered in the order they're first hit. The tabular format
+//! has no raw score or aligned residues, so the score of its hits is the bit
+//! score and their alignments have empty rows; XML has both. Only the
+//! default twelve tabular columns are read:
+//!
+//! `qseqid sseqid pident length mismatch gapopen qstart qend sstart send evalue bitscore`
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    align::Alignment,
+    search::{Hit, Strand},
+    seq::reverse_complement,
+};
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid BLAST tabular line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    #[error("invalid BLAST XML: {0}")]
+    InvalidXml(String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read BLAST report")]
+    ReadError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// read_tabular parses the hits of a BLAST tabular report, skipping its
+/// `#` comment lines.
+pub fn read_tabular<R: io::Read>(reader: R) -> Result<Vec<Hit>> {
+    let mut targets = Targets::default();
+    let mut hits = vec![];
+    for (i, line) in io::BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || Error::InvalidLine(i + 1, line.clone());
+
+        let fields: Vec<&str> = line.split('\t').map(|f| f.trim()).collect();
+        if fields.len() < 12 {
+            return Err(invalid());
+        }
+        let position = |k: usize| fields[k].parse::<usize>().map_err(|_| invalid());
+        let (query, _) = range(position(6)?, position(7)?).ok_or_else(invalid)?;
+        let (target_range, reverse) = range(position(8)?, position(9)?).ok_or_else(invalid)?;
+        let evalue = fields[10].parse::<f64>().map_err(|_| invalid())?;
+        let bit_score = fields[11].parse::<f64>().map_err(|_| invalid())?;
+
+        hits.push(Hit {
+            query_id: fields[0].to_string(),
+            target_id: fields[1].to_string(),
+            target: targets.index(fields[1]),
+            strand: if reverse {
+                Strand::Reverse
+            } else {
+                Strand::Forward
+            },
+            frame: None,
+            score: bit_score as f32,
+            bit_score,
+            evalue,
+            query_range: query,
+            target_range,
+            alignment: Alignment::new(vec![vec![], vec![]], vec![vec![]], bit_score as f32),
+        });
+    }
+    Ok(hits)
+}
+
+/// tabular_from_path reads a BLAST tabular report, decompressing it if it's
+/// compressed.
+pub fn tabular_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Hit>> {
+    read_tabular(open(path.as_ref())?)
+}
+
+/// read_xml parses the hits of a BLAST XML report, the HSPs of every
+/// iteration.
+///
+/// Hits to the reverse strand of a nucleotide target are turned around, like
+/// the search's, so the target's residues run forward and the query is
+/// reverse complemented. Translated queries keep their frame.
+pub fn read_xml<R: io::Read>(mut reader: R) -> Result<Vec<Hit>> {
+    let mut xml = String::new();
+    reader.read_to_string(&mut xml)?;
+
+    let mut targets = Targets::default();
+    let mut hits = vec![];
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut program = String::new();
+    let mut rest = xml.as_str();
+    while let Some(open) = rest.find('<') {
+        let close = rest[open..]
+            .find('>')
+            .ok_or_else(|| Error::InvalidXml("unterminated tag".to_string()))?
+            + open;
+        let tag = &rest[open + 1..close];
+        let text = &rest[..open];
+        rest = &rest[close + 1..];
+
+        if tag.starts_with('?') || tag.starts_with('!') || tag.ends_with('/') {
+            continue;
+        }
+        let Some(name) = tag.strip_prefix('/') else {
+            match tag {
+                "Iteration" => fields.clear(),
+                "Hit" => fields.retain(|name, _| name.starts_with("Iteration_")),
+                _ => {}
+            }
+            continue;
+        };
+        match name {
+            "Hsp" => {
+                hits.push(hsp(&fields, &program, &mut targets)?);
+            }
+            "BlastOutput_program" => program = unescape(text.trim()),
+            _ if name.starts_with("Hsp_")
+                || name.starts_with("Hit_")
+                || name.starts_with("Iteration_query") =>
+            {
+                fields.insert(name.to_string(), unescape(text.trim()));
+            }
+            _ => {}
+        }
+    }
+    Ok(hits)
+}
+
+/// xml_from_path reads a BLAST XML report, decompressing it if it's
+/// compressed.
+pub fn xml_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<Hit>> {
+    read_xml(open(path.as_ref())?)
+}
+
+/// hsp is the hit of an HSP with the fields of it, its hit and iteration.
+fn hsp(fields: &HashMap<String, String>, program: &str, targets: &mut Targets) -> Result<Hit> {
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .map(|f| f.as_str())
+            .ok_or_else(|| Error::InvalidXml(format!("HSP without {}", name)))
+    };
+    let number = |name: &str| {
+        field(name)?
+            .parse::<f64>()
+            .map_err(|_| Error::InvalidXml(format!("{} isn't a number", name)))
+    };
+    let position = |name: &str| number(name).map(|n| n as usize);
+    let frame = |name: &str| fields.get(name).and_then(|f| f.parse::<i8>().ok());
+
+    let (query_range, _) = range(position("Hsp_query-from")?, position("Hsp_query-to")?)
+        .ok_or_else(|| Error::InvalidXml("HSP at position 0".to_string()))?;
+    let (target_range, flipped) = range(position("Hsp_hit-from")?, position("Hsp_hit-to")?)
+        .ok_or_else(|| Error::InvalidXml("HSP at position 0".to_string()))?;
+    let translated = matches!(program, "blastx" | "tblastx");
+    let query_frame = frame("Hsp_query-frame").filter(|_| translated);
+    let reverse = flipped
+        || frame("Hsp_hit-frame").is_some_and(|f| f < 0)
+        || query_frame.is_some_and(|f| f < 0);
+
+    let (mut qseq, mut hseq) = (
+        field("Hsp_qseq")?.to_string(),
+        field("Hsp_hseq")?.to_string(),
+    );
+    if qseq.len() != hseq.len() {
+        return Err(Error::InvalidXml(
+            "HSP's query and hit are different lengths".to_string(),
+        ));
+    }
+    if flipped {
+        (qseq, hseq) = (reverse_complement(&qseq), reverse_complement(&hseq));
+    }
+    let score = number("Hsp_score")? as f32;
+
+    let target_id = id(field("Hit_id")?, fields.get("Hit_def"));
+    Ok(Hit {
+        query_id: id(
+            fields.get("Iteration_query-ID").map_or("", |f| f.as_str()),
+            fields.get("Iteration_query-def"),
+        ),
+        target: targets.index(&target_id),
+        target_id,
+        strand: if reverse {
+            Strand::Reverse
+        } else {
+            Strand::Forward
+        },
+        frame: query_frame,
+        score,
+        bit_score: number("Hsp_bit-score")?,
+        evalue: number("Hsp_evalue")?,
+        query_range,
+        target_range,
+        alignment: Alignment::new(
+            vec![qseq.chars().collect(), hseq.chars().collect()],
+            vec![vec![]],
+            score,
+        ),
+    })
+}
+
+/// id of a query or hit: BLAST's own ID unless it made one up, then the
+/// first word of its definition line.
+fn id(blast_id: &str, def: Option<&String>) -> String {
+    let made_up = blast_id.is_empty()
+        || blast_id.starts_with("Query_")
+        || blast_id.starts_with("gnl|BL_ORD_ID|");
+    match def.and_then(|d| d.split_whitespace().next()) {
+        Some(first) if made_up => first.to_string(),
+        _ => blast_id.to_string(),
+    }
+}
+
+/// range is the 0-based, half-open range of 1-based, inclusive positions,
+/// and whether they run backwards. None if one is 0.
+fn range(from: usize, to: usize) -> Option<(Range<usize>, bool)> {
+    if from == 0 || to == 0 {
+        return None;
+    }
+    Some((from.min(to) - 1..from.max(to), from > to))
+}
+
+/// unescape replaces the predefined entities of XML.
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn open(path: &Path) -> Result<Box<dyn Read>> {
+    compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Targets numbers the targets by when they're first hit.
+#[derive(Default)]
+struct Targets(HashMap<String, usize>);
+
+impl Targets {
+    fn index(&mut self, id: &str) -> usize {
+        let next = self.0.len();
+        *self.0.entry(id.to_string()).or_insert(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_tabular() {
+        let hits = read_tabular(
+            "# BLASTN 2.14.0+
+# Fields: query acc.ver, subject acc.ver, % identity, alignment length, mismatches, gap opens, q. start, q. end, s. start, s. end, evalue, bit score
+q1\tchr1\t98.5\t200\t3\t0\t1\t200\t1001\t1200\t1e-100\t350
+q1\tchr2\t90.0\t100\t10\t0\t51\t150\t500\t401\t2e-30\t120.5
+q2\tchr1\t100.0\t50\t0\t0\t1\t50\t10\t59\t3e-20\t92
+"
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(3, hits.len());
+        assert_eq!(
+            ("q1", "chr2", 1),
+            (
+                hits[1].query_id.as_str(),
+                hits[1].target_id.as_str(),
+                hits[1].target
+            )
+        );
+        assert_eq!(Strand::Reverse, hits[1].strand);
+        assert_eq!(
+            (50..150, 400..500),
+            (hits[1].query_range.clone(), hits[1].target_range.clone())
+        );
+        assert_eq!((120.5, 2e-30), (hits[1].bit_score, hits[1].evalue));
+        assert_eq!(0, hits[2].target);
+
+        assert!(matches!(
+            read_tabular("q1\tchr1\t98.5\n".as_bytes()),
+            Err(Error::InvalidLine(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_read_xml() {
+        let hits = read_xml(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE BlastOutput PUBLIC "-//NCBI//NCBI BlastOutput/EN" "http://www.ncbi.nlm.nih.gov/dtd/NCBI_BlastOutput.dtd">
+<BlastOutput>
+  <BlastOutput_program>blastn</BlastOutput_program>
+  <BlastOutput_iterations>
+    <Iteration>
+      <Iteration_iter-num>1</Iteration_iter-num>
+      <Iteration_query-ID>Query_1</Iteration_query-ID>
+      <Iteration_query-def>read1 sample=a&amp;b</Iteration_query-def>
+      <Iteration_hits>
+        <Hit>
+          <Hit_num>1</Hit_num>
+          <Hit_id>gnl|BL_ORD_ID|0</Hit_id>
+          <Hit_def>contig7 length=500</Hit_def>
+          <Hit_hsps>
+            <Hsp>
+              <Hsp_bit-score>20.1</Hsp_bit-score>
+              <Hsp_score>10</Hsp_score>
+              <Hsp_evalue>0.001</Hsp_evalue>
+              <Hsp_query-from>1</Hsp_query-from>
+              <Hsp_query-to>11</Hsp_query-to>
+              <Hsp_hit-from>110</Hsp_hit-from>
+              <Hsp_hit-to>101</Hsp_hit-to>
+              <Hsp_query-frame>1</Hsp_query-frame>
+              <Hsp_hit-frame>-1</Hsp_hit-frame>
+              <Hsp_qseq>ACGTTACGTAA</Hsp_qseq>
+              <Hsp_hseq>ACGT-ACGTAA</Hsp_hseq>
+            </Hsp>
+          </Hit_hsps>
+        </Hit>
+      </Iteration_hits>
+    </Iteration>
+  </BlastOutput_iterations>
+</BlastOutput>
+"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(1, hits.len());
+        let hit = &hits[0];
+        assert_eq!(
+            ("read1", "contig7", 0),
+            (hit.query_id.as_str(), hit.target_id.as_str(), hit.target)
+        );
+        assert_eq!((Strand::Reverse, None), (hit.strand, hit.frame));
+        assert_eq!(
+            (0..11, 100..110),
+            (hit.query_range.clone(), hit.target_range.clone())
+        );
+        assert_eq!((10f32, 0.001), (hit.score, hit.evalue));
+        let rows: Vec<String> = hit
+            .alignment
+            .rows
+            .iter()
+            .map(|r| r.iter().collect())
+            .collect();
+        assert_eq!(vec!["TTACGTAACGT", "TTACGT-ACGT"], rows);
+
+        assert!(read_xml("<Hsp></Hsp>".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! A reader and writer of Clustal `.aln` multiple alignments.
+//! https://www.clustal.org/download/clustalw_help.txt
+//!
+//! The file is a `CLUSTAL` header line, then blocks of `ID  residues` lines,
+//! one per sequence, each optionally ending in a residue count. The
+//! conservation lines under blocks start with whitespace and are skipped when
+//! reading. Written ones mark the columns with the same residue in every row.
+
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::align::MSAlignment;
+
+use super::compression;
+
+/// columns per block of written alignments
+const LINE_WIDTH: usize = 60;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("expected a CLUSTAL header")]
+    MissingHeader,
+
+    #[error("invalid Clustal line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    #[error("sequence {id} has {found} columns, expected {expected}")]
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write Clustal")]
+    ReadError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// read parses a Clustal alignment from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read<R: io::Read>(reader: R) -> Result<MSAlignment> {
+    let mut lines = io::BufReader::new(reader).lines().enumerate();
+    let header = lines.next().map(|(_, l)| l).transpose()?;
+    if !header.is_some_and(|h| h.starts_with("CLUSTAL")) {
+        return Err(Error::MissingHeader);
+    }
+
+    let mut ids: Vec<String> = vec![];
+    let mut rows: Vec<Vec<char>> = vec![];
+    for (i, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 || fields.len() > 3 {
+            return Err(Error::InvalidLine(i + 1, line));
+        }
+        let residues = fields[1].chars().map(|c| if c == '.' { '-' } else { c });
+        match ids.iter().position(|id| id == fields[0]) {
+            Some(row) => rows[row].extend(residues),
+            None => {
+                ids.push(fields[0].to_string());
+                rows.push(residues.collect());
+            }
+        }
+    }
+
+    let expected = rows.first().map_or(0, |r| r.len());
+    if let Some(row) = rows.iter().position(|r| r.len() != expected) {
+        return Err(Error::LengthMismatch {
+            id: ids[row].clone(),
+            expected,
+            found: rows[row].len(),
+        });
+    }
+    Ok(MSAlignment::new(ids, rows))
+}
+
+/// from_path reads a Clustal file, decompressing it if it's compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<MSAlignment> {
+    let path = path.as_ref();
+    let reader = compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    read(reader)
+}
+
+/// write an alignment as Clustal to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write<W: io::Write>(msa: &MSAlignment, mut w: W) -> Result<()> {
+    let width = msa.ids.iter().map(|id| id.len()).max().unwrap_or(0) + 4;
+    writeln!(w, "CLUSTAL multiple sequence alignment by seqalign")?;
+    for start in (0..msa.len()).step_by(LINE_WIDTH) {
+        let end = (start + LINE_WIDTH).min(msa.len());
+        writeln!(w)?;
+        for (id, row) in msa.ids.iter().zip(msa.rows.iter()) {
+            writeln!(
+                w,
+                "{:width$}{}",
+                id,
+                row[start..end].iter().collect::<String>(),
+                width = width
+            )?;
+        }
+        let conserved: String = (start..end)
+            .map(|col| {
+                let first = msa.rows[0][col].to_ascii_uppercase();
+                let same = first != '-'
+                    && msa
+                        .rows
+                        .iter()
+                        .all(|r| r[col].to_ascii_uppercase() == first);
+                if same {
+                    '*'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        writeln!(w, "{:width$}{}", "", conserved.trim_end(), width = width)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_clustal() {
+        let msa = read(
+            "CLUSTAL W (1.83) multiple sequence alignment
+
+seq1      ACGT--ACGT 8
+seq2      ACGTTTAC-T 9
+          ****  ** *
+
+seq1      AA
+seq2      A- 10
+          *
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(vec!["seq1", "seq2"], msa.ids);
+        assert_eq!("ACGT--ACGTAA\nACGTTTAC-TA-", msa.to_string());
+        assert_eq!(Some(9), msa.coordinate_map().column(1, 8));
+
+        let mut out = Vec::new();
+        write(&msa, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(
+            "CLUSTAL multiple sequence alignment by seqalign
+
+seq1    ACGT--ACGTAA
+seq2    ACGTTTAC-TA-
+        ****  ** **
+",
+            written
+        );
+        assert_eq!(msa, read(written.as_bytes()).unwrap());
+
+        assert!(matches!(
+            read("CLUSTAL\n\na  ACGT\nb  ACG\n".as_bytes()),
+            Err(Error::LengthMismatch { found: 3, .. })
+        ));
+        assert!(matches!(
+            read(">a\nACGT\n".as_bytes()),
+            Err(Error::MissingHeader)
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+//! Transparent decompression of reader input.
+//!
+//! The format is sniffed from the magic bytes at the start of the stream, not
+//! the file extension, so piped and misnamed input works too. gzip (and bgzip,
+//! which is multi-member gzip) needs the `gzip` feature, zstd the `zstd`
+//! feature. Uncompressed input is passed through as is.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// bytes read to detect the format, the longest magic
+const MAGIC_LEN: usize = 4;
+
+/// Compression format of a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+
+    /// gzip or bgzip
+    Gzip,
+
+    Zstd,
+}
+
+impl Compression {
+    /// detect the compression format from the first bytes of a stream.
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// decompress a stream, sniffing its compression format.
+pub fn decompress<R: Read + 'static>(mut reader: R) -> io::Result<Box<dyn Read>> {
+    // a read can return fewer bytes than asked for, on pipes in particular,
+    // so read until there's enough for the magic or the input ends
+    let mut magic = [0u8; MAGIC_LEN];
+    let mut filled = 0;
+    while filled < MAGIC_LEN {
+        match reader.read(&mut magic[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let compression = Compression::detect(&magic[..filled]);
+
+    // and put them back in front of the rest
+    let reader = BufReader::new(io::Cursor::new(magic[..filled].to_vec()).chain(reader));
+
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        Compression::Gzip => gzip(reader),
+        Compression::Zstd => zstd(reader),
+    }
+}
+
+/// open a file, decompressing it if it's compressed.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read>> {
+    decompress(File::open(path)?)
+}
+
+#[cfg(feature = "gzip")]
+fn gzip<R: Read + 'static>(reader: BufReader<R>) -> io::Result<Box<dyn Read>> {
+    // multi-member, so bgzip files are read to the end
+    Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip<R: Read + 'static>(_reader: BufReader<R>) -> io::Result<Box<dyn Read>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "input is gzip-compressed, rebuild with the gzip feature to read it",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd<R: Read + 'static>(reader: BufReader<R>) -> io::Result<Box<dyn Read>> {
+    let decoder = ruzstd::decoding::StreamingDecoder::new(reader)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(Box::new(decoder))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd<R: Read + 'static>(_reader: BufReader<R>) -> io::Result<Box<dyn Read>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "input is zstd-compressed, rebuild with the zstd feature to read it",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(mut r: Box<dyn Read>) -> String {
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::Gzip, Compression::detect(&[0x1f, 0x8b, 0x08]));
+        assert_eq!(
+            Compression::Zstd,
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd])
+        );
+        assert_eq!(Compression::None, Compression::detect(b">seq1"));
+        assert_eq!(Compression::None, Compression::detect(&[]));
+    }
+
+    #[test]
+    fn test_decompress_plain() {
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(&b">a\nACGT\n"[..]).unwrap())
+        );
+        assert_eq!("", read_all(decompress(&b""[..]).unwrap()));
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(Trickle(b">a\nACGT\n".to_vec())).unwrap())
+        );
+    }
+
+    /// Trickle reads one byte at a time, like a slow pipe.
+    struct Trickle(Vec<u8>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0.remove(0);
+            Ok(1)
+        }
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_decompress_zstd_unsupported() {
+        // the magic is found even when it takes more than one read
+        let err = decompress(Trickle(ZSTD_MAGIC.to_vec())).err().unwrap();
+        assert_eq!(io::ErrorKind::Unsupported, err.kind());
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_decompress_gzip_unsupported() {
+        let err = decompress(&[0x1f, 0x8b, 0x08, 0x00][..]).err().unwrap();
+        assert_eq!(io::ErrorKind::Unsupported, err.kind());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decompress_gzip() {
+        use std::io::Write;
+
+        // two members, like bgzip writes
+        let mut compressed = Vec::new();
+        for part in [">a\nAC", "GT\n"] {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(io::Cursor::new(compressed.clone())).unwrap())
+        );
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(Trickle(compressed)).unwrap())
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decompress_zstd() {
+        let compressed = ruzstd::encoding::compress_to_vec(
+            &b">a\nACGT\n"[..],
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(io::Cursor::new(compressed.clone())).unwrap())
+        );
+        assert_eq!(
+            ">a\nACGT\n",
+            read_all(decompress(Trickle(compressed)).unwrap())
+        );
+    }
+}
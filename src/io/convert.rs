@@ -0,0 +1,209 @@
+//! Conversion of multiple alignments between file formats.
+//!
+//! Aligned FASTA to aligned FASTA is streamed a record at a time, so only
+//! one row is in memory. Every other conversion holds the whole alignment,
+//! since the other formats interleave their rows in blocks or need every row
+//! before the first can be written (MSF's checksums, A2M's match columns),
+//! but nothing else is kept: an alignment goes from the reader to the writer
+//! in one call. IDs are kept in every format, and Stockholm markup survives
+//! a round trip through the alignment's metadata.
+
+use std::{io, path::Path, str::FromStr};
+
+use thiserror::Error;
+
+use crate::align::MSAlignment;
+
+use super::{a2m, clustal, fasta, msf, nexus, stockholm};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("no alignment format {0}")]
+    UnknownFormat(String),
+
+    #[error("{0:?} alignments can't be read")]
+    UnreadableFormat(Format),
+
+    #[error(transparent)]
+    Fasta(#[from] fasta::Error),
+
+    #[error(transparent)]
+    Clustal(#[from] clustal::Error),
+
+    #[error(transparent)]
+    Stockholm(#[from] stockholm::Error),
+
+    #[error(transparent)]
+    Msf(#[from] msf::Error),
+
+    #[error(transparent)]
+    A2m(#[from] a2m::Error),
+
+    #[error(transparent)]
+    Nexus(#[from] nexus::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Format of a multiple alignment file.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// aligned FASTA
+    Fasta,
+
+    /// Clustal `.aln`
+    Clustal,
+
+    /// Stockholm, as used by Pfam and HMMER
+    Stockholm,
+
+    /// GCG MSF
+    Msf,
+
+    /// A2M, with match columns from the `GC:RF` markup, or by occupancy, when
+    /// written
+    A2m,
+
+    /// NEXUS, written only
+    Nexus,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    /// from_str parses the name or a common file extension of a format.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "fasta" | "fa" | "fas" | "afa" | "mfa" => Ok(Format::Fasta),
+            "clustal" | "aln" => Ok(Format::Clustal),
+            "stockholm" | "sto" | "stk" => Ok(Format::Stockholm),
+            "msf" => Ok(Format::Msf),
+            "a2m" => Ok(Format::A2m),
+            "nexus" | "nex" | "nxs" => Ok(Format::Nexus),
+            _ => Err(Error::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+impl Format {
+    /// from_path guesses the format of a file from its extension, ignoring
+    /// any compression extension after it.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let path = match path.extension().and_then(|e| e.to_str()) {
+            Some("gz" | "zst") => Path::new(path.file_stem().unwrap_or_default()),
+            _ => path,
+        };
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        ext.parse()
+    }
+}
+
+/// fraction of rows with residues that makes a column a match state when
+/// writing A2M
+const A2M_OCCUPANCY: f32 = 0.5;
+
+/// a2m_columns are the match columns an alignment is written to A2M with:
+/// those of its Stockholm `GC:RF` markup, or those with enough residues.
+fn a2m_columns(msa: &MSAlignment) -> a2m::MatchColumns {
+    match msa.metadata.get("GC:RF") {
+        Some(rf) if rf.chars().count() == msa.len() => {
+            a2m::MatchColumns::Mask(rf.chars().map(|c| !matches!(c, '.' | '-')).collect())
+        }
+        _ => a2m::MatchColumns::Occupancy(A2M_OCCUPANCY),
+    }
+}
+
+/// read_msa reads an alignment in a format from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read_msa<R: io::Read>(reader: R, format: Format) -> Result<MSAlignment> {
+    Ok(match format {
+        Format::Fasta => fasta::read_alignment(reader)?,
+        Format::A2m => a2m::read(reader)?,
+        Format::Clustal => clustal::read(reader)?,
+        Format::Stockholm => stockholm::read(reader)?,
+        Format::Msf => msf::read(reader)?,
+        Format::Nexus => return Err(Error::UnreadableFormat(format)),
+    })
+}
+
+/// write_msa writes an alignment in a format to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write_msa<W: io::Write>(msa: &MSAlignment, writer: W, format: Format) -> Result<()> {
+    match format {
+        Format::Fasta => fasta::write_alignment(msa, writer)?,
+        Format::Clustal => clustal::write(msa, writer)?,
+        Format::Stockholm => stockholm::write(msa, writer)?,
+        Format::Msf => msf::write(msa, writer)?,
+        Format::A2m => a2m::Writer::new(writer, a2m_columns(msa)).write(msa)?,
+        Format::Nexus => nexus::Writer::new(writer)?.write(msa, nexus::Datatype::of(msa))?,
+    }
+    Ok(())
+}
+
+/// convert reads an alignment in one format and writes it in another.
+pub fn convert<R: io::Read, W: io::Write>(
+    reader: R,
+    from: Format,
+    writer: W,
+    to: Format,
+) -> Result<()> {
+    if (from, to) == (Format::Fasta, Format::Fasta) {
+        let mut writer = writer;
+        for row in fasta::Rows::new(reader) {
+            let (id, row) = row?;
+            fasta::write_row(&id, &row, &mut writer)?;
+        }
+        return Ok(());
+    }
+    let msa = read_msa(reader, from)?;
+    write_msa(&msa, writer, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let clustal = "CLUSTAL W (1.83) multiple sequence alignment
+
+seq1      ACGT--ACGT
+seq2      ACGTTTAC-T
+";
+        let mut msf = Vec::new();
+        convert(clustal.as_bytes(), Format::Clustal, &mut msf, Format::Msf).unwrap();
+        let mut fasta = Vec::new();
+        convert(&msf[..], Format::Msf, &mut fasta, Format::Fasta).unwrap();
+        assert_eq!(
+            ">seq1\nACGT--ACGT\n>seq2\nACGTTTAC-T\n",
+            String::from_utf8(fasta.clone()).unwrap()
+        );
+
+        // FASTA is streamed, checking the lengths as it goes
+        let mut streamed = Vec::new();
+        convert(&fasta[..], Format::Fasta, &mut streamed, Format::Fasta).unwrap();
+        assert_eq!(fasta, streamed);
+        let mut streamed = Vec::new();
+        assert!(matches!(
+            convert(
+                &b">seq1\nAC.T\n>seq2\nACGTT\n>seq3\nACGT\n"[..],
+                Format::Fasta,
+                &mut streamed,
+                Format::Fasta
+            ),
+            Err(Error::Fasta(fasta::Error::LengthMismatch { .. }))
+        ));
+        assert_eq!(">seq1\nAC-T\n", String::from_utf8(streamed).unwrap());
+
+        assert_eq!(
+            Format::Stockholm,
+            Format::from_path("PF00001.sto.gz").unwrap()
+        );
+        assert_eq!(Format::Clustal, "aln".parse().unwrap());
+        assert!(matches!(
+            convert(&b""[..], Format::Nexus, Vec::new(), Format::Fasta),
+            Err(Error::UnreadableFormat(Format::Nexus))
+        ));
+    }
+}
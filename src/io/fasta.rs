@@ -1,18 +1,32 @@
 use std::{
     io::{self, BufRead},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
+use crate::align::MSAlignment;
+
+use super::compression;
+
+/// residues per line of written sequences
+const LINE_WIDTH: usize = 60;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("expected '@' at record start")]
     MissingAt,
 
+    #[error("sequence {id} has {found} columns, expected {expected}")]
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
     #[error("can't open {path} file: {source}")]
     FileOpen { path: PathBuf, source: io::Error },
 
-    #[error("can't read input")]
+    #[error("can't read or write FASTA")]
     ReadError(#[from] io::Error),
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -33,6 +47,16 @@ impl Record {
         }
     }
 
+    /// id is the first word of the header.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// desc is the rest of the header after the ID.
+    pub fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
+    }
+
     fn is_empty(&self) -> bool {
         self.seq.is_empty()
     }
@@ -47,10 +71,7 @@ impl Record {
         }
 
         if !r.line_buffer.starts_with('>') {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Expected > at record start.",
-            ));
+            return Err(io::Error::other("Expected > at record start."));
         }
 
         let mut headers = r.line_buffer[1..].trim_end().splitn(2, ' ');
@@ -87,6 +108,18 @@ impl<R: io::Read> Reader<R> {
     }
 }
 
+impl Reader<Box<dyn io::Read>> {
+    /// from_path opens a FASTA file, decompressing it if it's compressed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = compression::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Reader::new(reader))
+    }
+}
+
 impl<R: io::Read> Iterator for Reader<R> {
     type Item = io::Result<Record>;
 
@@ -101,6 +134,75 @@ impl<R: io::Read> Iterator for Reader<R> {
     }
 }
 
+/// Rows is an iterator over the rows of aligned FASTA, reading a record at a
+/// time and checking that each is as long as the first.
+pub struct Rows<R> {
+    records: Reader<R>,
+    len: Option<usize>,
+}
+
+impl<R: io::Read> Rows<R> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Rows {
+            records: Reader::new(reader),
+            len: None,
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for Rows<R> {
+    /// the ID and row of a record, with `-` gaps
+    type Item = Result<(String, Vec<char>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let row: Vec<char> = record
+            .seq
+            .chars()
+            .map(|c| if c == '.' { '-' } else { c })
+            .collect();
+        let expected = *self.len.get_or_insert(row.len());
+        if row.len() != expected {
+            return Some(Err(Error::LengthMismatch {
+                id: record.id,
+                expected,
+                found: row.len(),
+            }));
+        }
+        Some(Ok((record.id, row)))
+    }
+}
+
+/// read_alignment parses aligned FASTA, with `-` or `.` gaps, from a given
+/// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read_alignment<R: io::Read>(reader: R) -> Result<MSAlignment> {
+    let (ids, rows) = Rows::new(reader).collect::<Result<(Vec<_>, Vec<_>)>>()?;
+    Ok(MSAlignment::new(ids, rows))
+}
+
+/// write_alignment writes the rows of an alignment, with `-` gaps, as FASTA
+/// to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write_alignment<W: io::Write>(msa: &MSAlignment, mut w: W) -> Result<()> {
+    for (id, row) in msa.ids.iter().zip(msa.rows.iter()) {
+        write_row(id, row, &mut w)?;
+    }
+    Ok(())
+}
+
+/// write_row writes one row of an alignment, with `-` gaps, as a FASTA
+/// record to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write_row<W: io::Write>(id: &str, row: &[char], mut w: W) -> Result<()> {
+    writeln!(w, ">{}", id)?;
+    for line in row.chunks(LINE_WIDTH) {
+        writeln!(w, "{}", line.iter().collect::<String>())?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,6 +235,22 @@ ATIGENLVVRRFATLKAGANG"
         );
     }
 
+    #[test]
+    fn test_read_alignment() {
+        let msa = read_alignment(">a desc\nAC-GT\n>b\nAC.G\nT\n".as_bytes()).unwrap();
+        assert_eq!(vec!["a", "b"], msa.ids);
+        assert_eq!("AC-GT\nAC-GT", msa.to_string());
+
+        let mut out = Vec::new();
+        write_alignment(&msa, &mut out).unwrap();
+        assert_eq!(">a\nAC-GT\n>b\nAC-GT\n", String::from_utf8(out).unwrap());
+
+        assert!(matches!(
+            read_alignment(">a\nACGT\n>b\nACG\n".as_bytes()),
+            Err(Error::LengthMismatch { found: 3, .. })
+        ));
+    }
+
     #[test]
     fn test_reader_fasta_file() {
         let mut r =
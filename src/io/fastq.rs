@@ -0,0 +1,220 @@
+//! FASTQ reader.
+//! https://en.wikipedia.org/wiki/FASTQ_format
+//!
+//! Qualities are decoded from Phred+33 into Phred scores, one per residue, so
+//! they can be passed straight to [`align_with_quality`](crate::align::align_with_quality).
+
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("expected '@' at record start")]
+    MissingAt,
+
+    #[error("expected '+' after the sequence of {0}")]
+    MissingPlus(String),
+
+    #[error("{id} has {seq} residues but {qual} qualities")]
+    QualityLength { id: String, seq: usize, qual: usize },
+
+    #[error("invalid quality character {0:?}")]
+    InvalidQuality(char),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// A FASTQ record.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub id: String,
+    pub desc: Option<String>,
+    pub seq: String,
+
+    /// Phred quality of each residue in `seq`
+    pub qual: Vec<u8>,
+}
+
+// A FASTQ Reader.
+pub struct Reader<R> {
+    reader: io::BufReader<R>,
+    line_buffer: String,
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader {
+            reader: io::BufReader::new(reader),
+            line_buffer: String::new(),
+        }
+    }
+
+    /// read_line into the line buffer, returning false at the end of the input.
+    fn read_line(&mut self) -> Result<bool> {
+        self.line_buffer.clear();
+        Ok(self.reader.read_line(&mut self.line_buffer)? > 0)
+    }
+
+    fn read(&mut self) -> Result<Option<Record>> {
+        // skip blank lines between records
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if !self.line_buffer.trim().is_empty() {
+                break;
+            }
+        }
+
+        let Some(header) = self.line_buffer.trim_end().strip_prefix('@') else {
+            return Err(Error::MissingAt);
+        };
+        let mut headers = header.splitn(2, ' ');
+        let mut record = Record {
+            id: headers.next().unwrap_or_default().to_string(),
+            desc: headers.next().map(str::to_string),
+            ..Default::default()
+        };
+
+        // the sequence may wrap onto several lines, up to the '+' separator
+        loop {
+            if !self.read_line()? {
+                return Err(Error::MissingPlus(record.id));
+            }
+            if self.line_buffer.starts_with('+') {
+                break;
+            }
+            record.seq.push_str(self.line_buffer.trim());
+        }
+
+        // so can the qualities, until there are as many as residues
+        while record.qual.len() < record.seq.len() {
+            if !self.read_line()? {
+                break;
+            }
+            for c in self.line_buffer.trim_end().chars() {
+                if !('!'..='~').contains(&c) {
+                    return Err(Error::InvalidQuality(c));
+                }
+                record.qual.push(c as u8 - 33);
+            }
+        }
+
+        if record.qual.len() != record.seq.len() {
+            return Err(Error::QualityLength {
+                seq: record.seq.len(),
+                qual: record.qual.len(),
+                id: record.id,
+            });
+        }
+        Ok(Some(record))
+    }
+}
+
+impl Reader<Box<dyn io::Read>> {
+    /// from_path opens a FASTQ file, decompressing it if it's compressed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = compression::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Reader::new(reader))
+    }
+}
+
+impl<R: io::Read> Iterator for Reader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_fastq() {
+        let mut r = Reader::new(
+            "@read1 sample=A
+ACGTN
++
+II5#!
+
+@read2
+ACGT
+ACGT
++read2
+IIII
+IIII
+"
+            .as_bytes(),
+        );
+
+        let first = r.next().unwrap().unwrap();
+        assert_eq!("read1", first.id);
+        assert_eq!(Some("sample=A".to_string()), first.desc);
+        assert_eq!("ACGTN", first.seq);
+        assert_eq!(vec![40, 40, 20, 2, 0], first.qual);
+
+        let second = r.next().unwrap().unwrap();
+        assert_eq!("read2", second.id);
+        assert_eq!("ACGTACGT", second.seq);
+        assert_eq!(vec![40; 8], second.qual);
+
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn test_reader_fastq_errors() {
+        let mut r = Reader::new(">read1\nACGT\n".as_bytes());
+        assert!(matches!(r.next(), Some(Err(Error::MissingAt))));
+
+        let mut r = Reader::new("@read1\nACGT\n+\nIII\n".as_bytes());
+        assert!(matches!(
+            r.next(),
+            Some(Err(Error::QualityLength {
+                seq: 4,
+                qual: 3,
+                ..
+            }))
+        ));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_reader_fastq_gzip() {
+        use std::io::Write;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("reads.fastq.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(b"@read1\nACGT\n+\nIIII\n").unwrap();
+        encoder.finish().unwrap();
+
+        let records: Vec<_> = Reader::from_path(&path)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(1, records.len());
+        assert_eq!("ACGT", records[0].seq);
+    }
+}
@@ -0,0 +1,408 @@
+//! GenBank and EMBL flat file reader.
+//! https://www.ncbi.nlm.nih.gov/genbank/samplerecord/
+//!
+//! Only the parts needed for alignment are kept: the record name, its
+//! features, and its sequence. Both formats share the same feature table
+//! layout (key in column 6, location and qualifiers from column 22), EMBL
+//! just prefixes each feature line with `FT`.
+
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid feature location: {0}")]
+    InvalidLocation(String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read input")]
+    ReadError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Strand of a feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strand {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+/// Location of a feature as 0-based, half-open ranges in sequence order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    pub ranges: Vec<(usize, usize)>,
+    pub strand: Strand,
+}
+
+impl Location {
+    /// start is the lowest position covered by the feature.
+    pub fn start(&self) -> usize {
+        self.ranges.iter().map(|r| r.0).min().unwrap_or(0)
+    }
+
+    /// end is one past the highest position covered by the feature.
+    pub fn end(&self) -> usize {
+        self.ranges.iter().map(|r| r.1).max().unwrap_or(0)
+    }
+
+    /// parse a feature table location like `complement(join(1..10,20..>30))`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let invalid = || Error::InvalidLocation(s.clone());
+
+        if let Some(inner) = s
+            .strip_prefix("complement(")
+            .and_then(|r| r.strip_suffix(')'))
+        {
+            let mut location = Location::parse(inner)?;
+            location.strand = match location.strand {
+                Strand::Forward => Strand::Reverse,
+                Strand::Reverse => Strand::Forward,
+            };
+            return Ok(location);
+        }
+
+        let inner = s
+            .strip_prefix("join(")
+            .or_else(|| s.strip_prefix("order("))
+            .and_then(|r| r.strip_suffix(')'));
+        if let Some(inner) = inner {
+            let mut location = Location::default();
+            for part in split_top_level(inner) {
+                let sub = Location::parse(part)?;
+                if sub.strand == Strand::Reverse {
+                    location.strand = Strand::Reverse;
+                }
+                location.ranges.extend(sub.ranges);
+            }
+            return Ok(location);
+        }
+
+        let pos = |p: &str| -> Result<usize> {
+            p.trim_start_matches(['<', '>'])
+                .parse::<usize>()
+                .map_err(|_| invalid())
+        };
+        let range = if let Some((start, end)) = s.split_once("..") {
+            (pos(start)?.checked_sub(1).ok_or_else(invalid)?, pos(end)?)
+        } else if let Some((start, _)) = s.split_once('^') {
+            // between two bases: an empty range after `start`
+            (pos(start)?, pos(start)?)
+        } else {
+            let p = pos(&s)?;
+            (p.checked_sub(1).ok_or_else(invalid)?, p)
+        };
+        if range.0 > range.1 {
+            return Err(invalid());
+        }
+
+        Ok(Location {
+            ranges: vec![range],
+            strand: Strand::Forward,
+        })
+    }
+}
+
+/// split_top_level splits on commas that aren't nested in parentheses.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Feature is an annotated region of a sequence, like a CDS or primer binding site.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Feature {
+    pub kind: String,
+    pub location: Location,
+    pub qualifiers: Vec<(String, String)>,
+}
+
+impl Feature {
+    /// qualifier returns the value of the first qualifier with the key.
+    pub fn qualifier(&self, key: &str) -> Option<&str> {
+        self.qualifiers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// name is the label, gene, or product of the feature, whichever is set first.
+    pub fn name(&self) -> Option<&str> {
+        ["label", "gene", "product", "note"]
+            .iter()
+            .find_map(|k| self.qualifier(k))
+    }
+}
+
+// A GenBank or EMBL record.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub features: Vec<Feature>,
+    pub seq: String,
+}
+
+// A GenBank or EMBL Reader.
+pub struct Reader<R> {
+    reader: io::BufReader<R>,
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader {
+            reader: io::BufReader::new(reader),
+        }
+    }
+
+    fn read(&mut self) -> Result<Option<Record>> {
+        let mut record = Record::default();
+        let mut in_features = false;
+        let mut in_seq = false;
+        let mut location = String::new();
+        let mut seen_any = false;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let l = line.trim_end();
+            if l.trim().is_empty() {
+                continue;
+            }
+            seen_any = true;
+
+            if l.starts_with("//") {
+                break;
+            }
+
+            if in_seq {
+                record
+                    .seq
+                    .extend(l.chars().filter(|c| c.is_ascii_alphabetic()));
+                continue;
+            }
+
+            // EMBL feature lines are GenBank feature lines with an FT prefix
+            let ft = if let Some(rest) = l.strip_prefix("FT") {
+                in_features = true;
+                format!("  {}", rest)
+            } else {
+                l.to_string()
+            };
+
+            if l.starts_with("LOCUS") || l.starts_with("ID ") {
+                record.name = l
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .trim_end_matches(';')
+                    .to_string();
+                continue;
+            }
+            if l.starts_with("FEATURES") {
+                in_features = true;
+                continue;
+            }
+            if l.starts_with("FH") {
+                continue;
+            }
+            if l.starts_with("ORIGIN") || l.starts_with("SQ") {
+                finish_location(&mut record, &mut location)?;
+                in_features = false;
+                in_seq = true;
+                continue;
+            }
+            if !in_features || !ft.starts_with(' ') {
+                in_features = false;
+                continue;
+            }
+
+            let key = ft.get(5..21).unwrap_or_default().trim();
+            let value = ft.get(21..).unwrap_or_default().trim();
+            if !key.is_empty() {
+                // a new feature
+                finish_location(&mut record, &mut location)?;
+                record.features.push(Feature {
+                    kind: key.to_string(),
+                    ..Default::default()
+                });
+                location.push_str(value);
+            } else if let Some(qualifier) = value.strip_prefix('/') {
+                finish_location(&mut record, &mut location)?;
+                let (k, v) = qualifier.split_once('=').unwrap_or((qualifier, ""));
+                if let Some(feature) = record.features.last_mut() {
+                    feature
+                        .qualifiers
+                        .push((k.to_string(), v.trim_matches('"').to_string()));
+                }
+            } else if !location.is_empty() {
+                // a location spanning several lines
+                location.push_str(value);
+            } else if let Some((k, v)) = record
+                .features
+                .last_mut()
+                .and_then(|f| f.qualifiers.last_mut())
+            {
+                // a qualifier value spanning several lines. Translations wrap
+                // mid-sequence, everything else wraps between words
+                if k != "translation" && !v.is_empty() {
+                    v.push(' ');
+                }
+                v.push_str(value.trim_matches('"'));
+            }
+        }
+
+        finish_location(&mut record, &mut location)?;
+        if !seen_any {
+            return Ok(None);
+        }
+        Ok(Some(record))
+    }
+}
+
+impl Reader<Box<dyn io::Read>> {
+    /// from_path opens a GenBank or EMBL file, decompressing it if it's compressed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = compression::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Reader::new(reader))
+    }
+}
+
+/// finish_location parses the pending location into the last feature.
+fn finish_location(record: &mut Record, location: &mut String) -> Result<()> {
+    if location.is_empty() {
+        return Ok(());
+    }
+    if let Some(feature) = record.features.last_mut() {
+        feature.location = Location::parse(location)?;
+    }
+    location.clear();
+    Ok(())
+}
+
+impl<R: io::Read> Iterator for Reader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_parse() {
+        assert_eq!(vec![(9, 20)], Location::parse("10..20").unwrap().ranges);
+        assert_eq!(vec![(0, 20)], Location::parse("<1..>20").unwrap().ranges);
+        assert_eq!(vec![(4, 5)], Location::parse("5").unwrap().ranges);
+
+        let location = Location::parse("complement(join(1..10, 20..30))").unwrap();
+        assert_eq!(vec![(0, 10), (19, 30)], location.ranges);
+        assert_eq!(Strand::Reverse, location.strand);
+
+        assert!(Location::parse("10..x").is_err());
+
+        // positions are 1-based, so 0 isn't one
+        assert!(Location::parse("0..5").is_err());
+        assert!(Location::parse("0").is_err());
+        assert!(Location::parse("complement(0..5)").is_err());
+    }
+
+    #[test]
+    fn test_reader_genbank() {
+        let mut r = Reader::new(
+            "LOCUS       pTest                     40 bp    DNA     circular SYN 01-JAN-2020
+DEFINITION  test plasmid.
+FEATURES             Location/Qualifiers
+     source          1..40
+                     /organism=\"synthetic DNA construct\"
+     CDS             complement(join(3..10,
+                     15..30))
+                     /gene=\"lacZ\"
+                     /note=\"a note that wraps
+                     onto another line\"
+     primer_bind     32..40
+                     /label=M13
+ORIGIN
+        1 atggctagca tcgatcgatc gatcgatcga tcgatgcatg
+//
+"
+            .as_bytes(),
+        );
+
+        let record = r.next().unwrap().unwrap();
+        assert_eq!("pTest", record.name);
+        assert_eq!(40, record.seq.len());
+        assert!(record.seq.starts_with("atggctagca"));
+        assert_eq!(3, record.features.len());
+
+        let cds = &record.features[1];
+        assert_eq!("CDS", cds.kind);
+        assert_eq!(vec![(2, 10), (14, 30)], cds.location.ranges);
+        assert_eq!(Strand::Reverse, cds.location.strand);
+        assert_eq!(Some("lacZ"), cds.name());
+        assert_eq!(
+            Some("a note that wraps onto another line"),
+            cds.qualifier("note")
+        );
+        assert_eq!(Some("M13"), record.features[2].name());
+
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn test_reader_embl() {
+        let mut r = Reader::new(
+            "ID   X56734; SV 1; linear; mRNA; STD; PLN; 20 BP.
+XX
+FH   Key             Location/Qualifiers
+FT   CDS             2..19
+FT                   /product=\"beta-glucosidase\"
+XX
+SQ   Sequence 20 BP;
+     aaacaaacca aatatggatt                                                  20
+//
+"
+            .as_bytes(),
+        );
+
+        let record = r.next().unwrap().unwrap();
+        assert_eq!("X56734", record.name);
+        assert_eq!("aaacaaaccaaatatggatt", record.seq);
+        assert_eq!(1, record.features.len());
+        assert_eq!(vec![(1, 19)], record.features[0].location.ranges);
+        assert_eq!(Some("beta-glucosidase"), record.features[0].name());
+    }
+}
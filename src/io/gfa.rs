@@ -0,0 +1,348 @@
+//! A GFA 1 reader and writer for sequence graphs.
+//! https://gfa-spec.github.io/GFA-spec/GFA1.html
+//!
+//! Segments, links and paths are kept. Optional tags and the other record
+//! types (containments, walks, jumps) are skipped when reading.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead},
+    path::{Path as FilePath, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{graph::SeqGraph, seq::reverse_complement};
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid GFA record on line {0}: {1}")]
+    InvalidRecord(usize, String),
+
+    #[error("unknown segment {0}")]
+    UnknownSegment(String),
+
+    #[error("link from {0} to {1} overlaps by {2}, only blunt links are supported")]
+    UnsupportedOverlap(String, String, String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write GFA")]
+    IoError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Orientation is the strand of a segment a link or path uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Forward,
+    Reverse,
+}
+
+impl Orientation {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "+" => Some(Orientation::Forward),
+            "-" => Some(Orientation::Reverse),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Orientation::Forward => "+",
+            Orientation::Reverse => "-",
+        }
+    }
+}
+
+/// Segment is an S record: a named sequence, empty if it's `*` in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub seq: String,
+}
+
+/// Link is an L record: the end of one oriented segment followed by the start
+/// of another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub from: String,
+    pub from_orientation: Orientation,
+    pub to: String,
+    pub to_orientation: Orientation,
+
+    /// CIGAR of the overlap between the segments, `*` if unknown
+    pub overlap: String,
+}
+
+/// Path is a P record: a named walk through oriented segments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    pub name: String,
+    pub segments: Vec<(String, Orientation)>,
+
+    /// CIGARs of the overlaps between consecutive segments, `*` if unknown
+    pub overlaps: String,
+}
+
+/// Gfa is the records of a GFA file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Gfa {
+    pub segments: Vec<Segment>,
+    pub links: Vec<Link>,
+    pub paths: Vec<Path>,
+}
+
+impl Gfa {
+    /// read parses GFA from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn read<R: io::Read>(reader: R) -> Result<Self> {
+        let mut gfa = Gfa::default();
+        for (i, line) in io::BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let invalid = || Error::InvalidRecord(i + 1, line.clone());
+            let fields: Vec<&str> = line.trim_end().split('\t').collect();
+            match fields[0] {
+                "S" if fields.len() >= 3 => gfa.segments.push(Segment {
+                    name: fields[1].to_string(),
+                    seq: match fields[2] {
+                        "*" => String::new(),
+                        seq => seq.to_string(),
+                    },
+                }),
+                "L" if fields.len() >= 6 => gfa.links.push(Link {
+                    from: fields[1].to_string(),
+                    from_orientation: Orientation::parse(fields[2]).ok_or_else(invalid)?,
+                    to: fields[3].to_string(),
+                    to_orientation: Orientation::parse(fields[4]).ok_or_else(invalid)?,
+                    overlap: fields[5].to_string(),
+                }),
+                "P" if fields.len() >= 4 => gfa.paths.push(Path {
+                    name: fields[1].to_string(),
+                    segments: fields[2]
+                        .split(',')
+                        .map(|step| {
+                            let (name, orientation) = step.split_at(step.len().saturating_sub(1));
+                            Orientation::parse(orientation).map(|o| (name.to_string(), o))
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?,
+                    overlaps: fields[3].to_string(),
+                }),
+                "S" | "L" | "P" => return Err(invalid()),
+                _ => continue,
+            }
+        }
+        Ok(gfa)
+    }
+
+    /// from_path reads a GFA file, decompressing it if it's compressed.
+    pub fn from_path<P: AsRef<FilePath>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = compression::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Gfa::read(reader)
+    }
+
+    /// write writes the header and records to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn write<W: io::Write>(&self, mut w: W) -> Result<()> {
+        writeln!(w, "H\tVN:Z:1.0")?;
+        for s in &self.segments {
+            let seq = if s.seq.is_empty() { "*" } else { &s.seq };
+            writeln!(w, "S\t{}\t{}", s.name, seq)?;
+        }
+        for l in &self.links {
+            writeln!(
+                w,
+                "L\t{}\t{}\t{}\t{}\t{}",
+                l.from,
+                l.from_orientation.as_str(),
+                l.to,
+                l.to_orientation.as_str(),
+                l.overlap
+            )?;
+        }
+        for p in &self.paths {
+            let segments: Vec<String> = p
+                .segments
+                .iter()
+                .map(|(name, o)| format!("{}{}", name, o.as_str()))
+                .collect();
+            writeln!(w, "P\t{}\t{}\t{}", p.name, segments.join(","), p.overlaps)?;
+        }
+        Ok(())
+    }
+
+    /// from_graph has a segment for each segment of a graph and a blunt link
+    /// for each of its links, all forward.
+    pub fn from_graph(graph: &SeqGraph) -> Self {
+        Gfa {
+            segments: graph
+                .segments
+                .iter()
+                .map(|s| Segment {
+                    name: s.name.clone(),
+                    seq: s.seq.clone(),
+                })
+                .collect(),
+            links: graph
+                .links
+                .iter()
+                .map(|(from, to)| Link {
+                    from: graph.segments[*from].name.clone(),
+                    from_orientation: Orientation::Forward,
+                    to: graph.segments[*to].name.clone(),
+                    to_orientation: Orientation::Forward,
+                    overlap: "0M".to_string(),
+                })
+                .collect(),
+            paths: vec![],
+        }
+    }
+
+    /// to_graph is the directed graph of the segments and links.
+    ///
+    /// Links are followed in the orientation they're written. A segment used
+    /// in reverse gets a second node, named with a trailing `-`, holding its
+    /// reverse complement. Links must be blunt: `*` or an overlap of `0M`.
+    pub fn to_graph(&self) -> Result<SeqGraph> {
+        let mut graph = SeqGraph::new();
+        // node of each segment by name, and of its reverse complement
+        let mut forward: HashMap<&str, usize> = HashMap::with_capacity(self.segments.len());
+        let mut reverse: HashMap<&str, usize> = HashMap::new();
+        for s in &self.segments {
+            let node = graph.add_segment(&s.name, &s.seq);
+            forward.entry(&s.name).or_insert(node);
+        }
+
+        let mut links = HashSet::with_capacity(self.links.len());
+        for l in &self.links {
+            if !is_blunt(&l.overlap) {
+                return Err(Error::UnsupportedOverlap(
+                    l.from.clone(),
+                    l.to.clone(),
+                    l.overlap.clone(),
+                ));
+            }
+            let from = node(
+                &mut graph,
+                &forward,
+                &mut reverse,
+                &l.from,
+                l.from_orientation,
+            )?;
+            let to = node(&mut graph, &forward, &mut reverse, &l.to, l.to_orientation)?;
+            if links.insert((from, to)) {
+                graph.links.push((from, to));
+            }
+        }
+        Ok(graph)
+    }
+}
+
+/// node is the index of the node of an oriented segment, added if it's new.
+fn node<'a>(
+    graph: &mut SeqGraph,
+    forward: &HashMap<&'a str, usize>,
+    reverse: &mut HashMap<&'a str, usize>,
+    name: &'a str,
+    orientation: Orientation,
+) -> Result<usize> {
+    let node = *forward
+        .get(name)
+        .ok_or_else(|| Error::UnknownSegment(name.to_string()))?;
+    if orientation == Orientation::Forward {
+        return Ok(node);
+    }
+    if let Some(reversed) = reverse.get(name) {
+        return Ok(*reversed);
+    }
+    let name_reverse = format!("{}-", name);
+    let reversed = match forward.get(name_reverse.as_str()) {
+        Some(reversed) => *reversed,
+        None => {
+            let seq = reverse_complement(&graph.segments[node].seq);
+            graph.add_segment(&name_reverse, &seq)
+        }
+    };
+    reverse.insert(name, reversed);
+    Ok(reversed)
+}
+
+/// is_blunt is true if an overlap is `*` or a CIGAR of 0 matches, like `0M`.
+fn is_blunt(overlap: &str) -> bool {
+    overlap == "*"
+        || overlap
+            .strip_suffix('M')
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b == b'0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFA: &str = "H\tVN:Z:1.0
+S\t1\tACGT
+S\t2\tGG\tLN:i:2
+S\t3\tTTA
+L\t1\t+\t2\t+\t0M
+L\t2\t+\t3\t-\t*
+P\tp1\t1+,2+,3-\t*
+";
+
+    #[test]
+    fn test_gfa_round_trip() {
+        let gfa = Gfa::read(GFA.as_bytes()).unwrap();
+        assert_eq!(3, gfa.segments.len());
+        assert_eq!(
+            vec![
+                ("1".to_string(), Orientation::Forward),
+                ("2".to_string(), Orientation::Forward),
+                ("3".to_string(), Orientation::Reverse),
+            ],
+            gfa.paths[0].segments
+        );
+
+        let mut out = Vec::new();
+        gfa.write(&mut out).unwrap();
+        assert_eq!(GFA.replace("\tLN:i:2", ""), String::from_utf8(out).unwrap());
+
+        assert!(matches!(
+            Gfa::read("L\t1\t+\t2\n".as_bytes()),
+            Err(Error::InvalidRecord(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_gfa_to_graph() {
+        let graph = Gfa::read(GFA.as_bytes()).unwrap().to_graph().unwrap();
+        assert_eq!(4, graph.segments.len());
+        assert_eq!("TAA", graph.segments[3].seq);
+        assert_eq!(vec![(0, 1), (1, 3)], graph.links);
+
+        let gfa = Gfa::from_graph(&graph);
+        assert_eq!("3-", gfa.links[1].to);
+        assert_eq!(graph, gfa.to_graph().unwrap());
+
+        // only blunt links
+        for overlap in ["*", "0M", "00M"] {
+            let mut gfa = gfa.clone();
+            gfa.links[0].overlap = overlap.to_string();
+            assert!(gfa.to_graph().is_ok(), "{}", overlap);
+        }
+        for overlap in ["M", "2M", "0", "0X", ""] {
+            let mut gfa = gfa.clone();
+            gfa.links[0].overlap = overlap.to_string();
+            assert!(
+                matches!(gfa.to_graph(), Err(Error::UnsupportedOverlap(..))),
+                "{}",
+                overlap
+            );
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! A writer of Jalview annotation files for tracks under multiple alignments.
+//! https://www.jalview.org/help/html/features/annotationsFormat.html
+//!
+//! Files have one graph per track, for the whole alignment rather than a
+//! sequence. Loaded onto an MSA written from here, in Jalview's File > Load
+//! Features / Annotations, the tracks line up with its columns.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::{align::MSAlignment, matrices::Matrix};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("annotation {label} has {found} columns, the alignment has {expected}")]
+    WrongLength {
+        label: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("can't write output")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Graph is how Jalview draws a track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Graph {
+    #[default]
+    Bar,
+    Line,
+
+    /// symbols only
+    None,
+}
+
+impl Graph {
+    fn as_str(&self) -> &str {
+        match self {
+            Graph::Bar => "BAR_GRAPH",
+            Graph::Line => "LINE_GRAPH",
+            Graph::None => "NO_GRAPH",
+        }
+    }
+}
+
+/// Annotation is a track of one value per column of an alignment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub label: String,
+    pub description: String,
+    pub graph: Graph,
+    pub values: Vec<f32>,
+
+    /// a character drawn over each column, ' ' for none
+    pub symbols: Option<Vec<char>>,
+}
+
+impl Annotation {
+    /// conservation is the fraction of each column with its most common
+    /// residue, marked `*` like ClustalX where every row has it.
+    pub fn conservation(msa: &MSAlignment) -> Self {
+        let values = msa.column_conservation();
+        let symbols = values
+            .iter()
+            .map(|v| if *v >= 1f32 { '*' } else { ' ' })
+            .collect();
+        Annotation {
+            label: "Conservation".to_string(),
+            description: "Fraction of rows with the most common residue".to_string(),
+            graph: Graph::Bar,
+            values,
+            symbols: Some(symbols),
+        }
+    }
+
+    /// quality is the mean substitution score of the residue pairs of each column.
+    pub fn quality(msa: &MSAlignment, matrix: &Matrix) -> Self {
+        Annotation {
+            label: "Quality".to_string(),
+            description: "Mean substitution score of residue pairs".to_string(),
+            graph: Graph::Bar,
+            values: msa.column_quality(matrix),
+            symbols: None,
+        }
+    }
+}
+
+// A Jalview annotation Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+    columns: usize,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Writes the header for annotations of an alignment with `columns` columns.
+    pub fn new(mut writer: W, columns: usize) -> Result<Self> {
+        writeln!(writer, "JALVIEW_ANNOTATION")?;
+        writeln!(writer, "# source=seqalign")?;
+        Ok(Writer { writer, columns })
+    }
+
+    /// write an annotation with a value for every column.
+    pub fn write(&mut self, annotation: &Annotation) -> Result<()> {
+        let lengths = [
+            Some(annotation.values.len()),
+            annotation.symbols.as_ref().map(|s| s.len()),
+        ];
+        if let Some(found) = lengths.into_iter().flatten().find(|l| *l != self.columns) {
+            return Err(Error::WrongLength {
+                label: annotation.label.clone(),
+                expected: self.columns,
+                found,
+            });
+        }
+
+        let values: Vec<String> = annotation
+            .values
+            .iter()
+            .enumerate()
+            .map(|(col, v)| {
+                let v = format!("{:.3}", v)
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string();
+                match annotation.symbols.as_ref().map(|s| s[col]) {
+                    Some(c) if c != ' ' => format!("{},{}", v, c),
+                    _ => v,
+                }
+            })
+            .collect();
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}",
+            annotation.graph.as_str(),
+            annotation.label,
+            annotation.description,
+            values.join("|")
+        )?;
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_writer_jalview() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["ACGT".chars().collect(), "AC-A".chars().collect()],
+        );
+
+        let mut w = Writer::new(Vec::new(), msa.len()).unwrap();
+        w.write(&Annotation::conservation(&msa)).unwrap();
+        w.write(&Annotation::quality(&msa, &NUC_4_4::MATRIX))
+            .unwrap();
+        assert_eq!(
+            "JALVIEW_ANNOTATION
+# source=seqalign
+BAR_GRAPH\tConservation\tFraction of rows with the most common residue\t1,*|1,*|0.5|0.5
+BAR_GRAPH\tQuality\tMean substitution score of residue pairs\t5|5|0|-4
+",
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+
+        let mut w = Writer::new(Vec::new(), 3).unwrap();
+        assert!(matches!(
+            w.write(&Annotation::conservation(&msa)),
+            Err(Error::WrongLength { found: 4, .. })
+        ));
+    }
+}
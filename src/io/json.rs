@@ -0,0 +1,284 @@
+//! A writer of pairwise chains as JSON, for genome browser tracks and
+//! dotplots drawn in a web page.
+//!
+//! A query and target are written as one object with their chains, like the
+//! mappings of a split query or the blocks of an inversion alignment. Each
+//! chain is split into its gapless blocks, the diagonal segments of a dotplot
+//! path, with the 0-based, half-open interval of each sequence in them and
+//! their identity. The alignment's metadata is written with each chain:
+//!
+//! ```json
+//! {"query":{"id":"contig1","length":4000},
+//!  "target":{"id":"chr1","length":6000},
+//!  "chains":[{"query":[0,2500],"target":[3000,5500],"strand":"+",
+//!    "supplementary":false,"score":12000,"identity":0.99,"metadata":{"sample":"s1"},
+//!    "blocks":[{"query":[0,812],"target":[3000,3812],"identity":1}, ...]}]}
+//! ```
+//!
+//! Target intervals are on the forward strand. On the reverse strand a
+//! block's query start meets its target end, so the blocks of a chain run
+//! down the target as they run up the query.
+
+use std::{io, ops::Range};
+
+use thiserror::Error;
+
+use crate::align::{Alignment, InversionBlock, LocalAlignment, Mapping};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't write output")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Sequence is the ID and length of the query or target of the chains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sequence<'a> {
+    pub id: &'a str,
+    pub length: usize,
+}
+
+/// Chain is an alignment of regions of the query and target.
+#[derive(Clone, Debug)]
+pub struct Chain<'a> {
+    /// the query over the target, or over its reverse complement if reverse
+    pub alignment: &'a Alignment,
+
+    /// 0-based, half-open region of the query in the alignment
+    pub query: Range<usize>,
+
+    /// 0-based, half-open region of the forward target in the alignment
+    pub target: Range<usize>,
+
+    /// whether the query is aligned to the reverse strand of the target
+    pub reverse: bool,
+
+    /// whether this is a supplementary alignment of a split query
+    pub supplementary: bool,
+}
+
+impl<'a> From<&'a Mapping> for Chain<'a> {
+    fn from(mapping: &'a Mapping) -> Self {
+        Chain {
+            alignment: &mapping.alignment,
+            query: mapping.query.clone(),
+            target: mapping.reference.clone(),
+            reverse: false,
+            supplementary: mapping.supplementary,
+        }
+    }
+}
+
+impl<'a> From<&'a InversionBlock> for Chain<'a> {
+    fn from(block: &'a InversionBlock) -> Self {
+        Chain {
+            alignment: &block.alignment,
+            query: block.query.clone(),
+            target: block.reference.clone(),
+            reverse: block.inverted,
+            supplementary: false,
+        }
+    }
+}
+
+impl<'a> From<&'a LocalAlignment> for Chain<'a> {
+    fn from(local: &'a LocalAlignment) -> Self {
+        Chain {
+            alignment: &local.alignment,
+            query: local.a.clone(),
+            target: local.b.clone(),
+            reverse: false,
+            supplementary: false,
+        }
+    }
+}
+
+/// write the chains of a query to a target as one JSON object.
+pub fn write<W: io::Write>(
+    mut writer: W,
+    query: Sequence,
+    target: Sequence,
+    chains: &[Chain],
+) -> Result<()> {
+    let chains: Vec<String> = chains.iter().map(chain).collect();
+    writeln!(
+        writer,
+        "{{\"query\":{},\"target\":{},\"chains\":[{}]}}",
+        sequence(query),
+        sequence(target),
+        chains.join(",")
+    )?;
+    Ok(())
+}
+
+fn sequence(seq: Sequence) -> String {
+    format!("{{\"id\":{},\"length\":{}}}", string(seq.id), seq.length)
+}
+
+fn chain(chain: &Chain) -> String {
+    let rows = &chain.alignment.rows;
+    let columns = rows.first().map_or(0, |r| r.len());
+
+    // gapless runs of columns, as offsets into the query and target regions
+    // and their matches
+    let mut blocks: Vec<(Range<usize>, Range<usize>, usize)> = vec![];
+    let (mut q, mut t, mut matches) = (0, 0, 0);
+    let mut open = false;
+    for (a, b) in rows[0].iter().zip(rows[1].iter()) {
+        let (a, b) = (*a, *b);
+        match (a == '-', b == '-') {
+            (false, false) => {
+                if !open {
+                    blocks.push((q..q, t..t, 0));
+                    open = true;
+                }
+                let block = blocks.last_mut().unwrap();
+                block.0.end += 1;
+                block.1.end += 1;
+                if a.eq_ignore_ascii_case(&b) {
+                    block.2 += 1;
+                    matches += 1;
+                }
+                q += 1;
+                t += 1;
+            }
+            (a_gap, b_gap) => {
+                open = false;
+                q += !a_gap as usize;
+                t += !b_gap as usize;
+            }
+        }
+    }
+
+    let target = |offsets: &Range<usize>| match chain.reverse {
+        true => chain.target.end - offsets.end..chain.target.end - offsets.start,
+        false => chain.target.start + offsets.start..chain.target.start + offsets.end,
+    };
+    let blocks: Vec<String> = blocks
+        .iter()
+        .map(|(q, t, matches)| {
+            format!(
+                "{{\"query\":{},\"target\":{},\"identity\":{}}}",
+                interval(&(chain.query.start + q.start..chain.query.start + q.end)),
+                interval(&target(t)),
+                identity(*matches, q.len())
+            )
+        })
+        .collect();
+    let metadata: Vec<String> = chain
+        .alignment
+        .metadata
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), string(value)))
+        .collect();
+
+    format!(
+        "{{\"query\":{},\"target\":{},\"strand\":\"{}\",\"supplementary\":{},\"score\":{},\"identity\":{},\"metadata\":{{{}}},\"blocks\":[{}]}}",
+        interval(&chain.query),
+        interval(&chain.target),
+        if chain.reverse { '-' } else { '+' },
+        chain.supplementary,
+        number(chain.alignment.score),
+        identity(matches, columns),
+        metadata.join(","),
+        blocks.join(",")
+    )
+}
+
+fn interval(range: &Range<usize>) -> String {
+    format!("[{},{}]", range.start, range.end)
+}
+
+fn identity(matches: usize, columns: usize) -> String {
+    number(match columns {
+        0 => 0.,
+        _ => matches as f32 / columns as f32,
+    })
+}
+
+/// number is a float as JSON, which has no infinities or NaN.
+pub(crate) fn number(val: f32) -> String {
+    match val.is_finite() {
+        true => (val + 0f32).to_string(),
+        false => "null".to_string(),
+    }
+}
+
+/// string is a JSON string literal.
+pub(crate) fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_json() {
+        let alignment = Alignment::new(
+            vec!["ACGTT-ACG".chars().collect(), "ACCTTAACG".chars().collect()],
+            vec![],
+            20f32,
+        )
+        .with_metadata("sample", "s1")
+        .with_metadata("matrix", "NUC.4.4");
+        let forward = Chain {
+            alignment: &alignment,
+            query: 10..18,
+            target: 100..109,
+            reverse: false,
+            supplementary: false,
+        };
+        let reverse = Chain {
+            reverse: true,
+            supplementary: true,
+            ..forward.clone()
+        };
+
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            Sequence {
+                id: "read \"1\"",
+                length: 30,
+            },
+            Sequence {
+                id: "chr1",
+                length: 200,
+            },
+            &[forward, reverse],
+        )
+        .unwrap();
+        assert_eq!(
+            concat!(
+                "{\"query\":{\"id\":\"read \\\"1\\\"\",\"length\":30},",
+                "\"target\":{\"id\":\"chr1\",\"length\":200},\"chains\":[",
+                "{\"query\":[10,18],\"target\":[100,109],\"strand\":\"+\",\"supplementary\":false,",
+                "\"score\":20,\"identity\":0.7777778,",
+                "\"metadata\":{\"matrix\":\"NUC.4.4\",\"sample\":\"s1\"},\"blocks\":[",
+                "{\"query\":[10,15],\"target\":[100,105],\"identity\":0.8},",
+                "{\"query\":[15,18],\"target\":[106,109],\"identity\":1}]},",
+                "{\"query\":[10,18],\"target\":[100,109],\"strand\":\"-\",\"supplementary\":true,",
+                "\"score\":20,\"identity\":0.7777778,",
+                "\"metadata\":{\"matrix\":\"NUC.4.4\",\"sample\":\"s1\"},\"blocks\":[",
+                "{\"query\":[10,15],\"target\":[104,109],\"identity\":0.8},",
+                "{\"query\":[15,18],\"target\":[100,103],\"identity\":1}]}]}\n"
+            ),
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -1 +1,18 @@
+pub mod a2m;
+pub mod binary;
+pub mod blast;
+pub mod clustal;
+pub mod compression;
+pub mod convert;
 pub mod fasta;
+pub mod fastq;
+pub mod genbank;
+pub mod gfa;
+pub mod jalview;
+pub mod json;
+pub mod msf;
+pub mod nexus;
+pub mod paf;
+pub mod sam;
+pub mod stockholm;
+pub mod vcf;
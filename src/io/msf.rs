@@ -0,0 +1,288 @@
+//! A GCG MSF reader and writer for multiple alignments.
+//! https://www.genome.jp/tools/clustalw/clustalw_help.html#msf
+//!
+//! Gaps are written `.` and read as either `.` or `~`. Every sequence has a
+//! GCG checksum in the header, and the file a checksum of those on its
+//! ` MSF:` line; both are checked when reading, since files passed through
+//! other tools are often silently mangled.
+
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::align::MSAlignment;
+
+use super::{compression, nexus::Datatype};
+
+/// residues per line, in blocks of 10
+const LINE_WIDTH: usize = 50;
+const BLOCK_WIDTH: usize = 10;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("invalid MSF on line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    #[error("MSF has no sequences")]
+    MissingSequences,
+
+    #[error("sequence {id} has checksum {found}, its header says {expected}")]
+    ChecksumMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("MSF has checksum {found}, its header says {expected}")]
+    FileChecksumMismatch { expected: usize, found: usize },
+
+    #[error("sequence {0} is named more than once in the header")]
+    DuplicateName(String),
+
+    #[error("sequence {id} has {found} columns, its header says {expected}")]
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write MSF")]
+    IoError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// checksum is the GCG checksum of a gapped sequence as written in MSF.
+pub fn checksum(row: &[char]) -> usize {
+    row.iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let c = if *c == '-' {
+                '.'
+            } else {
+                c.to_ascii_uppercase()
+            };
+            (i % 57 + 1) * c as usize
+        })
+        .sum::<usize>()
+        % 10000
+}
+
+/// read parses an MSF alignment from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read<R: io::Read>(reader: R) -> Result<MSAlignment> {
+    // (id, length, checksum) from the header
+    let mut names: Vec<(String, usize, usize)> = vec![];
+    // checksum of the file, from its MSF: line
+    let mut file_check = None;
+    let mut rows: Vec<Vec<char>> = vec![];
+    let mut in_body = false;
+
+    for (i, line) in io::BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let invalid = || Error::InvalidLine(i + 1, line.clone());
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+
+        if !in_body {
+            let value = |key: &str| -> Result<usize> {
+                fields
+                    .iter()
+                    .position(|f| *f == key)
+                    .and_then(|p| fields.get(p + 1))
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(invalid)
+            };
+            if fields[0] == "//" {
+                in_body = true;
+                rows = vec![vec![]; names.len()];
+            } else if fields[0] == "Name:" {
+                let id = fields.get(1).ok_or_else(invalid)?.to_string();
+                if names.iter().any(|(name, _, _)| *name == id) {
+                    return Err(Error::DuplicateName(id));
+                }
+                names.push((id, value("Len:")?, value("Check:")?));
+            } else if fields.contains(&"MSF:") && names.is_empty() {
+                file_check = Some(value("Check:")?);
+            }
+            continue;
+        }
+
+        // lines of column numbers don't start with a name
+        if let Some(row) = names.iter().position(|(id, _, _)| id == fields[0]) {
+            rows[row].extend(fields[1..].iter().flat_map(|f| f.chars()).map(|c| match c {
+                '.' | '~' => '-',
+                c => c,
+            }));
+        }
+    }
+
+    if names.is_empty() {
+        return Err(Error::MissingSequences);
+    }
+    for ((id, len, check), row) in names.iter().zip(rows.iter()) {
+        if row.len() != *len || row.len() != rows[0].len() {
+            return Err(Error::LengthMismatch {
+                id: id.clone(),
+                expected: *len,
+                found: row.len(),
+            });
+        }
+        if checksum(row) != *check {
+            return Err(Error::ChecksumMismatch {
+                id: id.clone(),
+                expected: *check,
+                found: checksum(row),
+            });
+        }
+    }
+
+    let found = names.iter().map(|(_, _, check)| check).sum::<usize>() % 10000;
+    match file_check {
+        Some(expected) if expected != found => {
+            return Err(Error::FileChecksumMismatch { expected, found })
+        }
+        _ => {}
+    }
+
+    Ok(MSAlignment::new(
+        names.into_iter().map(|(id, _, _)| id).collect(),
+        rows,
+    ))
+}
+
+/// from_path reads an MSF file, decompressing it if it's compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<MSAlignment> {
+    let path = path.as_ref();
+    let reader = compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    read(reader)
+}
+
+/// write an alignment as MSF to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub fn write<W: io::Write>(msa: &MSAlignment, mut w: W) -> Result<()> {
+    let checks: Vec<usize> = msa.rows.iter().map(|r| checksum(r)).collect();
+    let kind = match Datatype::of(msa) {
+        Datatype::Protein => "P",
+        _ => "N",
+    };
+    let width = msa.ids.iter().map(|id| id.len()).max().unwrap_or(0);
+
+    let header = if kind == "N" {
+        "!!NA_MULTIPLE_ALIGNMENT 1.0"
+    } else {
+        "!!AA_MULTIPLE_ALIGNMENT 1.0"
+    };
+    writeln!(w, "{}", header)?;
+    writeln!(w)?;
+    writeln!(
+        w,
+        " MSF: {}  Type: {}  Check: {}  ..",
+        msa.len(),
+        kind,
+        checks.iter().sum::<usize>() % 10000
+    )?;
+    writeln!(w)?;
+    for (id, check) in msa.ids.iter().zip(checks.iter()) {
+        writeln!(
+            w,
+            " Name: {:width$}  Len: {:5}  Check: {:4}  Weight: 1.00",
+            id,
+            msa.len(),
+            check,
+            width = width
+        )?;
+    }
+    writeln!(w)?;
+    writeln!(w, "//")?;
+
+    for start in (0..msa.len()).step_by(LINE_WIDTH) {
+        writeln!(w)?;
+        for (id, row) in msa.ids.iter().zip(msa.rows.iter()) {
+            let end = (start + LINE_WIDTH).min(row.len());
+            let blocks: Vec<String> = row[start..end]
+                .chunks(BLOCK_WIDTH)
+                .map(|b| b.iter().map(|c| if *c == '-' { '.' } else { *c }).collect())
+                .collect();
+            writeln!(w, "{:width$}  {}", id, blocks.join(" "), width = width)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msf() {
+        let msa = MSAlignment::new(
+            vec!["seq1".into(), "s2".into()],
+            vec![
+                "ACGT--ACGTACGTACGTAC".chars().collect(),
+                "ACGTTTACG-ACGTACG---".chars().collect(),
+            ],
+        );
+
+        assert_eq!(65 + 2 * 67 + 3 * 46, checksum(&['a', 'C', '-']));
+
+        let mut out = Vec::new();
+        write(&msa, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        let checks: Vec<usize> = msa.rows.iter().map(|r| checksum(r)).collect();
+        assert_eq!(
+            format!(
+                "!!NA_MULTIPLE_ALIGNMENT 1.0
+
+ MSF: 20  Type: N  Check: {}  ..
+
+ Name: seq1  Len:    20  Check: {:4}  Weight: 1.00
+ Name: s2    Len:    20  Check: {:4}  Weight: 1.00
+
+//
+
+seq1  ACGT..ACGT ACGTACGTAC
+s2    ACGTTTACG. ACGTACG...
+",
+                (checks[0] + checks[1]) % 10000,
+                checks[0],
+                checks[1]
+            ),
+            written
+        );
+        assert_eq!(msa, read(written.as_bytes()).unwrap());
+
+        let tildes = written.replace("ACGTACG...", "ACGTACG~~~");
+        assert_eq!(msa, read(tildes.as_bytes()).unwrap());
+        let mangled = written.replace("ACGTACG...", "ACGTACGT..");
+        assert!(matches!(
+            read(mangled.as_bytes()),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+
+        // the file's checksum is of the sequences' in the header
+        let file_check = format!("Check: {}  ..", (checks[0] + checks[1]) % 10000);
+        let mangled = written.replace(&file_check, "Check: 10000  ..");
+        assert!(matches!(
+            read(mangled.as_bytes()),
+            Err(Error::FileChecksumMismatch {
+                expected: 10000,
+                ..
+            })
+        ));
+
+        let twice = written.replace(" Name: s2  ", " Name: seq1");
+        assert!(matches!(
+            read(twice.as_bytes()),
+            Err(Error::DuplicateName(id)) if id == "seq1"
+        ));
+    }
+}
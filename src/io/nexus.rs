@@ -0,0 +1,153 @@
+//! A NEXUS writer for multiple alignments.
+//! https://doi.org/10.1093/sysbio/46.4.590
+//!
+//! Each alignment is a DATA block with its dimensions, datatype, and gap and
+//! missing symbols declared, in the non-interleaved form MrBayes and PAUP*
+//! read.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::align::MSAlignment;
+
+/// symbol of missing data
+const MISSING: char = '?';
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't write output")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Datatype of the characters of an alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Datatype {
+    Dna,
+    Rna,
+    Protein,
+}
+
+impl Datatype {
+    /// of an alignment: DNA or RNA if every residue is a nucleotide, or an
+    /// IUPAC ambiguity code, and protein otherwise.
+    pub fn of(msa: &MSAlignment) -> Self {
+        let residues = || msa.rows.iter().flatten().filter(|c| **c != '-');
+        if !residues().all(|c| "ACGTURYSWKMBDHVN".contains(c.to_ascii_uppercase())) {
+            Datatype::Protein
+        } else if residues().any(|c| c.eq_ignore_ascii_case(&'U')) {
+            Datatype::Rna
+        } else {
+            Datatype::Dna
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Datatype::Dna => "DNA",
+            Datatype::Rna => "RNA",
+            Datatype::Protein => "PROTEIN",
+        }
+    }
+}
+
+// A NEXUS Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Writes the header to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn new(mut writer: W) -> Result<Self> {
+        writeln!(writer, "#NEXUS")?;
+        Ok(Writer { writer })
+    }
+
+    /// write an alignment as a DATA block.
+    pub fn write(&mut self, msa: &MSAlignment, datatype: Datatype) -> Result<()> {
+        let names: Vec<String> = msa.ids.iter().map(|id| quote(id)).collect();
+        let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+
+        writeln!(self.writer)?;
+        writeln!(self.writer, "BEGIN DATA;")?;
+        writeln!(
+            self.writer,
+            "\tDIMENSIONS NTAX={} NCHAR={};",
+            msa.rows.len(),
+            msa.len()
+        )?;
+        writeln!(
+            self.writer,
+            "\tFORMAT DATATYPE={} MISSING={} GAP=-;",
+            datatype.as_str(),
+            MISSING
+        )?;
+        writeln!(self.writer, "\tMATRIX")?;
+        for (name, row) in names.iter().zip(msa.rows.iter()) {
+            writeln!(
+                self.writer,
+                "\t{:width$}  {}",
+                name,
+                row.iter().collect::<String>(),
+                width = width
+            )?;
+        }
+        writeln!(self.writer, "\t;")?;
+        writeln!(self.writer, "END;")?;
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// quote a name in single quotes if it has anything but letters, digits,
+/// and the punctuation NEXUS allows in a bare word.
+fn quote(name: &str) -> String {
+    let bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.|#".contains(c));
+    if bare {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer_nexus() {
+        let msa = MSAlignment::new(
+            vec!["seq_1".into(), "E. coli".into()],
+            vec!["AC--GT".chars().collect(), "ACTTGN".chars().collect()],
+        );
+        assert_eq!(Datatype::Dna, Datatype::of(&msa));
+
+        let mut w = Writer::new(Vec::new()).unwrap();
+        w.write(&msa, Datatype::of(&msa)).unwrap();
+        assert_eq!(
+            "#NEXUS
+
+BEGIN DATA;
+\tDIMENSIONS NTAX=2 NCHAR=6;
+\tFORMAT DATATYPE=DNA MISSING=? GAP=-;
+\tMATRIX
+\tseq_1      AC--GT
+\t'E. coli'  ACTTGN
+\t;
+END;
+",
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+
+        let protein = MSAlignment::new(vec!["p".into()], vec!["MKV-L".chars().collect()]);
+        assert_eq!(Datatype::Protein, Datatype::of(&protein));
+    }
+}
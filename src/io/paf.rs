@@ -0,0 +1,123 @@
+//! A writer of local and semi-global alignments as PAF, the pairwise mapping
+//! format of minimap2.
+//! https://github.com/lh3/miniasm/blob/master/PAF.md
+//!
+//! The query is a and the target is b. Their lengths and the aligned
+//! regions come from the clips of the alignment, and the CIGAR of the
+//! aligned region is in a `cg` tag.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::align::Clipped;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("can't write output")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// A PAF Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Write to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn new(writer: W) -> Self {
+        Writer { writer }
+    }
+
+    /// write the alignment of a query, as a, to a target, as b. It's a local
+    /// alignment, or a semi-global one whose overhangs are clipped.
+    ///
+    /// Empty alignments aren't written.
+    pub fn write<A: Clipped>(
+        &mut self,
+        query_id: &str,
+        target_id: &str,
+        alignment: &A,
+    ) -> Result<()> {
+        if alignment.is_unaligned() {
+            return Ok(());
+        }
+
+        let clips = alignment.clips();
+        let (a_len, b_len) = alignment.aligned_len();
+        let rows = alignment.aligned();
+        let matches = rows[0]
+            .iter()
+            .zip(rows[1].iter())
+            .filter(|(a, b)| **a != '-' && a.eq_ignore_ascii_case(b))
+            .count();
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t+\t{}\t{}\t{}\t{}\t{}\t{}\t255\tAS:i:{}\tcg:Z:{}",
+            query_id,
+            clips.a_start + a_len + clips.a_end,
+            clips.a_start,
+            clips.a_start + a_len,
+            target_id,
+            clips.b_start + b_len + clips.b_end,
+            clips.b_start,
+            clips.b_start + b_len,
+            matches,
+            rows[0].len(),
+            alignment.alignment().score.round() as i64,
+            alignment.aligned_cigar()
+        )?;
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        align::{align_local, Alignment, Scoring},
+        matrices::NUC_4_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_writer_paf() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let local = align_local("CCCCACGTACGTCCCCC", "GGACGTTACGTGG", &scoring);
+
+        let mut w = Writer::new(Vec::new());
+        w.write("read1", "ref", &local).unwrap();
+        w.write("read2", "ref", &align_local("AAAA", "CCCC", &scoring))
+            .unwrap();
+
+        // the overhangs of a semi-global alignment are outside the region
+        let overlap = Alignment::new(
+            vec![
+                "TTACGT-AC---".chars().collect(),
+                "--ACGTTACGGG".chars().collect(),
+            ],
+            vec![],
+            12f32,
+        );
+        w.write("read3", "ref", &overlap).unwrap();
+        assert_eq!(
+            format!(
+                "read1\t17\t4\t12\t+\tref\t13\t2\t11\t8\t9\t255\tAS:i:{}\tcg:Z:3M1D5M\n\
+                 read3\t8\t2\t8\t+\tref\t10\t0\t7\t6\t7\t255\tAS:i:12\tcg:Z:4M1D2M\n",
+                local.alignment.score as i64
+            ),
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+    }
+}
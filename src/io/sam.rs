@@ -0,0 +1,630 @@
+//! A minimal SAM reader and writer for local and semi-global alignments of
+//! queries to references.
+//! https://samtools.github.io/hts-specs/SAMv1.pdf
+//!
+//! Each alignment is one unpaired record. The unaligned ends of the query are
+//! soft clips, so the record keeps the whole query sequence, or hard clips
+//! if the writer is set to drop them. The metadata of an alignment is written
+//! as its optional tags. Records read are kept field by field, with their
+//! optional tags as text, and header lines are skipped.
+//!
+//! Reads mapped by another tool can be realigned to a haplotype of a region,
+//! like the alternate allele of an indel, with [`realign`]: the reads that
+//! overlap the region are aligned locally to the haplotype and come back with
+//! their CIGARs and positions in it.
+
+use std::{
+    io::{self, BufRead},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::align::{align_local, Clipped, Metadata, Scoring};
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("reference {0} isn't in the SAM header")]
+    UnknownReference(String),
+
+    #[error("query {id} has {found} residues, its alignment expected {expected}")]
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("metadata {0} isn't a valid SAM tag")]
+    InvalidTag(String),
+
+    #[error("invalid SAM record on line {0}: {1}")]
+    InvalidRecord(usize, String),
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write SAM")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Reference is a reference sequence declared in the SAM header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    pub id: String,
+    pub length: usize,
+}
+
+/// Record is an alignment line of a SAM file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub qname: String,
+    pub flag: u16,
+
+    /// reference name, `*` if unmapped
+    pub rname: String,
+
+    /// 1-based leftmost position in the reference, 0 if unmapped
+    pub pos: usize,
+    pub mapq: u8,
+
+    /// CIGAR, `*` if unavailable
+    pub cigar: String,
+    pub rnext: String,
+    pub pnext: usize,
+    pub tlen: i64,
+
+    /// the query, on the forward strand of the reference
+    pub seq: String,
+    pub qual: String,
+
+    /// optional fields, like `AS:i:40`
+    pub tags: Vec<String>,
+}
+
+impl Record {
+    /// is_unmapped is whether the record's unmapped flag is set.
+    pub fn is_unmapped(&self) -> bool {
+        self.flag & 0x4 != 0
+    }
+
+    /// reference_range is the 0-based, half-open region of the reference
+    /// the record's CIGAR covers, None if it's unmapped or has no CIGAR.
+    pub fn reference_range(&self) -> Option<Range<usize>> {
+        if self.is_unmapped() || self.pos == 0 || self.cigar == "*" {
+            return None;
+        }
+        let mut len = 0;
+        let mut digits = 0;
+        for c in self.cigar.chars() {
+            match c {
+                '0'..='9' => digits = 10 * digits + c.to_digit(10).unwrap() as usize,
+                'M' | 'D' | 'N' | '=' | 'X' => {
+                    len += digits;
+                    digits = 0
+                }
+                _ => digits = 0,
+            }
+        }
+        Some(self.pos - 1..self.pos - 1 + len)
+    }
+
+    /// metadata are the record's optional tags, by name, with their values
+    /// as text: the metadata of the alignment it was written from.
+    pub fn metadata(&self) -> Metadata {
+        self.tags
+            .iter()
+            .filter_map(|tag| {
+                let mut fields = tag.splitn(3, ':');
+                let (name, _, value) = (fields.next()?, fields.next()?, fields.next()?);
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// parse a tab-separated SAM line.
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 11 {
+            return None;
+        }
+        Some(Record {
+            qname: fields[0].to_string(),
+            flag: fields[1].parse().ok()?,
+            rname: fields[2].to_string(),
+            pos: fields[3].parse().ok()?,
+            mapq: fields[4].parse().ok()?,
+            cigar: fields[5].to_string(),
+            rnext: fields[6].to_string(),
+            pnext: fields[7].parse().ok()?,
+            tlen: fields[8].parse().ok()?,
+            seq: fields[9].to_string(),
+            qual: fields[10].to_string(),
+            tags: fields[11..].iter().map(|t| t.to_string()).collect(),
+        })
+    }
+}
+
+// A SAM Reader.
+pub struct Reader<R> {
+    lines: io::Lines<io::BufReader<R>>,
+    line: usize,
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Read from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+    pub fn new(reader: R) -> Self {
+        Reader {
+            lines: io::BufReader::new(reader).lines(),
+            line: 0,
+        }
+    }
+}
+
+impl Reader<Box<dyn io::Read>> {
+    /// from_path opens a SAM file, decompressing it if it's compressed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let reader = compression::open(path).map_err(|source| Error::FileOpen {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Reader::new(reader))
+    }
+}
+
+impl<R: io::Read> Iterator for Reader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+            if line.starts_with('@') || line.trim().is_empty() {
+                continue;
+            }
+            return Some(Record::parse(&line).ok_or(Error::InvalidRecord(self.line, line)));
+        }
+    }
+}
+
+/// Haplotype is a sequence of a region of a reference, like one with an
+/// indel, to realign the region's reads to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Haplotype {
+    /// name of the haplotype, the reference of the realigned records
+    pub id: String,
+
+    /// the reference and 0-based, half-open region of it the haplotype is of
+    pub reference: String,
+    pub region: Range<usize>,
+
+    pub seq: String,
+}
+
+impl Haplotype {
+    /// sam_reference is the haplotype as a reference of a SAM header.
+    pub fn sam_reference(&self) -> Reference {
+        Reference {
+            id: self.id.clone(),
+            length: self.seq.len(),
+        }
+    }
+}
+
+/// realign aligns the mapped records that overlap the region of a haplotype
+/// locally to it, and returns them with the haplotype as their reference.
+///
+/// Their positions, CIGARs and `AS` tags are those of the new alignment, with
+/// the ends of the read outside it soft clipped. `NM` and `MD` tags, which
+/// would be stale, are dropped. Records without a sequence are skipped.
+pub fn realign<I>(records: I, haplotype: &Haplotype, scoring: &Scoring) -> Result<Vec<Record>>
+where
+    I: IntoIterator<Item = Result<Record>>,
+{
+    let mut realigned = vec![];
+    for record in records {
+        let mut record = record?;
+        let overlaps = record
+            .reference_range()
+            .is_some_and(|r| r.start < haplotype.region.end && haplotype.region.start < r.end);
+        if record.rname != haplotype.reference || !overlaps || record.seq == "*" {
+            continue;
+        }
+
+        let local = align_local(&record.seq, &haplotype.seq, scoring);
+        record.rname = haplotype.id.clone();
+        record
+            .tags
+            .retain(|t| !["AS:", "NM:", "MD:"].iter().any(|p| t.starts_with(p)));
+        match local.a.is_empty() {
+            true => {
+                record.flag |= 0x4;
+                (record.pos, record.cigar) = (0, "*".to_string());
+            }
+            false => {
+                record.pos = local.b.start + 1;
+                record.cigar = clipped_cigar(&local, Clip::Soft);
+                record
+                    .tags
+                    .push(format!("AS:i:{}", local.alignment.score.round() as i64));
+            }
+        }
+        realigned.push(record);
+    }
+    Ok(realigned)
+}
+
+/// Clip is how the clipped ends of a query are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Clip {
+    /// soft clipped, `S`, and kept in the record's sequence
+    #[default]
+    Soft,
+
+    /// hard clipped, `H`, and left out of the record's sequence
+    Hard,
+}
+
+/// clipped_cigar is the CIGAR of an alignment with its clips.
+fn clipped_cigar<A: Clipped>(alignment: &A, clip: Clip) -> String {
+    let op = match clip {
+        Clip::Soft => 'S',
+        Clip::Hard => 'H',
+    };
+    let clips = alignment.clips();
+    let mut cigar = String::new();
+    if clips.a_start > 0 {
+        cigar.push_str(&format!("{}{}", clips.a_start, op));
+    }
+    cigar.push_str(&alignment.aligned_cigar());
+    if clips.a_end > 0 {
+        cigar.push_str(&format!("{}{}", clips.a_end, op));
+    }
+    cigar
+}
+
+/// tags are the metadata of an alignment as optional fields, each key a tag
+/// name and each value an integer, `i`, or text, `Z`.
+fn tags(metadata: &Metadata) -> Result<Vec<String>> {
+    metadata
+        .iter()
+        .map(|(key, value)| {
+            let name = key.as_bytes();
+            let valid = name.len() == 2
+                && name[0].is_ascii_alphabetic()
+                && name[1].is_ascii_alphanumeric()
+                // the writer's own
+                && key != "AS"
+                && value.bytes().all(|c| (b' '..=b'~').contains(&c));
+            if !valid {
+                return Err(Error::InvalidTag(key.clone()));
+            }
+            Ok(match value.parse::<i64>() {
+                Ok(n)
+                    if n.to_string() == *value
+                        && (i32::MIN as i64..=u32::MAX as i64).contains(&n) =>
+                {
+                    format!("{}:i:{}", key, value)
+                }
+                _ => format!("{}:Z:{}", key, value),
+            })
+        })
+        .collect()
+}
+
+// A SAM Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+    references: Vec<Reference>,
+    clip: Clip,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Writes the header for the references to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn new(mut writer: W, references: Vec<Reference>) -> Result<Self> {
+        writeln!(writer, "@HD\tVN:1.6\tSO:unsorted")?;
+        for reference in references.iter() {
+            writeln!(writer, "@SQ\tSN:{}\tLN:{}", reference.id, reference.length)?;
+        }
+        writeln!(writer, "@PG\tID:seqalign\tPN:seqalign")?;
+
+        Ok(Writer {
+            writer,
+            references,
+            clip: Clip::Soft,
+        })
+    }
+
+    /// with_clip sets how the clipped ends of queries are written.
+    pub fn with_clip(mut self, clip: Clip) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    /// write the alignment of a query, as a, to a reference from the header,
+    /// as b. It's a local alignment, or a semi-global one whose overhangs
+    /// are clipped.
+    ///
+    /// The metadata of the alignment is written after its `AS` tag, each key
+    /// a tag name. Keys that aren't tag names are an error.
+    pub fn write<A: Clipped>(
+        &mut self,
+        query_id: &str,
+        query: &str,
+        reference: &str,
+        alignment: &A,
+    ) -> Result<()> {
+        if !self.references.iter().any(|r| r.id == reference) {
+            return Err(Error::UnknownReference(reference.to_string()));
+        }
+        let clips = alignment.clips();
+        let (aligned, _) = alignment.aligned_len();
+        let expected = clips.a_start + aligned + clips.a_end;
+        if query.chars().count() != expected {
+            return Err(Error::LengthMismatch {
+                id: query_id.to_string(),
+                expected,
+                found: query.chars().count(),
+            });
+        }
+        let mut tags = tags(&alignment.alignment().metadata)?;
+
+        if alignment.is_unaligned() {
+            write!(
+                self.writer,
+                "{}\t4\t*\t0\t0\t*\t*\t0\t0\t{}\t*",
+                query_id, query
+            )?;
+        } else {
+            let seq = match self.clip {
+                Clip::Soft => query.to_string(),
+                Clip::Hard => query.chars().skip(clips.a_start).take(aligned).collect(),
+            };
+            write!(
+                self.writer,
+                "{}\t0\t{}\t{}\t255\t{}\t*\t0\t0\t{}\t*",
+                query_id,
+                reference,
+                clips.b_start + 1,
+                clipped_cigar(alignment, self.clip),
+                seq,
+            )?;
+            let score = alignment.alignment().score.round() as i64;
+            tags.insert(0, format!("AS:i:{}", score));
+        }
+        for tag in tags {
+            write!(self.writer, "\t{}", tag)?;
+        }
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    /// write_record writes a record as it is, to a reference from the header.
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        if record.rname != "*" && !self.references.iter().any(|r| r.id == record.rname) {
+            return Err(Error::UnknownReference(record.rname.clone()));
+        }
+        write!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            record.qname,
+            record.flag,
+            record.rname,
+            record.pos,
+            record.mapq,
+            record.cigar,
+            record.rnext,
+            record.pnext,
+            record.tlen,
+            record.seq,
+            record.qual
+        )?;
+        for tag in record.tags.iter() {
+            write!(self.writer, "\t{}", tag)?;
+        }
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align::Alignment, matrices::NUC_4_4};
+
+    use super::*;
+
+    #[test]
+    fn test_writer_sam() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let (query, reference) = ("CCCCACGTACGTCCCCC", "GGACGTTACGTGG");
+        let local = align_local(query, reference, &scoring);
+
+        let mut w = Writer::new(
+            Vec::new(),
+            vec![Reference {
+                id: "ref".to_string(),
+                length: reference.len(),
+            }],
+        )
+        .unwrap();
+        w.write("read1", query, "ref", &local).unwrap();
+        assert!(matches!(
+            w.write("read1", query, "chr1", &local),
+            Err(Error::UnknownReference(_))
+        ));
+        assert!(matches!(
+            w.write("read1", "ACGT", "ref", &local),
+            Err(Error::LengthMismatch { found: 4, .. })
+        ));
+
+        assert_eq!(
+            format!(
+                "@HD\tVN:1.6\tSO:unsorted
+@SQ\tSN:ref\tLN:13
+@PG\tID:seqalign\tPN:seqalign
+read1\t0\tref\t3\t255\t4S3M1D5M5S\t*\t0\t0\t{}\t*\tAS:i:{}
+",
+                query, local.alignment.score as i64
+            ),
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_writer_sam_metadata() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        let (query, reference) = ("CCCCACGTACGTCCCCC", "GGACGTTACGTGG");
+        let writer = || {
+            let reference = Reference {
+                id: "ref".to_string(),
+                length: reference.len(),
+            };
+            Writer::new(Vec::new(), vec![reference]).unwrap()
+        };
+        let local = align_local(query, reference, &scoring);
+        let with = |key: &str, value: &str| {
+            let mut local = align_local(query, reference, &scoring);
+            local.alignment = local.alignment.with_metadata(key, value);
+            local
+        };
+
+        // metadata is written as tags, and read back from them
+        let mut tagged = with("RG", "sample 1");
+        tagged.alignment = tagged
+            .alignment
+            .with_metadata("XN", "-42")
+            .with_metadata("XV", "007");
+        let mut w = writer();
+        w.write("read1", query, "ref", &tagged).unwrap();
+        let sam = String::from_utf8(w.into_inner()).unwrap();
+        let record = Reader::new(sam.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(
+            vec![
+                format!("AS:i:{}", local.alignment.score as i64),
+                "RG:Z:sample 1".to_string(),
+                "XN:i:-42".to_string(),
+                "XV:Z:007".to_string(),
+            ],
+            record.tags
+        );
+        let mut metadata = record.metadata();
+        metadata.remove("AS");
+        assert_eq!(tagged.alignment.metadata, metadata);
+
+        // keys that aren't tag names, and values that can't be in one, aren't
+        for (key, value) in [("sample", "1"), ("1X", "1"), ("AS", "1"), ("XT", "a\tb")] {
+            assert!(matches!(
+                writer().write("read1", query, "ref", &with(key, value)),
+                Err(Error::InvalidTag(k)) if k == key
+            ));
+        }
+    }
+
+    #[test]
+    fn test_writer_sam_clips() {
+        // the overhangs of a semi-global alignment are clipped
+        let overlap = Alignment::new(
+            vec![
+                "TTACGT-AC---".chars().collect(),
+                "--ACGTTACGGG".chars().collect(),
+            ],
+            vec![],
+            12f32,
+        );
+        let reference = Reference {
+            id: "ref".to_string(),
+            length: 10,
+        };
+        let mut w = Writer::new(Vec::new(), vec![reference.clone()]).unwrap();
+        w.write("read1", "TTACGTAC", "ref", &overlap).unwrap();
+        let mut hard = Writer::new(Vec::new(), vec![reference])
+            .unwrap()
+            .with_clip(Clip::Hard);
+        hard.write("read1", "TTACGTAC", "ref", &overlap).unwrap();
+
+        let record = |w: Writer<Vec<u8>>| {
+            let sam = String::from_utf8(w.into_inner()).unwrap();
+            Reader::new(sam.as_bytes()).next().unwrap().unwrap()
+        };
+        let soft = record(w);
+        assert_eq!(
+            (1, "2S4M1D2M", "TTACGTAC"),
+            (soft.pos, soft.cigar.as_str(), soft.seq.as_str())
+        );
+        let hard = record(hard);
+        assert_eq!(
+            (1, "2H4M1D2M", "ACGTAC"),
+            (hard.pos, hard.cigar.as_str(), hard.seq.as_str())
+        );
+    }
+
+    #[test]
+    fn test_realign() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -10f32,
+            gap_extension: -1f32,
+            ..Default::default()
+        };
+        // the mapper placed a 3bp deletion as mismatches and a clip
+        let sam = "@HD\tVN:1.6
+@SQ\tSN:chr1\tLN:1000
+r1\t0\tchr1\t101\t60\t14M6S\t*\t0\t0\tGGCATTACGACGTCAGGTTC\t*\tNM:i:4\tAS:i:30
+r2\t16\tchr1\t500\t60\t10M\t*\t0\t0\tACGTACGTAC\t*
+r3\t4\t*\t0\t0\t*\t*\t0\t0\tACGTACGTAC\t*
+";
+        let records: Vec<Record> = Reader::new(sam.as_bytes()).map(|r| r.unwrap()).collect();
+        assert_eq!(3, records.len());
+        assert_eq!(Some(100..114), records[0].reference_range());
+        assert_eq!(None, records[2].reference_range());
+
+        let haplotype = Haplotype {
+            id: "chr1:90-130:del".to_string(),
+            reference: "chr1".to_string(),
+            region: 90..130,
+            seq: "TTTTTTTTTTGGCATTACGACGTCAGGTTCAAAAAAAAA".to_string(),
+        };
+        let realigned = realign(records.into_iter().map(Ok), &haplotype, &scoring).unwrap();
+        assert_eq!(1, realigned.len());
+        let r1 = &realigned[0];
+        assert_eq!(
+            ("chr1:90-130:del", 11, "20M"),
+            (r1.rname.as_str(), r1.pos, r1.cigar.as_str())
+        );
+        assert_eq!(vec!["AS:i:100"], r1.tags);
+
+        let mut w = Writer::new(Vec::new(), vec![haplotype.sam_reference()]).unwrap();
+        w.write_record(r1).unwrap();
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert!(out.ends_with(
+            "r1\t0\tchr1:90-130:del\t11\t60\t20M\t*\t0\t0\tGGCATTACGACGTCAGGTTC\t*\tAS:i:100\n"
+        ));
+
+        assert!(matches!(
+            Reader::new("r1\t0\tchr1\n".as_bytes()).next(),
+            Some(Err(Error::InvalidRecord(1, _)))
+        ));
+    }
+}
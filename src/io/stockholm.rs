@@ -0,0 +1,272 @@
+//! A reader and writer of Stockholm multiple alignments, as used by Pfam,
+//! Rfam and HMMER.
+//! https://sonnhammer.sbc.su.se/Stockholm.html
+//!
+//! Only the first alignment of a file is read. Its markup is kept in the
+//! alignment's metadata, and written back out from it:
+//!
+//! - `#=GF <tag> <text>` as `GF:<tag>`, repeated lines joined by newlines
+//! - `#=GC <tag> <columns>` as `GC:<tag>`
+//! - `#=GS <id> <tag> <text>` as `GS:<id>:<tag>`
+//! - `#=GR <id> <tag> <columns>` as `GR:<id>:<tag>`
+//!
+//! Gaps may be written `-`, `.`, `_` or `~` and are all read as `-`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::align::{MSAlignment, Metadata};
+
+use super::compression;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("expected a STOCKHOLM header")]
+    MissingHeader,
+
+    #[error("invalid Stockholm line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    #[error("sequence {id} has {found} columns, expected {expected}")]
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("can't open {path} file: {source}")]
+    FileOpen { path: PathBuf, source: io::Error },
+
+    #[error("can't read or write Stockholm")]
+    IoError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// read parses a Stockholm alignment from a given [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub fn read<R: io::Read>(reader: R) -> Result<MSAlignment> {
+    let mut lines = io::BufReader::new(reader).lines().enumerate();
+    let header = lines.next().map(|(_, l)| l).transpose()?;
+    if !header.is_some_and(|h| h.starts_with("# STOCKHOLM")) {
+        return Err(Error::MissingHeader);
+    }
+
+    let mut ids: Vec<String> = vec![];
+    let mut rows: Vec<Vec<char>> = vec![];
+    // row of each ID, since a row's blocks are split across the file
+    let mut row_of: HashMap<String, usize> = HashMap::new();
+    let mut metadata = Metadata::new();
+    for (i, line) in lines {
+        let line = line?;
+        let invalid = || Error::InvalidLine(i + 1, line.clone());
+        let line = line.trim_end();
+        if line == "//" {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(markup) = line.strip_prefix("#=") {
+            let (kind, rest) = markup.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let (key, value, append) = match kind {
+                "GF" | "GC" => {
+                    let (tag, value) = split_field(rest).ok_or_else(invalid)?;
+                    (format!("{}:{}", kind, tag), value, kind == "GC")
+                }
+                "GS" | "GR" => {
+                    let (id, rest) = split_field(rest).ok_or_else(invalid)?;
+                    let (tag, value) = split_field(rest).ok_or_else(invalid)?;
+                    (format!("{}:{}:{}", kind, id, tag), value, kind == "GR")
+                }
+                _ => return Err(invalid()),
+            };
+            match metadata.get_mut(&key) {
+                Some(existing) if append => existing.push_str(value),
+                Some(existing) => {
+                    existing.push('\n');
+                    existing.push_str(value);
+                }
+                None => {
+                    metadata.insert(key, value.to_string());
+                }
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (id, residues) = split_field(line).ok_or_else(invalid)?;
+        let residues = residues.chars().map(|c| match c {
+            '.' | '_' | '~' => '-',
+            c => c,
+        });
+        match row_of.get(id) {
+            Some(row) => rows[*row].extend(residues),
+            None => {
+                row_of.insert(id.to_string(), ids.len());
+                ids.push(id.to_string());
+                rows.push(residues.collect());
+            }
+        }
+    }
+
+    let expected = rows.first().map_or(0, |r| r.len());
+    if let Some(row) = rows.iter().position(|r| r.len() != expected) {
+        return Err(Error::LengthMismatch {
+            id: ids[row].clone(),
+            expected,
+            found: rows[row].len(),
+        });
+    }
+    let mut msa = MSAlignment::new(ids, rows);
+    msa.metadata = metadata;
+    Ok(msa)
+}
+
+/// from_path reads a Stockholm file, decompressing it if it's compressed.
+pub fn from_path<P: AsRef<Path>>(path: P) -> Result<MSAlignment> {
+    let path = path.as_ref();
+    let reader = compression::open(path).map_err(|source| Error::FileOpen {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    read(reader)
+}
+
+/// write an alignment and the markup in its metadata as Stockholm to a given
+/// [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+///
+/// Other metadata is left out.
+pub fn write<W: io::Write>(msa: &MSAlignment, mut w: W) -> Result<()> {
+    // (kind, id, tag, value) of the markup
+    let markup: Vec<(&str, Option<&str>, &str, &str)> = msa
+        .metadata
+        .iter()
+        .filter_map(|(key, value)| {
+            let mut parts = key.splitn(3, ':');
+            match (parts.next()?, parts.next()?, parts.next()) {
+                (kind @ ("GF" | "GC"), tag, None) => Some((kind, None, tag, value.as_str())),
+                (kind @ ("GS" | "GR"), id, Some(tag)) => {
+                    Some((kind, Some(id), tag, value.as_str()))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    let width = msa
+        .ids
+        .iter()
+        .map(|id| id.len())
+        .chain(markup.iter().map(|(kind, id, tag, _)| match *kind {
+            "GR" => 6 + id.unwrap_or_default().len() + tag.len(),
+            _ => 5 + tag.len(),
+        }))
+        .max()
+        .unwrap_or(0);
+    let of_kind = |k: &'static str| markup.iter().filter(move |(kind, ..)| *kind == k);
+
+    writeln!(w, "# STOCKHOLM 1.0")?;
+    for (_, _, tag, value) in of_kind("GF") {
+        for line in value.lines() {
+            writeln!(w, "#=GF {} {}", tag, line)?;
+        }
+    }
+    for (_, id, tag, value) in of_kind("GS") {
+        writeln!(w, "#=GS {} {} {}", id.unwrap_or_default(), tag, value)?;
+    }
+    writeln!(w)?;
+    for (id, row) in msa.ids.iter().zip(msa.rows.iter()) {
+        writeln!(
+            w,
+            "{:width$} {}",
+            id,
+            row.iter().collect::<String>(),
+            width = width
+        )?;
+        for (_, _, tag, value) in of_kind("GR").filter(|(_, i, ..)| *i == Some(id.as_str())) {
+            let label = format!("#=GR {} {}", id, tag);
+            writeln!(w, "{:width$} {}", label, value, width = width)?;
+        }
+    }
+    for (_, _, tag, value) in of_kind("GC") {
+        let label = format!("#=GC {}", tag);
+        writeln!(w, "{:width$} {}", label, value, width = width)?;
+    }
+    writeln!(w, "//")?;
+    Ok(())
+}
+
+/// split_field splits the first whitespace-separated field from the rest of a line.
+fn split_field(s: &str) -> Option<(&str, &str)> {
+    let (field, rest) = s.trim_start().split_once(char::is_whitespace)?;
+    Some((field, rest.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_stockholm() {
+        let msa = read(
+            "# STOCKHOLM 1.0
+#=GF ID   example
+#=GF CC   first line
+#=GF CC   second line
+#=GS seq1 AC P12345
+
+seq1         ACGT..AC
+seq2         ACGTTTA-
+#=GR seq1 SS <<..>>..
+#=GC SS_cons <<..>>..
+
+seq1         GT
+seq2         G~
+#=GC SS_cons ..
+//
+seq3         ignored
+"
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(vec!["seq1", "seq2"], msa.ids);
+        assert_eq!("ACGT--ACGT\nACGTTTA-G-", msa.to_string());
+        assert_eq!("example", msa.metadata["GF:ID"]);
+        assert_eq!("first line\nsecond line", msa.metadata["GF:CC"]);
+        assert_eq!("P12345", msa.metadata["GS:seq1:AC"]);
+        assert_eq!("<<..>>..", msa.metadata["GR:seq1:SS"]);
+        assert_eq!("<<..>>....", msa.metadata["GC:SS_cons"]);
+
+        let mut out = Vec::new();
+        write(&msa, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert_eq!(
+            "# STOCKHOLM 1.0
+#=GF CC first line
+#=GF CC second line
+#=GF ID example
+#=GS seq1 AC P12345
+
+seq1         ACGT--ACGT
+#=GR seq1 SS <<..>>..
+seq2         ACGTTTA-G-
+#=GC SS_cons <<..>>....
+//
+",
+            written
+        );
+        assert_eq!(msa, read(written.as_bytes()).unwrap());
+
+        assert!(matches!(
+            read("# STOCKHOLM 1.0\na ACGT\nb ACG\n//\n".as_bytes()),
+            Err(Error::LengthMismatch { found: 3, .. })
+        ));
+    }
+}
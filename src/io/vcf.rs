@@ -0,0 +1,167 @@
+//! A minimal VCF 4.2 writer for variants called from alignments.
+//! https://samtools.github.io/hts-specs/VCFv4.2.pdf
+//!
+//! Files have a single sample and a GT format field, which is enough to load
+//! them in IGV or pass them to annotators.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::align::Variant;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("contig {0} isn't in the VCF header")]
+    UnknownContig(String),
+
+    #[error("can't write output")]
+    WriteError(#[from] io::Error),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Contig is a reference sequence declared in the VCF header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contig {
+    pub id: String,
+    pub length: usize,
+}
+
+/// Genotype of the single sample at a variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Genotype {
+    /// one copy, the alternate allele (e.g. a Sanger read or a plasmid)
+    #[default]
+    Haploid,
+
+    /// two copies of the alternate allele
+    HomozygousAlt,
+
+    /// one copy each of the reference and alternate allele
+    Heterozygous,
+}
+
+impl Genotype {
+    fn as_str(&self) -> &str {
+        match self {
+            Genotype::Haploid => "1",
+            Genotype::HomozygousAlt => "1/1",
+            Genotype::Heterozygous => "0/1",
+        }
+    }
+}
+
+// A VCF Writer.
+pub struct Writer<W: io::Write> {
+    writer: W,
+    contigs: Vec<Contig>,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Writes the header for the contigs and sample to a given [`io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+    pub fn new(mut writer: W, contigs: Vec<Contig>, sample: &str) -> Result<Self> {
+        writeln!(writer, "##fileformat=VCFv4.2")?;
+        writeln!(writer, "##source=seqalign")?;
+        for contig in contigs.iter() {
+            writeln!(
+                writer,
+                "##contig=<ID={},length={}>",
+                contig.id, contig.length
+            )?;
+        }
+        writeln!(
+            writer,
+            "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">"
+        )?;
+        writeln!(
+            writer,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\t{}",
+            sample
+        )?;
+
+        Ok(Writer { writer, contigs })
+    }
+
+    /// write a variant on a contig from the header.
+    pub fn write(&mut self, contig: &str, variant: &Variant, genotype: Genotype) -> Result<()> {
+        if !self.contigs.iter().any(|c| c.id == contig) {
+            return Err(Error::UnknownContig(contig.to_string()));
+        }
+
+        writeln!(
+            self.writer,
+            "{}\t{}\t.\t{}\t{}\t.\tPASS\t.\tGT\t{}",
+            contig,
+            variant.pos,
+            variant.reference,
+            variant.alt,
+            genotype.as_str()
+        )?;
+        Ok(())
+    }
+
+    /// into_inner returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::align::Alignment;
+
+    use super::*;
+
+    #[test]
+    fn test_writer_vcf() {
+        let alignment = Alignment::new(
+            vec![
+                "ACGTAC--GTTTAC".chars().collect(),
+                "ACCTACGTGT-TAC".chars().collect(),
+            ],
+            vec![],
+            0f32,
+        );
+
+        let mut w = Writer::new(
+            Vec::new(),
+            vec![Contig {
+                id: "pUC19".to_string(),
+                length: 2686,
+            }],
+            "read1",
+        )
+        .unwrap();
+        for variant in alignment.variants(10) {
+            w.write("pUC19", &variant, Genotype::Haploid).unwrap();
+        }
+
+        assert_eq!(
+            "##fileformat=VCFv4.2
+##source=seqalign
+##contig=<ID=pUC19,length=2686>
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tread1
+pUC19\t13\t.\tG\tC\t.\tPASS\t.\tGT\t1
+pUC19\t16\t.\tC\tCGT\t.\tPASS\t.\tGT\t1
+pUC19\t17\t.\tGT\tG\t.\tPASS\t.\tGT\t1
+",
+            String::from_utf8(w.into_inner()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_writer_vcf_unknown_contig() {
+        let mut w = Writer::new(Vec::new(), vec![], "read1").unwrap();
+        let alignment = Alignment::new(
+            vec!["ACGT".chars().collect(), "ACCT".chars().collect()],
+            vec![],
+            0f32,
+        );
+
+        assert!(matches!(
+            w.write("chr1", &alignment.variants(0)[0], Genotype::Heterozygous),
+            Err(Error::UnknownContig(_))
+        ));
+    }
+}
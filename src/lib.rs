@@ -0,0 +1,14 @@
+pub mod align;
+pub mod assembly;
+pub mod graph;
+pub mod intervals;
+pub mod io;
+pub mod matrices;
+pub mod search;
+pub mod seq;
+pub mod sketch;
+pub mod stats;
+pub mod trim;
+pub mod viz;
+
+mod trace;
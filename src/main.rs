@@ -1,11 +1,10 @@
-use std::{fs::File, io::BufReader};
-
-use align::{align, Method};
 use clap::Parser;
-
-pub mod align;
-pub mod io;
-pub mod matrices;
+use seqalign::{
+    align::{
+        align, align_with_quality, AlignConfig, GapChars, MSAlignment, Method, Symbols, TerminalGap,
+    },
+    io,
+};
 
 /// Align sequences in a FASTA using one of the supported sequence alignment algorithms
 #[derive(Parser, Debug)]
@@ -15,52 +14,158 @@ struct Args {
     #[arg(index = 1, value_name = "FILE", required = true)]
     file: String,
 
-    /// Algorithm to use
-    #[arg(value_enum, short, long, default_value_t = Method::NeedlemanWunsch)]
-    algo: Method,
+    /// Config file of alignment parameters, TOML or YAML, overridden by the flags below
+    #[arg(long, value_name = "CONFIG")]
+    config: Option<String>,
+
+    /// Algorithm to use [default: needleman-wunsch]
+    #[arg(value_enum, short, long)]
+    algo: Option<Method>,
+
+    /// Name of the replacement matrix [default: BLOSUM62]
+    #[arg(short, long)]
+    replacement_matrix: Option<String>,
+
+    /// Penalty of opening a gap [default: -1]
+    #[arg(long, allow_negative_numbers = true)]
+    gap_opening_penalty: Option<f32>,
+
+    /// Penalty of extending a gap [default: -1]
+    #[arg(long, allow_negative_numbers = true)]
+    gap_extension_penalty: Option<f32>,
 
-    /// Name of the replacement matrix
-    #[arg(short, long, default_value = "BLOSUM62")]
-    replacement_matrix: String,
+    /// Penalty of gaps at the ends of the sequences (global alignment) [default: full]
+    #[arg(value_enum, long)]
+    terminal_gaps: Option<TerminalGap>,
 
-    /// Penalty of opening a gap
-    #[arg(long, default_value_t = -1.0)]
-    gap_opening_penalty: f32,
+    /// FASTQ file of reads to align to the first sequence, weighting scores by base quality
+    #[arg(long, value_name = "FASTQ")]
+    reads: Option<String>,
 
-    /// Penalty of extending a gap
-    #[arg(long, default_value_t = -1.0)]
-    gap_extension_penalty: f32,
+    /// FASTA file of sequences to add to the alignment in FILE, keeping its columns fixed
+    #[arg(long, value_name = "FASTA")]
+    add: Option<String>,
+
+    /// Print a line between the rows of pairwise alignments marking identical and similar residues
+    #[arg(long)]
+    conservation: bool,
+
+    /// Character to write gaps with
+    #[arg(long, default_value_t = '-')]
+    gap_char: char,
+
+    /// Character to write gaps before and after a sequence with, if not the gap character
+    #[arg(long)]
+    terminal_gap_char: Option<char>,
+
+    /// Convert the alignment in FILE, in the format of its extension, to another format
+    #[arg(value_enum, long, value_name = "FORMAT")]
+    convert: Option<io::convert::Format>,
 }
 
 fn main() {
     let args = Args::parse();
 
+    // Convert an alignment between formats
+    if let Some(to) = args.convert {
+        let from = io::convert::Format::from_path(&args.file).expect("Unknown alignment format");
+        let reader = io::compression::open(&args.file).expect("Unable to open file");
+        io::convert::convert(reader, from, std::io::stdout().lock(), to)
+            .expect("Unable to convert alignment");
+        return;
+    }
+
     // Read seqs
-    let file = File::open(&args.file).expect("Unable to open file");
-    let reader = BufReader::new(file);
-    let mut reader_fasta = io::fasta::Reader::new(reader);
+    let mut reader_fasta = io::fasta::Reader::from_path(&args.file).expect("Unable to open file");
     let seq1 = reader_fasta
         .next()
         .expect("Missing first seq to align")
         .unwrap();
-    let seq2 = reader_fasta
-        .next()
-        .expect("Missing second seq to align")
-        .unwrap();
 
-    // Create scoring
-    let scoring = &align::Scoring {
-        matrix: match args.replacement_matrix.as_str() {
-            "blosum62" => matrices::BLOSUM62::MATRIX,
-            "nuc" => matrices::NUC_4_4::MATRIX,
-            _ => panic!("Unknown matrix"),
-        },
-        gap_opening: args.gap_opening_penalty,
-        gap_extension: args.gap_extension_penalty,
+    // Create scoring, from the config file then the flags
+    let mut config = match &args.config {
+        Some(path) => AlignConfig::from_path(path).expect("Invalid config"),
+        None => AlignConfig::default(),
     };
+    if let Some(algo) = args.algo {
+        config.method = algo;
+    }
+    if let Some(matrix) = args.replacement_matrix {
+        config.matrix = matrix;
+    }
+    if let Some(penalty) = args.gap_opening_penalty {
+        config.gap_opening = penalty;
+    }
+    if let Some(penalty) = args.gap_extension_penalty {
+        config.gap_extension = penalty;
+    }
+    if let Some(terminal_gaps) = args.terminal_gaps {
+        config.terminal_gaps = terminal_gaps;
+    }
+    config.validate().expect("Invalid parameters");
+    let scoring = &config.scoring().unwrap();
+    let symbols = args.conservation.then(Symbols::default);
+    let gaps = GapChars {
+        gap: args.gap_char,
+        terminal: args.terminal_gap_char,
+    };
+
+    // Add sequences to an existing alignment
+    if let Some(add) = &args.add {
+        let mut ids = vec![seq1.id().to_string()];
+        let mut rows = vec![seq1.seq.chars().collect()];
+        for record in reader_fasta {
+            let record = record.expect("Unable to read alignment");
+            ids.push(record.id().to_string());
+            rows.push(record.seq.chars().collect());
+        }
+        let mut msa = MSAlignment::new(ids, rows);
+
+        let reader_add = io::fasta::Reader::from_path(add).expect("Unable to open file");
+        for record in reader_add {
+            let record = record.expect("Unable to read FASTA record");
+            msa.add(record.id(), &record.seq, scoring);
+        }
+        for (id, row) in msa.ids.iter().zip(msa.rows_with(&gaps)) {
+            println!(">{}\n{}", id, row);
+        }
+        return;
+    }
+
+    // Align reads to the first sequence
+    if let Some(reads) = &args.reads {
+        let reader_fastq = io::fastq::Reader::from_path(reads).expect("Unable to open reads");
+        for read in reader_fastq {
+            let read = read.expect("Unable to read FASTQ record");
+            let alignment = align_with_quality(
+                &seq1.seq,
+                &read.seq,
+                &read.qual,
+                config.method.strategy(),
+                scoring,
+            );
+            println!(
+                "{}\n{}\n",
+                read.id,
+                alignment
+                    .conserved(&scoring.matrix, symbols)
+                    .with_gaps(gaps)
+            );
+        }
+        return;
+    }
 
     // Align a couple sequences
-    let alignment = align(vec![seq1.seq, seq2.seq], args.algo.strategy(), scoring);
+    let seq2 = reader_fasta
+        .next()
+        .expect("Missing second seq to align")
+        .unwrap();
+    let alignment = align(vec![seq1.seq, seq2.seq], config.method.strategy(), scoring);
 
-    println!("{}", alignment)
+    println!(
+        "{}",
+        alignment
+            .conserved(&scoring.matrix, symbols)
+            .with_gaps(gaps)
+    )
 }
@@ -0,0 +1,189 @@
+//! Introspection and validation of scoring matrices.
+//!
+//! A [`Matrix`] is a bare 128 x 128 table, so nothing stops one from being
+//! asymmetric, having holes in its alphabet, or scoring random residues
+//! positively on average, which makes local alignments run the length of the
+//! sequences and their statistics meaningless. These report that before it
+//! silently skews results.
+
+use ::std::prelude::rust_2021::*;
+use ::std::{fmt, vec};
+
+use crate::stats::background::Frequencies;
+
+use super::Matrix;
+
+/// MatrixWarning is a problem with a matrix found by [`MatrixInfo::validate`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatrixWarning {
+    /// a and b score differently than b and a
+    Asymmetric { a: char, b: char },
+
+    /// a and b are both in the alphabet but have no score together
+    Undefined { a: char, b: char },
+
+    /// the expected score of random residues isn't negative
+    NonNegativeExpectedScore(f64),
+
+    /// no pair of residues with a background frequency scores above 0
+    NoPositiveScore,
+
+    /// no residue with a background frequency is in the alphabet
+    EmptyAlphabet,
+}
+
+impl fmt::Display for MatrixWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixWarning::Asymmetric { a, b } => {
+                write!(f, "{}/{} and {}/{} score differently", a, b, b, a)
+            }
+            MatrixWarning::Undefined { a, b } => write!(f, "{}/{} has no score", a, b),
+            MatrixWarning::NonNegativeExpectedScore(mean) => write!(
+                f,
+                "expected score of random residues is {}, local alignments will be global",
+                mean
+            ),
+            MatrixWarning::NoPositiveScore => write!(f, "no pair of residues scores above 0"),
+            MatrixWarning::EmptyAlphabet => {
+                write!(f, "background frequencies don't overlap the alphabet")
+            }
+        }
+    }
+}
+
+/// MatrixInfo lists what's in a matrix and checks it for misuse.
+pub trait MatrixInfo {
+    /// alphabet is the residues with a score against themselves, in ASCII order.
+    fn alphabet(&self) -> Vec<char>;
+
+    /// score of aligning two residues, None if the matrix has none.
+    fn score(&self, a: char, b: char) -> Option<i32>;
+
+    /// is_symmetric is whether every pair scores the same in either order.
+    fn is_symmetric(&self) -> bool;
+
+    /// expected_score is the mean score of a pair of residues drawn from the
+    /// background frequencies, None if none of them are in the alphabet.
+    fn expected_score(&self, freqs: &Frequencies) -> Option<f64>;
+
+    /// validate lists the problems with the matrix for scoring residues with
+    /// the background frequencies, empty if there are none.
+    fn validate(&self, freqs: &Frequencies) -> Vec<MatrixWarning>;
+}
+
+impl MatrixInfo for Matrix {
+    fn alphabet(&self) -> Vec<char> {
+        (0..128u8)
+            .filter(|c| self[*c as usize][*c as usize] != i32::MIN)
+            .map(char::from)
+            .collect()
+    }
+
+    fn score(&self, a: char, b: char) -> Option<i32> {
+        if !a.is_ascii() || !b.is_ascii() {
+            return None;
+        }
+        Some(self[a as usize][b as usize]).filter(|s| *s != i32::MIN)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        (0..128).all(|a| (0..a).all(|b| self[a][b] == self[b][a]))
+    }
+
+    fn expected_score(&self, freqs: &Frequencies) -> Option<f64> {
+        let (mut total, mut weight) = (0f64, 0f64);
+        for a in self.alphabet() {
+            for b in self.alphabet() {
+                let p = freqs[a as usize] * freqs[b as usize];
+                if let (Some(s), true) = (self.score(a, b), p > 0f64) {
+                    total += s as f64 * p;
+                    weight += p;
+                }
+            }
+        }
+        (weight > 0f64).then(|| total / weight)
+    }
+
+    fn validate(&self, freqs: &Frequencies) -> Vec<MatrixWarning> {
+        let alphabet = self.alphabet();
+        let mut warnings = vec![];
+        for (i, a) in alphabet.iter().enumerate() {
+            for b in &alphabet[..i] {
+                match (self.score(*a, *b), self.score(*b, *a)) {
+                    (None, _) | (_, None) => {
+                        warnings.push(MatrixWarning::Undefined { a: *b, b: *a })
+                    }
+                    (x, y) if x != y => warnings.push(MatrixWarning::Asymmetric { a: *b, b: *a }),
+                    _ => {}
+                }
+            }
+        }
+
+        match self.expected_score(freqs) {
+            None => warnings.push(MatrixWarning::EmptyAlphabet),
+            Some(mean) => {
+                if mean >= 0f64 {
+                    warnings.push(MatrixWarning::NonNegativeExpectedScore(mean));
+                }
+                let positive = alphabet.iter().any(|a| {
+                    alphabet.iter().any(|b| {
+                        freqs[*a as usize] > 0f64
+                            && freqs[*b as usize] > 0f64
+                            && self.score(*a, *b).is_some_and(|s| s > 0)
+                    })
+                });
+                if !positive {
+                    warnings.push(MatrixWarning::NoPositiveScore);
+                }
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::std::{assert, assert_eq};
+
+    use crate::{
+        matrices::{BLOSUM62, NUC_4_4},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_matrix_info() {
+        let blosum = &BLOSUM62::MATRIX;
+        assert!(blosum.alphabet().starts_with(&['*', 'A', 'B', 'C']));
+        assert_eq!(Some(4), blosum.score('A', 'A'));
+        assert_eq!(None, blosum.score('a', 'A'));
+        assert!(blosum.is_symmetric());
+        assert!(blosum.expected_score(&background::robinson()).unwrap() < 0f64);
+        assert!(blosum.validate(&background::robinson()).is_empty());
+
+        let mut bogus = NUC_4_4::MATRIX;
+        bogus[b'A' as usize][b'C' as usize] = 5;
+        bogus[b'G' as usize][b'T' as usize] = i32::MIN;
+        for a in "ACGT".chars() {
+            for b in "ACGT".chars() {
+                if a != b && bogus[a as usize][b as usize] != i32::MIN {
+                    bogus[a as usize][b as usize] = bogus[a as usize][b as usize].max(0);
+                }
+            }
+        }
+        let warnings = bogus.validate(&background::nucleotide());
+        assert!(!bogus.is_symmetric());
+        assert!(warnings.contains(&MatrixWarning::Asymmetric { a: 'A', b: 'C' }));
+        assert!(warnings.contains(&MatrixWarning::Undefined { a: 'G', b: 'T' }));
+        assert!(matches!(
+            warnings.last(),
+            Some(MatrixWarning::NonNegativeExpectedScore(_))
+        ));
+        assert_eq!(
+            vec![MatrixWarning::EmptyAlphabet],
+            NUC_4_4::MATRIX.validate(&background::uniform("EFILPQ"))
+        );
+    }
+}
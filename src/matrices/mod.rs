@@ -1,13 +1,337 @@
 #![no_implicit_prelude]
 
+use ::std::{iter::Iterator, option::Option};
+
+pub use crate::matrices::info::MatrixInfo;
+pub use crate::matrices::info::MatrixWarning;
+
 /// Matrix is a single alignment matrix used in scoring an alignment.
 ///
 /// Maps a char to another char and the corresponding substitution penalty.
 pub type Matrix = [[i32; 128]; 128];
 
+/// MATRICES is every bundled matrix, by the name of its file in `data`.
+pub static MATRICES: [(&str, &Matrix); 94] = [
+    ("BLOSUM100", &BLOSUM100::MATRIX),
+    ("BLOSUM100.50", &BLOSUM100_50::MATRIX),
+    ("BLOSUM30", &BLOSUM30::MATRIX),
+    ("BLOSUM30.50", &BLOSUM30_50::MATRIX),
+    ("BLOSUM35", &BLOSUM35::MATRIX),
+    ("BLOSUM35.50", &BLOSUM35_50::MATRIX),
+    ("BLOSUM40", &BLOSUM40::MATRIX),
+    ("BLOSUM40.50", &BLOSUM40_50::MATRIX),
+    ("BLOSUM45", &BLOSUM45::MATRIX),
+    ("BLOSUM45.50", &BLOSUM45_50::MATRIX),
+    ("BLOSUM50", &BLOSUM50::MATRIX),
+    ("BLOSUM50.50", &BLOSUM50_50::MATRIX),
+    ("BLOSUM55", &BLOSUM55::MATRIX),
+    ("BLOSUM55.50", &BLOSUM55_50::MATRIX),
+    ("BLOSUM60", &BLOSUM60::MATRIX),
+    ("BLOSUM60.50", &BLOSUM60_50::MATRIX),
+    ("BLOSUM62", &BLOSUM62::MATRIX),
+    ("BLOSUM62.50", &BLOSUM62_50::MATRIX),
+    ("BLOSUM65", &BLOSUM65::MATRIX),
+    ("BLOSUM65.50", &BLOSUM65_50::MATRIX),
+    ("BLOSUM70", &BLOSUM70::MATRIX),
+    ("BLOSUM70.50", &BLOSUM70_50::MATRIX),
+    ("BLOSUM75", &BLOSUM75::MATRIX),
+    ("BLOSUM75.50", &BLOSUM75_50::MATRIX),
+    ("BLOSUM80", &BLOSUM80::MATRIX),
+    ("BLOSUM80.50", &BLOSUM80_50::MATRIX),
+    ("BLOSUM85", &BLOSUM85::MATRIX),
+    ("BLOSUM85.50", &BLOSUM85_50::MATRIX),
+    ("BLOSUM90", &BLOSUM90::MATRIX),
+    ("BLOSUM90.50", &BLOSUM90_50::MATRIX),
+    ("BLOSUMN", &BLOSUMN::MATRIX),
+    ("BLOSUMN.50", &BLOSUMN_50::MATRIX),
+    ("DAYHOFF", &DAYHOFF::MATRIX),
+    ("GONNET", &GONNET::MATRIX),
+    ("IDENTITY", &IDENTITY::MATRIX),
+    ("MATCH", &MATCH::MATRIX),
+    ("NUC.4.2", &NUC_4_2::MATRIX),
+    ("NUC.4.4", &NUC_4_4::MATRIX),
+    ("PAM10", &PAM10::MATRIX),
+    ("PAM100", &PAM100::MATRIX),
+    ("PAM110", &PAM110::MATRIX),
+    ("PAM120", &PAM120::MATRIX),
+    ("PAM120.cdi", &PAM120_CDI::MATRIX),
+    ("PAM130", &PAM130::MATRIX),
+    ("PAM140", &PAM140::MATRIX),
+    ("PAM150", &PAM150::MATRIX),
+    ("PAM160", &PAM160::MATRIX),
+    ("PAM160.cdi", &PAM160_CDI::MATRIX),
+    ("PAM170", &PAM170::MATRIX),
+    ("PAM180", &PAM180::MATRIX),
+    ("PAM190", &PAM190::MATRIX),
+    ("PAM20", &PAM20::MATRIX),
+    ("PAM200", &PAM200::MATRIX),
+    ("PAM200.cdi", &PAM200_CDI::MATRIX),
+    ("PAM210", &PAM210::MATRIX),
+    ("PAM220", &PAM220::MATRIX),
+    ("PAM230", &PAM230::MATRIX),
+    ("PAM240", &PAM240::MATRIX),
+    ("PAM250", &PAM250::MATRIX),
+    ("PAM250.cdi", &PAM250_CDI::MATRIX),
+    ("PAM260", &PAM260::MATRIX),
+    ("PAM270", &PAM270::MATRIX),
+    ("PAM280", &PAM280::MATRIX),
+    ("PAM290", &PAM290::MATRIX),
+    ("PAM30", &PAM30::MATRIX),
+    ("PAM300", &PAM300::MATRIX),
+    ("PAM310", &PAM310::MATRIX),
+    ("PAM320", &PAM320::MATRIX),
+    ("PAM330", &PAM330::MATRIX),
+    ("PAM340", &PAM340::MATRIX),
+    ("PAM350", &PAM350::MATRIX),
+    ("PAM360", &PAM360::MATRIX),
+    ("PAM370", &PAM370::MATRIX),
+    ("PAM380", &PAM380::MATRIX),
+    ("PAM390", &PAM390::MATRIX),
+    ("PAM40", &PAM40::MATRIX),
+    ("PAM400", &PAM400::MATRIX),
+    ("PAM40.cdi", &PAM40_CDI::MATRIX),
+    ("PAM410", &PAM410::MATRIX),
+    ("PAM420", &PAM420::MATRIX),
+    ("PAM430", &PAM430::MATRIX),
+    ("PAM440", &PAM440::MATRIX),
+    ("PAM450", &PAM450::MATRIX),
+    ("PAM460", &PAM460::MATRIX),
+    ("PAM470", &PAM470::MATRIX),
+    ("PAM480", &PAM480::MATRIX),
+    ("PAM490", &PAM490::MATRIX),
+    ("PAM50", &PAM50::MATRIX),
+    ("PAM500", &PAM500::MATRIX),
+    ("PAM60", &PAM60::MATRIX),
+    ("PAM70", &PAM70::MATRIX),
+    ("PAM80", &PAM80::MATRIX),
+    ("PAM80.cdi", &PAM80_CDI::MATRIX),
+    ("PAM90", &PAM90::MATRIX),
+];
+
+/// named is the bundled matrix with a name, ignoring case and whether it's
+/// written with dots or underscores, like "NUC.4.4" or "nuc_4_4". "NUC" is
+/// short for NUC.4.4.
+pub fn named(name: &str) -> Option<&'static Matrix> {
+    let name = match name.eq_ignore_ascii_case("NUC") {
+        true => "NUC.4.4",
+        false => name,
+    };
+    MATRICES
+        .iter()
+        .find(|(n, _)| {
+            n.len() == name.len()
+                && n.bytes()
+                    .zip(name.bytes())
+                    .all(|(a, b)| a.eq_ignore_ascii_case(&b) || (a == b'.' && b == b'_'))
+        })
+        .map(|(_, matrix)| *matrix)
+}
+
+#[allow(non_snake_case)]
+pub mod BLOSUM100;
+#[allow(non_snake_case)]
+pub mod BLOSUM100_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM30;
+#[allow(non_snake_case)]
+pub mod BLOSUM30_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM35;
+#[allow(non_snake_case)]
+pub mod BLOSUM35_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM40;
+#[allow(non_snake_case)]
+pub mod BLOSUM40_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM45;
+#[allow(non_snake_case)]
+pub mod BLOSUM45_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM50;
+#[allow(non_snake_case)]
+pub mod BLOSUM50_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM55;
+#[allow(non_snake_case)]
+pub mod BLOSUM55_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM60;
+#[allow(non_snake_case)]
+pub mod BLOSUM60_50;
 #[allow(non_snake_case)]
 pub mod BLOSUM62;
 #[allow(non_snake_case)]
+pub mod BLOSUM62_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM65;
+#[allow(non_snake_case)]
+pub mod BLOSUM65_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM70;
+#[allow(non_snake_case)]
+pub mod BLOSUM70_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM75;
+#[allow(non_snake_case)]
+pub mod BLOSUM75_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM80;
+#[allow(non_snake_case)]
+pub mod BLOSUM80_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM85;
+#[allow(non_snake_case)]
+pub mod BLOSUM85_50;
+#[allow(non_snake_case)]
+pub mod BLOSUM90;
+#[allow(non_snake_case)]
+pub mod BLOSUM90_50;
+#[allow(non_snake_case)]
+pub mod BLOSUMN;
+#[allow(non_snake_case)]
+pub mod BLOSUMN_50;
+#[allow(non_snake_case)]
+pub mod DAYHOFF;
+#[allow(non_snake_case)]
+pub mod GONNET;
+#[allow(non_snake_case)]
+pub mod IDENTITY;
+#[allow(non_snake_case)]
 pub mod MATCH;
 #[allow(non_snake_case)]
+pub mod NUC_4_2;
+#[allow(non_snake_case)]
 pub mod NUC_4_4;
+#[allow(non_snake_case)]
+pub mod PAM10;
+#[allow(non_snake_case)]
+pub mod PAM100;
+#[allow(non_snake_case)]
+pub mod PAM110;
+#[allow(non_snake_case)]
+pub mod PAM120;
+#[allow(non_snake_case)]
+pub mod PAM120_CDI;
+#[allow(non_snake_case)]
+pub mod PAM130;
+#[allow(non_snake_case)]
+pub mod PAM140;
+#[allow(non_snake_case)]
+pub mod PAM150;
+#[allow(non_snake_case)]
+pub mod PAM160;
+#[allow(non_snake_case)]
+pub mod PAM160_CDI;
+#[allow(non_snake_case)]
+pub mod PAM170;
+#[allow(non_snake_case)]
+pub mod PAM180;
+#[allow(non_snake_case)]
+pub mod PAM190;
+#[allow(non_snake_case)]
+pub mod PAM20;
+#[allow(non_snake_case)]
+pub mod PAM200;
+#[allow(non_snake_case)]
+pub mod PAM200_CDI;
+#[allow(non_snake_case)]
+pub mod PAM210;
+#[allow(non_snake_case)]
+pub mod PAM220;
+#[allow(non_snake_case)]
+pub mod PAM230;
+#[allow(non_snake_case)]
+pub mod PAM240;
+#[allow(non_snake_case)]
+pub mod PAM250;
+#[allow(non_snake_case)]
+pub mod PAM250_CDI;
+#[allow(non_snake_case)]
+pub mod PAM260;
+#[allow(non_snake_case)]
+pub mod PAM270;
+#[allow(non_snake_case)]
+pub mod PAM280;
+#[allow(non_snake_case)]
+pub mod PAM290;
+#[allow(non_snake_case)]
+pub mod PAM30;
+#[allow(non_snake_case)]
+pub mod PAM300;
+#[allow(non_snake_case)]
+pub mod PAM310;
+#[allow(non_snake_case)]
+pub mod PAM320;
+#[allow(non_snake_case)]
+pub mod PAM330;
+#[allow(non_snake_case)]
+pub mod PAM340;
+#[allow(non_snake_case)]
+pub mod PAM350;
+#[allow(non_snake_case)]
+pub mod PAM360;
+#[allow(non_snake_case)]
+pub mod PAM370;
+#[allow(non_snake_case)]
+pub mod PAM380;
+#[allow(non_snake_case)]
+pub mod PAM390;
+#[allow(non_snake_case)]
+pub mod PAM40;
+#[allow(non_snake_case)]
+pub mod PAM400;
+#[allow(non_snake_case)]
+pub mod PAM40_CDI;
+#[allow(non_snake_case)]
+pub mod PAM410;
+#[allow(non_snake_case)]
+pub mod PAM420;
+#[allow(non_snake_case)]
+pub mod PAM430;
+#[allow(non_snake_case)]
+pub mod PAM440;
+#[allow(non_snake_case)]
+pub mod PAM450;
+#[allow(non_snake_case)]
+pub mod PAM460;
+#[allow(non_snake_case)]
+pub mod PAM470;
+#[allow(non_snake_case)]
+pub mod PAM480;
+#[allow(non_snake_case)]
+pub mod PAM490;
+#[allow(non_snake_case)]
+pub mod PAM50;
+#[allow(non_snake_case)]
+pub mod PAM500;
+#[allow(non_snake_case)]
+pub mod PAM60;
+#[allow(non_snake_case)]
+pub mod PAM70;
+#[allow(non_snake_case)]
+pub mod PAM80;
+#[allow(non_snake_case)]
+pub mod PAM80_CDI;
+#[allow(non_snake_case)]
+pub mod PAM90;
+
+mod info;
+
+#[cfg(test)]
+mod tests {
+    use ::std::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_named() {
+        assert_eq!(Option::Some(&BLOSUM62::MATRIX), named("BLOSUM62"));
+        assert_eq!(Option::Some(&NUC_4_4::MATRIX), named("nuc.4.4"));
+        assert_eq!(Option::Some(&NUC_4_4::MATRIX), named("NUC_4_4"));
+        assert_eq!(Option::Some(&NUC_4_4::MATRIX), named("nuc"));
+        assert_eq!(Option::Some(&PAM250_CDI::MATRIX), named("PAM250.cdi"));
+        assert_eq!(Option::None, named("PAM251"));
+        assert_eq!(Option::None, named("NUC.4.4.4"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Hits of a query in a set of targets.
+//!
+//! A hit records which sequences it's between, by ID, on which strand and in
+//! which reading frame the query matched, where in each sequence, and how
+//! significant it is, so the results of a search can be sorted, filtered and
+//! reported without going back to the inputs.
+
+use std::{collections::HashSet, fmt::Display, ops::Range};
+
+use crate::align::Alignment;
+
+/// Strand of the query a hit is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strand {
+    Forward,
+
+    /// the reverse complement of the query matched
+    Reverse,
+}
+
+impl Display for Strand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// Hit is a local alignment of the query to one target.
+#[derive(Debug)]
+pub struct Hit {
+    /// ID of the query, empty if it was a bare sequence
+    pub query_id: String,
+
+    /// ID of the target, its 1-based index if it was a bare sequence
+    pub target_id: String,
+
+    /// index of the target
+    pub target: usize,
+
+    /// strand of the query that matched
+    pub strand: Strand,
+
+    /// reading frame of a translated query, 1 to 3 on the forward strand and
+    /// -1 to -3 on the reverse, None if it wasn't translated
+    pub frame: Option<i8>,
+
+    /// alignment score
+    pub score: f32,
+
+    /// normalized score, in bits
+    pub bit_score: f64,
+
+    /// number of hits at least this good expected by chance in the database
+    pub evalue: f64,
+
+    /// 0-based, half-open region of the query in the alignment, on the
+    /// forward strand even for hits on the reverse
+    pub query_range: Range<usize>,
+
+    /// 0-based, half-open region of the target in the alignment
+    pub target_range: Range<usize>,
+
+    /// the query, on the strand of the hit, over the target
+    pub alignment: Alignment,
+}
+
+impl Hit {
+    /// overlaps is whether two hits are to the same target and overlap in it.
+    pub fn overlaps(&self, other: &Hit) -> bool {
+        self.target == other.target
+            && self.target_range.start < other.target_range.end
+            && other.target_range.start < self.target_range.end
+    }
+}
+
+/// sort_hits sorts hits best first: by E-value, then score, then target.
+pub fn sort_hits(hits: &mut [Hit]) {
+    hits.sort_by(|a, b| {
+        a.evalue
+            .total_cmp(&b.evalue)
+            .then(b.score.total_cmp(&a.score))
+            .then(a.target.cmp(&b.target))
+            .then(a.strand.cmp(&b.strand))
+            .then(a.target_range.start.cmp(&b.target_range.start))
+    });
+}
+
+/// best_per_target keeps the best hit to each target, best first.
+pub fn best_per_target(mut hits: Vec<Hit>) -> Vec<Hit> {
+    sort_hits(&mut hits);
+    let mut seen = HashSet::new();
+    hits.retain(|h| seen.insert(h.target));
+    hits
+}
+
+/// remove_overlapping keeps the best of the hits that overlap in a target, best first.
+pub fn remove_overlapping(mut hits: Vec<Hit>) -> Vec<Hit> {
+    sort_hits(&mut hits);
+    let mut kept: Vec<Hit> = Vec::with_capacity(hits.len());
+    for hit in hits {
+        if !kept.iter().any(|k| k.overlaps(&hit)) {
+            kept.push(hit);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(target: usize, range: Range<usize>, evalue: f64) -> Hit {
+        Hit {
+            query_id: String::new(),
+            target_id: (target + 1).to_string(),
+            target,
+            strand: Strand::Forward,
+            frame: None,
+            score: 1f32,
+            bit_score: 1f64,
+            evalue,
+            query_range: 0..range.len(),
+            target_range: range,
+            alignment: Alignment::new(vec![vec![], vec![]], vec![], 1f32),
+        }
+    }
+
+    #[test]
+    fn test_hit_helpers() {
+        let hits = || {
+            vec![
+                hit(0, 10..20, 1e-3),
+                hit(1, 0..10, 1e-9),
+                hit(0, 15..30, 1e-6),
+                hit(0, 40..50, 1e-4),
+            ]
+        };
+
+        let best = best_per_target(hits());
+        assert_eq!(
+            vec![(1, 1e-9), (0, 1e-6)],
+            best.iter()
+                .map(|h| (h.target, h.evalue))
+                .collect::<Vec<_>>()
+        );
+
+        let kept = remove_overlapping(hits());
+        assert_eq!(
+            vec![0..10, 15..30, 40..50],
+            kept.iter()
+                .map(|h| h.target_range.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! k-mer index of a target database.
+//!
+//! An index is built once and saved so searches of a large database don't
+//! rebuild it every run. The file is a versioned header followed by every
+//! k-mer and its positions, all integers little-endian:
+//!
+//! - `SQAI`, then the format version as a u32
+//! - k, the seeding as a kind (0 for every k-mer, 1 for minimizers and 2 for
+//!   open syncmers) and two parameters (`w` and 0, or `s` and `t`), the
+//!   number of targets and their total length, as u64s. Version 1 files have
+//!   no seeding and index every k-mer, version 2 files have only `w`.
+//! - the number of k-mers as a u64, then for each, sorted: its k bytes, the
+//!   number of positions as a u32, and each position as u32 target and offset
+//!
+//! Rather than every k-mer, an index can keep a [`Seeding`] of them, like
+//! minimizers or syncmers, and query k-mers are picked the same way.
+//!
+//! With the `mmap` feature a saved index is memory-mapped to load it, so
+//! it's read straight from the page cache rather than copied through reads.
+//! Counts in a file are checked against its length before anything is
+//! allocated for them, so a corrupt index is an error rather than an abort.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::trace::stage;
+
+use super::{seeding::Seeding, Error, Result};
+
+const MAGIC: &[u8; 4] = b"SQAI";
+const VERSION: u32 = 3;
+
+/// Index maps every k-mer of the targets to where it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Index {
+    /// length of the k-mers
+    pub k: usize,
+
+    /// how the k-mers are picked
+    pub seeding: Seeding,
+
+    /// number of targets indexed
+    pub targets: usize,
+
+    /// total length of the targets
+    pub target_len: usize,
+
+    /// the targets and 0-based positions of each k-mer, in order
+    pub(super) words: HashMap<Vec<u8>, Vec<(u32, u32)>>,
+}
+
+impl Index {
+    /// new indexes the k-mers of the targets.
+    pub fn new<S: AsRef<str>>(targets: &[S], k: usize) -> Self {
+        Index::with_seeding(targets, k, Seeding::Dense)
+    }
+
+    /// with_minimizers indexes the minimizers of each `w` k-mers of the targets.
+    pub fn with_minimizers<S: AsRef<str>>(targets: &[S], k: usize, w: usize) -> Self {
+        Index::with_seeding(targets, k, Seeding::Minimizers { w })
+    }
+
+    /// with_seeding indexes the k-mers of the targets picked by a seeding,
+    /// its parameters [`normalized`](Seeding::normalized) for k.
+    pub fn with_seeding<S: AsRef<str>>(targets: &[S], k: usize, seeding: Seeding) -> Self {
+        let _stage = stage!("index", targets = targets.len(), k = k);
+        let k = k.max(1);
+        let seeding = seeding.normalized(k);
+        let mut words: HashMap<Vec<u8>, Vec<(u32, u32)>> = HashMap::new();
+        let mut target_len = 0;
+        for (t, target) in targets.iter().enumerate() {
+            let target = target.as_ref().as_bytes();
+            target_len += target.len();
+            for pos in seeding.positions(target, k) {
+                words
+                    .entry(target[pos..pos + k].to_vec())
+                    .or_default()
+                    .push((t as u32, pos as u32));
+            }
+        }
+
+        Index {
+            k,
+            seeding,
+            targets: targets.len(),
+            target_len,
+            words,
+        }
+    }
+
+    /// candidates counts the distinct k-mers of the query in each target, for
+    /// the targets with at least `min_words`, most shared first.
+    pub fn candidates(&self, query: &str, min_words: usize) -> Vec<(usize, usize)> {
+        self.candidates_of(self.words_of(query).collect(), min_words)
+    }
+
+    /// candidates_unmasked is `candidates` without the k-mers of the query
+    /// that have lowercase, soft-masked, residues.
+    pub fn candidates_unmasked(&self, query: &str, min_words: usize) -> Vec<(usize, usize)> {
+        let words = self
+            .words_of(query)
+            .filter(|w| !w.iter().any(u8::is_ascii_lowercase))
+            .collect();
+        self.candidates_of(words, min_words)
+    }
+
+    /// words_of the query picked the same way as the targets'.
+    fn words_of<'a>(&self, query: &'a str) -> impl Iterator<Item = &'a [u8]> {
+        let (query, k) = (query.as_bytes(), self.k);
+        self.seeding
+            .positions(query, k)
+            .into_iter()
+            .map(move |pos| &query[pos..pos + k])
+    }
+
+    fn candidates_of(&self, mut query_words: Vec<&[u8]>, min_words: usize) -> Vec<(usize, usize)> {
+        query_words.sort_unstable();
+        query_words.dedup();
+
+        let mut shared = vec![0usize; self.targets];
+        for word in query_words {
+            if let Some(hits) = self.words.get(word) {
+                let mut last = None;
+                for (t, _) in hits {
+                    if last != Some(*t) {
+                        shared[*t as usize] += 1;
+                        last = Some(*t);
+                    }
+                }
+            }
+        }
+
+        let mut candidates: Vec<(usize, usize)> = shared
+            .into_iter()
+            .enumerate()
+            .filter(|(_, n)| *n >= min_words.max(1))
+            .collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates
+    }
+
+    /// save writes the index to a file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).map_err(|e| Error::IndexError(path.to_path_buf(), e))?;
+        let mut w = BufWriter::new(file);
+        self.write_to(&mut w)
+            .and_then(|_| w.flush())
+            .map_err(|e| Error::IndexError(path.to_path_buf(), e))
+    }
+
+    /// load reads an index saved with `save`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let index_error = |e| Error::IndexError(path.to_path_buf(), e);
+        let file = File::open(path).map_err(index_error)?;
+
+        #[cfg(feature = "mmap")]
+        let index = {
+            // SAFETY: the map is only read, and the index isn't expected to
+            // change while it's loaded
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(index_error)?;
+            Index::read(&mut &map[..], map.len() as u64)
+        };
+        #[cfg(not(feature = "mmap"))]
+        let index = {
+            let size = file.metadata().map_err(index_error)?.len();
+            Index::read(&mut io::BufReader::new(file), size)
+        };
+
+        index.map_err(|e| match e {
+            Error::IoError(e) => index_error(e),
+            e => e,
+        })
+    }
+
+    /// write_to writes the index in the format above.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        let seeding = match self.seeding {
+            Seeding::Dense => [0, 0, 0],
+            Seeding::Minimizers { w } => [1, w, 0],
+            Seeding::OpenSyncmers { s, t } => [2, s, t],
+        };
+        let header = [self.k].into_iter().chain(seeding).chain([
+            self.targets,
+            self.target_len,
+            self.words.len(),
+        ]);
+        for n in header {
+            w.write_all(&(n as u64).to_le_bytes())?;
+        }
+
+        let mut words: Vec<_> = self.words.iter().collect();
+        words.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        for (word, positions) in words {
+            w.write_all(word)?;
+            w.write_all(&(positions.len() as u32).to_le_bytes())?;
+            for (t, pos) in positions {
+                w.write_all(&t.to_le_bytes())?;
+                w.write_all(&pos.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// read_from reads an index written by `write_to`.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        Index::read(r, u64::MAX)
+    }
+
+    /// read reads an index of at most `size` bytes.
+    fn read<R: Read>(r: &mut R, size: u64) -> Result<Self> {
+        let io = |e: io::Error| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::InvalidIndex,
+            _ => Error::IoError(e),
+        };
+
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic).map_err(io)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidIndex);
+        }
+        let version = read_u32(r).map_err(io)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(Error::UnsupportedIndexVersion(version));
+        }
+
+        let mut read = || read_u64(r).map_err(io);
+        let k = read()?;
+        let seeding = match version {
+            1 => Seeding::Dense,
+            2 => match read()? {
+                1 => Seeding::Dense,
+                w => Seeding::Minimizers { w: w as usize },
+            },
+            _ => match (read()?, read()?, read()?) {
+                (0, _, _) => Seeding::Dense,
+                (1, w, _) if w > 0 => Seeding::Minimizers { w: w as usize },
+                (2, s, t) if s > 0 && s <= k && t <= k - s => Seeding::OpenSyncmers {
+                    s: s as usize,
+                    t: t as usize,
+                },
+                _ => return Err(Error::InvalidIndex),
+            },
+        };
+        let (targets, target_len, len) = (read()?, read()?, read()?);
+        // positions are u32s, and each k-mer takes k bytes and a count
+        if k == 0
+            || targets > u32::MAX as u64 + 1
+            || target_len > u32::MAX as u64 * targets
+            || len.saturating_mul(k.saturating_add(4)) > size
+        {
+            return Err(Error::InvalidIndex);
+        }
+
+        let mut words = HashMap::new();
+        for _ in 0..len {
+            let mut word = Vec::new();
+            r.take(k).read_to_end(&mut word).map_err(io)?;
+            if word.len() as u64 != k {
+                return Err(Error::InvalidIndex);
+            }
+            let count = read_u32(r).map_err(io)?;
+            if count as u64 * 8 > size {
+                return Err(Error::InvalidIndex);
+            }
+            let mut positions = Vec::new();
+            for _ in 0..count {
+                let t = read_u32(r).map_err(io)?;
+                if t as u64 >= targets {
+                    return Err(Error::InvalidIndex);
+                }
+                positions.push((t, read_u32(r).map_err(io)?));
+            }
+            words.insert(word, positions);
+        }
+
+        Ok(Index {
+            k: k as usize,
+            seeding,
+            targets: targets as usize,
+            target_len: target_len as usize,
+            words,
+        })
+    }
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use crate::search::minimizers;
+
+    use super::*;
+
+    #[test]
+    fn test_index_candidates() {
+        let index = Index::new(&["ACGTACGT", "TTTTTTTT", "GTACGGGG"], 4);
+        assert_eq!(3, index.targets);
+        assert_eq!(24, index.target_len);
+        assert_eq!(vec![(0, 3), (2, 1)], index.candidates("TACGTA", 1));
+        assert_eq!(vec![(0, 3)], index.candidates("TACGTA", 2));
+    }
+
+    #[test]
+    fn test_index_minimizers() {
+        let mut rng = Rng::new(5);
+        let target = random_seq(20_000, &background::nucleotide(), &mut rng);
+
+        let dense = Index::new(&[&target], 15);
+        let sparse = Index::with_minimizers(&[&target], 15, 10);
+        assert!(dense.words.len() > 4 * sparse.words.len());
+        for query in [&target[5000..5200], &target[12_345..12_400]] {
+            assert_eq!(0, sparse.candidates(query, 2)[0].0);
+        }
+
+        let picked = minimizers(target.as_bytes(), 15, 10);
+        assert!(picked.windows(2).all(|p| p[0] < p[1] && p[1] - p[0] <= 10));
+        assert_eq!(
+            (0..20).collect::<Vec<_>>(),
+            minimizers(&target.as_bytes()[..34], 15, 1)
+        );
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let index = Index::with_seeding(
+            &["ACGTACGT", "TTTTTTTT", "GTACGGGG"],
+            4,
+            Seeding::OpenSyncmers { s: 2, t: 1 },
+        );
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        assert_eq!(index, Index::read_from(&mut bytes.as_slice()).unwrap());
+
+        // parameters out of range are stored as they're used
+        for (k, seeding, normalized) in [
+            (0, Seeding::Dense, Seeding::Dense),
+            (
+                4,
+                Seeding::Minimizers { w: 0 },
+                Seeding::Minimizers { w: 1 },
+            ),
+            (
+                4,
+                Seeding::OpenSyncmers { s: 0, t: 0 },
+                Seeding::OpenSyncmers { s: 1, t: 0 },
+            ),
+            (
+                4,
+                Seeding::OpenSyncmers { s: 6, t: 1 },
+                Seeding::OpenSyncmers { s: 4, t: 0 },
+            ),
+            (
+                4,
+                Seeding::OpenSyncmers { s: 2, t: 3 },
+                Seeding::OpenSyncmers { s: 2, t: 2 },
+            ),
+        ] {
+            let targets = ["ACGTACGTTGCA", "TTTTGGGGCCCC"];
+            let edge = Index::with_seeding(&targets, k, seeding);
+            assert_eq!(normalized, edge.seeding);
+            assert_eq!(
+                edge.words.values().map(Vec::len).sum::<usize>(),
+                targets
+                    .iter()
+                    .map(|t| seeding.positions(t.as_bytes(), k).len())
+                    .sum::<usize>()
+            );
+            let mut bytes = Vec::new();
+            edge.write_to(&mut bytes).unwrap();
+            assert_eq!(edge, Index::read_from(&mut bytes.as_slice()).unwrap());
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("index");
+        index.save(&path).unwrap();
+        assert_eq!(index, Index::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        bytes[4] = 9;
+        assert!(matches!(
+            Index::read_from(&mut bytes.as_slice()),
+            Err(Error::UnsupportedIndexVersion(9))
+        ));
+        assert!(matches!(
+            Index::read_from(&mut &b"FASTA"[..]),
+            Err(Error::InvalidIndex)
+        ));
+        match Index::load(&path) {
+            Err(Error::IndexError(missing, _)) => assert_eq!(path, missing),
+            other => panic!("expected an IndexError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_index_corrupt() {
+        let index = Index::new(&["ACGTACGT", "TTTTTTTT", "GTACGGGG"], 4);
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+
+        // the number of k-mers, the count of the first k-mer's positions, a
+        // truncated file, and k are all checked before they're trusted
+        let mut corrupt = vec![bytes[..bytes.len() - 1].to_vec()];
+        for (at, value) in [(56, u64::MAX), (8, u64::MAX >> 8), (8, 1 << 40)] {
+            let mut b = bytes.clone();
+            b[at..at + 8].copy_from_slice(&value.to_le_bytes());
+            corrupt.push(b);
+        }
+        let mut b = bytes.clone();
+        b[68..72].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupt.push(b);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("corrupt");
+        for bytes in corrupt {
+            assert!(matches!(
+                Index::read_from(&mut bytes.as_slice()),
+                Err(Error::InvalidIndex)
+            ));
+            std::fs::write(&path, &bytes).unwrap();
+            assert!(matches!(Index::load(&path), Err(Error::InvalidIndex)));
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Database search: one query against many targets, like BLAST.
+//!
+//! Targets that share too few k-mers with the query are skipped without
+//! aligning, the rest are aligned to the query with Smith-Waterman, and the
+//! hits are ranked by their Karlin-Altschul E-value over the whole database.
+
+pub use crate::search::hit::best_per_target;
+pub use crate::search::hit::remove_overlapping;
+pub use crate::search::hit::sort_hits;
+pub use crate::search::hit::Hit;
+pub use crate::search::hit::Strand;
+pub use crate::search::index::Index;
+pub use crate::search::seeding::minimizers;
+pub use crate::search::seeding::syncmers;
+pub use crate::search::seeding::Seeding;
+
+use std::{io, path::PathBuf, thread};
+
+use thiserror::Error;
+
+use crate::{
+    align::{align_local, Scoring},
+    matrices::BLOSUM62,
+    seq::{reverse_complement, ReducedAlphabet, SeqRecord},
+    stats::{KarlinAltschul, SearchSpace},
+    trace::stage,
+};
+
+mod hit;
+mod index;
+mod seeding;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("not a seqalign index")]
+    InvalidIndex,
+
+    #[error("index format version {0} isn't supported")]
+    UnsupportedIndexVersion(u32),
+
+    #[error("can't read or write index {0}")]
+    IndexError(PathBuf, #[source] io::Error),
+
+    #[error("can't read or write index")]
+    IoError(#[from] io::Error),
+
+    #[error("index is of {index} targets, {targets} were given")]
+    TargetMismatch { index: usize, targets: usize },
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// SearchConfig configures a database search.
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    /// scoring of the alignments
+    pub scoring: Scoring,
+
+    /// statistical parameters of the scoring, for E-values
+    pub statistics: KarlinAltschul,
+
+    /// length of the k-mers of the prefilter
+    pub k: usize,
+
+    /// which k-mers of the prefilter are indexed and looked up
+    pub seeding: Seeding,
+
+    /// k-mers a target must share with the query to be aligned
+    pub min_words: usize,
+
+    /// hits with a higher E-value are dropped
+    pub max_evalue: f64,
+
+    /// most hits returned
+    pub max_hits: usize,
+
+    /// also search the reverse complement of a DNA query
+    pub both_strands: bool,
+
+    /// alphabet the k-mers of the prefilter are reduced to, None to match
+    /// residues as they are
+    pub seed_alphabet: Option<ReducedAlphabet>,
+
+    /// leave lowercase residues of the query, like those masked by
+    /// [`Dust`](crate::seq::Dust) or [`Seg`](crate::seq::Seg), out of the
+    /// prefilter. They're still aligned.
+    pub soft_masking: bool,
+}
+
+impl Default for SearchConfig {
+    /// default is for proteins, BLOSUM62 with BLAST's default gap penalties.
+    fn default() -> Self {
+        SearchConfig {
+            scoring: Scoring {
+                matrix: BLOSUM62::MATRIX,
+                gap_opening: -12f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            statistics: KarlinAltschul::BLOSUM62_GAPPED,
+            k: 3,
+            seeding: Seeding::Dense,
+            min_words: 2,
+            max_evalue: 10f64,
+            max_hits: 500,
+            both_strands: false,
+            seed_alphabet: None,
+            soft_masking: false,
+        }
+    }
+}
+
+/// search a query against targets, returning the hits best first.
+pub fn search<S: AsRef<str> + Sync>(query: &str, targets: &[S], config: &SearchConfig) -> Vec<Hit> {
+    let index = match &config.seed_alphabet {
+        Some(alphabet) => {
+            let reduced: Vec<String> = targets
+                .iter()
+                .map(|t| alphabet.reduce(t.as_ref()))
+                .collect();
+            Index::with_seeding(&reduced, config.k, config.seeding)
+        }
+        None => Index::with_seeding(targets, config.k, config.seeding),
+    };
+    search_index(query, &index, targets, config).expect("the index is of the targets")
+}
+
+/// search_records searches a query record against target records, naming
+/// the hits by their IDs.
+pub fn search_records(query: &SeqRecord, targets: &[SeqRecord], config: &SearchConfig) -> Vec<Hit> {
+    let mut hits = search(&query.seq, targets, config);
+    for hit in hits.iter_mut() {
+        hit.query_id = query.id.clone();
+        hit.target_id = targets[hit.target].id.clone();
+    }
+    hits
+}
+
+/// search_index searches a query against targets that were already indexed.
+///
+/// With a `seed_alphabet`, the index must be of the targets reduced to it.
+/// It's an error if the index isn't of as many targets as are given.
+pub fn search_index<S: AsRef<str> + Sync>(
+    query: &str,
+    index: &Index,
+    targets: &[S],
+    config: &SearchConfig,
+) -> Result<Vec<Hit>> {
+    if index.targets != targets.len() {
+        return Err(Error::TargetMismatch {
+            index: index.targets,
+            targets: targets.len(),
+        });
+    }
+
+    let mut hits = search_strand(query, Strand::Forward, index, targets, config);
+    if config.both_strands {
+        let reverse = reverse_complement(query);
+        hits.extend(search_strand(
+            &reverse,
+            Strand::Reverse,
+            index,
+            targets,
+            config,
+        ));
+    }
+
+    sort_hits(&mut hits);
+    hits.truncate(config.max_hits);
+    Ok(hits)
+}
+
+/// search_strand searches one strand of a query.
+fn search_strand<S: AsRef<str> + Sync>(
+    query: &str,
+    strand: Strand,
+    index: &Index,
+    targets: &[S],
+    config: &SearchConfig,
+) -> Vec<Hit> {
+    let seeds = match &config.seed_alphabet {
+        // reducing uppercases, so the soft mask is put back
+        Some(alphabet) => alphabet
+            .reduce(query)
+            .chars()
+            .zip(query.chars())
+            .map(|(r, q)| match q.is_ascii_lowercase() {
+                true => r.to_ascii_lowercase(),
+                false => r,
+            })
+            .collect(),
+        None => query.to_string(),
+    };
+    let candidates = {
+        let _stage = stage!("seeding", query = query.len(), targets = index.targets);
+        match config.soft_masking {
+            true => index.candidates_unmasked(&seeds, config.min_words),
+            false => index.candidates(&seeds, config.min_words),
+        }
+    };
+    let candidates: Vec<usize> = candidates.into_iter().map(|(t, _)| t).collect();
+    let space = SearchSpace {
+        query_len: query.len(),
+        target_len: index.target_len,
+        targets: index.targets,
+    };
+
+    // soft-masked residues are aligned like the rest
+    let unmasked = match config.soft_masking {
+        true => query.to_ascii_uppercase(),
+        false => query.to_string(),
+    };
+    let query = unmasked.as_str();
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = candidates.len().div_ceil(threads).max(1);
+    thread::scope(|s| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|t| {
+                            let local = align_local(query, targets[*t].as_ref(), &config.scoring);
+                            let score = local.alignment.score;
+                            let query_range = match strand {
+                                Strand::Forward => local.a,
+                                Strand::Reverse => {
+                                    query.len() - local.a.end..query.len() - local.a.start
+                                }
+                            };
+                            Hit {
+                                query_id: String::new(),
+                                target_id: (t + 1).to_string(),
+                                target: *t,
+                                strand,
+                                frame: None,
+                                score,
+                                bit_score: config.statistics.bit_score(score),
+                                evalue: config.statistics.evalue(score, &space),
+                                query_range,
+                                target_range: local.b,
+                                alignment: local.alignment,
+                            }
+                        })
+                        .filter(|h| h.score > 0f32 && h.evalue <= config.max_evalue)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{matrices::NUC_4_4, stats::background};
+
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let targets = [
+            "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEVQAPILSRVGDGTQDNLSGAEKAVQVKVKALPDAQFEVVHSLAKWKR",
+            "GGGGGGGGGGGGGGGGGGGG",
+            "PPPPMKTAYIAKQRQISFVKSHFSRQPPPP",
+        ];
+        let hits = search(
+            "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEV",
+            &targets,
+            &SearchConfig::default(),
+        );
+
+        assert_eq!(
+            vec![0, 2],
+            hits.iter().map(|h| h.target).collect::<Vec<_>>()
+        );
+        assert_eq!(0..32, hits[0].query_range);
+        assert_eq!(0..32, hits[0].target_range);
+        assert_eq!(0..22, hits[1].query_range);
+        assert_eq!(4..26, hits[1].target_range);
+        assert!(hits[0].evalue < hits[1].evalue);
+        assert!(hits[1].evalue < 1e-5);
+        assert_eq!("3", hits[1].target_id);
+
+        let index = Index::new(&targets[..2], 3);
+        assert!(matches!(
+            search_index("MKTAYIAK", &index, &targets, &SearchConfig::default()),
+            Err(Error::TargetMismatch {
+                index: 2,
+                targets: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_search_seed_alphabet() {
+        // conservative substitutions everywhere, so no 3-mer is shared
+        let query = "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEV";
+        let targets = ["LRSAWMAREKEMTYIRTHYTKEVDDKVGVMDI"];
+        assert!(search(query, &targets, &SearchConfig::default()).is_empty());
+
+        let config = SearchConfig {
+            seed_alphabet: Some(ReducedAlphabet::murphy10()),
+            ..Default::default()
+        };
+        let hits = search(query, &targets, &config);
+        assert_eq!(1, hits.len());
+        assert_eq!(0..32, hits[0].target_range);
+
+        // a soft-masked query isn't seeded
+        let config = SearchConfig {
+            soft_masking: true,
+            ..config
+        };
+        assert!(search(&query.to_lowercase(), &targets, &config).is_empty());
+        let half = format!("{}{}", &query[..16].to_lowercase(), &query[16..]);
+        assert_eq!(0..32, search(&half, &targets, &config)[0].query_range);
+    }
+
+    #[test]
+    fn test_search_both_strands() {
+        let config = SearchConfig {
+            scoring: Scoring {
+                matrix: NUC_4_4::MATRIX,
+                gap_opening: -10f32,
+                gap_extension: -1f32,
+                ..Default::default()
+            },
+            statistics: KarlinAltschul::estimate(&NUC_4_4::MATRIX, &background::nucleotide())
+                .unwrap(),
+            k: 11,
+            both_strands: true,
+            ..Default::default()
+        };
+        let query = SeqRecord::new("q", "TTTTGATTACAGGCCTTAGCATCGAACG");
+        let targets = [
+            SeqRecord::new("fwd", "CCCGATTACAGGCCTTAGCATCGAACGCCC"),
+            SeqRecord::new("rev", reverse_complement("GGGGATTACAGGCCTTAGCATCGAACGGG")),
+        ];
+
+        let hits = search_records(&query, &targets, &config);
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.target_id.as_str(), h.strand, h.query_range.clone()))
+            .collect();
+        assert!(found.contains(&("fwd", Strand::Forward, 4..28)));
+        assert!(found.contains(&("rev", Strand::Reverse, 4..28)));
+        assert!(hits.iter().all(|h| h.query_id == "q"));
+    }
+}
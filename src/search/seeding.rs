@@ -0,0 +1,166 @@
+//! Which k-mers of a sequence seed a search.
+//!
+//! Indexing every k-mer finds the most, but for whole genomes the index is
+//! many times the size of the sequences. A seeding scheme picks a subset of
+//! the k-mers by their content alone, so two sequences that share a stretch
+//! pick the same k-mers in it and still meet in the index:
+//!
+//! - minimizers keep the k-mer with the smallest hash of each `w`
+//!   consecutive ones, about `2 / (w + 1)` of them, and any stretch of
+//!   `w + k - 1` shared residues shares one
+//! - open syncmers keep the k-mers whose smallest s-mer is at offset `t`,
+//!   about `1 / (k - s + 1)` of them. Whether a k-mer is picked depends only
+//!   on itself, not on its neighbours, so more of the picked k-mers survive
+//!   mutations nearby than minimizers do.
+//!   https://doi.org/10.7717/peerj.10805
+
+use std::collections::VecDeque;
+
+use crate::sketch::hash;
+
+/// Seeding is how the k-mers of the targets and query are picked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Seeding {
+    /// every k-mer
+    #[default]
+    Dense,
+
+    /// the minimizer of each `w` consecutive k-mers
+    Minimizers { w: usize },
+
+    /// the k-mers whose smallest s-mer starts `t` residues in
+    OpenSyncmers { s: usize, t: usize },
+}
+
+impl Seeding {
+    /// normalized is the seeding with its parameters clamped to those it
+    /// picks k-mers of length k with: `w` at least 1, `s` from 1 to k, and
+    /// `t` at most `k - s`.
+    pub fn normalized(self, k: usize) -> Self {
+        let k = k.max(1);
+        match self {
+            Seeding::Dense => Seeding::Dense,
+            Seeding::Minimizers { w } => Seeding::Minimizers { w: w.max(1) },
+            Seeding::OpenSyncmers { s, t } => {
+                let s = s.clamp(1, k);
+                Seeding::OpenSyncmers { s, t: t.min(k - s) }
+            }
+        }
+    }
+
+    /// positions are the 0-based positions of the picked k-mers, in order.
+    pub fn positions(&self, seq: &[u8], k: usize) -> Vec<usize> {
+        match *self {
+            Seeding::Dense => (0..(seq.len() + 1).saturating_sub(k.max(1))).collect(),
+            Seeding::Minimizers { w } => minimizers(seq, k, w),
+            Seeding::OpenSyncmers { s, t } => syncmers(seq, k, s, t),
+        }
+    }
+}
+
+/// minimizers are the 0-based positions of the minimizers of each `w`
+/// consecutive k-mers of a sequence, in order and without repeats: the
+/// k-mer with the smallest hash, the leftmost on ties. With a `w` of 1 they
+/// are every k-mer.
+pub fn minimizers(seq: &[u8], k: usize, w: usize) -> Vec<usize> {
+    let k = k.max(1);
+    if seq.len() < k {
+        return vec![];
+    }
+    let hashes: Vec<u64> = seq.windows(k).map(hash).collect();
+    let w = w.max(1).min(hashes.len());
+
+    let mut picked: Vec<usize> = vec![];
+    for min in sliding_min(&hashes, w) {
+        if picked.last() != Some(&min) {
+            picked.push(min);
+        }
+    }
+    picked
+}
+
+/// syncmers are the 0-based positions of the open syncmers of a sequence:
+/// the k-mers whose s-mer with the smallest hash, the leftmost on ties,
+/// starts at offset `t` in it.
+///
+/// `s` is clamped from 1 to `k`, and `t` to at most `k - s`.
+pub fn syncmers(seq: &[u8], k: usize, s: usize, t: usize) -> Vec<usize> {
+    let k = k.max(1);
+    let s = s.clamp(1, k);
+    let t = t.min(k - s);
+    if seq.len() < k {
+        return vec![];
+    }
+    let hashes: Vec<u64> = seq.windows(s).map(hash).collect();
+    sliding_min(&hashes, k - s + 1)
+        .into_iter()
+        .enumerate()
+        .filter(|(pos, min)| *min == pos + t)
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+/// sliding_min is the position of the smallest value, the leftmost on ties,
+/// of each window of `w` consecutive values.
+fn sliding_min(values: &[u64], w: usize) -> Vec<usize> {
+    // positions in the window with increasing values
+    let mut window: VecDeque<usize> = VecDeque::new();
+    let mut mins = Vec::with_capacity((values.len() + 1).saturating_sub(w));
+    for (pos, v) in values.iter().enumerate() {
+        while window.back().is_some_and(|b| values[*b] > *v) {
+            window.pop_back();
+        }
+        window.push_back(pos);
+        if window[0] + w <= pos {
+            window.pop_front();
+        }
+        if pos + 1 >= w {
+            mins.push(window[0]);
+        }
+    }
+    mins
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_syncmers() {
+        let mut rng = Rng::new(6);
+        let seq = random_seq(20_000, &background::nucleotide(), &mut rng);
+        let (k, s, t) = (15, 11, 2);
+
+        // about 1 in k - s + 1, each with its smallest s-mer at t
+        let picked = syncmers(seq.as_bytes(), k, s, t);
+        let density = picked.len() as f32 / (seq.len() - k + 1) as f32;
+        assert!((density - 0.2).abs() < 0.02, "{}", density);
+        for pos in picked.iter().take(50) {
+            let kmer = &seq.as_bytes()[*pos..*pos + k];
+            let smallest = (0..=k - s).min_by_key(|o| (hash(&kmer[*o..*o + s]), *o));
+            assert_eq!(Some(t), smallest);
+        }
+
+        // picking depends on the k-mer alone, so a copy picks the same ones
+        let copy = format!("GGG{}", &seq[1000..2000]);
+        let shifted: Vec<usize> = syncmers(copy.as_bytes(), k, s, t)
+            .into_iter()
+            .map(|p| p + 997)
+            .collect();
+        let expected: Vec<usize> = picked
+            .into_iter()
+            .filter(|p| (1000..2000 - k + 1).contains(p))
+            .collect();
+        assert!(expected.iter().all(|p| shifted.contains(p)));
+
+        assert_eq!(
+            Seeding::Dense.positions(seq.as_bytes(), k),
+            Seeding::Minimizers { w: 1 }.positions(seq.as_bytes(), k)
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Reduced amino acid alphabets.
+//!
+//! Grouping residues that substitute for each other into one letter keeps
+//! most of the signal of distant homologs while making exact k-mer matches
+//! between them far more likely, so seeds of a protein search in a reduced
+//! alphabet find hits a full one misses, and its k-mers can be longer for the
+//! same sensitivity. Each group is written as its first residue.
+
+use crate::{matrices::Matrix, stats::background::Frequencies};
+
+use super::{Error, Result};
+
+/// ReducedAlphabet maps the residues of each group to one letter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReducedAlphabet {
+    groups: Vec<String>,
+    map: Vec<u8>,
+}
+
+impl ReducedAlphabet {
+    /// new is an alphabet of groups of uppercase residues. Residues in no
+    /// group are kept as they are, and lowercase ones are reduced like
+    /// uppercase.
+    ///
+    /// It's an error if a group is empty or has a residue that isn't ASCII.
+    pub fn new<S: AsRef<str>>(groups: &[S]) -> Result<Self> {
+        let mut map: Vec<u8> = (0..128u8).map(|c| c.to_ascii_uppercase()).collect();
+        let groups: Vec<String> = groups.iter().map(|g| g.as_ref().to_string()).collect();
+        if let Some(group) = groups.iter().find(|g| g.is_empty() || !g.is_ascii()) {
+            return Err(Error::InvalidGroup(group.clone()));
+        }
+        for group in groups.iter() {
+            let letter = group.as_bytes()[0];
+            for c in group.bytes() {
+                map[c as usize] = letter;
+                map[c.to_ascii_lowercase() as usize] = letter;
+            }
+        }
+        Ok(ReducedAlphabet { groups, map })
+    }
+
+    /// murphy10 is the 10 letter alphabet of Murphy, Wallqvist and Levy (2000),
+    /// from BLOSUM50.
+    /// https://doi.org/10.1093/protein/13.3.149
+    pub fn murphy10() -> Self {
+        ReducedAlphabet::new(&["LVIM", "C", "A", "G", "ST", "P", "FYW", "EDNQ", "KR", "H"])
+            .expect("the groups are ASCII")
+    }
+
+    /// hydrophobicity is the 3 letter alphabet of polar, neutral and
+    /// hydrophobic residues of Dubchak et al. (1995).
+    /// https://doi.org/10.1073/pnas.92.19.8700
+    pub fn hydrophobicity() -> Self {
+        ReducedAlphabet::new(&["RKEDQN", "GASTPHY", "CLVIMFW"]).expect("the groups are ASCII")
+    }
+
+    /// groups of residues, in the order they were given.
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    /// letter of the group a residue is in.
+    pub fn letter(&self, c: char) -> char {
+        match self.map.get(c as usize) {
+            Some(letter) => *letter as char,
+            None => c,
+        }
+    }
+
+    /// reduce a sequence to the alphabet.
+    pub fn reduce(&self, seq: &str) -> String {
+        seq.chars().map(|c| self.letter(c)).collect()
+    }
+
+    /// matrix derives a scoring matrix for reduced sequences from one for
+    /// full sequences: the score of two letters is the mean score of the
+    /// residues in their groups, weighted by their background frequencies.
+    ///
+    /// Only the groups' letters have scores. Residues without a frequency, or
+    /// pairs without a score, don't count, and a pair of groups with none of
+    /// either has no score.
+    pub fn matrix(&self, matrix: &Matrix, freqs: &Frequencies) -> Matrix {
+        let mut reduced = [[i32::MIN; 128]; 128];
+        for x in self.groups.iter() {
+            for y in self.groups.iter() {
+                let (mut total, mut weight) = (0f64, 0f64);
+                // groups are ASCII, so every residue is in the matrix
+                for a in x.bytes() {
+                    for b in y.bytes() {
+                        let (s, p) = (
+                            matrix[a as usize][b as usize],
+                            freqs[a as usize] * freqs[b as usize],
+                        );
+                        if s != i32::MIN && p > 0f64 {
+                            total += s as f64 * p;
+                            weight += p;
+                        }
+                    }
+                }
+                if weight > 0f64 {
+                    reduced[x.as_bytes()[0] as usize][y.as_bytes()[0] as usize] =
+                        (total / weight).round() as i32;
+                }
+            }
+        }
+        reduced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{matrices::BLOSUM62, stats::background};
+
+    use super::*;
+
+    #[test]
+    fn test_reduced_alphabet() {
+        let murphy = ReducedAlphabet::murphy10();
+        assert_eq!(10, murphy.groups().len());
+        assert_eq!("LLCAGSSPFEEKHX", murphy.reduce("IMCAGTsPWqDRHX"));
+        assert_eq!(murphy.reduce("MKTAYIAKQR"), murphy.reduce("LRSAFVAREK"));
+
+        let matrix = murphy.matrix(&BLOSUM62::MATRIX, &background::robinson());
+        assert_eq!(9, matrix['C' as usize]['C' as usize]);
+        assert_eq!(4, matrix['A' as usize]['A' as usize]);
+        assert!(matrix['L' as usize]['L' as usize] > 0);
+        assert!(matrix['L' as usize]['K' as usize] < 0);
+        assert_eq!(i32::MIN, matrix['I' as usize]['I' as usize]);
+
+        let hydrophobicity = ReducedAlphabet::hydrophobicity();
+        assert_eq!("RGCRG", hydrophobicity.reduce("DSFKP"));
+
+        // residues outside ASCII are kept as they are, but can't be grouped
+        assert_eq!("LLé", murphy.reduce("IMé"));
+        assert_eq!(
+            Err(Error::InvalidGroup(String::new())),
+            ReducedAlphabet::new(&["AG", ""])
+        );
+        assert_eq!(
+            Err(Error::InvalidGroup("Aé".to_string())),
+            ReducedAlphabet::new(&["Aé"])
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Low-complexity masking: DUST for nucleotides and SEG for proteins.
+//!
+//! Runs of one residue, short tandem repeats and compositionally biased
+//! stretches score well against each other anywhere, so they seed spurious
+//! hits and drag local alignments off the real homology. Both maskers return
+//! the low-complexity intervals, half-open and 0-based, which can go straight
+//! into an [`align::Mask`](crate::align::Mask) or be lowercased with
+//! [`soft_mask`] so the seeding of a search with `soft_masking` skips them.
+
+/// Dust masks nucleotide sequences by how often their triplets repeat.
+/// https://doi.org/10.1089/cmb.2006.13.1028
+///
+/// Each window of a low-complexity region is narrowed to its best-scoring
+/// interval, as in symmetric DUST, so the masks don't bleed into the
+/// sequence around a repeat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dust {
+    /// residues per window
+    pub window: usize,
+
+    /// intervals whose score, in tenths, is above this are masked
+    pub level: f32,
+}
+
+impl Default for Dust {
+    fn default() -> Self {
+        Dust {
+            window: 64,
+            level: 20f32,
+        }
+    }
+}
+
+impl Dust {
+    /// mask is the low-complexity intervals of a nucleotide sequence.
+    pub fn mask(&self, seq: &str) -> Vec<(usize, usize)> {
+        let seq: Vec<u8> = seq.bytes().map(|c| c.to_ascii_uppercase()).collect();
+        let window = self.window.max(4);
+        let mut intervals = vec![];
+        let mut start = 0;
+        while start + 3 <= seq.len() {
+            let end = (start + window).min(seq.len());
+            if let Some((s, e, score)) = best_interval(&seq[start..end]) {
+                if 10f32 * score > self.level {
+                    intervals.push((start + s, start + e));
+                }
+            }
+            if end == seq.len() {
+                break;
+            }
+            // a repeat of up to half a window is within one of these windows,
+            // and longer ones are masked in overlapping pieces
+            start += (window / 2).max(1);
+        }
+        merge(intervals)
+    }
+}
+
+/// best_interval is the interval of a window with the highest DUST score:
+/// the sum of c(c - 1) / 2 over the counts c of each triplet in it, over one
+/// less than its number of triplets. Triplets with residues other than ACGT
+/// count toward the length but never repeat.
+fn best_interval(window: &[u8]) -> Option<(usize, usize, f32)> {
+    let code = |c: u8| match c {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    };
+    let triplets: Vec<Option<usize>> = window
+        .windows(3)
+        .map(|t| Some(code(t[0])? * 16 + code(t[1])? * 4 + code(t[2])?))
+        .collect();
+
+    let mut best: Option<(usize, usize, f32)> = None;
+    for s in 0..triplets.len() {
+        let mut counts = [0usize; 64];
+        let mut pairs = 0usize;
+        for (n, t) in triplets[s..].iter().enumerate() {
+            if let Some(t) = t {
+                pairs += counts[*t];
+                counts[*t] += 1;
+            }
+            if n > 0 {
+                let score = pairs as f32 / n as f32;
+                if best.is_none_or(|(_, _, b)| score > b) {
+                    best = Some((s, s + n + 3, score));
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Seg masks protein sequences by the entropy of their composition.
+/// https://doi.org/10.1016/0097-8485(93)85006-X
+///
+/// Windows with at most `trigger` bits of entropy start a low-complexity
+/// region, which extends over neighbouring windows with at most `extension`
+/// bits. SEG's last step, trimming each region to its least probable
+/// subsequence, is left out, so the masks are a little wider.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seg {
+    /// residues per window
+    pub window: usize,
+
+    /// entropy, in bits, at or below which a window starts a region
+    pub trigger: f32,
+
+    /// entropy, in bits, at or below which a window extends a region
+    pub extension: f32,
+}
+
+impl Default for Seg {
+    fn default() -> Self {
+        Seg {
+            window: 12,
+            trigger: 2.2,
+            extension: 2.5,
+        }
+    }
+}
+
+impl Seg {
+    /// mask is the low-complexity intervals of a protein sequence.
+    pub fn mask(&self, seq: &str) -> Vec<(usize, usize)> {
+        let seq: Vec<u8> = seq.bytes().map(|c| c.to_ascii_uppercase()).collect();
+        let window = self.window.max(1);
+        if seq.len() < window {
+            return vec![];
+        }
+
+        let entropies: Vec<f32> = seq.windows(window).map(entropy).collect();
+        let mut intervals = vec![];
+        let mut w = 0;
+        while w < entropies.len() {
+            if entropies[w] > self.trigger {
+                w += 1;
+                continue;
+            }
+            let mut first = w;
+            while first > 0 && entropies[first - 1] <= self.extension {
+                first -= 1;
+            }
+            let mut last = w;
+            while last + 1 < entropies.len() && entropies[last + 1] <= self.extension {
+                last += 1;
+            }
+            intervals.push((first, last + window));
+            w = last + 1;
+        }
+        merge(intervals)
+    }
+}
+
+/// entropy of the composition of residues, in bits.
+fn entropy(residues: &[u8]) -> f32 {
+    let mut counts = [0usize; 256];
+    for r in residues {
+        counts[*r as usize] += 1;
+    }
+    let n = residues.len() as f32;
+    counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(|c| {
+            let p = *c as f32 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// merge sorted intervals that overlap or touch.
+fn merge(intervals: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut merged: Vec<(usize, usize)> = vec![];
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// soft_mask lowercases the residues of a sequence in the intervals and
+/// uppercases the rest.
+pub fn soft_mask(seq: &str, intervals: &[(usize, usize)]) -> String {
+    seq.chars()
+        .enumerate()
+        .map(
+            |(i, c)| match intervals.iter().any(|(s, e)| *s <= i && i < *e) {
+                true => c.to_ascii_lowercase(),
+                false => c.to_ascii_uppercase(),
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        seq::random::{random_seq, Rng},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_dust() {
+        let mut rng = Rng::new(1);
+        let flank = |rng: &mut Rng| random_seq(100, &background::nucleotide(), rng);
+        let seq = format!(
+            "{}{}{}{}{}",
+            flank(&mut rng),
+            "A".repeat(50),
+            flank(&mut rng),
+            "CAG".repeat(30),
+            flank(&mut rng)
+        );
+
+        let masked = Dust::default().mask(&seq);
+        assert_eq!(2, masked.len(), "{:?}", masked);
+        assert!(masked[0].0.abs_diff(100) < 3 && masked[0].1.abs_diff(150) < 3);
+        assert!(masked[1].0.abs_diff(250) < 3 && masked[1].1.abs_diff(340) < 3);
+
+        let soft = soft_mask(&seq, &masked);
+        assert_eq!("a".repeat(40), soft[105..145]);
+        assert!(Dust::default().mask(&flank(&mut rng)).is_empty());
+    }
+
+    #[test]
+    fn test_seg() {
+        let seq = "MKTAYIAKQRQISFVKSHFSRQPPPPPPAPPPPPPAPPPLEERLGLIEVQAPILSRVGDGTQDNLSGAEK";
+        let masked = Seg::default().mask(seq);
+        assert_eq!(1, masked.len(), "{:?}", masked);
+        let (start, end) = masked[0];
+        // the PA-rich stretch, 22..39, and at most a window either side
+        assert!((10..=22).contains(&start) && (39..=51).contains(&end));
+    }
+}
@@ -0,0 +1,63 @@
+pub use crate::seq::alphabet::ReducedAlphabet;
+pub use crate::seq::complexity::soft_mask;
+pub use crate::seq::complexity::Dust;
+pub use crate::seq::complexity::Seg;
+pub use crate::seq::record::SeqRecord;
+
+pub mod random;
+
+use thiserror::Error;
+
+mod alphabet;
+mod complexity;
+mod record;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("alphabet group {0:?} is empty or not ASCII")]
+    InvalidGroup(String),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// reverse_complement is the reverse complement of a DNA sequence.
+///
+/// IUPAC ambiguity codes are complemented and case is kept. Anything else,
+/// like gaps, is copied as is.
+pub fn reverse_complement(seq: &str) -> String {
+    seq.chars().rev().map(complement).collect()
+}
+
+/// complement is the complement of a DNA residue.
+pub fn complement(c: char) -> char {
+    let comp = match c.to_ascii_uppercase() {
+        'A' => 'T',
+        'T' | 'U' => 'A',
+        'C' => 'G',
+        'G' => 'C',
+        'R' => 'Y',
+        'Y' => 'R',
+        'K' => 'M',
+        'M' => 'K',
+        'B' => 'V',
+        'V' => 'B',
+        'D' => 'H',
+        'H' => 'D',
+        other => other,
+    };
+    if c.is_ascii_lowercase() {
+        comp.to_ascii_lowercase()
+    } else {
+        comp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_complement() {
+        assert_eq!("ACGTN-ary", reverse_complement("ryt-NACGT"));
+        assert_eq!("", reverse_complement(""));
+    }
+}
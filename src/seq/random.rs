@@ -0,0 +1,326 @@
+//! Random sequences and sequence shuffling.
+//!
+//! Everything here takes an explicit, seeded [`Rng`] so results are
+//! reproducible across runs and platforms.
+
+use std::collections::HashMap;
+
+use crate::stats::background::Frequencies;
+
+/// Rng is a small, seedable SplitMix64 generator.
+///
+/// https://prng.di.unimi.it/splitmix64.c
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// below returns a number in [0, n).
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// next_f64 returns a number in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// choose picks an item with probability proportional to its weight, None
+    /// if there are no items or no weight above 0.
+    fn choose<T: Copy>(&mut self, weighted: &[(T, f64)]) -> Option<T> {
+        let total: f64 = weighted.iter().map(|(_, w)| w).sum();
+        if total.is_nan() || total <= 0f64 {
+            return None;
+        }
+        let mut target = self.next_f64() * total;
+        for (item, w) in weighted {
+            if target < *w {
+                return Some(*item);
+            }
+            target -= w;
+        }
+        weighted.last().map(|(item, _)| *item)
+    }
+}
+
+/// shuffle_slice randomizes the order of items in place (Fisher-Yates).
+pub fn shuffle_slice<T>(items: &mut [T], rng: &mut Rng) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.below(i + 1));
+    }
+}
+
+/// shuffle randomizes the order of the residues of a sequence, keeping its composition.
+pub fn shuffle(seq: &str, rng: &mut Rng) -> String {
+    let mut chars: Vec<char> = seq.chars().collect();
+    shuffle_slice(&mut chars, rng);
+    chars.into_iter().collect()
+}
+
+/// shuffle_dinucleotide randomizes a sequence keeping the count of each adjacent pair.
+///
+/// Altschul & Erickson 1985. The sequence is a walk through a multigraph with an
+/// edge for every adjacent pair. A random Eulerian walk is found by picking, for
+/// every residue but the last, a random final exit edge such that the final exits
+/// form a tree into the last residue, then shuffling the other exits of each residue.
+pub fn shuffle_dinucleotide(seq: &str, rng: &mut Rng) -> String {
+    let chars: Vec<char> = seq.chars().collect();
+    if chars.len() < 3 {
+        return seq.to_string();
+    }
+
+    let first = chars[0];
+    let last = chars[chars.len() - 1];
+    let mut edges: HashMap<char, Vec<char>> = HashMap::new();
+    for pair in chars.windows(2) {
+        edges.entry(pair[0]).or_default().push(pair[1]);
+    }
+
+    let mut vertices: Vec<char> = edges.keys().copied().collect();
+    vertices.sort_unstable();
+
+    // pick last exits until they form a tree directed at the last residue
+    let last_exits: HashMap<char, usize> = loop {
+        let exits: HashMap<char, usize> = vertices
+            .iter()
+            .filter(|v| **v != last)
+            .map(|v| (*v, rng.below(edges[v].len())))
+            .collect();
+        let reaches_last = |start: char| {
+            let mut v = start;
+            for _ in 0..=vertices.len() {
+                if v == last {
+                    return true;
+                }
+                v = edges[&v][exits[&v]];
+            }
+            false
+        };
+        if vertices.iter().all(|v| reaches_last(*v)) {
+            break exits;
+        }
+    };
+
+    // shuffle the other exits and put the last exit at the end
+    for v in vertices.iter() {
+        let exits = edges.get_mut(v).unwrap();
+        let last_exit = last_exits.get(v).map(|e| exits.remove(*e));
+        shuffle_slice(exits, rng);
+        exits.extend(last_exit);
+        exits.reverse(); // so pop() takes exits in order
+    }
+
+    let mut result = vec![first];
+    let mut v = first;
+    while let Some(next) = edges.get_mut(&v).and_then(|e| e.pop()) {
+        result.push(next);
+        v = next;
+    }
+    result.into_iter().collect()
+}
+
+/// random_seq generates a sequence with each residue drawn independently from the frequencies.
+///
+/// The sequence is empty if no residue has a frequency above 0.
+pub fn random_seq(len: usize, freqs: &Frequencies, rng: &mut Rng) -> String {
+    let weighted: Vec<(char, f64)> = freqs
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| **f > 0f64)
+        .map(|(c, f)| (c as u8 as char, *f))
+        .collect();
+    if weighted.is_empty() {
+        return String::new();
+    }
+    (0..len).filter_map(|_| rng.choose(&weighted)).collect()
+}
+
+/// MarkovChain generates sequences where each residue depends on the `order` residues before it.
+#[derive(Clone, Debug)]
+pub struct MarkovChain {
+    /// number of preceding residues each residue depends on
+    pub order: usize,
+
+    /// counts of the residues following each context of `order` residues
+    transitions: HashMap<String, Vec<(char, f64)>>,
+
+    /// counts of the first `order` residues of the training sequences
+    starts: Vec<(String, f64)>,
+}
+
+impl MarkovChain {
+    /// train counts the transitions in the sequences.
+    pub fn train<S: AsRef<str>>(seqs: &[S], order: usize) -> Self {
+        let mut transitions: HashMap<String, HashMap<char, f64>> = HashMap::new();
+        let mut starts: HashMap<String, f64> = HashMap::new();
+
+        for seq in seqs {
+            let chars: Vec<char> = seq.as_ref().chars().collect();
+            if chars.len() <= order {
+                continue;
+            }
+            *starts.entry(chars[..order].iter().collect()).or_default() += 1f64;
+            for w in chars.windows(order + 1) {
+                *transitions
+                    .entry(w[..order].iter().collect())
+                    .or_default()
+                    .entry(w[order])
+                    .or_default() += 1f64;
+            }
+        }
+
+        // sort so generation doesn't depend on HashMap iteration order
+        let sorted = |counts: HashMap<char, f64>| {
+            let mut counts: Vec<(char, f64)> = counts.into_iter().collect();
+            counts.sort_by_key(|(c, _)| *c);
+            counts
+        };
+        let mut starts: Vec<(String, f64)> = starts.into_iter().collect();
+        starts.sort_by(|a, b| a.0.cmp(&b.0));
+
+        MarkovChain {
+            order,
+            transitions: transitions
+                .into_iter()
+                .map(|(k, v)| (k, sorted(v)))
+                .collect(),
+            starts,
+        }
+    }
+
+    /// generate a sequence of the given length.
+    ///
+    /// If the chain reaches a context that was never followed by another
+    /// residue in training, it restarts from a random training start.
+    pub fn generate(&self, len: usize, rng: &mut Rng) -> String {
+        let mut seq: Vec<char> = Vec::with_capacity(len + self.order);
+        if self.starts.is_empty() {
+            return String::new();
+        }
+
+        let starts: Vec<(&str, f64)> = self.starts.iter().map(|(s, w)| (s.as_str(), *w)).collect();
+        while seq.len() < len {
+            let next = if seq.len() >= self.order {
+                let context: String = seq[seq.len() - self.order..].iter().collect();
+                self.transitions.get(&context)
+            } else {
+                None
+            };
+
+            // every context and start was counted at least once
+            match next.and_then(|next| rng.choose(next)) {
+                Some(next) => seq.push(next),
+                None => seq.extend(rng.choose(&starts).unwrap_or_default().chars()),
+            }
+        }
+
+        seq.into_iter().take(len).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::background;
+
+    use super::*;
+
+    fn counts(seq: &str, k: usize) -> HashMap<String, usize> {
+        let chars: Vec<char> = seq.chars().collect();
+        let mut counts = HashMap::new();
+        for w in chars.windows(k) {
+            *counts.entry(w.iter().collect()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_rng() {
+        // the first outputs of the reference SplitMix64 from a seed of 0, the
+        // same on every platform
+        let mut rng = Rng::new(0);
+        assert_eq!(0xE220_A839_7B1D_CDAF, rng.next_u64());
+        assert_eq!(0x6E78_9E6A_A1B9_65F4, rng.next_u64());
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let seq = "ACGTTGCAAGCTTAGGCATCGATCGGATCCA";
+        let shuffled = shuffle(seq, &mut Rng::new(1));
+
+        assert_ne!(seq, shuffled);
+        assert_eq!(counts(seq, 1), counts(&shuffled, 1));
+    }
+
+    #[test]
+    fn test_shuffle_dinucleotide() {
+        let seq = "ACGTTGCAAGCTTAGGCATCGATCGGATCCA";
+        for seed in 0..10 {
+            let shuffled = shuffle_dinucleotide(seq, &mut Rng::new(seed));
+
+            assert_eq!(seq.len(), shuffled.len());
+            assert_eq!(counts(seq, 2), counts(&shuffled, 2));
+            assert_eq!(seq.chars().next(), shuffled.chars().next());
+            assert_eq!(seq.chars().last(), shuffled.chars().last());
+        }
+    }
+
+    #[test]
+    fn test_random_seq() {
+        let seq = random_seq(
+            1000,
+            &background::from_pairs(&[('A', 3f64), ('T', 1f64)]),
+            &mut Rng::new(7),
+        );
+
+        assert_eq!(1000, seq.len());
+        let a = seq.chars().filter(|c| *c == 'A').count();
+        assert!(a > 700 && a < 800, "{}", a);
+        assert!(seq.chars().all(|c| c == 'A' || c == 'T'));
+
+        // same seed, same sequence
+        assert_eq!(
+            seq,
+            random_seq(
+                1000,
+                &background::from_pairs(&[('A', 3f64), ('T', 1f64)]),
+                &mut Rng::new(7)
+            )
+        );
+
+        // no residue to draw
+        assert_eq!("", random_seq(10, &[0f64; 128], &mut Rng::new(7)));
+        let mut rng = Rng::new(7);
+        assert_eq!(None, rng.choose::<char>(&[]));
+        assert_eq!(None, rng.choose(&[('A', 0f64), ('C', 0f64)]));
+    }
+
+    #[test]
+    fn test_markov_chain() {
+        // every A is followed by C, every C by G, every G by T, every T by A
+        let chain = MarkovChain::train(&["ACGTACGTACGTACGT"], 1);
+        let seq = chain.generate(40, &mut Rng::new(3));
+
+        assert_eq!(40, seq.len());
+        let chars: Vec<char> = seq.chars().collect();
+        for w in chars.windows(2) {
+            let expected = match w[0] {
+                'A' => 'C',
+                'C' => 'G',
+                'G' => 'T',
+                _ => 'A',
+            };
+            assert_eq!(expected, w[1], "{}", seq);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Sequences with the names they were read with.
+
+use crate::io::{fasta, fastq};
+
+/// SeqRecord is a sequence and its ID and description.
+///
+/// It's `AsRef<str>` of its sequence, so it can be passed to any aligner
+/// that takes sequences, and the aligners that take records name their
+/// output by `id`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeqRecord {
+    pub id: String,
+    pub desc: Option<String>,
+    pub seq: String,
+}
+
+impl SeqRecord {
+    pub fn new(id: impl Into<String>, seq: impl Into<String>) -> Self {
+        SeqRecord {
+            id: id.into(),
+            desc: None,
+            seq: seq.into(),
+        }
+    }
+}
+
+impl AsRef<str> for SeqRecord {
+    fn as_ref(&self) -> &str {
+        &self.seq
+    }
+}
+
+impl From<fasta::Record> for SeqRecord {
+    fn from(record: fasta::Record) -> Self {
+        SeqRecord {
+            id: record.id().to_string(),
+            desc: record.desc().map(str::to_string),
+            seq: record.seq,
+        }
+    }
+}
+
+impl From<fastq::Record> for SeqRecord {
+    fn from(record: fastq::Record) -> Self {
+        SeqRecord {
+            id: record.id,
+            desc: record.desc,
+            seq: record.seq,
+        }
+    }
+}
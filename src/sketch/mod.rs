@@ -0,0 +1,248 @@
+//! Alignment-free sequence comparison with MinHash sketches, like Mash.
+//! https://doi.org/10.1186/s13059-016-0997-x
+//!
+//! A sketch keeps the smallest hashes of a sequence's k-mers. The fraction of
+//! the smallest hashes of two sketches combined that are in both estimates
+//! the Jaccard index of their k-mer sets, and from it the Mash distance
+//! estimates the per-residue mutation rate. Comparing sketches takes time in
+//! the sketch size rather than the sequence lengths, so it's fast enough to
+//! screen a large database and only align the closest candidates.
+
+use std::collections::{BinaryHeap, HashSet};
+
+use thiserror::Error;
+
+use crate::seq::reverse_complement;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("sketches with k-mer lengths {0} and {1} can't be compared")]
+    IncompatibleSketches(usize, usize),
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// SketchConfig configures how sequences are sketched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SketchConfig {
+    /// length of the k-mers
+    pub k: usize,
+
+    /// number of hashes kept
+    pub size: usize,
+
+    /// hash each k-mer and its reverse complement the same, for DNA
+    pub canonical: bool,
+}
+
+impl Default for SketchConfig {
+    fn default() -> Self {
+        SketchConfig {
+            k: 21,
+            size: 1000,
+            canonical: true,
+        }
+    }
+}
+
+/// Sketch is the bottom hashes of the k-mers of a sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sketch {
+    /// length of the k-mers
+    pub k: usize,
+
+    /// most hashes kept
+    pub size: usize,
+
+    /// the smallest k-mer hashes, sorted and without repeats
+    pub hashes: Vec<u64>,
+}
+
+impl Sketch {
+    /// new sketches a sequence.
+    pub fn new(seq: &str, config: &SketchConfig) -> Self {
+        let seq = seq.to_ascii_uppercase();
+        let rc = match config.canonical {
+            true => reverse_complement(&seq),
+            false => String::new(),
+        };
+        let (k, seq, rc) = (config.k.max(1), seq.as_bytes(), rc.as_bytes());
+
+        // a max-heap of the smallest hashes so far, so the largest is the
+        // one to replace
+        let mut heap: BinaryHeap<u64> = BinaryHeap::with_capacity(config.size + 1);
+        let mut kept: HashSet<u64> = HashSet::with_capacity(config.size + 1);
+        for (i, kmer) in seq.windows(k).enumerate() {
+            let h = match config.canonical {
+                // the reverse complement of the k-mer ends where it starts
+                true => hash(kmer).min(hash(&rc[seq.len() - i - k..seq.len() - i])),
+                false => hash(kmer),
+            };
+            if heap.len() == config.size && heap.peek().is_none_or(|max| h >= *max) {
+                continue;
+            }
+            if kept.insert(h) {
+                heap.push(h);
+                if heap.len() > config.size {
+                    kept.remove(&heap.pop().unwrap());
+                }
+            }
+        }
+        let hashes = heap.into_sorted_vec();
+
+        Sketch {
+            k,
+            size: config.size,
+            hashes,
+        }
+    }
+
+    /// jaccard estimates the Jaccard index of the k-mers of two sketched sequences.
+    pub fn jaccard(&self, other: &Sketch) -> Result<f64> {
+        if self.k != other.k {
+            return Err(Error::IncompatibleSketches(self.k, other.k));
+        }
+
+        // walk the smallest hashes of the union, counting those in both
+        let size = self.size.min(other.size);
+        let (mut i, mut j, mut union, mut shared) = (0, 0, 0, 0);
+        while union < size && (i < self.hashes.len() || j < other.hashes.len()) {
+            match (self.hashes.get(i), other.hashes.get(j)) {
+                (Some(a), Some(b)) if a == b => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+                (Some(a), Some(b)) if a < b => i += 1,
+                (Some(_), None) => i += 1,
+                _ => j += 1,
+            }
+            union += 1;
+        }
+
+        Ok(if union == 0 {
+            0f64
+        } else {
+            shared as f64 / union as f64
+        })
+    }
+
+    /// distance is the Mash distance between two sketched sequences, an
+    /// estimate of the fraction of residues that differ. It's 1 if they share
+    /// no k-mers.
+    pub fn distance(&self, other: &Sketch) -> Result<f64> {
+        let jaccard = self.jaccard(other)?;
+        if jaccard == 0f64 {
+            return Ok(1f64);
+        }
+        let distance = -(2f64 * jaccard / (1f64 + jaccard)).ln() / self.k as f64;
+        Ok(distance.min(1f64))
+    }
+}
+
+/// closest finds the `n` targets nearest to the query by Mash distance,
+/// nearest first, as pairs of their index and distance.
+pub fn closest(query: &Sketch, targets: &[Sketch], n: usize) -> Result<Vec<(usize, f64)>> {
+    let mut distances = targets
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Ok((i, query.distance(t)?)))
+        .collect::<Result<Vec<_>>>()?;
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    distances.truncate(n);
+    Ok(distances)
+}
+
+/// hash is 64-bit FNV-1a mixed with the SplitMix64 finalizer, so the bottom
+/// hashes are a uniform sample of the k-mers.
+pub(crate) fn hash(kmer: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in kmer {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seq::random::Rng;
+
+    use super::*;
+
+    fn random(len: usize, rng: &mut Rng) -> String {
+        (0..len).map(|_| b"ACGT"[rng.below(4)] as char).collect()
+    }
+
+    #[test]
+    fn test_sketch() {
+        let mut rng = Rng::new(1);
+        let a = random(5000, &mut rng);
+
+        // 1 in 100 residues changed
+        let mut b: Vec<u8> = a.bytes().collect();
+        for i in (0..b.len()).step_by(100) {
+            b[i] = if b[i] == b'A' { b'C' } else { b'A' };
+        }
+        let b = String::from_utf8(b).unwrap();
+        let c = random(5000, &mut rng);
+
+        let config = SketchConfig::default();
+        let (sa, sb, sc) = (
+            Sketch::new(&a, &config),
+            Sketch::new(&b, &config),
+            Sketch::new(&c, &config),
+        );
+        assert_eq!(1000, sa.hashes.len());
+        assert_eq!(0f64, sa.distance(&sa).unwrap());
+
+        let d = sa.distance(&sb).unwrap();
+        assert!(d > 0.005 && d < 0.015, "{}", d);
+        assert_eq!(1f64, sa.distance(&sc).unwrap());
+        assert_eq!(
+            vec![0, 1],
+            closest(&sa, &[sb.clone(), sc], 5)
+                .unwrap()
+                .iter()
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>()
+        );
+
+        // the reverse complement has the same canonical k-mers
+        assert_eq!(sa, Sketch::new(&reverse_complement(&a), &config));
+
+        // the bottom hashes of every k-mer, same as sorting them all
+        let bottom = |mut hashes: Vec<u64>| {
+            hashes.sort_unstable();
+            hashes.dedup();
+            hashes.truncate(50);
+            hashes
+        };
+        let small = SketchConfig { size: 50, ..config };
+        let rc = reverse_complement(&a);
+        let forward = a.as_bytes().windows(21).map(hash);
+        let reverse = rc.as_bytes().windows(21).rev().map(hash);
+        assert_eq!(
+            bottom(forward.clone().collect()),
+            Sketch::new(
+                &a,
+                &SketchConfig {
+                    canonical: false,
+                    ..small
+                }
+            )
+            .hashes
+        );
+        assert_eq!(
+            bottom(forward.zip(reverse).map(|(f, r)| f.min(r)).collect()),
+            Sketch::new(&a, &small).hashes
+        );
+        assert!(Sketch::new(&a, &SketchConfig { size: 0, ..config })
+            .hashes
+            .is_empty());
+
+        let other = Sketch::new(&a, &SketchConfig { k: 15, ..config });
+        assert_eq!(Err(Error::IncompatibleSketches(21, 15)), sa.jaccard(&other));
+    }
+}
@@ -0,0 +1,75 @@
+//! Background residue frequencies used to compute score statistics.
+//!
+//! Frequencies are indexed by the ASCII value of the residue, the same way
+//! a [`Matrix`](crate::matrices::Matrix) is.
+
+/// Frequencies maps each ASCII residue to its background probability.
+pub type Frequencies = [f64; 128];
+
+/// from_pairs creates frequencies from residue/probability pairs, normalized to sum to 1.
+///
+/// Residues that aren't ASCII have no index in the frequencies, so they're
+/// left out.
+pub fn from_pairs(pairs: &[(char, f64)]) -> Frequencies {
+    let mut freqs = [0f64; 128];
+    let ascii = || pairs.iter().filter(|(c, _)| c.is_ascii());
+    let total: f64 = ascii().map(|(_, p)| p).sum();
+    for (c, p) in ascii() {
+        freqs[*c as usize] = p / total;
+    }
+    freqs
+}
+
+/// uniform gives each residue in the alphabet the same probability.
+pub fn uniform(alphabet: &str) -> Frequencies {
+    from_pairs(&alphabet.chars().map(|c| (c, 1f64)).collect::<Vec<_>>())
+}
+
+/// nucleotide is a uniform distribution over ACGT.
+pub fn nucleotide() -> Frequencies {
+    uniform("ACGT")
+}
+
+/// robinson is the amino acid distribution from Robinson and Robinson (1991), as used by BLAST.
+pub fn robinson() -> Frequencies {
+    from_pairs(&[
+        ('A', 0.07805),
+        ('C', 0.01925),
+        ('D', 0.05364),
+        ('E', 0.06295),
+        ('F', 0.03856),
+        ('G', 0.07377),
+        ('H', 0.02199),
+        ('I', 0.05142),
+        ('K', 0.05744),
+        ('L', 0.09019),
+        ('M', 0.02243),
+        ('N', 0.04487),
+        ('P', 0.05203),
+        ('Q', 0.04264),
+        ('R', 0.05129),
+        ('S', 0.07120),
+        ('T', 0.05841),
+        ('V', 0.06441),
+        ('W', 0.01330),
+        ('Y', 0.03216),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pairs() {
+        let freqs = from_pairs(&[('A', 3f64), ('T', 1f64)]);
+        assert_eq!((0.75, 0.25), (freqs[b'A' as usize], freqs[b'T' as usize]));
+        assert_eq!(1f64, freqs.iter().sum::<f64>());
+
+        // non-ASCII residues are left out
+        let freqs = from_pairs(&[('A', 1f64), ('É', 2f64), ('T', 1f64)]);
+        assert_eq!((0.5, 0.5), (freqs[b'A' as usize], freqs[b'T' as usize]));
+        assert_eq!(1f64, freqs.iter().sum::<f64>());
+        assert_eq!(nucleotide(), uniform("ACGTÄ"));
+    }
+}
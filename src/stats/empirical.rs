@@ -0,0 +1,196 @@
+//! Empirical significance of an alignment score from shuffled sequences.
+//!
+//! When Karlin-Altschul parameters aren't known for a scoring scheme, the
+//! significance of a score can be estimated by aligning the query against many
+//! shuffles of the target. Shuffling keeps the target's composition, so the
+//! shuffled scores are a sample of what's expected by chance.
+//!
+//! Local alignment scores of random sequences follow an extreme value (Gumbel)
+//! distribution, which is fit to the mean and standard deviation of the sample.
+
+use crate::{
+    align::{align, Method, Scoring},
+    seq::random::{shuffle, shuffle_dinucleotide, Rng},
+};
+
+/// Euler-Mascheroni constant, used to fit the location of the Gumbel distribution.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Shuffle is how the target sequence is randomized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shuffle {
+    /// keep the residue composition
+    #[default]
+    Composition,
+
+    /// keep the dinucleotide (adjacent pair) frequencies, Altschul & Erickson 1985
+    Dinucleotide,
+}
+
+/// EmpiricalConfig sets how many shuffles are scored and how.
+#[derive(Clone, Debug)]
+pub struct EmpiricalConfig {
+    /// number of shuffled targets to align against
+    pub shuffles: usize,
+
+    /// how the target is shuffled
+    pub shuffle: Shuffle,
+
+    /// seed for the shuffles, so results are reproducible
+    pub seed: u64,
+}
+
+impl Default for EmpiricalConfig {
+    fn default() -> Self {
+        EmpiricalConfig {
+            shuffles: 100,
+            shuffle: Shuffle::Composition,
+            seed: 0,
+        }
+    }
+}
+
+/// EmpiricalSignificance is the significance of a score against shuffled targets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmpiricalSignificance {
+    /// score of the query against the real target
+    pub score: f32,
+
+    /// mean score against the shuffled targets
+    pub mean: f64,
+
+    /// standard deviation of the scores against the shuffled targets
+    pub std_dev: f64,
+
+    /// z_score is the number of standard deviations the score is above the mean.
+    pub z_score: f64,
+
+    /// p_value is the probability of the score by chance from a Gumbel fit of the shuffled scores.
+    pub p_value: f64,
+
+    /// empirical_p_value is the fraction of shuffles (plus the real target) scoring at least as well.
+    pub empirical_p_value: f64,
+}
+
+/// empirical_significance aligns the query against the target and shuffles of it.
+pub fn empirical_significance(
+    query: &str,
+    target: &str,
+    method: &Method,
+    scoring: &Scoring,
+    config: &EmpiricalConfig,
+) -> EmpiricalSignificance {
+    let mut rng = Rng::new(config.seed);
+    empirical_significance_with_rng(query, target, method, scoring, config, &mut rng)
+}
+
+/// empirical_significance_with_rng is [`empirical_significance`] with the
+/// target shuffled by a given generator rather than one from `config.seed`.
+pub fn empirical_significance_with_rng(
+    query: &str,
+    target: &str,
+    method: &Method,
+    scoring: &Scoring,
+    config: &EmpiricalConfig,
+    rng: &mut Rng,
+) -> EmpiricalSignificance {
+    let score_of = |t: String| align(vec![query.to_string(), t], method.strategy(), scoring).score;
+
+    let score = score_of(target.to_string());
+
+    let scores: Vec<f64> = (0..config.shuffles)
+        .map(|_| {
+            let shuffled = match config.shuffle {
+                Shuffle::Composition => shuffle(target, rng),
+                Shuffle::Dinucleotide => shuffle_dinucleotide(target, rng),
+            };
+            score_of(shuffled) as f64
+        })
+        .collect();
+
+    let n = scores.len().max(1) as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let std_dev = (scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let z_score = if std_dev > 0f64 {
+        (score as f64 - mean) / std_dev
+    } else {
+        0f64
+    };
+
+    // Gumbel fit by the method of moments
+    let p_value = if std_dev > 0f64 {
+        let lambda = std::f64::consts::PI / (std_dev * 6f64.sqrt());
+        let mu = mean - EULER_GAMMA / lambda;
+        1f64 - (-(-lambda * (score as f64 - mu)).exp()).exp()
+    } else if score as f64 > mean {
+        0f64
+    } else {
+        1f64
+    };
+
+    let at_least = scores.iter().filter(|s| **s >= score as f64).count();
+    let empirical_p_value = (at_least + 1) as f64 / (scores.len() + 1) as f64;
+
+    EmpiricalSignificance {
+        score,
+        mean,
+        std_dev,
+        z_score,
+        p_value,
+        empirical_p_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrices::NUC_4_4;
+
+    use super::*;
+
+    #[test]
+    fn test_empirical_significance() {
+        let scoring = Scoring {
+            matrix: NUC_4_4::MATRIX,
+            gap_opening: -5f32,
+            gap_extension: -2f32,
+            ..Default::default()
+        };
+        let config = EmpiricalConfig {
+            shuffles: 50,
+            ..Default::default()
+        };
+
+        let related = empirical_significance(
+            "ATGGCTAGCTAGGATCCGATTACA",
+            "CCATGGCTAGCTAGGATCCGATTACATT",
+            &Method::SmithWaterman,
+            &scoring,
+            &config,
+        );
+        assert!(related.z_score > 3f64, "{:?}", related);
+        assert!(related.p_value < 0.01, "{:?}", related);
+        assert!(related.empirical_p_value < 0.05, "{:?}", related);
+
+        // the same seed gives the same results
+        let again = empirical_significance(
+            "ATGGCTAGCTAGGATCCGATTACA",
+            "CCATGGCTAGCTAGGATCCGATTACATT",
+            &Method::SmithWaterman,
+            &scoring,
+            &config,
+        );
+        assert_eq!(related, again);
+
+        // and so does the same generator, while drawing from it moves it on
+        let mut rng = Rng::new(config.seed);
+        let query = "ATGGCTAGCTAGGATCCGATTACA";
+        let target = "CCATGGCTAGCTAGGATCCGATTACATT";
+        let method = Method::SmithWaterman;
+        let first =
+            empirical_significance_with_rng(query, target, &method, &scoring, &config, &mut rng);
+        assert_eq!(related, first);
+        let second =
+            empirical_significance_with_rng(query, target, &method, &scoring, &config, &mut rng);
+        assert_ne!(first.mean, second.mean);
+    }
+}
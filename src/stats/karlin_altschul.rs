@@ -0,0 +1,338 @@
+//! Karlin-Altschul statistics for local alignment scores.
+//! https://www.pnas.org/doi/10.1073/pnas.87.6.2264
+//!
+//! The number of distinct local alignments with a score of at least S between
+//! two random sequences of length m and n is approximately Poisson distributed
+//! with mean E = K m n e^(-lambda S). lambda and K depend only on the scoring
+//! scheme and the background residue frequencies.
+//!
+//! lambda and K can be computed exactly for ungapped alignment. For gapped
+//! alignment they have to be estimated by simulation, so published values
+//! should be passed to [`KarlinAltschul::new`] instead.
+
+use crate::{align::Alignment, matrices::Matrix};
+
+use super::{background::Frequencies, Error, Result};
+
+/// upper bound on the number of terms in the sum used to compute K.
+const K_ITER_MAX: usize = 100;
+
+/// terms of the sum used to compute K that are smaller than this are dropped.
+const K_SUM_LIMIT: f64 = 0.0001;
+
+/// KarlinAltschul are the statistical parameters of a scoring scheme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KarlinAltschul {
+    /// lambda scales raw scores into nats.
+    pub lambda: f64,
+
+    /// k scales the search space.
+    pub k: f64,
+
+    /// h is the relative entropy of the scoring scheme, in nats per aligned pair.
+    pub h: f64,
+}
+
+/// SearchSpace is the size of the space a score was found in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchSpace {
+    /// length of the query sequence
+    pub query_len: usize,
+
+    /// total length of all the target sequences
+    pub target_len: usize,
+
+    /// number of target sequences
+    pub targets: usize,
+}
+
+/// Significance is the statistical significance of a single alignment score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Significance {
+    /// bit_score is the normalized score, comparable across scoring schemes.
+    pub bit_score: f64,
+
+    /// evalue is the number of alignments with at least this score expected by chance.
+    pub evalue: f64,
+}
+
+impl KarlinAltschul {
+    pub fn new(lambda: f64, k: f64, h: f64) -> Self {
+        KarlinAltschul { lambda, k, h }
+    }
+
+    /// BLOSUM62 with a gap opening of -12 and extension of -1 (BLAST's 11/1), as published by NCBI.
+    pub const BLOSUM62_GAPPED: KarlinAltschul = KarlinAltschul {
+        lambda: 0.267,
+        k: 0.041,
+        h: 0.14,
+    };
+
+    /// estimate computes the ungapped parameters of a matrix for the background frequencies.
+    ///
+    /// Residues without a background frequency, and undefined matrix entries, are ignored.
+    pub fn estimate(matrix: &Matrix, freqs: &Frequencies) -> Result<Self> {
+        let (low, probs) = score_probabilities(matrix, freqs)?;
+
+        let mean: f64 = probs
+            .iter()
+            .enumerate()
+            .map(|(s, p)| (s as i32 + low) as f64 * p)
+            .sum();
+        if mean >= 0f64 {
+            return Err(Error::NonNegativeExpectedScore(mean));
+        }
+        if probs.len() as i32 + low <= 1 {
+            return Err(Error::NoPositiveScore);
+        }
+
+        let lambda = estimate_lambda(low, &probs);
+        let h = lambda
+            * probs
+                .iter()
+                .enumerate()
+                .map(|(s, p)| {
+                    let s = (s as i32 + low) as f64;
+                    s * p * (lambda * s).exp()
+                })
+                .sum::<f64>();
+        let k = estimate_k(low, &probs, lambda, h, mean);
+
+        Ok(KarlinAltschul { lambda, k, h })
+    }
+
+    /// bit_score normalizes a raw score into bits.
+    pub fn bit_score(&self, score: f32) -> f64 {
+        (self.lambda * score as f64 - self.k.ln()) / std::f64::consts::LN_2
+    }
+
+    /// evalue is the number of hits with at least this score expected by chance in the search space.
+    pub fn evalue(&self, score: f32, space: &SearchSpace) -> f64 {
+        self.k * space.effective(self) * (-self.lambda * score as f64).exp()
+    }
+
+    /// significance of an alignment's score within a search space.
+    pub fn significance(&self, alignment: &Alignment, space: &SearchSpace) -> Significance {
+        Significance {
+            bit_score: self.bit_score(alignment.score),
+            evalue: self.evalue(alignment.score, space),
+        }
+    }
+}
+
+impl SearchSpace {
+    /// pairwise is the search space of a single query against a single target.
+    pub fn pairwise(query_len: usize, target_len: usize) -> Self {
+        SearchSpace {
+            query_len,
+            target_len,
+            targets: 1,
+        }
+    }
+
+    /// effective is the search space size after correcting for edge effects.
+    ///
+    /// An alignment can't start near the end of a sequence, so each sequence is
+    /// shortened by the expected length of a significant alignment (ln(Kmn) / H).
+    /// Without an entropy the raw size is used.
+    pub fn effective(&self, params: &KarlinAltschul) -> f64 {
+        let m = self.query_len as f64;
+        let n = self.target_len as f64;
+        let size = m * n;
+        if params.h <= 0f64 || size <= 0f64 {
+            return size;
+        }
+
+        let overhang = (params.k * size).ln().max(0f64) / params.h;
+        let m_eff = (m - overhang).max(1f64 / params.k);
+        let n_eff = (n - self.targets as f64 * overhang).max(1f64 / params.k);
+        m_eff * n_eff
+    }
+}
+
+/// score_probabilities returns the lowest score and the probability of each score from it up.
+fn score_probabilities(matrix: &Matrix, freqs: &Frequencies) -> Result<(i32, Vec<f64>)> {
+    let mut scores: Vec<(i32, f64)> = Vec::new();
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, s) in row.iter().enumerate() {
+            if freqs[i] > 0f64 && freqs[j] > 0f64 && *s != i32::MIN {
+                scores.push((*s, freqs[i] * freqs[j]));
+            }
+        }
+    }
+    if scores.is_empty() {
+        return Err(Error::EmptyAlphabet);
+    }
+
+    let low = scores.iter().map(|(s, _)| *s).min().unwrap();
+    let high = scores.iter().map(|(s, _)| *s).max().unwrap();
+    let total: f64 = scores.iter().map(|(_, p)| p).sum();
+
+    let mut probs = vec![0f64; (high - low + 1) as usize];
+    for (s, p) in scores {
+        probs[(s - low) as usize] += p / total;
+    }
+    Ok((low, probs))
+}
+
+/// estimate_lambda finds the positive root of sum(p(s) * e^(lambda * s)) = 1 by bisection.
+fn estimate_lambda(low: i32, probs: &[f64]) -> f64 {
+    let f = |lambda: f64| -> f64 {
+        probs
+            .iter()
+            .enumerate()
+            .map(|(s, p)| p * (lambda * (s as i32 + low) as f64).exp())
+            .sum::<f64>()
+            - 1f64
+    };
+
+    let mut hi = 0.5f64;
+    while f(hi) < 0f64 {
+        hi *= 2f64;
+    }
+    let mut lo = 0f64;
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2f64;
+        if f(mid) < 0f64 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2f64
+}
+
+/// estimate_k computes K from the score distribution, following BLAST's BlastKarlinLHtoK.
+///
+/// K = lambda e^(-2 sigma) / (H (1 - e^(-lambda))), where sigma sums over the
+/// distribution of scores of ungapped alignments of increasing length.
+fn estimate_k(low: i32, probs: &[f64], lambda: f64, h: f64, mean: f64) -> f64 {
+    let high = low + probs.len() as i32 - 1;
+
+    // scores are only defined on a lattice of their greatest common divisor
+    let divisor = probs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, p)| **p > 0f64)
+        .fold(-low, |d, (i, _)| gcd(d, i as i32));
+    let low = low / divisor;
+    let high = high / divisor;
+    let lambda = lambda * divisor as f64;
+    let mean = mean / divisor as f64;
+    let probs: Vec<f64> = (low..=high)
+        .map(|s| probs[((s - low) * divisor) as usize])
+        .collect();
+
+    let exp_minus_lambda = (-lambda).exp();
+    if low == -1 && high == 1 {
+        let (p_low, p_high) = (probs[0], probs[probs.len() - 1]);
+        return (p_low - p_high) * (p_low - p_high) / p_low;
+    }
+    if low == -1 || high == 1 {
+        let mut first = h / lambda;
+        if high != 1 {
+            first = mean * mean / first;
+        }
+        return first * (1f64 - exp_minus_lambda);
+    }
+
+    // dist holds the probability of each total score of an alignment of `iter` pairs
+    let mut dist = vec![1f64];
+    let mut dist_low = 0i32;
+    let mut sigma = 0f64;
+    for iter in 1..=K_ITER_MAX {
+        let mut next = vec![0f64; dist.len() + probs.len() - 1];
+        for (x, px) in dist.iter().enumerate() {
+            for (y, py) in probs.iter().enumerate() {
+                next[x + y] += px * py;
+            }
+        }
+        dist = next;
+        dist_low += low;
+
+        let term: f64 = dist
+            .iter()
+            .enumerate()
+            .map(|(s, p)| {
+                let s = s as i32 + dist_low;
+                if s < 0 {
+                    p * (lambda * s as f64).exp()
+                } else {
+                    *p
+                }
+            })
+            .sum::<f64>()
+            / iter as f64;
+        sigma += term;
+        if term <= K_SUM_LIMIT {
+            break;
+        }
+    }
+
+    (-2f64 * sigma).exp() * lambda / (h * (1f64 - exp_minus_lambda))
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        matrices::{BLOSUM62, NUC_4_4},
+        stats::background,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_estimate_blosum62() {
+        let params = KarlinAltschul::estimate(&BLOSUM62::MATRIX, &background::robinson()).unwrap();
+
+        // BLAST's ungapped BLOSUM62 parameters
+        assert!((params.lambda - 0.3176).abs() < 0.001, "{:?}", params);
+        assert!((params.k - 0.134).abs() < 0.005, "{:?}", params);
+        assert!((params.h - 0.401).abs() < 0.01, "{:?}", params);
+    }
+
+    #[test]
+    fn test_estimate_nucleotide() {
+        let params = KarlinAltschul::estimate(&NUC_4_4::MATRIX, &background::nucleotide()).unwrap();
+
+        // +5/-4 over uniform ACGT: 0.25e^(5 lambda) + 0.75e^(-4 lambda) = 1
+        assert!((params.lambda - 0.1915).abs() < 0.001, "{:?}", params);
+        assert!(params.k > 0f64 && params.k < 1f64, "{:?}", params);
+    }
+
+    #[test]
+    fn test_estimate_positive_expected_score() {
+        let mut matrix = [[i32::MIN; 128]; 128];
+        for a in "AC".bytes() {
+            for b in "AC".bytes() {
+                matrix[a as usize][b as usize] = 1;
+            }
+        }
+
+        assert!(matches!(
+            KarlinAltschul::estimate(&matrix, &background::uniform("AC")),
+            Err(Error::NonNegativeExpectedScore(_))
+        ));
+    }
+
+    #[test]
+    fn test_evalue() {
+        let params = KarlinAltschul::new(0.3176, 0.134, 0f64);
+        let space = SearchSpace::pairwise(100, 1000);
+
+        assert!((params.bit_score(50f32) - 25.81).abs() < 0.01);
+        assert!((params.evalue(50f32, &space) - 1.7002e-3).abs() < 1e-6);
+
+        // higher scores are less likely by chance
+        assert!(params.evalue(60f32, &space) < params.evalue(50f32, &space));
+    }
+}
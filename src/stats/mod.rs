@@ -0,0 +1,27 @@
+pub use crate::stats::empirical::empirical_significance;
+pub use crate::stats::empirical::empirical_significance_with_rng;
+pub use crate::stats::empirical::EmpiricalConfig;
+pub use crate::stats::empirical::EmpiricalSignificance;
+pub use crate::stats::empirical::Shuffle;
+pub use crate::stats::karlin_altschul::KarlinAltschul;
+pub use crate::stats::karlin_altschul::SearchSpace;
+pub use crate::stats::karlin_altschul::Significance;
+
+use thiserror::Error;
+
+pub mod background;
+mod empirical;
+mod karlin_altschul;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("expected score must be negative for local alignment statistics, got {0}")]
+    NonNegativeExpectedScore(f64),
+
+    #[error("scoring matrix has no positive score for the background frequencies")]
+    NoPositiveScore,
+
+    #[error("background frequencies don't overlap the scoring matrix alphabet")]
+    EmptyAlphabet,
+}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
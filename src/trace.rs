@@ -0,0 +1,24 @@
+//! Spans of the major stages of alignment, like seeding, filling the DP grid,
+//! the distance matrix and the progressive stage, for profiling.
+//!
+//! With the `tracing` feature each stage is a `tracing` span at the info
+//! level, with the sizes of its input as fields, so a subscriber that times
+//! spans shows where the time goes. Without it the spans are nothing and cost
+//! nothing.
+
+/// stage enters a span named for a stage of alignment, with some sizes as
+/// fields, until the returned guard is dropped.
+macro_rules! stage {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = ::tracing::info_span!($name $(, $field = $value)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::trace::Disabled;
+        guard
+    }};
+}
+pub(crate) use stage;
+
+/// Disabled is the guard of a span without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Disabled;
@@ -0,0 +1,323 @@
+//! Adapter and primer trimming.
+//!
+//! Adapters are found with an overlap (semi-global) alignment of the adapter
+//! to the read: the adapter may start anywhere in the read and may run off
+//! its end, so partial adapters at the end of short inserts are found too.
+//! Edits are counted with unit cost, and a match is kept if its errors are
+//! within the error rate of the length of the adapter that overlaps the read.
+//! This is the approach of cutadapt, https://doi.org/10.14806/ej.17.1.200.
+
+use std::{fmt, ops::Range};
+
+/// End is the end of the read an adapter is ligated to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    /// the adapter and everything before it are removed
+    Five,
+
+    /// the adapter and everything after it are removed
+    Three,
+}
+
+/// Adapter is an adapter or primer sequence to trim.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Adapter {
+    pub name: String,
+    pub seq: String,
+    pub end: End,
+}
+
+impl Adapter {
+    /// new creates an adapter.
+    pub fn new(name: &str, seq: &str, end: End) -> Self {
+        Adapter {
+            name: name.to_string(),
+            seq: seq.to_string(),
+            end,
+        }
+    }
+}
+
+/// TrimConfig configures how adapters are matched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrimConfig {
+    /// most errors per residue of adapter in the overlap
+    pub max_error_rate: f32,
+
+    /// fewest residues of a partial adapter at the end of a read to trim
+    pub min_overlap: usize,
+}
+
+impl Default for TrimConfig {
+    fn default() -> Self {
+        TrimConfig {
+            max_error_rate: 0.1,
+            min_overlap: 3,
+        }
+    }
+}
+
+/// AdapterMatch is where an adapter was found in a read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterMatch {
+    /// index of the adapter
+    pub adapter: usize,
+
+    /// 0-based, half-open region of the read the adapter aligned to
+    pub range: Range<usize>,
+
+    /// number of edits between the adapter and the read
+    pub errors: usize,
+}
+
+/// Trimmed is a read after trimming.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trimmed {
+    /// the trimmed sequence
+    pub seq: String,
+
+    /// 0-based, half-open region of the read that was kept
+    pub range: Range<usize>,
+
+    /// the adapters found, in the order they were trimmed
+    pub matches: Vec<AdapterMatch>,
+}
+
+/// TrimReport is a summary of trimming many reads.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrimReport {
+    /// number of reads
+    pub reads: usize,
+
+    /// number of reads with at least one adapter
+    pub trimmed: usize,
+
+    /// residues before trimming
+    pub residues_in: usize,
+
+    /// residues after trimming
+    pub residues_out: usize,
+
+    /// number of reads each adapter was found in, by adapter index
+    pub adapters: Vec<usize>,
+}
+
+impl TrimReport {
+    /// add counts a trimmed read.
+    pub fn add(&mut self, read_len: usize, trimmed: &Trimmed) {
+        self.reads += 1;
+        self.residues_in += read_len;
+        self.residues_out += trimmed.seq.len();
+        if !trimmed.matches.is_empty() {
+            self.trimmed += 1;
+        }
+        for m in &trimmed.matches {
+            if self.adapters.len() <= m.adapter {
+                self.adapters.resize(m.adapter + 1, 0);
+            }
+            self.adapters[m.adapter] += 1;
+        }
+    }
+}
+
+impl fmt::Display for TrimReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "reads\t{}", self.reads)?;
+        writeln!(f, "reads with adapters\t{}", self.trimmed)?;
+        writeln!(f, "residues in\t{}", self.residues_in)?;
+        write!(f, "residues out\t{}", self.residues_out)?;
+        for (i, count) in self.adapters.iter().enumerate() {
+            write!(f, "\nadapter {}\t{}", i, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// trim removes each adapter, in order, from a read.
+///
+/// Each adapter is trimmed at most once, from what's left of the read after
+/// the adapters before it.
+pub fn trim(read: &str, adapters: &[Adapter], config: &TrimConfig) -> Trimmed {
+    let bytes = read.as_bytes();
+    let mut range = 0..bytes.len();
+    let mut matches = Vec::new();
+
+    for (index, adapter) in adapters.iter().enumerate() {
+        let seq = &bytes[range.clone()];
+        let found = match adapter.end {
+            End::Three => locate(seq, adapter.seq.as_bytes(), config),
+            End::Five => {
+                let rev_seq: Vec<u8> = seq.iter().rev().copied().collect();
+                let rev_adapter: Vec<u8> = adapter.seq.bytes().rev().collect();
+                locate(&rev_seq, &rev_adapter, config)
+                    .map(|(r, errors)| (seq.len() - r.end..seq.len() - r.start, errors))
+            }
+        };
+        let Some((found, errors)) = found else {
+            continue;
+        };
+
+        let start = range.start;
+        matches.push(AdapterMatch {
+            adapter: index,
+            range: start + found.start..start + found.end,
+            errors,
+        });
+        range = match adapter.end {
+            End::Three => start..start + found.start,
+            End::Five => start + found.end..range.end,
+        };
+    }
+
+    Trimmed {
+        seq: read[range.clone()].to_string(),
+        range,
+        matches,
+    }
+}
+
+/// trim_all trims every read and reports what was removed.
+pub fn trim_all<I, S>(
+    reads: I,
+    adapters: &[Adapter],
+    config: &TrimConfig,
+) -> (Vec<Trimmed>, TrimReport)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut report = TrimReport {
+        adapters: vec![0; adapters.len()],
+        ..Default::default()
+    };
+    let trimmed = reads
+        .into_iter()
+        .map(|read| {
+            let read = read.as_ref();
+            let trimmed = trim(read, adapters, config);
+            report.add(read.len(), &trimmed);
+            trimmed
+        })
+        .collect();
+    (trimmed, report)
+}
+
+/// locate finds a 3' adapter in a read, either whole or as a prefix of the
+/// adapter running off the end of the read.
+///
+/// The best match has the most matching residues, then the fewest errors.
+fn locate(read: &[u8], adapter: &[u8], config: &TrimConfig) -> Option<(Range<usize>, usize)> {
+    let (n, m) = (read.len(), adapter.len());
+    if m == 0 || n == 0 {
+        return None;
+    }
+    let allowed = |overlap: usize| (config.max_error_rate * overlap as f32) as usize;
+
+    // edits of adapter[..i] ending at read[..j], and the column it started in
+    let mut prev: Vec<(usize, usize)> = (0..=n).map(|j| (0, j)).collect();
+    let mut cur = vec![(0, 0); n + 1];
+    let mut best: Option<(usize, usize, Range<usize>)> = None;
+    let mut consider = |overlap: usize, errors: usize, range: Range<usize>| {
+        if errors > allowed(overlap) {
+            return;
+        }
+        let matches = overlap - errors;
+        let better = match &best {
+            None => true,
+            Some((m, e, _)) => matches > *m || (matches == *m && errors < *e),
+        };
+        if better {
+            best = Some((matches, errors, range));
+        }
+    };
+
+    for i in 1..=m {
+        cur[0] = (i, 0);
+        for j in 1..=n {
+            let cost = usize::from(!adapter[i - 1].eq_ignore_ascii_case(&read[j - 1]));
+            let diagonal = (prev[j - 1].0 + cost, prev[j - 1].1);
+            let up = (prev[j].0 + 1, prev[j].1);
+            let left = (cur[j - 1].0 + 1, cur[j - 1].1);
+            cur[j] = [diagonal, up, left]
+                .into_iter()
+                .min_by_key(|(edits, _)| *edits)
+                .unwrap();
+        }
+        if i == m {
+            for (j, (errors, start)) in cur.iter().enumerate().skip(1) {
+                consider(m, *errors, *start..j);
+            }
+        } else if i >= config.min_overlap {
+            let (errors, start) = cur[n];
+            consider(i, errors, start..n);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    best.map(|(_, errors, range)| (range, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim() {
+        let config = TrimConfig::default();
+        let adapters = [
+            Adapter::new("primer", "GGATCC", End::Five),
+            Adapter::new("adapter", "AGATCGGAAGAGC", End::Three),
+        ];
+
+        // whole adapters at both ends, one mismatch in the 3' adapter
+        let trimmed = trim("TTGGATCCACGTACGTAGATCGGTAGAGCTT", &adapters, &config);
+        assert_eq!("ACGTACGT", trimmed.seq);
+        assert_eq!(8..16, trimmed.range);
+        assert_eq!(
+            vec![
+                AdapterMatch {
+                    adapter: 0,
+                    range: 2..8,
+                    errors: 0
+                },
+                AdapterMatch {
+                    adapter: 1,
+                    range: 16..29,
+                    errors: 1
+                },
+            ],
+            trimmed.matches
+        );
+
+        // a partial adapter at the end of the read
+        let trimmed = trim("ACGTACGTTTAGATC", &adapters[1..], &config);
+        assert_eq!("ACGTACGTTT", trimmed.seq);
+
+        // too short an overlap to trim
+        let trimmed = trim("ACGTACGTTTAG", &adapters[1..], &config);
+        assert_eq!("ACGTACGTTTAG", trimmed.seq);
+        assert!(trimmed.matches.is_empty());
+    }
+
+    #[test]
+    fn test_trim_all() {
+        let adapters = [Adapter::new("adapter", "AGATCGGAAGAGC", End::Three)];
+        let (trimmed, report) = trim_all(
+            ["ACGTAGATCGGAAGAGC", "ACGTACGT"],
+            &adapters,
+            &TrimConfig::default(),
+        );
+        assert_eq!("ACGT", trimmed[0].seq);
+        assert_eq!("ACGTACGT", trimmed[1].seq);
+        assert_eq!(
+            TrimReport {
+                reads: 2,
+                trimmed: 1,
+                residues_in: 25,
+                residues_out: 12,
+                adapters: vec![1],
+            },
+            report
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Drawings of alignments.
+//!
+//! Drawings are standalone SVG documents as strings, so they can be written
+//! to a file, embedded in HTML, or converted to an image without any
+//! dependencies here.
+
+use std::fmt::Write;
+
+use crate::align::Logo;
+
+/// LogoStyle is the size and colors of a drawn sequence logo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogoStyle {
+    /// width of a column, in pixels
+    pub column_width: f64,
+
+    /// height of a column with the most information possible, in pixels
+    pub height: f64,
+
+    /// fill of each residue, residues not listed are drawn in black
+    pub colors: Vec<(char, &'static str)>,
+}
+
+impl Default for LogoStyle {
+    fn default() -> Self {
+        LogoStyle {
+            column_width: 20f64,
+            height: 100f64,
+            colors: vec![
+                ('A', "#109648"),
+                ('C', "#255c99"),
+                ('G', "#f7b32b"),
+                ('T', "#d62839"),
+                ('U', "#d62839"),
+            ],
+        }
+    }
+}
+
+/// glyphs of monospace capital letters are about this fraction of the font
+/// size tall
+const CAP_HEIGHT: f64 = 0.72;
+
+/// logo_svg draws a sequence logo, most frequent residue of each column on top.
+pub fn logo_svg(logo: &Logo, style: &LogoStyle) -> String {
+    let width = style.column_width * logo.columns.len() as f64;
+    let font_size = style.column_width / CAP_HEIGHT;
+    let px_per_bit = style.height / logo.max_bits;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width, style.height, width, style.height
+    );
+    for (col, column) in logo.columns.iter().enumerate() {
+        let x = style.column_width * col as f64;
+
+        // stacked from the bottom up, so least frequent first
+        let mut y = style.height;
+        let mut heights: Vec<(char, f64)> = column.heights().collect();
+        heights.reverse();
+        for (residue, bits) in heights {
+            let h = bits * px_per_bit;
+            if h < 0.01 {
+                continue;
+            }
+            let fill = style
+                .colors
+                .iter()
+                .find(|(c, _)| *c == residue)
+                .map_or("#000000", |(_, f)| f);
+            let _ = writeln!(
+                svg,
+                r#"  <text transform="translate({:.2},{:.2}) scale(1,{:.4})" font-family="monospace" font-size="{:.2}" textLength="{:.2}" lengthAdjust="spacingAndGlyphs" fill="{}">{}</text>"#,
+                x,
+                y,
+                h / style.column_width,
+                font_size,
+                style.column_width,
+                escape(fill),
+                escape(&residue.to_string())
+            );
+            y -= h;
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// escape the characters that aren't allowed as is in XML text and attributes.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::align::MSAlignment;
+
+    use super::*;
+
+    #[test]
+    fn test_logo_svg() {
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["AC".chars().collect(), "AG".chars().collect()],
+        );
+        let svg = logo_svg(&msa.logo(4), &LogoStyle::default());
+
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"100\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(1, svg.matches(">A</text>").count());
+        assert!(svg.contains("fill=\"#109648\""));
+
+        // residues that are markup in XML are escaped
+        let msa = MSAlignment::new(
+            vec!["a".into(), "b".into()],
+            vec!["<&".chars().collect(), "<&".chars().collect()],
+        );
+        let svg = logo_svg(&msa.logo(4), &LogoStyle::default());
+        assert_eq!(1, svg.matches(">&lt;</text>").count());
+        assert_eq!(1, svg.matches(">&amp;</text>").count());
+        assert!(!svg.contains("><</text>"));
+    }
+}